@@ -37,7 +37,7 @@ pub fn small_benchmark_mean(c: &mut Criterion) {
             black_box(&imp_data),
             black_box(&vec![&wgt]),
             black_box(&vec![&rep_wgts]),
-            black_box(1.0));
+            black_box(1.0)).unwrap();
     }));
 }
 
@@ -55,7 +55,7 @@ pub fn large_benchmark_mean(c: &mut Criterion) {
             black_box(&vec![&test_data.wgt]),
             black_box(&vec![&test_data.repwgt]),
             black_box(1.0)
-        );
+        ).unwrap();
     }));
 }
 
@@ -73,6 +73,6 @@ pub fn large_benchmark_correlation(c: &mut Criterion) {
             black_box(&vec![&test_data.wgt]),
             black_box(&vec![&test_data.repwgt]),
             black_box(1.0)
-        );
+        ).unwrap();
     }));
 }
\ No newline at end of file
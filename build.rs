@@ -27,4 +27,11 @@ fn main() {
         .arg(config_file)
         .output()
         .expect("Failed when generating C# bindings");
+
+    #[cfg(feature = "grpc")]
+    {
+        // protox compiles the service definition without requiring protoc
+        let file_descriptors = protox::compile(["proto/replicest.proto"], ["proto/"]).unwrap();
+        tonic_build::configure().compile_fds(file_descriptors).unwrap();
+    }
 }
\ No newline at end of file
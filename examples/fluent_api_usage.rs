@@ -14,7 +14,7 @@ pub fn main() {
     analysis.mean().set_weights(&wgts);
     println!("{}", analysis.summary());
 
-    let analysis2 = analysis.copy();
+    let analysis2 = analysis.clone();
     println!("{}", analysis2.summary());
 
     let new_wgts = dvector![2.1, 2.5, 2.3, 2.7, 2.7, 2.0];
@@ -1,467 +1,2896 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use nalgebra::{DMatrix, DVector};
-use crate::errors::{InconsistencyError, MissingElementError};
-use crate::estimates;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::errors::{InconsistencyError, MissingElementError, ReplicestError};
+use crate::{data_preparation, design, estimates};
+use crate::data_preparation::MissingStrategy;
+use crate::design::{JackknifeScheme, To};
+use crate::external::Estimate;
 use crate::helper::Split;
-use crate::replication::{replicate_estimates, ReplicatedEstimates};
+use crate::replication::{apply_nan_replicate_weight_policy, compare_independent, Comparison, difference, replicate_estimates_in_current_pool, NanReplicateWeightPolicy, ReplicatedEstimates, ReplicationMonitor, VarianceMethod};
 
 pub enum Imputation<'a> {
     Yes(&'a Vec<&'a DMatrix<f64>>),
     No(&'a DMatrix<f64>),
+    PlausibleValues(&'a DMatrix<f64>, &'a [Vec<usize>]),
 }
 
-pub struct Analysis {
-    x: Option<Rc<Vec<DMatrix<f64>>>>,
-    wgt: Option<Rc<DVector<f64>>>,
-    repwgts: Option<Rc<DMatrix<f64>>>,
-    variance_adjustment_factor: f64,
-    estimate_name: Option<String>,
-    estimate: Option<fn(&DMatrix<f64>, &DVector<f64>) -> estimates::Estimates>,
-    groups: Option<Rc<Vec<DMatrix<f64>>>>,
-}
+fn append_imputations(existing: Option<&Arc<Vec<DMatrix<f64>>>>, new: Vec<DMatrix<f64>>, what: &str) -> Result<Vec<DMatrix<f64>>, InconsistencyError> {
+    let existing = match existing {
+        None => return Ok(new),
+        Some(existing) => existing.deref(),
+    };
 
-pub fn analysis() -> Analysis {
-    Analysis {
-        x: None,
-        wgt: None,
-        repwgts: None,
-        variance_adjustment_factor: 1.0,
-        estimate_name: None,
-        estimate: None,
-        groups: None,
+    if existing.len() != new.len() {
+        return Err(InconsistencyError::new(&format!("appended {} differ in number of imputations", what)))
+    }
+    if existing.iter().zip(new.iter()).any(|(mat, new_mat)| mat.ncols() != new_mat.ncols()) {
+        return Err(InconsistencyError::new(&format!("appended {} differ in number of columns", what)))
     }
+
+    Ok(existing.iter().zip(new.iter()).map(|(mat, new_mat)| data_preparation::append_rows(mat, new_mat)).collect())
 }
 
-impl Analysis {
-    pub fn for_data(&mut self, data: Imputation) -> &mut Self {
-        let mut new_vec : Vec<DMatrix<f64>> = Vec::new();
+fn imputations_from(data: Imputation) -> Result<Vec<DMatrix<f64>>, InconsistencyError> {
+    let mut new_vec : Vec<DMatrix<f64>> = Vec::new();
 
-        match data {
-            Imputation::Yes(&ref vec) => {
-                for &mat in vec.iter() {
-                    new_vec.push(mat.clone());
-                }
-            }
-            Imputation::No(&ref mat) => {
+    match data {
+        Imputation::Yes(&ref vec) => {
+            for &mat in vec.iter() {
                 new_vec.push(mat.clone());
             }
         }
+        Imputation::No(&ref mat) => {
+            new_vec.push(mat.clone());
+        }
+        Imputation::PlausibleValues(&ref mat, plausible_values) => {
+            let number_plausible_values = plausible_values.first().map(|pvs| pvs.len()).unwrap_or(1);
+            if plausible_values.iter().any(|pvs| pvs.len() != number_plausible_values) {
+                return Err(InconsistencyError::new("unequal number of plausible values per construct"))
+            }
+            if number_plausible_values == 0 {
+                return Err(InconsistencyError::new("no plausible values per construct"))
+            }
+            if plausible_values.iter().flatten().any(|&c| c >= mat.ncols()) {
+                return Err(InconsistencyError::new("plausible value column out of range"))
+            }
 
-        self.x = Some(Rc::new(new_vec));
-        self
+            // each construct takes the position of its first plausible value column, all other plausible value columns are dropped
+            let dropped_columns : Vec<usize> = plausible_values.iter().flat_map(|pvs| pvs.iter().skip(1).cloned()).collect();
+            for i in 0..number_plausible_values {
+                let mut columns : Vec<DVector<f64>> = Vec::new();
+                for c in (0..mat.ncols()).filter(|c| !dropped_columns.contains(c)) {
+                    match plausible_values.iter().find(|pvs| pvs[0] == c) {
+                        Some(pvs) => columns.push(mat.column(pvs[i]).clone_owned()),
+                        None => columns.push(mat.column(c).clone_owned()),
+                    }
+                }
+                new_vec.push(DMatrix::from_columns(&columns));
+            }
+        }
     }
 
-    pub fn set_weights(&mut self, wgt: &DVector<f64>) -> &mut Self {
-        self.wgt = Some(Rc::new(wgt.clone()));
-        self
-    }
+    Ok(new_vec)
+}
 
-    pub fn with_replicate_weights(&mut self, replicate_weights: &DMatrix<f64>) -> &mut Self {
-        self.repwgts = Some(Rc::new(replicate_weights.clone()));
-        self
-    }
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlausibleValues {
+    construct: String,
+    variable_names: Vec<String>,
+}
 
-    pub fn set_variance_adjustment_factor(&mut self, variance_adjustment_factor: f64) -> &mut Self {
-        self.variance_adjustment_factor = variance_adjustment_factor;
-        self
+impl PlausibleValues {
+    pub fn new(construct: &str, variable_names: &[&str]) -> PlausibleValues {
+        PlausibleValues {
+            construct: construct.to_string(),
+            variable_names: variable_names.iter().map(|name| name.to_string()).collect(),
+        }
     }
+}
 
-    pub fn mean(&mut self) -> &mut Self {
-        self.estimate_name = Some("mean".to_string());
-        self.estimate = Some(estimates::mean);
-        self
+// plausible values named after their construct with a two digit number as in IEA studies, e.g. ASRREA01 to ASRREA05
+pub fn pv(construct: &str, number_plausible_values: usize) -> PlausibleValues {
+    PlausibleValues {
+        construct: construct.to_string(),
+        variable_names: (1..=number_plausible_values).map(|i| format!("{}{:02}", construct, i)).collect(),
     }
+}
 
-    pub fn group_by(&mut self, data: Imputation) -> &mut Self {
-        let mut new_vec : Vec<DMatrix<f64>> = Vec::new();
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupKey {
+    key: Vec<String>,
+    variable_names: Vec<String>,
+    values: Vec<f64>,
+    labels: Vec<String>,
+    compared_to: Option<Box<GroupKey>>,
+}
 
-        match data {
-            Imputation::Yes(&ref vec) => {
-                for &mat in vec.iter() {
-                    new_vec.push(mat.clone());
-                }
-            }
-            Imputation::No(&ref mat) => {
-                new_vec.push(mat.clone());
-            }
+impl GroupKey {
+    pub fn overall() -> GroupKey {
+        GroupKey {
+            key: vec!["overall".to_string()],
+            variable_names: Vec::new(),
+            values: Vec::new(),
+            labels: Vec::new(),
+            compared_to: None,
         }
-
-        self.groups = Some(Rc::new(new_vec));
-        self
     }
 
-    fn prepare_missing_weights(&mut self) -> Result<(), Box<dyn Error>> {
-        if self.x.is_none() || self.x.as_ref().unwrap().deref().len() == 0 {
-            return Err(Box::new(MissingElementError::new("data")))
-        }
-
-        let ncases = self.x.as_ref().unwrap().deref()[0].nrows();
+    pub fn group(variable_names: Vec<String>, values: Vec<f64>, labels: Vec<String>) -> GroupKey {
+        assert_eq!(variable_names.len(), values.len(), "unequal number of variable names and values in group");
+        assert_eq!(values.len(), labels.len(), "unequal number of values and labels in group");
 
-        if self.wgt.is_none() {
-            self.wgt = Some(Rc::new(DVector::<f64>::from_element(ncases, 1.0)));
+        GroupKey {
+            key: labels.clone(),
+            variable_names,
+            values,
+            labels,
+            compared_to: None,
         }
+    }
 
-        if self.repwgts.is_none() {
-            self.repwgts = Some(Rc::new(DMatrix::<f64>::from_row_slice(ncases, 0, &[])));
+    pub fn comparison(minuend: &GroupKey, subtrahend: &GroupKey) -> GroupKey {
+        let mut key = minuend.key.clone();
+        key.push("vs".to_string());
+        key.extend(subtrahend.key.iter().cloned());
+
+        GroupKey {
+            key,
+            variable_names: minuend.variable_names.clone(),
+            values: minuend.values.clone(),
+            labels: minuend.labels.clone(),
+            compared_to: Some(Box::new(subtrahend.clone())),
         }
-
-        Ok(())
     }
 
-    fn prepare_for_calculate_overall(&self)
-        -> Result<(HashSet<Vec<String>>, HashMap<Vec<String>, Vec<&DMatrix<f64>>>, HashMap<Vec<String>, Vec<&DVector<f64>>>, HashMap<Vec<String>, Vec<&DMatrix<f64>>>), Box<dyn Error>>
-    {
-        let mut keys : HashSet<Vec<String>> = HashSet::new();
-        let mut x_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>> = HashMap::new();
-        let mut wgt_split : HashMap<Vec<String>, Vec<&DVector<f64>>> = HashMap::new();
-        let mut repwgt_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>> = HashMap::new();
-
-        keys.insert(vec!["overall".to_string()]);
+    pub fn key(&self) -> &Vec<String> {
+        &self.key
+    }
 
-        let mut x : Vec<&DMatrix<f64>> = Vec::new();
-        for mat in self.x.as_ref().unwrap().deref() {
-            x.push(mat);
-        }
-        let ncases = x[0].nrows();
+    pub fn variable_names(&self) -> &Vec<String> {
+        &self.variable_names
+    }
 
-        x_split.insert(vec!["overall".to_string()], x);
+    pub fn values(&self) -> &Vec<f64> {
+        &self.values
+    }
 
-        if ncases != self.wgt.as_ref().unwrap().nrows() {
-            return Err(Box::new(InconsistencyError::new("unequal number of rows for data and weights")))
-        }
-        wgt_split.insert(vec!["overall".to_string()], vec![self.wgt.as_ref().unwrap().deref()]);
+    pub fn labels(&self) -> &Vec<String> {
+        &self.labels
+    }
 
-        if ncases != self.repwgts.as_ref().unwrap().nrows() {
-            return Err(Box::new(InconsistencyError::new("unequal number of rows for data and replicate weights")))
-        }
-        repwgt_split.insert(vec!["overall".to_string()], vec![self.repwgts.as_ref().unwrap().deref()]);
+    pub fn compared_to(&self) -> Option<&GroupKey> {
+        self.compared_to.as_deref()
+    }
 
-        Ok((keys, x_split, wgt_split, repwgt_split))
+    pub fn is_overall(&self) -> bool {
+        self.variable_names.is_empty() && self.compared_to.is_none()
     }
+}
 
-    fn prepare_for_calculate_group_by(&self)
-        -> Result<(HashSet<Vec<String>>, HashMap<Vec<String>, Vec<DMatrix<f64>>>, HashMap<Vec<String>, Vec<DVector<f64>>>, HashMap<Vec<String>, Vec<DMatrix<f64>>>), Box<dyn Error>>
-    {
-        let mut keys : HashSet<Vec<String>> = HashSet::new();
-        let mut x_split : HashMap<Vec<String>, Vec<DMatrix<f64>>> = HashMap::new();
-        let mut wgt_split : HashMap<Vec<String>, Vec<DVector<f64>>> = HashMap::new();
-        let mut repwgt_split : HashMap<Vec<String>, Vec<DMatrix<f64>>> = HashMap::new();
+// keys are identified and ordered by their group values, the labels only tell apart values without a numeric representation
+impl PartialEq for GroupKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
 
-        let groups = self.groups.as_ref().unwrap().deref();
+impl Eq for GroupKey {}
 
-        if groups.len() > 1 && groups.len() != self.x.as_ref().unwrap().deref().len() {
-            return Err(Box::new(InconsistencyError::new("number of data sets does not match number of sets with grouping columns")))
-        }
+impl PartialOrd for GroupKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-        let multiple_imputation_groups = groups.len() > 1;
+impl Ord for GroupKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.values.iter().zip(other.values.iter())
+            .map(|(value, other_value)| value.total_cmp(other_value))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(self.values.len().cmp(&other.values.len()))
+            .then_with(|| self.compared_to.cmp(&other.compared_to))
+            .then_with(|| self.key.cmp(&other.key))
+    }
+}
 
-        let unique_combinations = groups.first().unwrap().get_keys();
-        for combination in unique_combinations {
-            keys.insert(combination);
+impl Hash for GroupKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for value in self.values.iter() {
+            value.to_bits().hash(state);
         }
+        self.compared_to.hash(state);
+        self.key.hash(state);
+    }
+}
 
-        for (i, mat) in self.x.as_ref().unwrap().deref().iter().enumerate() {
-            let mat_split = mat.split_by(if multiple_imputation_groups { &groups[i] }  else { &groups[0] });
+// the result of the group with the given key, e.g. ["overall"], ["female", "1"] or ["1", "vs", "2"]
+pub fn result_for<'a, T>(results: &'a BTreeMap<GroupKey, T>, key: &[&str]) -> Option<&'a T> {
+    results.iter().find(|(group_key, _)| group_key.key.iter().map(|label| label.as_str()).eq(key.iter().copied())).map(|(_, result)| result)
+}
 
-            match i {
-                0 => {
-                    for (key, mat0) in mat_split {
-                        x_split.insert(key, vec![mat0]);
-                    }
-                }
-                _ => {
-                    for (key, mat0) in mat_split {
-                        x_split.get_mut(&key).unwrap().push(mat0);
-                    }
-                }
-            }
-        }
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
 
-        for (i, groups0) in groups.iter().enumerate() {
-            let vec_split = self.wgt.as_ref().unwrap().deref().split_by(groups0);
-            let mat_split = self.repwgts.as_ref().unwrap().deref().split_by(groups0);
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EstimateSpecification {
+    Mean,
+    StandardizedMean,
+    Frequencies(estimates::FrequenciesOptions),
+    Agreement,
+    Crosstab,
+    Range,
+    Missingness,
+    Total,
+    Correlation,
+    LinearRegression(estimates::LinregOptions),
+    LinearRegressionOnColumns(Column, Vec<Column>),
+    PoissonRegression,
+    EffectSize,
+    TTest(f64),
+    TwoSampleTTest,
+    EtaSquared,
+    MantelHaenszelDif,
+    OddsRatio,
+    Gini,
+    Concentration(usize),
+    PercentileShares(Vec<(f64, f64)>),
+    Theil,
+    Atkinson(f64),
+    PercentileRanks(Vec<f64>),
+    BinnedFrequencies(Vec<f64>),
+    BenchmarkLevels(Vec<f64>),
+    EqualWidthBinnedFrequencies(usize),
+    PolychoricCorrelation,
+    PolyserialCorrelation,
+    PrincipalComponents,
+    ProportionsAbove(Vec<f64>),
+}
 
-            match i {
-                0 => {
-                    for (key, vec0) in vec_split {
-                        wgt_split.insert(key, vec![vec0]);
-                    }
-                    for (key, mat0) in mat_split {
-                        repwgt_split.insert(key, vec![mat0]);
-                    }
-                }
-                _ => {
-                    for (key, vec0) in vec_split {
-                        wgt_split.get_mut(&key).unwrap().push(vec0);
-                    }
-                    for (key, mat0) in mat_split {
-                        repwgt_split.get_mut(&key).unwrap().push(mat0);
-                    }
-                }
-            }
+impl EstimateSpecification {
+    pub fn name(&self) -> &str {
+        match self {
+            EstimateSpecification::Mean => "mean",
+            EstimateSpecification::StandardizedMean => "standardized mean",
+            EstimateSpecification::Frequencies(_) => "frequencies",
+            EstimateSpecification::Agreement => "agreement",
+            EstimateSpecification::Crosstab => "crosstab",
+            EstimateSpecification::Range => "range",
+            EstimateSpecification::Missingness => "missingness",
+            EstimateSpecification::Total => "total",
+            EstimateSpecification::Correlation => "correlation",
+            EstimateSpecification::LinearRegression(_) | EstimateSpecification::LinearRegressionOnColumns(_, _) => "linear regression",
+            EstimateSpecification::PoissonRegression => "poisson regression",
+            EstimateSpecification::EffectSize => "effect size",
+            EstimateSpecification::TTest(_) => "t test",
+            EstimateSpecification::TwoSampleTTest => "two sample t test",
+            EstimateSpecification::EtaSquared => "eta squared",
+            EstimateSpecification::MantelHaenszelDif => "mantel haenszel dif",
+            EstimateSpecification::OddsRatio => "odds ratio",
+            EstimateSpecification::Gini => "gini",
+            EstimateSpecification::Concentration(_) => "concentration",
+            EstimateSpecification::PercentileShares(_) => "percentile shares",
+            EstimateSpecification::Theil => "theil",
+            EstimateSpecification::Atkinson(_) => "atkinson",
+            EstimateSpecification::PercentileRanks(_) => "percentile ranks",
+            EstimateSpecification::BinnedFrequencies(_) => "binned frequencies",
+            EstimateSpecification::BenchmarkLevels(_) => "benchmark levels",
+            EstimateSpecification::EqualWidthBinnedFrequencies(_) => "binned frequencies",
+            EstimateSpecification::PolychoricCorrelation => "polychoric correlation",
+            EstimateSpecification::PolyserialCorrelation => "polyserial correlation",
+            EstimateSpecification::PrincipalComponents => "principal components",
+            EstimateSpecification::ProportionsAbove(_) => "proportions above",
         }
+    }
 
-        Ok((keys, x_split, wgt_split, repwgt_split))
+    pub(crate) fn estimate_function(&self, variable_names: &[String]) -> Arc<estimates::EstimateFunction> {
+        let variable_names = variable_names.to_vec();
+        match self.clone() {
+            // the reference values of standardized means depend on the data and are prepared by the analysis
+            EstimateSpecification::Mean | EstimateSpecification::StandardizedMean => Arc::new(move |x, wgt| estimates::named_mean(x, wgt, &variable_names)),
+            EstimateSpecification::Frequencies(options) => Arc::new(move |x, wgt| estimates::named_frequencies(x, wgt, &options, &variable_names)),
+            EstimateSpecification::Agreement => Arc::new(estimates::agreement),
+            EstimateSpecification::Crosstab => Arc::new(move |x, wgt| estimates::named_crosstab(x, wgt, &variable_names)),
+            EstimateSpecification::Range => Arc::new(move |x, wgt| estimates::named_range(x, wgt, &variable_names)),
+            EstimateSpecification::Missingness => Arc::new(move |x, wgt| estimates::named_missingness(x, wgt, &variable_names)),
+            EstimateSpecification::Total => Arc::new(move |x, wgt| estimates::named_total(x, wgt, &variable_names)),
+            EstimateSpecification::Correlation => Arc::new(move |x, wgt| estimates::named_correlation(x, wgt, &variable_names)),
+            EstimateSpecification::LinearRegression(options) => Arc::new(move |x, wgt| estimates::named_linreg(x, wgt, &options, &variable_names)),
+            EstimateSpecification::LinearRegressionOnColumns(_, _) => unreachable!("columns of a linear regression are resolved by the analysis"),
+            EstimateSpecification::PoissonRegression => Arc::new(move |x, wgt| estimates::named_poisson_regression(x, wgt, &variable_names)),
+            EstimateSpecification::EffectSize => Arc::new(move |x, wgt| estimates::named_effect_size(x, wgt, &variable_names)),
+            EstimateSpecification::TTest(mu) => Arc::new(move |x, wgt| estimates::named_t_test(x, wgt, mu, &variable_names)),
+            EstimateSpecification::TwoSampleTTest => Arc::new(move |x, wgt| estimates::named_two_sample_t_test(x, wgt, &variable_names)),
+            EstimateSpecification::EtaSquared => Arc::new(move |x, wgt| estimates::named_eta_squared(x, wgt, &variable_names)),
+            EstimateSpecification::MantelHaenszelDif => Arc::new(move |x, wgt| estimates::named_mantel_haenszel_dif(x, wgt, &variable_names)),
+            EstimateSpecification::OddsRatio => Arc::new(move |x, wgt| estimates::named_odds_ratio(x, wgt, &variable_names)),
+            EstimateSpecification::Gini => Arc::new(move |x, wgt| estimates::named_gini(x, wgt, &variable_names)),
+            EstimateSpecification::Concentration(number_ordinates) => Arc::new(move |x, wgt| estimates::concentration(x, wgt, number_ordinates)),
+            EstimateSpecification::PercentileShares(ranges) => Arc::new(move |x, wgt| estimates::named_percentile_shares(x, wgt, &ranges, &variable_names)),
+            EstimateSpecification::Theil => Arc::new(move |x, wgt| estimates::named_theil(x, wgt, &variable_names)),
+            EstimateSpecification::Atkinson(epsilon) => Arc::new(move |x, wgt| estimates::named_atkinson(x, wgt, epsilon, &variable_names)),
+            EstimateSpecification::PercentileRanks(values) => Arc::new(move |x, wgt| estimates::named_percentile_ranks(x, wgt, &values, &variable_names)),
+            EstimateSpecification::BinnedFrequencies(breaks) => Arc::new(move |x, wgt| estimates::named_binned_frequencies(x, wgt, &breaks, &variable_names)),
+            EstimateSpecification::BenchmarkLevels(cutpoints) => Arc::new(move |x, wgt| estimates::named_benchmark_levels(x, wgt, &cutpoints, &variable_names)),
+            EstimateSpecification::EqualWidthBinnedFrequencies(number_bins) => Arc::new(move |x, wgt| estimates::named_equal_width_binned_frequencies(x, wgt, number_bins, &variable_names)),
+            EstimateSpecification::PolychoricCorrelation => Arc::new(move |x, wgt| estimates::named_polychoric_correlation(x, wgt, &variable_names)),
+            EstimateSpecification::PolyserialCorrelation => Arc::new(move |x, wgt| estimates::named_polyserial_correlation(x, wgt, &variable_names)),
+            EstimateSpecification::PrincipalComponents => Arc::new(move |x, wgt| estimates::named_principal_components(x, wgt, &variable_names)),
+            EstimateSpecification::ProportionsAbove(cutpoints) => Arc::new(move |x, wgt| estimates::named_proportions_above(x, wgt, &cutpoints, &variable_names)),
+        }
     }
+}
 
-    pub fn calculate(&mut self) -> Result<HashMap<Vec<String>, ReplicatedEstimates>, Box<dyn Error>> {
-        if self.estimate.is_none() {
-            return Err(Box::new(MissingElementError::new("estimate")))
+impl From<Estimate> for EstimateSpecification {
+    fn from(estimate: Estimate) -> Self {
+        match estimate {
+            Estimate::Mean => EstimateSpecification::Mean,
+            Estimate::StandardizedMean => EstimateSpecification::StandardizedMean,
+            Estimate::Total => EstimateSpecification::Total,
+            Estimate::Missingness => EstimateSpecification::Missingness,
+            Estimate::Frequencies { percentages } => EstimateSpecification::Frequencies(estimates::FrequenciesOptions { percentages, expected_categories: None }),
+            Estimate::Crosstab => EstimateSpecification::Crosstab,
+            Estimate::Agreement => EstimateSpecification::Agreement,
+            Estimate::Range => EstimateSpecification::Range,
+            Estimate::Correlation => EstimateSpecification::Correlation,
+            Estimate::LinearRegression { residual_diagnostics, number_residual_bins } => EstimateSpecification::LinearRegression(estimates::LinregOptions {
+                residual_diagnostics,
+                number_residual_bins: number_residual_bins as usize,
+                ..Default::default()
+            }),
+            Estimate::PoissonRegression => EstimateSpecification::PoissonRegression,
+            Estimate::EffectSize => EstimateSpecification::EffectSize,
+            Estimate::TTest { mu } => EstimateSpecification::TTest(mu),
+            Estimate::TwoSampleTTest => EstimateSpecification::TwoSampleTTest,
+            Estimate::OddsRatio => EstimateSpecification::OddsRatio,
+            Estimate::EtaSquared => EstimateSpecification::EtaSquared,
+            Estimate::MantelHaenszelDif => EstimateSpecification::MantelHaenszelDif,
+            Estimate::Gini => EstimateSpecification::Gini,
+            Estimate::Concentration { number_ordinates } => EstimateSpecification::Concentration(number_ordinates as usize),
+            Estimate::Theil => EstimateSpecification::Theil,
+            Estimate::Atkinson { epsilon } => EstimateSpecification::Atkinson(epsilon),
+            Estimate::PercentileRanks { values } => EstimateSpecification::PercentileRanks(values),
+            Estimate::BinnedFrequencies { breaks } => EstimateSpecification::BinnedFrequencies(breaks),
+            Estimate::EqualWidthBinnedFrequencies { number_bins } => EstimateSpecification::EqualWidthBinnedFrequencies(number_bins as usize),
+            Estimate::BenchmarkLevels { cutpoints } => EstimateSpecification::BenchmarkLevels(cutpoints),
+            Estimate::ProportionsAbove { cutpoints } => EstimateSpecification::ProportionsAbove(cutpoints),
+            Estimate::PolychoricCorrelation => EstimateSpecification::PolychoricCorrelation,
+            Estimate::PolyserialCorrelation => EstimateSpecification::PolyserialCorrelation,
+            Estimate::PrincipalComponents => EstimateSpecification::PrincipalComponents,
         }
+    }
+}
 
-        self.prepare_missing_weights()?;
+type CachedThreadPool = (usize, Arc<rayon::ThreadPool>);
 
-        let keys : HashSet<Vec<String>>;
+type PreparedWeights = (Arc<DVector<f64>>, Arc<DMatrix<f64>>);
 
-        let x_storage : HashMap<Vec<String>, Vec<DMatrix<f64>>>;
-        let wgt_storage : HashMap<Vec<String>, Vec<DVector<f64>>>;
-        let repwgt_storage : HashMap<Vec<String>, Vec<DMatrix<f64>>>;
+type GroupSplit = (HashSet<Vec<String>>, HashMap<Vec<String>, Vec<DMatrix<f64>>>, HashMap<Vec<String>, Vec<DVector<f64>>>, HashMap<Vec<String>, Vec<DMatrix<f64>>>);
 
-        let mut x_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>>;
-        let mut wgt_split : HashMap<Vec<String>, Vec<&DVector<f64>>>;
-        let mut repwgt_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>>;
+// the split is only valid for the very same data, weights and groups, which are kept alive so that their identity cannot be reused
+struct CachedSplit {
+    x: Arc<Vec<DMatrix<f64>>>,
+    wgt: Arc<DVector<f64>>,
+    repwgts: Arc<DMatrix<f64>>,
+    weight_preparation: WeightPreparation,
+    groups: Arc<Vec<DMatrix<f64>>>,
+    split: Arc<GroupSplit>,
+}
 
-        match self.groups {
-            Some(ref groups) if groups.deref().len() > 0 => {
-                (keys, x_storage, wgt_storage, repwgt_storage) = self.prepare_for_calculate_group_by()?;
+// everything applied to the configured weights before calculating
+#[derive(Clone, PartialEq)]
+struct WeightPreparation {
+    nan_replicate_weight_policy: NanReplicateWeightPolicy,
+    normalization: Option<To>,
+    trimming: Option<f64>,
+    senate_weights: Option<(DVector<f64>, f64)>,
+}
 
-                x_split = HashMap::new();
-                for (key, data) in x_storage.iter() {
-                    let x : Vec<&DMatrix<f64>> = data.iter().map(|mat| mat).collect();
-                    x_split.insert(key.clone(), x);
-                }
+#[derive(Clone)]
+pub struct Analysis {
+    x: Option<Arc<Vec<DMatrix<f64>>>>,
+    wgt: Option<Arc<DVector<f64>>>,
+    repwgts: Option<Arc<DMatrix<f64>>>,
+    variance_adjustment_factor: f64,
+    variance_method: Option<VarianceMethod>,
+    confidence_level: f64,
+    estimate: Option<EstimateSpecification>,
+    groups: Option<Arc<Vec<DMatrix<f64>>>>,
+    pairwise_group_differences: bool,
+    number_threads: usize,
+    nan_replicate_weight_policy: NanReplicateWeightPolicy,
+    suppression: Option<(usize, f64)>,
+    design_effects: bool,
+    unweighted_estimates: bool,
+    monitor: ReplicationMonitor,
+    weight_normalization: Option<To>,
+    weight_trimming: Option<f64>,
+    variable_names: Option<Vec<String>>,
+    group_labels: HashMap<usize, Vec<(f64, String)>>,
+    group_names: Option<Vec<String>>,
+    previous_estimates: Vec<EstimateSpecification>,
+    marginals: bool,
+    overall_comparisons: bool,
+    expected_categories: Option<Vec<f64>>,
+    missing_strategy: MissingStrategy,
+    senate_weights: Option<(DVector<f64>, f64)>,
+    group_parallelism: usize,
+    quantile_groups: Option<(usize, usize)>,
+    metadata: BTreeMap<String, String>,
+    // invalid data, grouping columns or designs are only reported when calculating
+    data_error: Option<String>,
+    groups_error: Option<String>,
+    design_error: Option<String>,
+    split_cache: Arc<Mutex<Option<CachedSplit>>>,
+    // built once for the thread budget it was built with
+    thread_pool: Arc<Mutex<Option<CachedThreadPool>>>,
+}
 
-                wgt_split = HashMap::new();
-                for (key, data) in wgt_storage.iter() {
-                    let wgt : Vec<&DVector<f64>> = data.iter().map(|wgt| wgt).collect();
-                    wgt_split.insert(key.clone(), wgt);
-                }
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisSummary {
+    pub estimates: Vec<String>,
+    pub number_datasets: usize,
+    pub number_cases: Option<usize>,
+    pub number_weights: Option<usize>,
+    pub weight_sum: Option<f64>,
+    pub number_replicates: Option<usize>,
+    pub variance_adjustment_factor: f64,
+    pub number_grouping_columns: Option<usize>,
+    pub group_names: Option<Vec<String>>,
+}
 
-                repwgt_split = HashMap::new();
-                for (key, data) in repwgt_storage.iter() {
-                    let repwgt : Vec<&DMatrix<f64>> = data.iter().map(|repwgt| repwgt).collect();
-                    repwgt_split.insert(key.clone(), repwgt);
-                }
-            }
-            _ => {
-                (keys, x_split, wgt_split, repwgt_split) = self.prepare_for_calculate_overall()?
-            }
-        }
+#[derive(Serialize, Deserialize)]
+pub struct AnalysisState {
+    x: Option<Vec<DMatrix<f64>>>,
+    wgt: Option<DVector<f64>>,
+    repwgts: Option<DMatrix<f64>>,
+    variance_adjustment_factor: f64,
+    variance_method: Option<VarianceMethod>,
+    confidence_level: f64,
+    estimate: Option<EstimateSpecification>,
+    groups: Option<Vec<DMatrix<f64>>>,
+    pairwise_group_differences: bool,
+    number_threads: usize,
+    nan_replicate_weight_policy: NanReplicateWeightPolicy,
+    suppression: Option<(usize, f64)>,
+    design_effects: bool,
+    unweighted_estimates: bool,
+    weight_normalization: Option<To>,
+    weight_trimming: Option<f64>,
+    variable_names: Option<Vec<String>>,
+    group_labels: HashMap<usize, Vec<(f64, String)>>,
+    group_names: Option<Vec<String>>,
+    previous_estimates: Vec<EstimateSpecification>,
+    marginals: bool,
+    overall_comparisons: bool,
+    expected_categories: Option<Vec<f64>>,
+    missing_strategy: MissingStrategy,
+    senate_weights: Option<(DVector<f64>, f64)>,
+    group_parallelism: usize,
+    quantile_groups: Option<(usize, usize)>,
+    metadata: BTreeMap<String, String>,
+}
+
+pub fn analysis() -> Analysis {
+    Analysis {
+        x: None,
+        wgt: None,
+        repwgts: None,
+        variance_adjustment_factor: 1.0,
+        variance_method: None,
+        confidence_level: 0.95,
+        estimate: None,
+        groups: None,
+        pairwise_group_differences: false,
+        number_threads: 0,
+        nan_replicate_weight_policy: NanReplicateWeightPolicy::Error,
+        suppression: None,
+        design_effects: false,
+        unweighted_estimates: false,
+        monitor: ReplicationMonitor::default(),
+        weight_normalization: None,
+        weight_trimming: None,
+        variable_names: None,
+        group_labels: HashMap::new(),
+        group_names: None,
+        previous_estimates: Vec::new(),
+        marginals: false,
+        overall_comparisons: false,
+        expected_categories: None,
+        missing_strategy: MissingStrategy::Pairwise,
+        senate_weights: None,
+        group_parallelism: 1,
+        quantile_groups: None,
+        metadata: BTreeMap::new(),
+        data_error: None,
+        groups_error: None,
+        design_error: None,
+        split_cache: Arc::new(Mutex::new(None)),
+        thread_pool: Arc::new(Mutex::new(None)),
+    }
+}
 
-        let mut results : HashMap<Vec<String>, ReplicatedEstimates> = HashMap::new();
+pub fn analysis_from_state(state: AnalysisState) -> Analysis {
+    Analysis {
+        x: state.x.map(Arc::new),
+        wgt: state.wgt.map(Arc::new),
+        repwgts: state.repwgts.map(Arc::new),
+        variance_adjustment_factor: state.variance_adjustment_factor,
+        variance_method: state.variance_method,
+        confidence_level: state.confidence_level,
+        estimate: state.estimate,
+        groups: state.groups.map(Arc::new),
+        pairwise_group_differences: state.pairwise_group_differences,
+        number_threads: state.number_threads,
+        nan_replicate_weight_policy: state.nan_replicate_weight_policy,
+        suppression: state.suppression,
+        design_effects: state.design_effects,
+        unweighted_estimates: state.unweighted_estimates,
+        monitor: ReplicationMonitor::default(),
+        weight_normalization: state.weight_normalization,
+        weight_trimming: state.weight_trimming,
+        variable_names: state.variable_names,
+        group_labels: state.group_labels,
+        group_names: state.group_names,
+        previous_estimates: state.previous_estimates,
+        marginals: state.marginals,
+        overall_comparisons: state.overall_comparisons,
+        expected_categories: state.expected_categories,
+        missing_strategy: state.missing_strategy,
+        senate_weights: state.senate_weights,
+        group_parallelism: state.group_parallelism,
+        quantile_groups: state.quantile_groups,
+        metadata: state.metadata,
+        data_error: None,
+        groups_error: None,
+        design_error: None,
+        split_cache: Arc::new(Mutex::new(None)),
+        thread_pool: Arc::new(Mutex::new(None)),
+    }
+}
 
-        for key in keys {
-            let result = replicate_estimates(
-                self.estimate.as_ref().unwrap().clone(),
-                x_split.get(&key).unwrap(),
-                wgt_split.get(&key).unwrap(),
-                repwgt_split.get(&key).unwrap(),
-                self.variance_adjustment_factor,
-            );
+pub fn restore_analysis(path: &str) -> Result<Analysis, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let state : AnalysisState = rmp_serde::decode::from_read(file)?;
+    Ok(analysis_from_state(state))
+}
 
-            results.insert(key, result);
+impl Analysis {
+    pub fn for_data(&mut self, data: Imputation) -> &mut Self {
+        match imputations_from(data) {
+            Ok(x) => {
+                self.x = Some(Arc::new(x));
+                self.data_error = None;
+            }
+            Err(err) => {
+                self.x = None;
+                self.data_error = Some(err.to_string());
+            }
         }
+        self
+    }
 
-        Ok(results)
+    // the columns of the data are named by the variable names, which are reduced to one name per construct
+    pub fn for_plausible_values(&mut self, data: &DMatrix<f64>, plausible_values: &[PlausibleValues]) -> &mut Self {
+        match self.resolve_plausible_values(data, plausible_values) {
+            Ok((x, variable_names)) => {
+                self.x = Some(Arc::new(x));
+                self.variable_names = Some(variable_names);
+                self.data_error = None;
+            }
+            Err(err) => {
+                self.x = None;
+                self.data_error = Some(err.to_string());
+            }
+        }
+        self
     }
 
-    pub fn summary(&self) -> String {
-        let estimate_name = self.estimate_name.as_ref().unwrap_or(&"none".to_string()).clone();
+    fn resolve_plausible_values(&self, data: &DMatrix<f64>, plausible_values: &[PlausibleValues]) -> Result<(Vec<DMatrix<f64>>, Vec<String>), InconsistencyError> {
+        let variable_names = self.variable_names.as_ref().ok_or(InconsistencyError::new("plausible values can only be declared by name with variable names"))?;
+        if variable_names.len() != data.ncols() {
+            return Err(InconsistencyError::new("number of variable names does not match number of columns"))
+        }
 
-        let group_info = match self.groups.as_ref() {
-            None => { "".to_string() }
-            Some(groups) => {
-                let group_data = groups.as_ref();
-                format!(" by {} grouping columns", group_data[0].ncols())
+        let columns = plausible_values.iter().map(|pvs| {
+            pvs.variable_names.iter()
+                .map(|name| variable_names.iter().position(|variable_name| variable_name == name).ok_or(InconsistencyError::new(&format!("unknown variable name {}", name))))
+                .collect::<Result<Vec<usize>, InconsistencyError>>()
+        }).collect::<Result<Vec<Vec<usize>>, InconsistencyError>>()?;
+        let x = imputations_from(Imputation::PlausibleValues(data, &columns))?;
+
+        // as in the data, each construct takes the position of its first plausible value
+        let dropped_columns : Vec<usize> = columns.iter().flat_map(|pvs| pvs.iter().skip(1).cloned()).collect();
+        let names = (0..data.ncols()).filter(|c| !dropped_columns.contains(c)).map(|c| {
+            match columns.iter().position(|pvs| pvs[0] == c) {
+                Some(construct) => plausible_values[construct].construct.clone(),
+                None => variable_names[c].clone(),
             }
-        };
+        }).collect();
 
-        let data_info = if self.x.is_none() {
-            "no data".to_string()
-        } else {
-            let data = self.x.as_ref().unwrap().deref();
-            format!("{} datasets with {} cases", data.len(), data[0].nrows())
-        };
+        Ok((x, names))
+    }
 
-        let wgt_info = if self.wgt.is_none() {
-            "wgt missing".to_string()
-        } else {
-            let wgts = self.wgt.as_ref().unwrap().deref();
-            format!("{} weights of sum {}", wgts.len(), wgts.sum())
-        };
+    pub fn for_data_owned(&mut self, data: Vec<DMatrix<f64>>) -> &mut Self {
+        self.x = Some(Arc::new(data));
+        self
+    }
 
-        let factor_info = if self.variance_adjustment_factor == 1.0 {
-            "".to_string()
-        } else {
-            format!(", factor {}", self.variance_adjustment_factor)
-        };
+    pub fn for_data_f32(&mut self, data: Vec<DMatrix<f32>>) -> &mut Self {
+        self.x = Some(Arc::new(data_preparation::widen(data)));
+        self
+    }
 
-        let repwgt_info = if self.repwgts.is_none() {
-            "no replicate weights".to_string()
-        } else {
-            let repwgts = self.repwgts.as_ref().unwrap().deref();
-            format!("{} replicate weights{}", repwgts.ncols(), factor_info)
-        };
+    // shared data is never copied, neither here nor when cloning the analysis
+    pub fn for_data_shared(&mut self, data: Arc<Vec<DMatrix<f64>>>) -> &mut Self {
+        self.x = Some(data);
+        self
+    }
 
-        estimate_name + &group_info +  " (" + &data_info + "; " + &wgt_info + "; " + &repwgt_info + ")"
+    pub fn set_variable_names(&mut self, variable_names: &[&str]) -> &mut Self {
+        self.variable_names = Some(variable_names.iter().map(|name| name.to_string()).collect());
+        self
     }
 
-    pub fn copy(&self) -> Analysis {
-        Analysis {
-            x: self.x.clone(),
-            wgt: self.wgt.clone(),
-            repwgts: self.repwgts.clone(),
-            variance_adjustment_factor: self.variance_adjustment_factor,
-            estimate_name: self.estimate_name.clone(),
-            estimate: self.estimate.clone(),
-            groups: self.groups.clone(),
-        }
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> &mut Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
     }
-}
 
-#[cfg(test)]
+    pub fn set_weights(&mut self, wgt: &DVector<f64>) -> &mut Self {
+        self.wgt = Some(Arc::new(wgt.clone()));
+        self
+    }
+
+    pub fn with_replicate_weights(&mut self, replicate_weights: &DMatrix<f64>) -> &mut Self {
+        self.repwgts = Some(Arc::new(replicate_weights.clone()));
+        self
+    }
+
+    pub fn append_data(&mut self, data: Imputation) -> Result<&mut Self, Box<dyn Error>> {
+        self.x = Some(Arc::new(append_imputations(self.x.as_ref(), imputations_from(data)?, "data")?));
+        Ok(self)
+    }
+
+    pub fn append_groups(&mut self, data: Imputation) -> Result<&mut Self, Box<dyn Error>> {
+        self.groups = Some(Arc::new(append_imputations(self.groups.as_ref(), imputations_from(data)?, "grouping columns")?));
+        Ok(self)
+    }
+
+    pub fn append_weights(&mut self, wgt: &DVector<f64>) -> Result<&mut Self, Box<dyn Error>> {
+        let new_wgt = match self.wgt.as_ref() {
+            None => wgt.clone(),
+            Some(existing) => DVector::from_iterator(existing.len() + wgt.len(), existing.iter().chain(wgt.iter()).cloned()),
+        };
+        self.wgt = Some(Arc::new(new_wgt));
+        Ok(self)
+    }
+
+    pub fn append_replicate_weights(&mut self, replicate_weights: &DMatrix<f64>) -> Result<&mut Self, Box<dyn Error>> {
+        let new_repwgts = match self.repwgts.as_ref() {
+            None => replicate_weights.clone(),
+            Some(existing) if existing.ncols() != replicate_weights.ncols() => {
+                return Err(Box::new(InconsistencyError::new("appended replicate weights differ in number of columns")))
+            }
+            Some(existing) => data_preparation::append_rows(existing, replicate_weights),
+        };
+        self.repwgts = Some(Arc::new(new_repwgts));
+        Ok(self)
+    }
+
+    pub fn with_brr_replicate_weights(&mut self, strata: &DVector<f64>, psus: &DVector<f64>) -> &mut Self {
+        // replicate weights are derived from the weights set so far, or from unit weights if there are none
+        let wgt = match self.wgt.as_ref() {
+            Some(wgt) => wgt.deref().clone(),
+            None => DVector::<f64>::from_element(strata.len(), 1.0),
+        };
+
+        if let Err(err) = design::check_design(&wgt, strata, psus, true) {
+            self.design_error = Some(InconsistencyError::new(&err).to_string());
+            return self;
+        }
+
+        self.repwgts = Some(Arc::new(design::brr_replicate_weights(&wgt, strata, psus)));
+        self.variance_method = Some(VarianceMethod::BRR);
+        self.design_error = None;
+        self
+    }
+
+    // JK1 treats the sample as a single stratum, the strata only tell apart psus numbered within strata
+    pub fn with_jackknife_design(&mut self, strata: &DVector<f64>, psus: &DVector<f64>, scheme: JackknifeScheme) -> &mut Self {
+        let wgt = match self.wgt.as_ref() {
+            Some(wgt) => wgt.deref().clone(),
+            None => DVector::<f64>::from_element(strata.len(), 1.0),
+        };
+
+        if let Err(err) = design::check_design(&wgt, strata, psus, scheme == JackknifeScheme::JK2) {
+            self.design_error = Some(InconsistencyError::new(&err).to_string());
+            return self;
+        }
+
+        self.repwgts = Some(Arc::new(design::jackknife_replicate_weights(&wgt, strata, psus, scheme)));
+        self.variance_method = Some(match scheme {
+            JackknifeScheme::JK1 => VarianceMethod::JK1,
+            JackknifeScheme::JK2 => VarianceMethod::JK2,
+        });
+        self.design_error = None;
+        self
+    }
+
+    // the strata and psus are taken out of the data set so far, the first dataset provides them for all imputations
+    pub fn with_jackknife_design_columns(&mut self, strata_column: usize, psu_column: usize, scheme: JackknifeScheme) -> &mut Self {
+        let x = match self.x.as_ref() {
+            Some(x) => Arc::clone(x),
+            None => {
+                self.design_error = Some(MissingElementError::new("data").to_string());
+                return self;
+            }
+        };
+        if strata_column == psu_column || strata_column.max(psu_column) >= x[0].ncols() {
+            self.design_error = Some(InconsistencyError::new("strata and psu columns have to be two different columns of the data").to_string());
+            return self;
+        }
+
+        let strata = x[0].column(strata_column).clone_owned();
+        let psus = x[0].column(psu_column).clone_owned();
+        self.with_jackknife_design(&strata, &psus, scheme);
+        if self.design_error.is_none() {
+            let mut design_columns = [strata_column, psu_column];
+            design_columns.sort();
+            self.x = Some(Arc::new(x.iter().map(|imputation| imputation.clone().remove_columns_at(&design_columns)).collect()));
+            if let Some(variable_names) = self.variable_names.as_mut().filter(|names| names.len() == x[0].ncols()) {
+                *variable_names = variable_names.iter().enumerate().filter(|(c, _)| !design_columns.contains(c)).map(|(_, name)| name.clone()).collect();
+            }
+        }
+        self
+    }
+
+    pub fn with_bootstrap(&mut self, number_replicates: usize, seed: u64) -> &mut Self {
+        // without weights, unit weights for the data set so far are used and each case is resampled on its own
+        let wgt = match (self.wgt.as_ref(), self.x.as_ref().and_then(|x| x.first())) {
+            (Some(wgt), _) => wgt.deref().clone(),
+            (None, Some(x)) => DVector::<f64>::from_element(x.nrows(), 1.0),
+            (None, None) => DVector::<f64>::zeros(0),
+        };
+        let strata = DVector::<f64>::from_element(wgt.len(), 1.0);
+        let psus = DVector::<f64>::from_fn(wgt.len(), |r, _| r as f64);
+
+        self.repwgts = Some(Arc::new(design::bootstrap_replicate_weights(&wgt, &strata, &psus, number_replicates, seed)));
+        self.variance_method = Some(VarianceMethod::Bootstrap(number_replicates));
+        self
+    }
+
+    pub fn set_variance_adjustment_factor(&mut self, variance_adjustment_factor: f64) -> &mut Self {
+        self.variance_adjustment_factor = variance_adjustment_factor;
+        self.variance_method = None;
+        self
+    }
+
+    pub fn set_variance_method(&mut self, variance_method: VarianceMethod) -> &mut Self {
+        self.variance_method = Some(variance_method);
+        self
+    }
+
+    fn current_variance_adjustment_factor(&self, number_replicates: usize) -> f64 {
+        match self.variance_method {
+            Some(variance_method) if self.repwgts.is_some() => variance_method.variance_adjustment_factor(number_replicates),
+            _ => self.variance_adjustment_factor,
+        }
+    }
+
+    fn check_variance_method(&self, number_replicates: usize) -> Result<(), Box<dyn Error>> {
+        match self.variance_method {
+            Some(VarianceMethod::Fay(k)) if !(0.0..1.0).contains(&k) => {
+                Err(Box::new(InconsistencyError::new("Fay coefficient has to be at least 0 and below 1")))
+            }
+            Some(VarianceMethod::Bootstrap(number_bootstrap_replicates)) if number_bootstrap_replicates != number_replicates => {
+                Err(Box::new(InconsistencyError::new("number of bootstrap replicates does not match replicate weights")))
+            }
+            _ => Ok(())
+        }
+    }
+
+    fn select_estimate(&mut self, estimate: EstimateSpecification) -> &mut Self {
+        self.estimate = Some(estimate);
+        self
+    }
+
+    pub fn set_estimate(&mut self, estimate: Estimate) -> &mut Self {
+        self.select_estimate(EstimateSpecification::from(estimate))
+    }
+
+    pub fn also(&mut self) -> &mut Self {
+        // the current estimate is kept, so that the next estimate is calculated alongside it in the same replication pass
+        if let Some(estimate) = self.estimate.take() {
+            self.previous_estimates.push(estimate);
+        }
+        self
+    }
+
+    pub fn set_expected_categories(&mut self, categories: &[f64]) -> &mut Self {
+        self.expected_categories = Some(categories.to_vec());
+        self
+    }
+
+    fn column_index(&self, column: &Column) -> Result<usize, Box<dyn Error>> {
+        let number_columns = self.x.as_ref().map(|x| x[0].ncols()).unwrap_or(0);
+        let index = match column {
+            Column::Index(index) => *index,
+            Column::Name(name) => {
+                let variable_names = self.variable_names.as_ref().ok_or(InconsistencyError::new("columns can only be selected by name with variable names"))?;
+                variable_names.iter().position(|variable_name| variable_name == name).ok_or(InconsistencyError::new(&format!("unknown variable name {}", name)))?
+            }
+        };
+
+        if index >= number_columns {
+            return Err(Box::new(InconsistencyError::new("selected column out of range")))
+        }
+        Ok(index)
+    }
+
+    fn resolved_estimate(&self, estimate: &EstimateSpecification) -> EstimateSpecification {
+        match (estimate, self.expected_categories.as_ref()) {
+            // categories given with the frequencies options take precedence
+            (EstimateSpecification::Frequencies(options), Some(categories)) if options.expected_categories.is_none() => {
+                EstimateSpecification::Frequencies(estimates::FrequenciesOptions { percentages: options.percentages, expected_categories: Some(categories.clone()) })
+            }
+            // the columns have been checked before calculating
+            (EstimateSpecification::LinearRegressionOnColumns(dependent, predictors), _) => {
+                EstimateSpecification::LinearRegression(estimates::LinregOptions {
+                    dependent: Some(self.column_index(dependent).unwrap()),
+                    predictors: Some(predictors.iter().map(|predictor| self.column_index(predictor).unwrap()).collect()),
+                    ..Default::default()
+                })
+            }
+            _ => estimate.clone(),
+        }
+    }
+
+    pub fn set_missing_strategy(&mut self, missing_strategy: MissingStrategy) -> &mut Self {
+        self.missing_strategy = missing_strategy;
+        self
+    }
+
+    fn estimate_with_missing_strategy(&self) -> Result<Arc<estimates::EstimateFunction>, ReplicestError> {
+        let estimate = self.combined_estimate()?;
+        Ok(match self.missing_strategy {
+            // deleting within the estimator keeps the same cases for every replicate weight
+            MissingStrategy::Listwise => Arc::new(move |x, wgt| {
+                let (x, wgt) = data_preparation::listwise_delete(x, wgt);
+                estimate(&x, &wgt)
+            }),
+            MissingStrategy::Pairwise | MissingStrategy::FailFast => estimate,
+        })
+    }
+
+    fn combined_estimate(&self) -> Result<Arc<estimates::EstimateFunction>, ReplicestError> {
+        let variable_names = self.variable_names.clone().unwrap_or_default();
+        let current_estimate : Arc<estimates::EstimateFunction> = match self.estimate.as_ref().unwrap() {
+            EstimateSpecification::StandardizedMean => {
+                let variable_names = variable_names.clone();
+                Arc::new(move |x, wgt| estimates::named_standardized_group_mean(x, wgt, &variable_names))
+            },
+            estimate => self.resolved_estimate(estimate).estimate_function(&variable_names),
+        };
+        if self.previous_estimates.is_empty() {
+            return Ok(current_estimate);
+        }
+
+        let mut estimate_functions : Vec<Arc<estimates::EstimateFunction>> = self.previous_estimates.iter().map(|estimate| self.resolved_estimate(estimate).estimate_function(&variable_names)).collect();
+        estimate_functions.push(current_estimate);
+
+        Ok(Arc::new(move |x, wgt| {
+            let mut parameter_names : Vec<String> = Vec::new();
+            let mut values : Vec<f64> = Vec::new();
+            for estimate_function in estimate_functions.iter() {
+                let result = estimate_function(x, wgt)?;
+                parameter_names.extend(result.parameter_names().iter().cloned());
+                values.extend(result.estimates().iter());
+            }
+            Ok(estimates::Estimates::new(parameter_names, DVector::from_vec(values)))
+        }))
+    }
+
+    pub fn set_confidence_level(&mut self, confidence_level: f64) -> &mut Self {
+        self.confidence_level = confidence_level;
+        self
+    }
+
+    pub fn set_nan_replicate_weight_policy(&mut self, policy: NanReplicateWeightPolicy) -> &mut Self {
+        self.nan_replicate_weight_policy = policy;
+        self
+    }
+
+    pub fn set_suppression(&mut self, minimum_cases: usize, minimum_weight_sum: f64) -> &mut Self {
+        self.suppression = Some((minimum_cases, minimum_weight_sum));
+        self
+    }
+
+    pub fn with_design_effects(&mut self) -> &mut Self {
+        self.design_effects = true;
+        self
+    }
+
+    pub fn with_unweighted_estimates(&mut self) -> &mut Self {
+        self.unweighted_estimates = true;
+        self
+    }
+
+    pub fn set_monitor(&mut self, monitor: &ReplicationMonitor) -> &mut Self {
+        self.monitor = monitor.clone();
+        self
+    }
+
+    pub fn with_weight_normalization(&mut self) -> &mut Self {
+        self.normalize_weights(To::SampleSize)
+    }
+
+    pub fn normalize_weights(&mut self, to: To) -> &mut Self {
+        self.weight_normalization = Some(to);
+        self
+    }
+
+    pub fn set_senate_weights(&mut self, units: &DVector<f64>, total: f64) -> &mut Self {
+        self.senate_weights = Some((units.clone(), total));
+        self
+    }
+
+    pub fn set_weight_trimming(&mut self, percentile: f64) -> &mut Self {
+        self.weight_trimming = Some(percentile);
+        self
+    }
+
+    pub fn set_thread_budget(&mut self, number_threads: usize) -> &mut Self {
+        self.number_threads = number_threads;
+        self
+    }
+
+    // a thread budget of 0 runs on rayon's global pool, any other budget on a pool that is kept for further calculations
+    fn thread_pool(&self) -> Result<Option<Arc<rayon::ThreadPool>>, Box<dyn Error>> {
+        if self.number_threads == 0 {
+            return Ok(None);
+        }
+
+        let mut thread_pool = self.thread_pool.lock().unwrap();
+        if let Some((number_threads, pool)) = thread_pool.as_ref() {
+            if *number_threads == self.number_threads {
+                return Ok(Some(pool.clone()));
+            }
+        }
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(self.number_threads).build()?);
+        *thread_pool = Some((self.number_threads, pool.clone()));
+        Ok(Some(pool))
+    }
+
+    pub fn set_group_parallelism(&mut self, number_groups: usize) -> &mut Self {
+        self.group_parallelism = number_groups;
+        self
+    }
+
+    pub fn mean(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Mean)
+    }
+
+    pub fn standardized_mean(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::StandardizedMean)
+    }
+
+    pub fn frequencies(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Frequencies(estimates::FrequenciesOptions::default()))
+    }
+
+    pub fn frequencies_with_options(&mut self, options: &estimates::FrequenciesOptions) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Frequencies(options.clone()))
+    }
+
+    pub fn agreement(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Agreement)
+    }
+
+    pub fn crosstab(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Crosstab)
+    }
+
+    pub fn range(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Range)
+    }
+
+    pub fn missingness(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Missingness)
+    }
+
+    pub fn total(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Total)
+    }
+
+    pub fn correlation(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Correlation)
+    }
+
+    pub fn linreg(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::LinearRegression(estimates::LinregOptions::default()))
+    }
+
+    pub fn linreg_with_options(&mut self, options: &estimates::LinregOptions) -> &mut Self {
+        self.select_estimate(EstimateSpecification::LinearRegression(options.clone()))
+    }
+
+    pub fn linreg_with_columns(&mut self, dependent: Column, predictors: &[Column]) -> &mut Self {
+        self.select_estimate(EstimateSpecification::LinearRegressionOnColumns(dependent, predictors.to_vec()))
+    }
+
+    pub fn poisson_regression(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::PoissonRegression)
+    }
+
+    pub fn effect_size(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::EffectSize)
+    }
+
+    pub fn t_test(&mut self, mu: f64) -> &mut Self {
+        self.select_estimate(EstimateSpecification::TTest(mu))
+    }
+
+    pub fn two_sample_t_test(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::TwoSampleTTest)
+    }
+
+    pub fn eta_squared(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::EtaSquared)
+    }
+
+    pub fn mantel_haenszel_dif(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::MantelHaenszelDif)
+    }
+
+    pub fn odds_ratio(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::OddsRatio)
+    }
+
+    pub fn gini(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Gini)
+    }
+
+    pub fn concentration(&mut self, number_ordinates: usize) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Concentration(number_ordinates))
+    }
+
+    pub fn percentile_shares(&mut self, ranges: &[(f64, f64)]) -> &mut Self {
+        self.select_estimate(EstimateSpecification::PercentileShares(ranges.to_vec()))
+    }
+
+    pub fn theil(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Theil)
+    }
+
+    pub fn atkinson(&mut self, epsilon: f64) -> &mut Self {
+        self.select_estimate(EstimateSpecification::Atkinson(epsilon))
+    }
+
+    pub fn percentile_ranks(&mut self, values: &[f64]) -> &mut Self {
+        self.select_estimate(EstimateSpecification::PercentileRanks(values.to_vec()))
+    }
+
+    pub fn binned_frequencies(&mut self, breaks: &[f64]) -> &mut Self {
+        self.select_estimate(EstimateSpecification::BinnedFrequencies(breaks.to_vec()))
+    }
+
+    pub fn benchmark_levels(&mut self, cutpoints: &[f64]) -> &mut Self {
+        self.select_estimate(EstimateSpecification::BenchmarkLevels(cutpoints.to_vec()))
+    }
+
+    pub fn equal_width_binned_frequencies(&mut self, number_bins: usize) -> &mut Self {
+        self.select_estimate(EstimateSpecification::EqualWidthBinnedFrequencies(number_bins))
+    }
+
+    pub fn polychoric_correlation(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::PolychoricCorrelation)
+    }
+
+    pub fn polyserial_correlation(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::PolyserialCorrelation)
+    }
+
+    pub fn principal_components(&mut self) -> &mut Self {
+        self.select_estimate(EstimateSpecification::PrincipalComponents)
+    }
+
+    pub fn proportions_above(&mut self, cutpoints: &[f64]) -> &mut Self {
+        self.select_estimate(EstimateSpecification::ProportionsAbove(cutpoints.to_vec()))
+    }
+
+    pub fn group_by(&mut self, data: Imputation) -> &mut Self {
+        match imputations_from(data) {
+            Ok(groups) => {
+                self.groups = Some(Arc::new(groups));
+                self.groups_error = None;
+            }
+            Err(err) => {
+                self.groups = None;
+                self.groups_error = Some(err.to_string());
+            }
+        }
+        self.quantile_groups = None;
+        self
+    }
+
+    pub fn group_by_owned(&mut self, data: Vec<DMatrix<f64>>) -> &mut Self {
+        self.groups = Some(Arc::new(data));
+        self.quantile_groups = None;
+        self
+    }
+
+    pub fn group_by_f32(&mut self, data: Vec<DMatrix<f32>>) -> &mut Self {
+        self.groups = Some(Arc::new(data_preparation::widen(data)));
+        self.quantile_groups = None;
+        self
+    }
+
+    pub fn group_by_shared(&mut self, data: Arc<Vec<DMatrix<f64>>>) -> &mut Self {
+        self.groups = Some(data);
+        self.quantile_groups = None;
+        self
+    }
+
+    pub fn recode_groups(&mut self, column: usize, recodes: &[(f64, f64)]) -> &mut Self {
+        // recoding applies to the grouping columns already set, columns out of range are left untouched
+        if let Some(groups) = self.groups.as_ref() {
+            let recoded_groups : Vec<DMatrix<f64>> = groups.iter()
+                .map(|mat| if column < mat.ncols() { data_preparation::recode(mat, column, recodes) } else { mat.clone() })
+                .collect();
+            self.groups = Some(Arc::new(recoded_groups));
+        }
+        self
+    }
+
+    pub fn group_by_quantiles(&mut self, column: usize, number_groups: usize) -> &mut Self {
+        self.quantile_groups = Some((column, number_groups));
+        self
+    }
+
+    fn prepare_quantile_groups(&mut self, wgt: &DVector<f64>) -> Result<(), Box<dyn Error>> {
+        let (column, number_groups) = self.quantile_groups.unwrap();
+        let data = self.x.as_ref().unwrap().deref();
+
+        if number_groups < 2 {
+            return Err(Box::new(InconsistencyError::new("number of quantile groups has to be at least 2")))
+        }
+        if column >= data[0].ncols() {
+            return Err(Box::new(InconsistencyError::new("quantile grouping column out of range")))
+        }
+
+        // cutpoints are determined within each imputation, so that group membership may vary between imputations
+        let groups : Vec<DMatrix<f64>> = data.iter().map(|x| {
+            DMatrix::from_columns(&[data_preparation::weighted_quantile_groups(&x.column(column).clone_owned(), wgt, number_groups)])
+        }).collect();
+        self.groups = Some(Arc::new(groups));
+
+        Ok(())
+    }
+
+    pub fn set_group_labels(&mut self, column: usize, labels: &[(f64, &str)]) -> &mut Self {
+        self.group_labels.insert(column, labels.iter().map(|(value, label)| (*value, label.to_string())).collect());
+        self
+    }
+
+    pub fn set_group_names(&mut self, group_names: &[&str]) -> &mut Self {
+        self.group_names = Some(group_names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    fn group_key(&self, key: Vec<String>) -> GroupKey {
+        if key == vec!["overall".to_string()] && self.groups.as_ref().is_none_or(|groups| groups.is_empty()) {
+            return GroupKey::overall();
+        }
+
+        let variable_names = (0..key.len()).map(|column| {
+            self.group_names.as_ref().map(|names| names[column].clone()).unwrap_or(format!("group{}", column + 1))
+        }).collect();
+        let values : Vec<f64> = key.iter().map(|value| value.parse::<f64>().unwrap_or(f64::NAN)).collect();
+
+        // values without a label keep their numeric representation
+        let labels = key.into_iter().enumerate().map(|(column, value)| {
+            self.group_labels.get(&column)
+                .and_then(|labels| labels.iter().find(|(v, _)| v.to_string() == value))
+                .map(|(_, label)| label.clone())
+                .unwrap_or(value)
+        }).collect();
+
+        GroupKey::group(variable_names, values, labels)
+    }
+
+    pub fn with_marginals(&mut self) -> &mut Self {
+        self.marginals = true;
+        self
+    }
+
+    fn calculate_marginals(&self) -> Result<BTreeMap<GroupKey, ReplicatedEstimates>, Box<dyn Error>> {
+        let groups = self.groups.as_ref().unwrap().deref();
+        let number_columns = groups[0].ncols();
+        let group_names : Vec<String> = (0..number_columns).map(|column| {
+            self.group_names.as_ref().map(|names| names[column].clone()).unwrap_or(format!("group{}", column + 1))
+        }).collect();
+
+        let mut marginal_analysis = self.clone();
+        marginal_analysis.marginals = false;
+        marginal_analysis.pairwise_group_differences = false;
+        marginal_analysis.overall_comparisons = false;
+        marginal_analysis.groups = None;
+        marginal_analysis.quantile_groups = None;
+
+        let mut results = marginal_analysis.calculate()?;
+
+        // with a single grouping variable, its marginal results are the grouped results themselves
+        if number_columns > 1 {
+            for column in 0..number_columns {
+                marginal_analysis.groups = Some(Arc::new(groups.iter().map(|mat| mat.select_columns(&[column])).collect()));
+                marginal_analysis.group_names = Some(vec![group_names[column].clone()]);
+                marginal_analysis.group_labels = self.group_labels.get(&column).map(|labels| HashMap::from([(0, labels.clone())])).unwrap_or_default();
+
+                for (key, result) in marginal_analysis.calculate()? {
+                    // the other grouping variables are marked as aggregated over
+                    let values = (0..number_columns).map(|c| if c == column { key.values()[0] } else { f64::NAN }).collect();
+                    let labels = (0..number_columns).map(|c| if c == column { key.labels()[0].clone() } else { "all".to_string() }).collect();
+                    results.insert(GroupKey::group(group_names.clone(), values, labels), result);
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub fn with_overall_comparisons(&mut self) -> &mut Self {
+        self.overall_comparisons = true;
+        self
+    }
+
+    fn calculate_overall(&self) -> Result<ReplicatedEstimates, Box<dyn Error>> {
+        let mut overall_analysis = self.clone();
+        overall_analysis.marginals = false;
+        overall_analysis.pairwise_group_differences = false;
+        overall_analysis.overall_comparisons = false;
+        overall_analysis.groups = None;
+        overall_analysis.quantile_groups = None;
+
+        let mut results = overall_analysis.calculate()?;
+        Ok(results.remove(&GroupKey::overall()).unwrap())
+    }
+
+    pub fn with_pairwise_differences(&mut self) -> &mut Self {
+        self.pairwise_group_differences = true;
+        self
+    }
+
+    fn weight_preparation(&self) -> WeightPreparation {
+        WeightPreparation {
+            nan_replicate_weight_policy: self.nan_replicate_weight_policy,
+            normalization: self.weight_normalization,
+            trimming: self.weight_trimming,
+            senate_weights: self.senate_weights.clone(),
+        }
+    }
+
+    fn prepare_missing_weights(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.x.is_none() || self.x.as_ref().unwrap().deref().len() == 0 {
+            return Err(Box::new(MissingElementError::new("data")))
+        }
+
+        let ncases = self.x.as_ref().unwrap().deref()[0].nrows();
+
+        if self.wgt.is_none() {
+            self.wgt = Some(Arc::new(DVector::<f64>::from_element(ncases, 1.0)));
+        }
+
+        if self.repwgts.is_none() {
+            self.repwgts = Some(Arc::new(DMatrix::<f64>::from_row_slice(ncases, 0, &[])));
+        }
+
+        Ok(())
+    }
+
+    // the configured weights are left untouched, so that calculating again starts from them
+    fn prepare_weights(&self) -> Result<PreparedWeights, Box<dyn Error>> {
+        let ncases = self.x.as_ref().unwrap().deref()[0].nrows();
+        let mut wgt = self.wgt.clone().unwrap();
+        let mut repwgts = self.repwgts.clone().unwrap();
+
+        let policy_repwgts = apply_nan_replicate_weight_policy(repwgts.deref(), self.nan_replicate_weight_policy)?;
+        if policy_repwgts != *repwgts.deref() {
+            repwgts = Arc::new(policy_repwgts);
+        }
+
+        if self.weight_normalization.is_some() || self.weight_trimming.is_some() {
+            if self.weight_normalization.is_some_and(|to| matches!(to, To::Sum(target) if target <= 0.0 || target.is_nan())) {
+                return Err(Box::new(InconsistencyError::new("target sum of weights has to be positive")))
+            }
+            if self.weight_trimming.is_some_and(|percentile| percentile <= 0.0 || percentile > 1.0) {
+                return Err(Box::new(InconsistencyError::new("trimming percentile has to be above 0 and at most 1")))
+            }
+            if wgt.nrows() != repwgts.nrows() {
+                return Err(Box::new(InconsistencyError::new("unequal number of rows for weights and replicate weights")))
+            }
+            // trimming leaves non-negative weights with a positive sum, which is what normalizing them needs
+            let normalizable = |weights: &[f64]| weights.iter().all(|&w| w >= 0.0) && weights.iter().sum::<f64>() > 0.0;
+            if self.weight_normalization.is_some() && !(normalizable(wgt.as_slice()) && repwgts.column_iter().all(|column| normalizable(column.as_slice()))) {
+                return Err(Box::new(InconsistencyError::new("weights and replicate weights have to be non-negative with a positive sum to be normalized")))
+            }
+
+            let (new_wgt, new_repwgts) = design::preprocess_weights(wgt.deref(), repwgts.deref(), self.weight_normalization, self.weight_trimming);
+            wgt = Arc::new(new_wgt);
+            repwgts = Arc::new(new_repwgts);
+        }
+
+        if let Some((units, total)) = self.senate_weights.as_ref() {
+            if units.len() != ncases {
+                return Err(Box::new(InconsistencyError::new("unequal number of rows for data and senate weight units")))
+            }
+            if units.iter().any(|unit| unit.is_nan()) || *total <= 0.0 {
+                return Err(Box::new(InconsistencyError::new("senate weight units must not be missing and the total has to be positive")))
+            }
+            if wgt.nrows() != repwgts.nrows() {
+                return Err(Box::new(InconsistencyError::new("unequal number of rows for weights and replicate weights")))
+            }
+
+            let (new_wgt, new_repwgts) = design::senate_weights(wgt.deref(), repwgts.deref(), units, *total);
+            wgt = Arc::new(new_wgt);
+            repwgts = Arc::new(new_repwgts);
+        }
+
+        Ok((wgt, repwgts))
+    }
+
+    fn standardized_mean_data(&self, keys: &HashSet<Vec<String>>) -> HashMap<Vec<String>, Vec<DMatrix<f64>>> {
+        let data = self.x.as_ref().unwrap().deref();
+        let groups = self.groups.as_ref().filter(|groups| !groups.is_empty());
+
+        keys.iter().map(|key| {
+            let marked_data = data.iter().enumerate().map(|(i, mat)| {
+                let in_group = match groups {
+                    None => DVector::<f64>::from_element(mat.nrows(), 1.0),
+                    Some(groups) => {
+                        let groups = if groups.len() > 1 { &groups[i] } else { &groups[0] };
+                        DVector::<f64>::from_iterator(mat.nrows(), groups.row_iter().map(|row| if row.iter().map(|v| v.to_string()).eq(key.iter().cloned()) { 1.0 } else { 0.0 }))
+                    }
+                };
+                let mut marked = mat.clone().insert_column(mat.ncols(), 0.0);
+                marked.set_column(mat.ncols(), &in_group);
+                marked
+            }).collect();
+            (key.clone(), marked_data)
+        }).collect()
+    }
+
+    fn prepare_for_calculate_overall<'a>(&'a self, wgt: &'a DVector<f64>, repwgts: &'a DMatrix<f64>)
+        -> Result<(HashSet<Vec<String>>, HashMap<Vec<String>, Vec<&'a DMatrix<f64>>>, HashMap<Vec<String>, Vec<&'a DVector<f64>>>, HashMap<Vec<String>, Vec<&'a DMatrix<f64>>>), Box<dyn Error>>
+    {
+        let mut keys : HashSet<Vec<String>> = HashSet::new();
+        let mut x_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>> = HashMap::new();
+        let mut wgt_split : HashMap<Vec<String>, Vec<&DVector<f64>>> = HashMap::new();
+        let mut repwgt_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>> = HashMap::new();
+
+        keys.insert(vec!["overall".to_string()]);
+
+        let mut x : Vec<&DMatrix<f64>> = Vec::new();
+        for mat in self.x.as_ref().unwrap().deref() {
+            x.push(mat);
+        }
+        let ncases = x[0].nrows();
+
+        x_split.insert(vec!["overall".to_string()], x);
+
+        if ncases != wgt.nrows() {
+            return Err(Box::new(InconsistencyError::new("unequal number of rows for data and weights")))
+        }
+        wgt_split.insert(vec!["overall".to_string()], vec![wgt]);
+
+        if ncases != repwgts.nrows() {
+            return Err(Box::new(InconsistencyError::new("unequal number of rows for data and replicate weights")))
+        }
+        repwgt_split.insert(vec!["overall".to_string()], vec![repwgts]);
+
+        Ok((keys, x_split, wgt_split, repwgt_split))
+    }
+
+    fn cached_group_split(&self, wgt: &DVector<f64>, repwgts: &DMatrix<f64>) -> Result<Arc<GroupSplit>, Box<dyn Error>> {
+        let x = self.x.as_ref().unwrap();
+        let groups = self.groups.as_ref().unwrap();
+        let weight_preparation = self.weight_preparation();
+
+        // the prepared weights follow from the configured weights and their preparation
+        if let Some(cached) = self.split_cache.lock().unwrap().as_ref() {
+            if Arc::ptr_eq(&cached.x, x) && Arc::ptr_eq(&cached.wgt, self.wgt.as_ref().unwrap()) && Arc::ptr_eq(&cached.repwgts, self.repwgts.as_ref().unwrap())
+                && cached.weight_preparation == weight_preparation && Arc::ptr_eq(&cached.groups, groups) {
+                return Ok(cached.split.clone());
+            }
+        }
+
+        let split = Arc::new(self.prepare_for_calculate_group_by(wgt, repwgts)?);
+        *self.split_cache.lock().unwrap() = Some(CachedSplit {
+            x: x.clone(),
+            wgt: self.wgt.clone().unwrap(),
+            repwgts: self.repwgts.clone().unwrap(),
+            weight_preparation,
+            groups: groups.clone(),
+            split: split.clone(),
+        });
+        Ok(split)
+    }
+
+    fn prepare_for_calculate_group_by(&self, wgt: &DVector<f64>, repwgts: &DMatrix<f64>) -> Result<GroupSplit, Box<dyn Error>> {
+        let mut keys : HashSet<Vec<String>> = HashSet::new();
+        let mut x_split : HashMap<Vec<String>, Vec<DMatrix<f64>>> = HashMap::new();
+        let mut wgt_split : HashMap<Vec<String>, Vec<DVector<f64>>> = HashMap::new();
+        let mut repwgt_split : HashMap<Vec<String>, Vec<DMatrix<f64>>> = HashMap::new();
+
+        let groups = self.groups.as_ref().unwrap().deref();
+
+        if groups.len() > 1 && groups.len() != self.x.as_ref().unwrap().deref().len() {
+            return Err(Box::new(InconsistencyError::new("number of data sets does not match number of sets with grouping columns")))
+        }
+
+        let multiple_imputation_groups = groups.len() > 1;
+
+        let unique_combinations = groups.first().unwrap().get_keys();
+        for combination in unique_combinations {
+            keys.insert(combination);
+        }
+
+        for (i, mat) in self.x.as_ref().unwrap().deref().iter().enumerate() {
+            let mat_split = mat.split_by(if multiple_imputation_groups { &groups[i] }  else { &groups[0] });
+
+            match i {
+                0 => {
+                    for (key, mat0) in mat_split {
+                        x_split.insert(key, vec![mat0]);
+                    }
+                }
+                _ => {
+                    for (key, mat0) in mat_split {
+                        x_split.get_mut(&key).unwrap().push(mat0);
+                    }
+                }
+            }
+        }
+
+        for (i, groups0) in groups.iter().enumerate() {
+            let vec_split = wgt.split_by(groups0);
+            let mat_split = repwgts.split_by(groups0);
+
+            match i {
+                0 => {
+                    for (key, vec0) in vec_split {
+                        wgt_split.insert(key, vec![vec0]);
+                    }
+                    for (key, mat0) in mat_split {
+                        repwgt_split.insert(key, vec![mat0]);
+                    }
+                }
+                _ => {
+                    for (key, vec0) in vec_split {
+                        wgt_split.get_mut(&key).unwrap().push(vec0);
+                    }
+                    for (key, mat0) in mat_split {
+                        repwgt_split.get_mut(&key).unwrap().push(mat0);
+                    }
+                }
+            }
+        }
+
+        Ok((keys, x_split, wgt_split, repwgt_split))
+    }
+
+    pub fn calculate(&mut self) -> Result<BTreeMap<GroupKey, ReplicatedEstimates>, Box<dyn Error>> {
+        if let Some(err) = self.data_error.as_ref().or(self.groups_error.as_ref()).or(self.design_error.as_ref()) {
+            return Err(err.clone().into())
+        }
+
+        if self.estimate.is_none() {
+            return Err(Box::new(MissingElementError::new("estimate")))
+        }
+
+        let mut all_estimates = self.previous_estimates.iter().chain(self.estimate.iter());
+        if !self.previous_estimates.is_empty() && all_estimates.any(|estimate| *estimate == EstimateSpecification::StandardizedMean) {
+            return Err(Box::new(InconsistencyError::new("standardized mean cannot be combined with other estimates")))
+        }
+
+        if self.confidence_level <= 0.0 || self.confidence_level >= 1.0 {
+            return Err(Box::new(InconsistencyError::new("confidence level has to be between 0 and 1")))
+        }
+
+        if self.expected_categories.as_ref().is_some_and(|categories| categories.is_empty() || categories.iter().any(|category| category.is_nan())) {
+            return Err(Box::new(InconsistencyError::new("expected categories have to be non-empty and must not contain NaN")))
+        }
+
+        self.prepare_missing_weights()?;
+        let (wgt, repwgts) = self.prepare_weights()?;
+        self.check_variance_method(self.repwgts.as_ref().unwrap().ncols())?;
+        if self.variance_method.is_none() && repwgts.ncols() != self.repwgts.as_ref().unwrap().ncols() {
+            // a fixed factor was chosen for the original number of replicates
+            return Err(Box::new(InconsistencyError::new("replicate weights were dropped, so a variance method is needed to adjust the variance factor")))
+        }
+
+        if self.quantile_groups.is_some() {
+            self.prepare_quantile_groups(&wgt)?;
+        }
+
+        if self.variable_names.as_ref().is_some_and(|names| names.len() != self.x.as_ref().unwrap().deref()[0].ncols()) {
+            return Err(Box::new(InconsistencyError::new("number of variable names does not match number of columns")))
+        }
+
+        if self.missing_strategy == MissingStrategy::FailFast && self.x.as_ref().unwrap().iter().any(|x| x.iter().any(|value| value.is_nan())) {
+            return Err(Box::new(InconsistencyError::new("data contains missing values")))
+        }
+
+        for estimate in self.previous_estimates.iter().chain(self.estimate.iter()) {
+            if let EstimateSpecification::LinearRegressionOnColumns(dependent, predictors) = estimate {
+                let dependent = self.column_index(dependent)?;
+                for predictor in predictors.iter() {
+                    if self.column_index(predictor)? == dependent {
+                        return Err(Box::new(InconsistencyError::new("dependent variable cannot be a predictor")))
+                    }
+                }
+            }
+        }
+
+        let estimate = self.estimate_with_missing_strategy()?;
+
+        let keys : HashSet<Vec<String>>;
+
+        let group_split : Arc<GroupSplit>;
+
+        let mut x_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>>;
+        let mut wgt_split : HashMap<Vec<String>, Vec<&DVector<f64>>>;
+        let mut repwgt_split : HashMap<Vec<String>, Vec<&DMatrix<f64>>>;
+
+        match self.groups {
+            Some(ref groups) if groups.deref().len() > 0 => {
+                group_split = self.cached_group_split(&wgt, &repwgts)?;
+                let (group_keys, x_storage, wgt_storage, repwgt_storage) = group_split.deref();
+                keys = group_keys.clone();
+
+                x_split = HashMap::new();
+                for (key, data) in x_storage.iter() {
+                    let x : Vec<&DMatrix<f64>> = data.iter().map(|mat| mat).collect();
+                    x_split.insert(key.clone(), x);
+                }
+
+                wgt_split = HashMap::new();
+                for (key, data) in wgt_storage.iter() {
+                    let wgt : Vec<&DVector<f64>> = data.iter().map(|wgt| wgt).collect();
+                    wgt_split.insert(key.clone(), wgt);
+                }
+
+                repwgt_split = HashMap::new();
+                for (key, data) in repwgt_storage.iter() {
+                    let repwgt : Vec<&DMatrix<f64>> = data.iter().map(|repwgt| repwgt).collect();
+                    repwgt_split.insert(key.clone(), repwgt);
+                }
+            }
+            _ => {
+                (keys, x_split, wgt_split, repwgt_split) = self.prepare_for_calculate_overall(&wgt, &repwgts)?
+            }
+        }
+
+        if let (Some(group_names), Some(groups)) = (self.group_names.as_ref(), self.groups.as_ref()) {
+            if !groups.is_empty() && group_names.len() != groups[0].ncols() {
+                return Err(Box::new(InconsistencyError::new("number of group names does not match number of grouping columns")))
+            }
+        }
+
+        let mut results : BTreeMap<GroupKey, ReplicatedEstimates> = BTreeMap::new();
+
+        // the reference of a standardized mean are all cases with the same (replicate) weights, the group is marked in an additional column
+        let standardized_mean_data = match self.estimate.as_ref() {
+            Some(EstimateSpecification::StandardizedMean) => Some(self.standardized_mean_data(&keys)),
+            _ => None,
+        };
+
+        let calculate_group = |key: Vec<String>| -> Result<(Vec<String>, ReplicatedEstimates), ReplicestError> {
+            let (x_replication, wgt_replication, repwgt_replication) = match standardized_mean_data.as_ref() {
+                Some(standardized_mean_data) => (standardized_mean_data.get(&key).unwrap().iter().collect(), vec![wgt.deref()], vec![repwgts.deref()]),
+                None => (x_split.get(&key).unwrap().clone(), wgt_split.get(&key).unwrap().clone(), repwgt_split.get(&key).unwrap().clone()),
+            };
+
+            let mut result = replicate_estimates_in_current_pool(
+                estimate.deref(),
+                &x_replication,
+                &wgt_replication,
+                &repwgt_replication,
+                self.current_variance_adjustment_factor(repwgts.ncols()),
+                &self.monitor,
+            )?;
+
+            if self.design_effects && wgt_replication[0].len() < 2 {
+                // a jackknife reference needs at least two cases, e.g. single case groups have no design effects
+                result.set_design_effects(&DVector::from_element(result.sampling_variances().len(), f64::NAN), x_split.get(&key).unwrap()[0].nrows());
+            } else if self.design_effects {
+                // the reference for the design effect is a jackknife of the same estimator ignoring the replication design
+                let srs_repwgts : Vec<DMatrix<f64>> = wgt_replication.iter().map(|wgt| design::srs_replicate_weights(wgt, 100)).collect();
+                let srs_result = replicate_estimates_in_current_pool(
+                    estimate.deref(),
+                    &x_replication,
+                    &wgt_replication,
+                    &srs_repwgts.iter().collect(),
+                    design::srs_variance_adjustment_factor(&srs_repwgts[0]),
+                    &self.monitor,
+                )?;
+                result.set_design_effects(srs_result.sampling_variances(), x_split.get(&key).unwrap()[0].nrows());
+            }
+
+            if self.unweighted_estimates {
+                // unweighted estimates are averaged over imputations, no standard errors are derived for them
+                let mut unweighted_estimates = DVector::<f64>::zeros(result.final_estimates().len());
+                for mat in x_replication.iter() {
+                    unweighted_estimates += estimate(mat, &DVector::<f64>::from_element(mat.nrows(), 1.0))?.estimates();
+                }
+                result.set_unweighted_estimates(&(unweighted_estimates / x_replication.len() as f64));
+            }
+
+            // valid cases per variable may differ between imputations and are therefore averaged
+            let x = x_split.get(&key).unwrap();
+            let mut valid_cases = DVector::<f64>::zeros(x[0].ncols());
+            for mat in x.iter() {
+                valid_cases += DVector::from_iterator(mat.ncols(), mat.column_iter().map(|column| column.iter().filter(|value| !value.is_nan()).count() as f64));
+            }
+            result.set_case_counts(x[0].nrows(), wgt_split.get(&key).unwrap()[0].sum(), &(valid_cases / x.len() as f64));
+
+            for warning in data_warnings(&key, x_split.get(&key).unwrap(), self.variable_names.as_ref()) {
+                result.add_warning(&warning);
+            }
+
+            if let Some((minimum_cases, minimum_weight_sum)) = self.suppression {
+                let number_cases = x_split.get(&key).unwrap()[0].nrows();
+                let weight_sum = wgt_split.get(&key).unwrap()[0].sum();
+                if number_cases < minimum_cases || weight_sum < minimum_weight_sum {
+                    result.suppress();
+                }
+            }
+
+            Ok((key, result))
+        };
+
+        // groups are independent of each other and share one pool with their replications, at most the group parallelism
+        // of them are calculated at a time, a parallelism of 0 calculates all of them at the same time
+        let keys : Vec<Vec<String>> = keys.into_iter().collect();
+        let chunk_size = if self.group_parallelism == 0 { keys.len().max(1) } else { self.group_parallelism };
+        let calculate_groups = || keys.chunks(chunk_size)
+            .map(|chunk| chunk.par_iter().cloned().map(calculate_group).collect::<Result<Vec<_>, ReplicestError>>())
+            .collect::<Result<Vec<_>, ReplicestError>>();
+        let group_results : Vec<(Vec<String>, ReplicatedEstimates)> = match self.thread_pool()? {
+            Some(thread_pool) => thread_pool.install(calculate_groups)?,
+            None => calculate_groups()?,
+        }.into_iter().flatten().collect();
+
+        for (key, result) in group_results {
+            results.insert(self.group_key(key), result);
+        }
+
+        if self.overall_comparisons && self.groups.as_ref().is_some_and(|groups| !groups.is_empty()) {
+            let overall = self.calculate_overall()?;
+            for result in results.values_mut() {
+                if result.parameter_names() != overall.parameter_names() {
+                    return Err(Box::new(InconsistencyError::new("parameters of groups differ from overall parameters")))
+                }
+                result.set_difference_to_overall(&overall)?;
+            }
+        }
+
+        if self.pairwise_group_differences {
+            // e.g. frequencies of groups with different observed categories, which can be aligned with expected categories
+            if results.values().any(|result| result.parameter_names() != results.values().next().unwrap().parameter_names()) {
+                return Err(Box::new(InconsistencyError::new("parameters differ between groups, so pairwise differences cannot be calculated")))
+            }
+            for (key, mut result) in pairwise_differences(&results)? {
+                let label = format!("diff_{}_vs_{}", key.labels().join("_"), key.compared_to().unwrap().labels().join("_"));
+                let parameter_names = result.parameter_names().iter().map(|name| format!("{}_{}", name, label)).collect();
+                result.set_parameter_names(parameter_names);
+                results.insert(key, result);
+            }
+        }
+
+        if self.marginals && self.groups.as_ref().is_some_and(|groups| !groups.is_empty()) {
+            results.extend(self.calculate_marginals()?);
+        }
+
+        for result in results.values_mut() {
+            result.set_confidence_level(self.confidence_level)?;
+            result.set_metadata(&self.metadata);
+        }
+
+        Ok(results)
+    }
+
+    pub fn summary(&self) -> String {
+        let mut estimate_names : Vec<&str> = self.previous_estimates.iter().map(|estimate| estimate.name()).collect();
+        estimate_names.push(self.estimate.as_ref().map(|estimate| estimate.name()).unwrap_or("none"));
+        let estimate_name = estimate_names.join(" and ");
+
+        let group_info = match self.groups.as_ref() {
+            None => { "".to_string() }
+            Some(groups) => {
+                let group_data = groups.as_ref();
+                format!(" by {} grouping columns", group_data[0].ncols())
+            }
+        };
+
+        let data_info = if self.x.is_none() {
+            "no data".to_string()
+        } else {
+            let data = self.x.as_ref().unwrap().deref();
+            format!("{} datasets with {} cases", data.len(), data[0].nrows())
+        };
+
+        let wgt_info = if self.wgt.is_none() {
+            "wgt missing".to_string()
+        } else {
+            let wgts = self.wgt.as_ref().unwrap().deref();
+            format!("{} weights of sum {}", wgts.len(), wgts.sum())
+        };
+
+        let variance_adjustment_factor = self.current_variance_adjustment_factor(self.repwgts.as_ref().map_or(0, |repwgts| repwgts.ncols()));
+        let factor_info = if variance_adjustment_factor == 1.0 {
+            "".to_string()
+        } else {
+            format!(", factor {}", variance_adjustment_factor)
+        };
+
+        let repwgt_info = if self.repwgts.is_none() {
+            "no replicate weights".to_string()
+        } else {
+            let repwgts = self.repwgts.as_ref().unwrap().deref();
+            format!("{} replicate weights{}", repwgts.ncols(), factor_info)
+        };
+
+        estimate_name + &group_info +  " (" + &data_info + "; " + &wgt_info + "; " + &repwgt_info + ")"
+    }
+
+    pub fn summary_struct(&self) -> AnalysisSummary {
+        AnalysisSummary {
+            estimates: self.previous_estimates.iter().chain(self.estimate.iter()).map(|estimate| estimate.name().to_string()).collect(),
+            number_datasets: self.x.as_ref().map(|x| x.len()).unwrap_or(0),
+            number_cases: self.x.as_ref().and_then(|x| x.first()).map(|x| x.nrows()),
+            number_weights: self.wgt.as_ref().map(|wgt| wgt.len()),
+            weight_sum: self.wgt.as_ref().map(|wgt| wgt.sum()),
+            number_replicates: self.repwgts.as_ref().map(|repwgts| repwgts.ncols()),
+            variance_adjustment_factor: self.current_variance_adjustment_factor(self.repwgts.as_ref().map_or(0, |repwgts| repwgts.ncols())),
+            number_grouping_columns: self.groups.as_ref().and_then(|groups| groups.first()).map(|groups| groups.ncols()),
+            group_names: self.group_names.clone(),
+        }
+    }
+
+    pub fn state(&self) -> AnalysisState {
+        // progress callbacks and cancellation tokens belong to the running session and are not part of the state
+        AnalysisState {
+            x: self.x.as_ref().map(|x| x.deref().clone()),
+            wgt: self.wgt.as_ref().map(|wgt| wgt.deref().clone()),
+            repwgts: self.repwgts.as_ref().map(|repwgts| repwgts.deref().clone()),
+            variance_adjustment_factor: self.variance_adjustment_factor,
+            variance_method: self.variance_method,
+            confidence_level: self.confidence_level,
+            estimate: self.estimate.clone(),
+            groups: self.groups.as_ref().map(|groups| groups.deref().clone()),
+            pairwise_group_differences: self.pairwise_group_differences,
+            number_threads: self.number_threads,
+            nan_replicate_weight_policy: self.nan_replicate_weight_policy,
+            suppression: self.suppression,
+            design_effects: self.design_effects,
+            unweighted_estimates: self.unweighted_estimates,
+            weight_normalization: self.weight_normalization,
+            weight_trimming: self.weight_trimming,
+            variable_names: self.variable_names.clone(),
+            group_labels: self.group_labels.clone(),
+            group_names: self.group_names.clone(),
+            previous_estimates: self.previous_estimates.clone(),
+            marginals: self.marginals,
+            overall_comparisons: self.overall_comparisons,
+            expected_categories: self.expected_categories.clone(),
+            missing_strategy: self.missing_strategy,
+            senate_weights: self.senate_weights.clone(),
+            group_parallelism: self.group_parallelism,
+            quantile_groups: self.quantile_groups,
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(path)?;
+        rmp_serde::encode::write(&mut file, &self.state())?;
+        Ok(())
+    }
+
+    #[deprecated(note = "use clone() instead")]
+    pub fn copy(&self) -> Analysis {
+        self.clone()
+    }
+}
+
+pub type PlanConfiguration = dyn Fn(&mut Analysis);
+
+pub struct AnalysisPlan {
+    shared: Analysis,
+    entries: Vec<(String, Vec<usize>, Box<PlanConfiguration>)>,
+}
+
+pub fn analysis_plan(shared: &Analysis) -> AnalysisPlan {
+    AnalysisPlan {
+        shared: shared.clone(),
+        entries: Vec::new(),
+    }
+}
+
+impl AnalysisPlan {
+    pub fn add<F>(&mut self, name: &str, columns: &[usize], configure: F) -> &mut Self
+    where F: Fn(&mut Analysis) + 'static
+    {
+        self.entries.push((name.to_string(), columns.to_vec(), Box::new(configure)));
+        self
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries.iter().map(|(name, _, _)| name.clone()).collect()
+    }
+
+    fn prepare(&self, columns: &[usize]) -> Result<Analysis, Box<dyn Error>> {
+        let mut analysis = self.shared.clone();
+        if columns.is_empty() {
+            return Ok(analysis);
+        }
+
+        // each analysis only sees the selected columns of the shared data, other settings are inherited unchanged
+        let data = match self.shared.x.as_ref() {
+            None => return Err(Box::new(MissingElementError::new("data"))),
+            Some(data) => data.deref(),
+        };
+        if columns.iter().any(|&c| c >= data[0].ncols()) {
+            return Err(Box::new(InconsistencyError::new("selected column out of range in analysis plan")))
+        }
+
+        analysis.x = Some(Arc::new(data.iter().map(|mat| mat.select_columns(columns)).collect()));
+        analysis.variable_names = self.shared.variable_names.as_ref().map(|names| columns.iter().map(|&c| names[c].clone()).collect());
+        Ok(analysis)
+    }
+
+    pub fn execute(&self) -> Result<BTreeMap<String, BTreeMap<GroupKey, ReplicatedEstimates>>, Box<dyn Error>> {
+        let mut results : BTreeMap<String, BTreeMap<GroupKey, ReplicatedEstimates>> = BTreeMap::new();
+
+        for (name, columns, configure) in self.entries.iter() {
+            if results.contains_key(name) {
+                return Err(Box::new(InconsistencyError::new(&format!("duplicate analysis name {} in analysis plan", name))))
+            }
+
+            let mut analysis = self.prepare(columns)?;
+            configure(&mut analysis);
+            results.insert(name.clone(), analysis.calculate()?);
+        }
+
+        Ok(results)
+    }
+}
+
+const MINIMUM_CASES_WITHOUT_WARNING : usize = 30;
+
+fn data_warnings(key: &[String], x: &[&DMatrix<f64>], variable_names: Option<&Vec<String>>) -> Vec<String> {
+    let mut warnings : Vec<String> = Vec::new();
+
+    let number_cases = x.first().map(|mat| mat.nrows()).unwrap_or(0);
+    if number_cases < MINIMUM_CASES_WITHOUT_WARNING {
+        warnings.push(format!("group '{}' has fewer than {} cases", key.join(" "), MINIMUM_CASES_WITHOUT_WARNING));
+    }
+
+    for (i, mat) in x.iter().enumerate() {
+        for (c, column) in mat.column_iter().enumerate() {
+            if !column.is_empty() && column.iter().all(|value| value.is_nan()) {
+                let column_name = variable_names.and_then(|names| names.get(c)).cloned().unwrap_or(format!("x{}", c + 1));
+                warnings.push(format!("column {} all NaN in imputation {}", column_name, i + 1));
+            }
+        }
+    }
+
+    warnings
+}
+
+pub fn pairwise_differences(results: &BTreeMap<GroupKey, ReplicatedEstimates>) -> Result<BTreeMap<GroupKey, ReplicatedEstimates>, ReplicestError> {
+    let keys : Vec<&GroupKey> = results.keys().filter(|key| !key.is_overall() && key.compared_to().is_none()).collect();
+
+    let mut differences : BTreeMap<GroupKey, ReplicatedEstimates> = BTreeMap::new();
+
+    for (i, &minuend_key) in keys.iter().enumerate() {
+        for &subtrahend_key in keys.iter().skip(i + 1) {
+            differences.insert(GroupKey::comparison(minuend_key, subtrahend_key), difference(&results[minuend_key], &results[subtrahend_key])?);
+        }
+    }
+
+    Ok(differences)
+}
+
+pub fn compare(results_a: &BTreeMap<GroupKey, ReplicatedEstimates>, results_b: &BTreeMap<GroupKey, ReplicatedEstimates>) -> BTreeMap<GroupKey, Comparison> {
+    // groups are aligned by their key, groups present in only one of the results are left out
+    results_a.iter()
+        .filter_map(|(key, estimates_a)| results_b.get(key).map(|estimates_b| (key.clone(), compare_independent(estimates_a, estimates_b))))
+        .collect()
+}
+
+#[cfg(test)]
 mod tests {
+    use std::fs::remove_file;
     use nalgebra::{dmatrix, dvector};
     use crate::analysis::*;
     use crate::assert_approx_eq_iter_f64;
+    use crate::replication::{rao_scott_chi_square, CancellationToken};
+
+    #[test]
+    fn test_for_data() {
+        let data1 = dmatrix![
+            537.0, 456.2, 501.7;
+            499.1, 433.2, 500.6;
+            611.0, 501.9, 588.2;
+        ];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data1));
+
+        assert_eq!("none (1 datasets with 3 cases; wgt missing; no replicate weights)", analysis1.summary());
+
+        let data2imp1 = dmatrix![
+            537.0, 456.2, 501.7;
+            499.1, 433.2, 500.6;
+            611.0, 501.9, 588.2;
+            534.7, 479.9, 512.4;
+        ];
+        let data2imp2 = dmatrix![
+            538.0, 456.2, 501.7;
+            490.1, 433.2, 500.6;
+            612.0, 501.9, 588.2;
+            533.7, 479.9, 512.4;
+        ];
+        let data2imp3 = dmatrix![
+            537.0, 457.2, 501.7;
+            499.1, 432.2, 500.6;
+            611.0, 503.9, 588.2;
+            534.7, 475.9, 512.4;
+        ];
+        let mut data2 : Vec<&DMatrix<f64>> = Vec::new();
+        data2.push(&data2imp1);
+        data2.push(&data2imp2);
+        data2.push(&data2imp3);
+
+        let mut analysis2 = analysis();
+        analysis2.for_data(Imputation::Yes(&data2));
+
+        assert_eq!("none (3 datasets with 4 cases; wgt missing; no replicate weights)", analysis2.summary());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let groups1 = dmatrix![
+            1.0, 1.0;
+            2.0, 1.0;
+            1.0, 2.0;
+        ];
+
+        let mut analysis1 = analysis();
+        analysis1.group_by(Imputation::No(&groups1));
+
+        assert_eq!("none by 2 grouping columns (no data; wgt missing; no replicate weights)", analysis1.summary());
+
+        let groups2imp1 = dmatrix![ 1.0; 1.0; 2.0; 2.0; ];
+        let groups2imp2 = dmatrix![ 1.0; 1.0; 1.0; 2.0; ];
+        let groups2imp3 = dmatrix![ 1.0; 1.0; 2.0; 2.0; ];
+        let mut groups2: Vec<&DMatrix<f64>> = Vec::new();
+        groups2.push(&groups2imp1);
+        groups2.push(&groups2imp2);
+        groups2.push(&groups2imp3);
+
+        let mut analysis2 = analysis();
+        analysis2.group_by(Imputation::Yes(&groups2));
+
+        assert_eq!("none by 1 grouping columns (no data; wgt missing; no replicate weights)", analysis2.summary());
+    }
+
+    #[test]
+    fn test_calculate_does_not_work_without_data() {
+        let mut analysis1 = analysis();
+        let result = analysis1.mean().calculate();
+
+        assert!(result.is_err());
+        assert_eq!("Analysis is missing some element: data", result.err().unwrap().deref().to_string());
+
+        let mut analysis1 = analysis();
+        let result = analysis1.mean().for_data(Imputation::Yes(&Vec::<&DMatrix<f64>>::new())).calculate();
+
+        assert!(result.is_err());
+        assert_eq!("Analysis is missing some element: data", result.err().unwrap().deref().to_string());
+    }
+
+    #[test]
+    fn test_calculate_does_not_work_without_estimate() {
+        let data = dmatrix![
+            537.0, 456.2, 501.7;
+            499.1, 433.2, 500.6;
+            611.0, 501.9, 588.2;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).calculate();
+
+        assert!(result.is_err());
+        assert_eq!("Analysis is missing some element: estimate", result.err().unwrap().deref().to_string());
+    }
+
+    #[test]
+    fn test_calculate_does_not_work_with_unequal_rows_between_data_and_weights() {
+        let data = dmatrix![
+            537.0, 456.2, 501.7;
+            499.1, 433.2, 500.6;
+            611.0, 501.9, 588.2;
+        ];
+
+        let wgt = dvector![1.0, 2.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).mean().calculate();
+
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: unequal number of rows for data and weights", result.err().unwrap().deref().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_without_weights() {
+        let data = dmatrix![
+            537.0, 456.2, 501.7;
+            499.1, 433.2, 502.9;
+            611.0, 501.9, 589.3;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).mean().calculate();
+
+        assert!(result.is_ok());
+        let result = result.unwrap();
+
+        assert_eq!(1, result.len());
+        assert_eq!(3, result[&GroupKey::overall()].final_estimates().len());
+        assert_eq!(531.3, result[&GroupKey::overall()].final_estimates()[2]);
+        assert_eq!(0.0, result[&GroupKey::overall()].standard_errors()[1]);
+    }
+
+    #[test]
+    fn test_calculate_works_for_plausible_values() {
+        let data = dmatrix![
+            1.0, 500.0, 10.0, 510.0, 20.0, 520.0, 30.0;
+            2.0, 400.0, 11.0, 420.0, 21.0, 440.0, 31.0;
+            1.0, 450.0, 12.0, 460.0, 22.0, 470.0, 32.0;
+        ];
+        let plausible_values = vec![vec![1, 3, 5], vec![2, 4, 6]];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::PlausibleValues(&data, &plausible_values)).mean().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(3, overall_result.parameter_names().len());
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![4.0 / 3.0, 463.3333333333333, 21.0]);
+        assert_approx_eq_iter_f64!(overall_result.imputation_variances(), dvector![0.0, 177.77777777777777, 100.0], 1e-8);
+        assert_eq!("mean (3 datasets with 3 cases; 3 weights of sum 3; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_plausible_values_with_unequal_number_of_columns() {
+        let data = dmatrix![1.0, 500.0, 10.0, 510.0];
+        let plausible_values = vec![vec![1, 3], vec![2]];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::PlausibleValues(&data, &plausible_values)).mean().calculate();
+        assert_eq!("Inconsistency in analysis: unequal number of plausible values per construct", result.unwrap_err().to_string());
+
+        let result = analysis1.append_data(Imputation::PlausibleValues(&data, &plausible_values));
+        assert_eq!("Inconsistency in analysis: unequal number of plausible values per construct", result.err().unwrap().to_string());
+
+        let result = analysis1.for_data(Imputation::No(&data)).group_by(Imputation::PlausibleValues(&data, &plausible_values)).calculate();
+        assert_eq!("Inconsistency in analysis: unequal number of plausible values per construct", result.unwrap_err().to_string());
+
+        let result = analysis1.group_by(Imputation::No(&data)).calculate();
+        assert!(result.is_ok());
+
+        let result = analysis1.for_data(Imputation::PlausibleValues(&data, &[vec![1, 3], vec![2, 4]])).calculate();
+        assert_eq!("Inconsistency in analysis: plausible value column out of range", result.unwrap_err().to_string());
+
+        let result = analysis1.for_data(Imputation::PlausibleValues(&data, &[vec![4, 1], vec![2, 3]])).calculate();
+        assert_eq!("Inconsistency in analysis: plausible value column out of range", result.unwrap_err().to_string());
+
+        let result = analysis1.for_data(Imputation::PlausibleValues(&data, &[vec![]])).calculate();
+        assert_eq!("Inconsistency in analysis: no plausible values per construct", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_for_named_plausible_values() {
+        let data = dmatrix![
+            1.0, 500.0, 10.0, 510.0, 20.0, 520.0, 30.0;
+            2.0, 400.0, 11.0, 420.0, 21.0, 440.0, 31.0;
+            1.0, 450.0, 12.0, 460.0, 22.0, 470.0, 32.0;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.set_variable_names(&["ITSEX", "ASRREA01", "ASRLIT01", "ASRREA02", "ASRLIT02", "ASRREA03", "ASRLIT03"])
+            .for_plausible_values(&data, &[pv("ASRREA", 3), PlausibleValues::new("LITERACY", &["ASRLIT01", "ASRLIT02", "ASRLIT03"])])
+            .mean().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(&vec!["mean_ITSEX".to_string(), "mean_ASRREA".to_string(), "mean_LITERACY".to_string()], overall_result.parameter_names());
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![4.0 / 3.0, 463.3333333333333, 21.0]);
+
+        let result = analysis1.set_variable_names(&["ITSEX", "ASRREA01", "ASRLIT01", "ASRREA02", "ASRLIT02", "ASRREA03", "ASRLIT03"])
+            .for_plausible_values(&data, &[pv("ASRREA", 5)]).calculate();
+        assert_eq!("Inconsistency in analysis: unknown variable name ASRREA04", result.unwrap_err().to_string());
+
+        let result = analysis().for_plausible_values(&data, &[pv("ASRREA", 3)]).mean().calculate();
+        assert_eq!("Inconsistency in analysis: plausible values can only be declared by name with variable names", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_with_confidence_level() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let rep_wgts = dmatrix![
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(2.0 / 3.0).mean().set_confidence_level(0.9).calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(0.9, overall_result.confidence_level());
+        assert_approx_eq_iter_f64!(overall_result.confidence_intervals_lower(), dvector![3.0 - 2.353363434801823 * (4.0_f64 / 3.0).sqrt()], 1e-8);
+
+        let result = analysis1.set_confidence_level(1.5).calculate();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_calculate_works_with_brr_replicate_weights() {
+        let data = dmatrix![1.0; 3.0; 2.0; 6.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+        let strata = dvector![1.0, 1.0, 2.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0, 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).with_brr_replicate_weights(&strata, &psus).mean();
+        assert_eq!("mean (1 datasets with 4 cases; 4 weights of sum 4; 4 replicate weights, factor 0.25)", analysis1.summary());
+
+        let result = analysis1.calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![3.0]);
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![1.25]);
+    }
+
+    #[test]
+    fn test_calculate_works_with_jackknife_design() {
+        let data = dmatrix![1.0; 3.0; 2.0; 6.0];
+        let strata = dvector![1.0, 1.0, 2.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0, 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).with_jackknife_design(&strata, &psus, JackknifeScheme::JK2).mean();
+        assert_eq!("mean (1 datasets with 4 cases; wgt missing; 4 replicate weights, factor 0.5)", analysis1.summary());
+
+        let result = analysis1.calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![3.0]);
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].sampling_variances(), dvector![1.25]);
+
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let mut analysis2 = analysis();
+        let result = analysis2.for_data(Imputation::No(&data)).with_jackknife_design(&dvector![1.0, 1.0, 2.0], &dvector![1.0, 2.0, 1.0], JackknifeScheme::JK1).mean().calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].sampling_variances(), dvector![4.0 / 3.0]);
+
+        // invalid designs are reported when calculating
+        let result = analysis2.with_jackknife_design(&dvector![1.0, 1.0, 2.0], &dvector![1.0, 2.0, 1.0], JackknifeScheme::JK2).calculate();
+        assert_eq!("Inconsistency in analysis: every stratum has to have exactly two psus", result.unwrap_err().to_string());
+        let result = analysis2.with_jackknife_design(&dvector![1.0, f64::NAN, 2.0], &dvector![1.0, 2.0, 1.0], JackknifeScheme::JK1).calculate();
+        assert_eq!("Inconsistency in analysis: strata and psus cannot be missing", result.unwrap_err().to_string());
+        let result = analysis2.with_brr_replicate_weights(&dvector![1.0, 1.0], &dvector![1.0, 2.0, 1.0]).calculate();
+        assert_eq!("Inconsistency in analysis: strata and psus have to be given for every case", result.unwrap_err().to_string());
+        let result = analysis2.with_jackknife_design(&dvector![1.0, 1.0, 2.0], &dvector![1.0, 2.0, 1.0], JackknifeScheme::JK1).calculate();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_calculate_works_with_jackknife_design_columns() {
+        let data = dmatrix![
+            1.0, 1.0, 1.0;
+            3.0, 1.0, 2.0;
+            2.0, 2.0, 1.0;
+            6.0, 2.0, 2.0;
+        ];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_variable_names(&["ASRREA", "JKZONE", "JKREP"]).with_jackknife_design_columns(1, 2, JackknifeScheme::JK2).mean();
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(&vec!["mean_ASRREA".to_string()], result[&GroupKey::overall()].parameter_names());
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].sampling_variances(), dvector![1.25]);
+
+        let mut analysis2 = analysis();
+        let result = analysis2.for_data(Imputation::No(&data)).with_jackknife_design_columns(1, 3, JackknifeScheme::JK2).mean().calculate();
+        assert_eq!("Inconsistency in analysis: strata and psu columns have to be two different columns of the data", result.unwrap_err().to_string());
+
+        let result = analysis().with_jackknife_design_columns(1, 2, JackknifeScheme::JK2).for_data(Imputation::No(&data)).mean().calculate();
+        assert_eq!("Analysis is missing some element: data", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_with_bootstrap() {
+        let data = dmatrix![1.0; 3.0; 2.0; 6.0; 4.0];
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).with_bootstrap(100, 4711).mean();
+        assert_eq!("mean (1 datasets with 5 cases; 5 weights of sum 7; 100 replicate weights, factor 0.01)", analysis1.summary());
+
+        let result1 = analysis1.calculate().unwrap();
+        let result2 = analysis().for_data(Imputation::No(&data)).set_weights(&wgt).with_bootstrap(100, 4711).mean().calculate().unwrap();
+        let result3 = analysis().for_data(Imputation::No(&data)).set_weights(&wgt).with_bootstrap(100, 4712).mean().calculate().unwrap();
+
+        assert_approx_eq_iter_f64!(result1[&GroupKey::overall()].final_estimates(), dvector![23.0 / 7.0]);
+        assert_eq!(result1[&GroupKey::overall()].sampling_variances(), result2[&GroupKey::overall()].sampling_variances());
+        assert_ne!(result1[&GroupKey::overall()].sampling_variances(), result3[&GroupKey::overall()].sampling_variances());
+        assert!(result1[&GroupKey::overall()].sampling_variances()[0] > 0.0);
+    }
+
+    #[test]
+    fn test_calculate_works_with_thread_budget() {
+        let data = dmatrix![1.0; 3.0; 2.0; 6.0];
+        let strata = dvector![1.0, 1.0, 2.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0, 2.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).with_brr_replicate_weights(&strata, &psus).mean().set_thread_budget(1).calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![1.25]);
+
+        // the pool is built once for the thread budget
+        let thread_pool = analysis1.thread_pool().unwrap().unwrap();
+        analysis1.calculate().unwrap();
+        assert!(Arc::ptr_eq(&thread_pool, &analysis1.thread_pool().unwrap().unwrap()));
+        assert!(analysis1.set_thread_budget(0).thread_pool().unwrap().is_none());
+    }
 
     #[test]
-    fn test_for_data() {
-        let data1 = dmatrix![
-            537.0, 456.2, 501.7;
-            499.1, 433.2, 500.6;
-            611.0, 501.9, 588.2;
+    fn test_calculate_works_with_variance_method() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let rep_wgts = dmatrix![
+            0.0, 1.5, 1.5;
+            1.5, 0.0, 1.5;
+            1.5, 1.5, 0.0;
         ];
 
         let mut analysis1 = analysis();
-        analysis1.for_data(Imputation::No(&data1));
+        analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).set_variance_method(VarianceMethod::JK1).mean();
+        assert_eq!("mean (1 datasets with 3 cases; wgt missing; 3 replicate weights, factor 0.6666666666666666)", analysis1.summary());
 
-        assert_eq!("none (1 datasets with 3 cases; wgt missing; no replicate weights)", analysis1.summary());
+        let result = analysis1.calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        // replicated means are 4.0, 3.0 and 2.0
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![4.0 / 3.0]);
 
-        let data2imp1 = dmatrix![
-            537.0, 456.2, 501.7;
-            499.1, 433.2, 500.6;
-            611.0, 501.9, 588.2;
-            534.7, 479.9, 512.4;
+        let result = analysis1.set_variance_method(VarianceMethod::Bootstrap(3)).calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![2.0 / 3.0]);
+
+        assert!(analysis1.set_variance_method(VarianceMethod::Bootstrap(100)).calculate().is_err());
+        assert!(analysis1.set_variance_method(VarianceMethod::Fay(1.0)).calculate().is_err());
+
+        let result = analysis1.set_variance_method(VarianceMethod::Fay(0.5)).set_variance_adjustment_factor(1.0).calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![2.0]);
+    }
+
+    #[test]
+    fn test_calculate_with_nan_replicate_weights() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let rep_wgts = dmatrix![
+            0.0, 1.0, 1.0;
+            1.0, f64::NAN, 1.0;
+            1.0, 1.0, 0.0;
         ];
-        let data2imp2 = dmatrix![
-            538.0, 456.2, 501.7;
-            490.1, 433.2, 500.6;
-            612.0, 501.9, 588.2;
-            533.7, 479.9, 512.4;
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(2.0 / 3.0).mean();
+        let result = analysis1.calculate();
+        assert!(result.is_err());
+        assert_eq!("Replicate weight column 2 contains NaN", result.unwrap_err().to_string());
+
+        let result = analysis1.clone().set_nan_replicate_weight_policy(NanReplicateWeightPolicy::TreatAsZero).calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        // replicated means are 4.0, 3.0 and 2.0
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![4.0 / 3.0]);
+
+        let result = analysis1.clone().set_nan_replicate_weight_policy(NanReplicateWeightPolicy::DropReplicate).calculate();
+        assert_eq!("Inconsistency in analysis: replicate weights were dropped, so a variance method is needed to adjust the variance factor", result.unwrap_err().to_string());
+
+        let result = analysis1.clone().set_nan_replicate_weight_policy(NanReplicateWeightPolicy::DropReplicate).set_variance_method(VarianceMethod::JK1).calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        // replicated means are 4.0 and 2.0, the factor follows from the remaining two replicates
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![1.0]);
+    }
+
+    #[test]
+    fn test_calculate_with_weight_normalization_and_trimming() {
+        let data = dmatrix![1.0; 3.0; 5.0; 7.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 5.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).total();
+
+        let result = analysis1.clone().with_weight_normalization().calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![22.0]);
+
+        let result = analysis1.clone().normalize_weights(To::Sum(2.0)).calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![11.0]);
+
+        let result = analysis1.clone().normalize_weights(To::Sum(-1.0)).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: target sum of weights has to be positive", result.unwrap_err().to_string());
+
+        let result = analysis1.clone().set_weight_trimming(0.75).calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![16.0]);
+
+        // the configured weights are kept, so calculating again trims the original weights
+        let mut trimmed = analysis1.clone();
+        trimmed.set_weight_trimming(0.75).calculate().unwrap();
+        let result = trimmed.calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![16.0]);
+        assert_eq!("total (1 datasets with 4 cases; 4 weights of sum 8; 0 replicate weights)", trimmed.summary());
+
+        let result = analysis1.clone().set_weight_trimming(1.5).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: trimming percentile has to be above 0 and at most 1", result.unwrap_err().to_string());
+
+        // weights that cannot be normalized are an error instead of a panic
+        let message = "Inconsistency in analysis: weights and replicate weights have to be non-negative with a positive sum to be normalized";
+        let result = analysis1.clone().set_weights(&dvector![1.0, f64::NAN, 1.0, 5.0]).with_weight_normalization().calculate();
+        assert_eq!(message, result.unwrap_err().to_string());
+
+        let rep_wgts = dmatrix![1.0, 0.0; 1.0, 0.0; 1.0, 0.0; 5.0, 0.0];
+        let result = analysis1.clone().with_replicate_weights(&rep_wgts).with_weight_normalization().calculate();
+        assert_eq!(message, result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_with_senate_weights() {
+        let data = dmatrix![1.0; 3.0; 5.0; 10.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+        let countries = dvector![1.0, 1.0, 1.0, 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).mean();
+
+        let result = analysis1.clone().set_senate_weights(&countries, 500.0).calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![6.5]);
+
+        let result = analysis1.clone().set_senate_weights(&countries, 500.0).total().calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![6500.0]);
+
+        let result = analysis1.clone().set_senate_weights(&dvector![1.0, 2.0], 500.0).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: unequal number of rows for data and senate weight units", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_with_variable_names() {
+        let data = dmatrix![1.0, 2.0; 3.0, 4.0; 5.0, 9.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_variable_names(&["ASRREA", "ASBGSES"]).mean();
+
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(&vec!["mean_ASRREA".to_string(), "mean_ASBGSES".to_string()], result[&GroupKey::overall()].parameter_names());
+
+        let result = analysis1.set_variable_names(&["ASRREA"]).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: number of variable names does not match number of columns", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_with_suppression() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0; 7.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0; 3.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 3.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).group_by(Imputation::No(&groups)).mean().set_suppression(2, 2.0);
+
+        let result = analysis1.calculate().unwrap();
+        assert!(!result_for(&result, &["1"]).unwrap().suppressed());
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![3.0]);
+        assert!(result_for(&result, &["2"]).unwrap().suppressed());
+        assert!(result_for(&result, &["2"]).unwrap().final_estimates()[0].is_nan());
+        assert!(result_for(&result, &["3"]).unwrap().suppressed());
+    }
+
+    #[test]
+    fn test_calculate_works_with_design_effects() {
+        let data = dmatrix![1.0; 3.0; 5.0; 4.0; 7.0; 3.0];
+        let rep_wgts = dmatrix![
+            0.0, 1.0, 1.0;
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+            1.0, 1.0, 0.0;
         ];
-        let data2imp3 = dmatrix![
-            537.0, 457.2, 501.7;
-            499.1, 432.2, 500.6;
-            611.0, 503.9, 588.2;
-            534.7, 475.9, 512.4;
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(2.0 / 3.0).mean().with_design_effects().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        // the srs reference is the variance of the mean for independent cases, i.e. s^2 / n = 25 / 6 / 6
+        let sampling_variance = overall_result.sampling_variances()[0];
+        assert_approx_eq_iter_f64!(overall_result.design_effects().unwrap(), dvector![sampling_variance / (25.0 / 6.0 / 6.0)], 1e-10);
+        assert_approx_eq_iter_f64!(overall_result.effective_sample_sizes().unwrap(), dvector![6.0 * (25.0 / 6.0 / 6.0) / sampling_variance], 1e-10);
+
+        let groups = dmatrix![1.0; 1.0; 1.0; 1.0; 1.0; 2.0];
+        let result = analysis1.group_by(Imputation::No(&groups)).calculate().unwrap();
+        assert!(result_for(&result, &["1"]).unwrap().design_effects().unwrap()[0].is_finite());
+        assert!(result_for(&result, &["2"]).unwrap().design_effects().unwrap()[0].is_nan());
+        assert!(result_for(&result, &["2"]).unwrap().effective_sample_sizes().unwrap()[0].is_nan());
+    }
+
+    #[test]
+    fn test_calculate_can_be_cancelled() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let token = CancellationToken::new();
+        let mut monitor = ReplicationMonitor::default();
+        monitor.set_cancellation_token(&token);
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).mean().set_monitor(&monitor);
+        assert!(analysis1.calculate().is_ok());
+
+        token.cancel();
+        let result = analysis1.calculate();
+        assert_eq!("Calculation was cancelled", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_for_total() {
+        let data = dmatrix![
+            1.0, 4.0;
+            2.5, f64::NAN;
+            3.0, 3.0;
         ];
-        let mut data2 : Vec<&DMatrix<f64>> = Vec::new();
-        data2.push(&data2imp1);
-        data2.push(&data2imp2);
-        data2.push(&data2imp3);
 
-        let mut analysis2 = analysis();
-        analysis2.for_data(Imputation::Yes(&data2));
+        let wgt = dvector![1.0, 0.5, 1.5];
 
-        assert_eq!("none (3 datasets with 4 cases; wgt missing; no replicate weights)", analysis2.summary());
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).total().calculate();
+
+        assert!(result.is_ok());
+        let result = result.unwrap();
+
+        assert_eq!(1, result.len());
+        assert_eq!("total_x1", result[&GroupKey::overall()].parameter_names()[0]);
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![6.75, 8.5]);
+        assert_eq!("total (1 datasets with 3 cases; 3 weights of sum 3; 0 replicate weights)", analysis1.summary());
     }
 
     #[test]
-    fn test_group_by() {
-        let groups1 = dmatrix![
+    fn test_calculate_works_for_multiple_estimates() {
+        let data = dmatrix![1.0; 2.0; 2.0; 3.0];
+        let rep_wgts = dmatrix![
+            0.0, 1.0;
+            1.0, 0.0;
+            1.0, 1.0;
+            1.0, 1.0;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).mean().also().total().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(&vec!["mean_x1".to_string(), "total_x1".to_string()], overall_result.parameter_names());
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![2.0, 8.0]);
+        // replicated means are 7/3 and 2, replicated totals are 7 and 6
+        assert_approx_eq_iter_f64!(overall_result.sampling_variances(), dvector![1.0 / 9.0, 5.0]);
+        assert_eq!("mean and total (1 datasets with 4 cases; 4 weights of sum 4; 2 replicate weights)", analysis1.summary());
+
+        let result = analysis1.clone().also().standardized_mean().calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: standardized mean cannot be combined with other estimates", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_for_linreg_with_options() {
+        let data = dmatrix![
+            3.0, 1.0;
+            5.0, 2.0;
+            7.0, 3.0;
+            9.0, 4.0;
+        ];
+
+        let options = estimates::LinregOptions { residual_diagnostics: true, number_residual_bins: 2, ..Default::default() };
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).linreg_with_options(&options).calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(8, overall_result.parameter_names().len());
+        assert_eq!("linreg_b_x2", overall_result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates().rows(0, 5), dvector![1.0, 2.0, 1.0, 0.0, 0.0]);
+        assert_eq!("linear regression (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_set_estimate() {
+        let data = dmatrix![1.0, 2.0; 2.0, 2.0; 1.0, 3.0; 3.0, 3.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data));
+
+        let result_by_enum = analysis1.set_estimate(Estimate::Frequencies { percentages: true }).calculate().unwrap();
+        let options = estimates::FrequenciesOptions { percentages: true, expected_categories: None };
+        let result_by_method = analysis1.frequencies_with_options(&options).calculate().unwrap();
+        assert_eq!(result_by_method[&GroupKey::overall()].parameter_names(), result_by_enum[&GroupKey::overall()].parameter_names());
+        assert_approx_eq_iter_f64!(result_by_method[&GroupKey::overall()].final_estimates(), result_by_enum[&GroupKey::overall()].final_estimates());
+
+        analysis1.set_estimate(Estimate::TTest { mu: 2.0 });
+        assert_eq!("t test (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+
+        let result = analysis1.set_estimate(Estimate::Correlation).calculate().unwrap();
+        assert_eq!("correlation (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+        assert_eq!(result[&GroupKey::overall()].parameter_names(), estimates::correlation(&data, &DVector::from_element(4, 1.0)).unwrap().parameter_names());
+    }
+
+    #[test]
+    fn test_calculate_works_for_linreg_with_columns() {
+        let data = dmatrix![1.0, 2.0, 7.0; 2.0, 4.0, 1.0; 3.0, 5.0, 2.0; 4.0, 7.0, 9.0; 5.0, 10.0, 3.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).linreg_with_columns(Column::Index(1), &[Column::Index(0)]);
+
+        let result = analysis1.calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(&vec!["linreg_x2_intercept".to_string(), "linreg_x2_b_x1".to_string(), "linreg_x2_r2".to_string(), "linreg_x2_sigma".to_string()], overall_result.parameter_names());
+        assert_eq!("linear regression (1 datasets with 5 cases; 5 weights of sum 5; 0 replicate weights)", analysis1.summary());
+
+        let result = analysis1.set_variable_names(&["ASBGSES", "ASRREA", "ASBGHRL"])
+            .linreg_with_columns(Column::Name("ASRREA".to_string()), &[Column::Name("ASBGSES".to_string()), Column::Index(2)]).calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("linreg_ASRREA_b_ASBGSES", overall_result.parameter_names()[1]);
+        assert_eq!("linreg_ASRREA_b_ASBGHRL", overall_result.parameter_names()[2]);
+
+        let result = analysis1.linreg_with_columns(Column::Name("ASRREA".to_string()), &[Column::Name("ASBGXXX".to_string())]).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: unknown variable name ASBGXXX", result.unwrap_err().to_string());
+
+        let result = analysis1.linreg_with_columns(Column::Index(1), &[Column::Index(1)]).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: dependent variable cannot be a predictor", result.unwrap_err().to_string());
+
+        let result = analysis1.linreg_with_columns(Column::Index(3), &[Column::Index(1)]).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: selected column out of range", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_for_frequencies_with_expected_categories() {
+        let data = dmatrix![1.0; 2.0; 1.0; 3.0; 3.0; 3.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0];
+
+        let options = estimates::FrequenciesOptions { percentages: true, expected_categories: Some(vec![1.0, 2.0, 3.0]) };
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).frequencies_with_options(&options).group_by(Imputation::No(&groups)).calculate().unwrap();
+
+        let first_result = result_for(&result, &["1"]).unwrap();
+        let second_result = result_for(&result, &["2"]).unwrap();
+        assert_eq!(first_result.parameter_names(), second_result.parameter_names());
+        assert_eq!("percentage_x1_cat1", first_result.parameter_names()[3]);
+        assert_approx_eq_iter_f64!(first_result.final_estimates(), dvector![2.0, 1.0, 0.0, 200.0 / 3.0, 100.0 / 3.0, 0.0]);
+        assert_approx_eq_iter_f64!(second_result.final_estimates(), dvector![0.0, 0.0, 3.0, 0.0, 0.0, 100.0]);
+        assert_eq!("frequencies by 1 grouping columns (1 datasets with 6 cases; 6 weights of sum 6; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_crosstab_with_rao_scott_chi_square() {
+        let data = dmatrix![
+            1.0, 1.0;
             1.0, 1.0;
+            1.0, 2.0;
             2.0, 1.0;
+            2.0, 2.0;
+            2.0, 2.0;
+            2.0, 2.0;
+            1.0, 1.0;
+        ];
+        let rep_wgts = DMatrix::from_fn(8, 4, |r, c| if r / 2 == c { 0.0 } else { 1.0 });
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).set_variable_names(&["ITSEX", "ASBG01"]).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(0.75).crosstab().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("crosstab_ITSEX_1_ASBG01_1", overall_result.parameter_names()[0]);
+
+        let test = rao_scott_chi_square(overall_result).unwrap();
+        assert_approx_eq_iter_f64!(dvector![test.chi_square(), test.adjusted_chi_square()], dvector![2.0, 1.0]);
+        assert_eq!("crosstab (1 datasets with 8 cases; 8 weights of sum 8; 4 replicate weights, factor 0.75)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_t_tests() {
+        let data = dmatrix![
+            1.0, 1.0;
+            1.0, 3.0;
+            1.0, 5.0;
+            2.0, 2.0;
+            2.0, 6.0;
+            2.0, 7.0;
+        ];
+        let rep_wgts = DMatrix::from_fn(6, 3, |r, c| if r % 3 == c { 0.0 } else { 1.0 });
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(2.0 / 3.0).two_sample_t_test().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("mean_diff_x2", overall_result.parameter_names()[0]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![2.0]);
+        assert_approx_eq_iter_f64!(overall_result.t_statistics(), overall_result.final_estimates().component_div(overall_result.standard_errors()));
+        assert_eq!("two sample t test (1 datasets with 6 cases; 6 weights of sum 6; 3 replicate weights, factor 0.6666666666666666)", analysis1.summary());
+
+        let result = analysis1.t_test(3.0).calculate().unwrap();
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("mean_diff_3_x2", overall_result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![-1.5, 1.0]);
+        assert!(overall_result.p_values()[1] > 0.0 && overall_result.p_values()[1] < 1.0);
+    }
+
+    #[test]
+    fn test_calculate_works_for_eta_squared() {
+        let data = dmatrix![
+            1.0, 1.0;
+            1.0, 3.0;
+            2.0, 4.0;
+            2.0, 6.0;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).eta_squared().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("eta_squared_x2", overall_result.parameter_names()[0]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![9.0 / 13.0]);
+        assert_eq!("eta squared (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_mantel_haenszel_dif() {
+        let data = dmatrix![
+            0.0, 1.0, 0.0;
+            0.0, 0.0, 1.0;
+            0.0, 1.0, 0.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+            1.0, 0.0, 1.0;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).mantel_haenszel_dif().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("mh_d_dif_x2", overall_result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates().rows(0, 2), dvector![4.0, -2.35 * 4.0_f64.ln()]);
+        assert_eq!("mantel haenszel dif (1 datasets with 6 cases; 6 weights of sum 6; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_odds_ratio() {
+        let data = dmatrix![
+            0.0, 1.0;
+            0.0, 0.0;
+            0.0, 0.0;
+            1.0, 1.0;
+            1.0, 1.0;
+            1.0, 0.0;
+        ];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).odds_ratio().calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("relative_risk_x2", overall_result.parameter_names()[2]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![4.0, 4.0_f64.ln(), 2.0, 2.0_f64.ln(), 1.0 / 3.0]);
+        assert_eq!("odds ratio (1 datasets with 6 cases; 6 weights of sum 6; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_concentration() {
+        let data = dmatrix![1.0; 2.0; 3.0; 4.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).concentration(2).calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("lorenz_0.5", overall_result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![0.25, 0.3]);
+        assert_eq!("concentration (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_percentile_shares() {
+        let data = dmatrix![1.0; 2.0; 3.0; 4.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).percentile_shares(&[(0.0, 20.0), (90.0, 100.0)]).calculate().unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("share_90_100_x1", overall_result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![0.08, 0.16]);
+        assert_eq!("percentile shares (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_agreement() {
+        let data = dmatrix![
+            1.0, 1.0;
             1.0, 2.0;
+            2.0, 2.0;
+            2.0, 2.0;
         ];
 
         let mut analysis1 = analysis();
-        analysis1.group_by(Imputation::No(&groups1));
+        let result = analysis1.for_data(Imputation::No(&data)).agreement().calculate().unwrap();
 
-        assert_eq!("none by 2 grouping columns (no data; wgt missing; no replicate weights)", analysis1.summary());
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("percent_agreement", overall_result.parameter_names()[0]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates().rows(0, 2), dvector![75.0, 0.5]);
+        assert_eq!("agreement (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+    }
 
-        let groups2imp1 = dmatrix![ 1.0; 1.0; 2.0; 2.0; ];
-        let groups2imp2 = dmatrix![ 1.0; 1.0; 1.0; 2.0; ];
-        let groups2imp3 = dmatrix![ 1.0; 1.0; 2.0; 2.0; ];
-        let mut groups2: Vec<&DMatrix<f64>> = Vec::new();
-        groups2.push(&groups2imp1);
-        groups2.push(&groups2imp2);
-        groups2.push(&groups2imp3);
+    #[test]
+    fn test_calculate_works_for_benchmark_levels() {
+        let data = dmatrix![380.0; 480.0; 530.0; 640.0];
 
-        let mut analysis2 = analysis();
-        analysis2.group_by(Imputation::Yes(&groups2));
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).benchmark_levels(&estimates::ILSA_BENCHMARKS).calculate().unwrap();
 
-        assert_eq!("none by 1 grouping columns (no data; wgt missing; no replicate weights)", analysis2.summary());
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("benchmark_400_475_x1", overall_result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![25.0, 0.0, 50.0, 0.0, 25.0]);
+        assert_eq!("benchmark levels (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
     }
 
     #[test]
-    fn test_calculate_does_not_work_without_data() {
+    fn test_calculate_works_for_range() {
+        let data = dmatrix![
+            1.0, 4.0;
+            2.5, f64::NAN;
+            -3.0, 3.0;
+        ];
+
         let mut analysis1 = analysis();
-        let result = analysis1.mean().calculate();
+        let result = analysis1.for_data(Imputation::No(&data)).range().calculate().unwrap();
 
-        assert!(result.is_err());
-        assert_eq!("Analysis is missing some element: data", result.err().unwrap().deref().to_string());
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("range_x2", overall_result.parameter_names()[5]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![-3.0, 3.0, 2.5, 4.0, 5.5, 1.0]);
+        assert_eq!("range (1 datasets with 3 cases; 3 weights of sum 3; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_missingness() {
+        let data = dmatrix![
+            1.0, 4.0;
+            2.5, f64::NAN;
+            f64::NAN, f64::NAN;
+            3.0, 3.0;
+        ];
+
+        let wgt = dvector![1.0, 0.5, 1.5, 2.0];
 
         let mut analysis1 = analysis();
-        let result = analysis1.mean().for_data(Imputation::Yes(&Vec::<&DMatrix<f64>>::new())).calculate();
+        let result = analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).missingness().calculate().unwrap();
 
-        assert!(result.is_err());
-        assert_eq!("Analysis is missing some element: data", result.err().unwrap().deref().to_string());
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("missing_unweighted_x1", overall_result.parameter_names()[2]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![30.0, 40.0, 25.0, 50.0]);
+        assert_eq!("missingness (1 datasets with 4 cases; 4 weights of sum 5; 0 replicate weights)", analysis1.summary());
     }
 
     #[test]
-    fn test_calculate_does_not_work_without_estimate() {
+    fn test_calculate_works_for_proportions_above() {
         let data = dmatrix![
-            537.0, 456.2, 501.7;
-            499.1, 433.2, 500.6;
-            611.0, 501.9, 588.2;
+            380.0, 512.0;
+            475.0, f64::NAN;
+            530.0, 401.0;
+            640.0, 470.0;
+        ];
+
+        let wgt = dvector![1.0, 0.5, 1.5, 1.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).proportions_above(&[400.0, 475.0]).calculate();
+
+        assert!(result.is_ok());
+        let result = result.unwrap();
+
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(4, overall_result.parameter_names().len());
+        assert_eq!("proportion_above_475_x2", overall_result.parameter_names()[3]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![0.75, 0.75, 1.0, 2.0 / 7.0]);
+        assert_eq!("proportions above (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
+    }
+
+    #[test]
+    fn test_calculate_works_for_percentile_ranks() {
+        let data = dmatrix![
+            380.0;
+            475.0;
+            530.0;
+            640.0;
         ];
 
         let mut analysis1 = analysis();
-        let result = analysis1.for_data(Imputation::No(&data)).calculate();
+        let result = analysis1.for_data(Imputation::No(&data)).percentile_ranks(&[500.0, 530.0]).calculate().unwrap();
 
-        assert!(result.is_err());
-        assert_eq!("Analysis is missing some element: estimate", result.err().unwrap().deref().to_string());
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!("percentile_rank_530_x1", overall_result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates(), dvector![50.0, 62.5]);
+        assert_eq!("percentile ranks (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
     }
 
     #[test]
-    fn test_calculate_does_not_work_with_unequal_rows_between_data_and_weights() {
+    fn test_calculate_works_for_principal_components() {
         let data = dmatrix![
-            537.0, 456.2, 501.7;
-            499.1, 433.2, 500.6;
-            611.0, 501.9, 588.2;
+            1.0, 2.0;
+            2.0, 1.0;
+            3.0, 3.0;
+            4.0, 4.0;
         ];
 
-        let wgt = dvector![1.0, 2.0];
-
         let mut analysis1 = analysis();
-        let result = analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).mean().calculate();
+        let result = analysis1.for_data(Imputation::No(&data)).principal_components().calculate().unwrap();
 
-        assert!(result.is_err());
-        assert_eq!("Inconsistency in analysis: unequal number of rows for data and weights", result.err().unwrap().deref().to_string());
+        let overall_result = &result[&GroupKey::overall()];
+        assert_eq!(6, overall_result.parameter_names().len());
+        assert_eq!("loading_pc1_x2", overall_result.parameter_names()[3]);
+        assert_approx_eq_iter_f64!(overall_result.final_estimates().rows(0, 4), dvector![1.8, 0.2, 0.9f64.sqrt(), 0.9f64.sqrt()]);
+        assert_eq!("principal components (1 datasets with 4 cases; 4 weights of sum 4; 0 replicate weights)", analysis1.summary());
     }
 
     #[test]
-    fn test_calculate_works_without_weights() {
-        let data = dmatrix![
-            537.0, 456.2, 501.7;
-            499.1, 433.2, 502.9;
-            611.0, 501.9, 589.3;
-        ];
+    fn test_calculate_works_for_standardized_mean_with_groups() {
+        let data = dmatrix![1.0; 2.0; 3.0; 4.0; 5.0; 6.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0];
 
         let mut analysis1 = analysis();
-        let result = analysis1.for_data(Imputation::No(&data)).mean().calculate();
+        let result = analysis1.for_data(Imputation::No(&data)).standardized_mean().group_by(Imputation::No(&groups)).calculate().unwrap();
 
-        assert!(result.is_ok());
-        let result = result.unwrap();
+        let first_result = result_for(&result, &["1"]).unwrap();
+        assert_eq!("standardized_mean_x1", first_result.parameter_names()[0]);
+        assert_approx_eq_iter_f64!(first_result.final_estimates(), dvector![-1.5 / 3.5f64.sqrt()]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["2"]).unwrap().final_estimates(), dvector![1.5 / 3.5f64.sqrt()]);
+        assert_eq!("standardized mean by 1 grouping columns (1 datasets with 6 cases; 6 weights of sum 6; 0 replicate weights)", analysis1.summary());
 
-        assert_eq!(1, result.len());
-        assert_eq!(3, result[&vec!["overall".to_string()]].final_estimates().len());
-        assert_eq!(531.3, result[&vec!["overall".to_string()]].final_estimates()[2]);
-        assert_eq!(0.0, result[&vec!["overall".to_string()]].standard_errors()[1]);
+        analysis1.mean();
+        let result = analysis1.calculate().unwrap();
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![2.0]);
+
+        // the reference mean and standard deviation follow the replicate weights, which drop the first case
+        let rep_wgts = dmatrix![0.0; 1.2; 1.2; 1.2; 1.2; 1.2];
+        let result = analysis().for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).standardized_mean().group_by(Imputation::No(&groups)).calculate().unwrap();
+        let first_result = result_for(&result, &["1"]).unwrap();
+        assert_approx_eq_iter_f64!(first_result.final_estimates(), dvector![-1.5 / 3.5f64.sqrt()]);
+        assert_approx_eq_iter_f64!(first_result.sampling_variances(), dvector![(-1.5 / 2.4f64.sqrt() + 1.5 / 3.5f64.sqrt()).powi(2)]);
+        assert_eq!(Some(3), first_result.number_cases());
     }
 
     #[test]
@@ -495,7 +2924,7 @@ mod tests {
         let result = result.unwrap();
 
         assert_eq!(1, result.len());
-        let first_result = result[&vec!["overall".to_string()]].clone();
+        let first_result = result[&GroupKey::overall()].clone();
 
         assert_eq!(4, first_result.parameter_names().len());
         assert_eq!("mean_x2", first_result.parameter_names()[1]);
@@ -547,7 +2976,7 @@ mod tests {
         let result = result.unwrap();
 
         assert_eq!(1, result.len());
-        let first_result = result[&vec!["overall".to_string()]].clone();
+        let first_result = result[&GroupKey::overall()].clone();
 
         assert_eq!(4, first_result.parameter_names().len());
         assert_eq!("mean_x2", first_result.parameter_names()[1]);
@@ -623,19 +3052,734 @@ mod tests {
 
         assert_eq!(2, result.len());
 
-        let first_result = result[&vec!["1".to_string()]].clone();
+        let first_result = result_for(&result, &["1"]).unwrap().clone();
         assert_approx_eq_iter_f64!(first_result.final_estimates(), dvector![2.25, 3.125, 2.0, -2.5]);
         assert_approx_eq_iter_f64!(first_result.sampling_variances(), dvector![1.000486111111111, 0.28265624999999994, 1.2229166666666667, 1.5625]);
         assert_approx_eq_iter_f64!(first_result.imputation_variances(), dvector![0.0069444444444443955, 0.0, 0.0002777777777777758, 0.0]);
         assert_approx_eq_iter_f64!(first_result.standard_errors(), dvector![1.0048608711510119, 0.5316542579534184, 1.1060230725608924, 1.25]);
 
-        let second_result = result[&vec!["2".to_string()]].clone();
+        let second_result = result_for(&result, &["2"]).unwrap().clone();
         assert_approx_eq_iter_f64!(second_result.final_estimates(), dvector![2.25, 3.125, 2.0, -2.5]);
         assert_approx_eq_iter_f64!(second_result.sampling_variances(), dvector![1.000486111111111, 0.28265624999999994, 1.2229166666666667, 1.5625]);
         assert_approx_eq_iter_f64!(second_result.imputation_variances(), dvector![0.0069444444444443955, 0.0, 0.0002777777777777758, 0.0]);
         assert_approx_eq_iter_f64!(second_result.standard_errors(), dvector![1.0048608711510119, 0.5316542579534184, 1.1060230725608924, 1.25]);
     }
 
+    #[test]
+    fn test_calculate_works_with_group_labels() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0];
+        let groups = dmatrix![1.0, 1.0; 1.0, 1.0; 1.0, 3.0; 2.0, 1.0; 2.0, 1.0; 2.0, 3.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).mean()
+            .set_group_labels(0, &[(1.0, "female"), (2.0, "male")])
+            .set_group_labels(1, &[(1.0, "urban")])
+            .with_pairwise_differences();
+
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(4 + 6, result.len());
+        assert_approx_eq_iter_f64!(result_for(&result, &["female", "urban"]).unwrap().final_estimates(), dvector![2.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["male", "3"]).unwrap().final_estimates(), dvector![9.0]);
+
+        // groups are ordered by their values rather than their labels
+        assert_eq!("mean_x1_diff_female_urban_vs_female_3", result_for(&result, &["female", "urban", "vs", "female", "3"]).unwrap().parameter_names()[0]);
+    }
+
+    #[test]
+    fn test_calculate_returns_structured_group_keys() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0];
+        let groups = dmatrix![1.0, 0.5; 1.0, 0.5; 2.0, 0.5; 2.0, 1.5];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).mean()
+            .set_group_names(&["gender", "track"])
+            .set_group_labels(0, &[(1.0, "female"), (2.0, "male")])
+            .with_pairwise_differences();
+
+        let result = analysis1.calculate().unwrap();
+        let key = result.keys().find(|key| key.key() == &vec!["male".to_string(), "1.5".to_string()]).unwrap();
+        assert_eq!(&vec!["gender".to_string(), "track".to_string()], key.variable_names());
+        assert_eq!(&vec![2.0, 1.5], key.values());
+        assert_eq!(&vec!["male".to_string(), "1.5".to_string()], key.labels());
+        assert!(!key.is_overall());
+        assert!(key.compared_to().is_none());
+
+        let key = result.keys().find(|key| key.key() == &vec!["female".to_string(), "0.5".to_string(), "vs".to_string(), "male".to_string(), "0.5".to_string()]).unwrap();
+        assert_eq!(&vec![1.0, 0.5], key.values());
+        assert_eq!(&vec![2.0, 0.5], key.compared_to().unwrap().values());
+
+        let result = analysis1.set_group_names(&["gender"]).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: number of group names does not match number of grouping columns", result.unwrap_err().to_string());
+
+        let result = analysis().for_data(Imputation::No(&data)).mean().calculate().unwrap();
+        let key = result.keys().find(|key| key.key() == &vec!["overall".to_string()]).unwrap();
+        assert!(key.is_overall());
+        assert_eq!(&GroupKey::overall(), key);
+    }
+
+    #[test]
+    fn test_group_keys_are_compared_by_values() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0];
+        let groups = dmatrix![10.0; 1.0; 2.0; 2.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).mean()
+            .set_group_labels(0, &[(1.0, "low"), (2.0, "low")]).calculate().unwrap();
+
+        assert_eq!(3, result.len());
+        assert_eq!(vec![vec![1.0], vec![2.0], vec![10.0]], result.keys().map(|key| key.values().clone()).collect::<Vec<Vec<f64>>>());
+        assert_eq!(vec![3.0, 3.5, 1.0], result.values().map(|estimates| estimates.final_estimates()[0]).collect::<Vec<f64>>());
+
+        let low = GroupKey::group(vec!["group1".to_string()], vec![1.0], vec!["low".to_string()]);
+        assert_ne!(low, GroupKey::group(vec!["group1".to_string()], vec![2.0], vec!["low".to_string()]));
+        assert!(low < GroupKey::group(vec!["group1".to_string()], vec![10.0], vec!["10".to_string()]));
+        assert!(GroupKey::overall() < low);
+    }
+
+    #[test]
+    fn test_calculate_works_with_marginals() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0];
+        let groups = dmatrix![1.0, 1.0; 1.0, 1.0; 1.0, 2.0; 2.0, 1.0; 2.0, 1.0; 2.0, 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).mean()
+            .set_group_names(&["gender", "urban"]).set_group_labels(0, &[(1.0, "female"), (2.0, "male")]).with_marginals();
+
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(4 + 1 + 2 + 2, result.len());
+
+        assert_approx_eq_iter_f64!(result_for(&result, &["female", "1"]).unwrap().final_estimates(), dvector![2.0]);
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![4.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["female", "all"]).unwrap().final_estimates(), dvector![3.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["male", "all"]).unwrap().final_estimates(), dvector![5.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["all", "1"]).unwrap().final_estimates(), dvector![2.5]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["all", "2"]).unwrap().final_estimates(), dvector![7.0]);
+
+        let key = result.keys().find(|key| key.key() == &vec!["all".to_string(), "2".to_string()]).unwrap();
+        assert_eq!(&vec!["gender".to_string(), "urban".to_string()], key.variable_names());
+        assert!(key.values()[0].is_nan());
+        assert_eq!(2.0, key.values()[1]);
+
+        let result = analysis1.group_by(Imputation::No(&groups.columns(0, 1).clone_owned())).set_group_names(&["gender"]).calculate().unwrap();
+        assert_eq!(2 + 1, result.len());
+    }
+
+    #[test]
+    fn test_calculate_works_with_overall_comparisons() {
+        let data = dmatrix![1.0; 3.0; 5.0; 8.0];
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0];
+        let rep_wgts = dmatrix![
+            0.0, 2.0, 1.0, 1.0;
+            2.0, 0.0, 1.0, 1.0;
+            1.0, 1.0, 0.0, 2.0;
+            1.0, 1.0, 2.0, 0.0;
+        ];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(0.5)
+            .group_by(Imputation::No(&groups)).mean().set_confidence_level(0.5);
+
+        let result = analysis1.calculate().unwrap();
+        assert!(result_for(&result, &["1"]).unwrap().difference_to_overall().is_none());
+
+        let result = analysis1.with_overall_comparisons().calculate().unwrap();
+        assert_eq!(2, result.len());
+
+        let difference = result_for(&result, &["1"]).unwrap().difference_to_overall().unwrap();
+        assert_approx_eq_iter_f64!(difference.final_estimates(), dvector![-2.25]);
+        assert_approx_eq_iter_f64!(difference.sampling_variances(), dvector![0.8125]);
+        assert_eq!(Some(vec![true]), result_for(&result, &["1"]).unwrap().significantly_different_from_overall());
+
+        let difference = result_for(&result, &["2"]).unwrap().difference_to_overall().unwrap();
+        assert_approx_eq_iter_f64!(difference.final_estimates(), dvector![2.25]);
+
+        let result = analysis1.group_by(Imputation::No(&groups)).frequencies().calculate();
+        assert!(result.is_err(), "groups with missing categories should not be compared to overall in test_calculate_works_with_overall_comparisons");
+    }
+
+    #[test]
+    fn test_calculate_works_with_expected_categories() {
+        let data = dmatrix![1.0; 2.0; 1.0; 3.0; 3.0; 3.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).frequencies();
+
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(2, result_for(&result, &["1"]).unwrap().parameter_names().len());
+        assert_eq!(1, result_for(&result, &["2"]).unwrap().parameter_names().len());
+
+        let result = analysis1.set_expected_categories(&[1.0, 2.0, 3.0]).calculate().unwrap();
+        assert_eq!(result_for(&result, &["1"]).unwrap().parameter_names(), result_for(&result, &["2"]).unwrap().parameter_names());
+        assert_eq!(3, result_for(&result, &["2"]).unwrap().parameter_names().len());
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![2.0, 1.0, 0.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["2"]).unwrap().final_estimates(), dvector![0.0, 0.0, 3.0]);
+
+        let options = estimates::FrequenciesOptions { percentages: false, expected_categories: Some(vec![3.0]) };
+        let result = analysis1.frequencies_with_options(&options).calculate().unwrap();
+        assert_eq!(&vec!["frequency_x1_cat3".to_string()], result_for(&result, &["1"]).unwrap().parameter_names());
+
+        let result = analysis1.set_expected_categories(&[]).calculate();
+        assert!(result.is_err(), "empty expected categories should be rejected in test_calculate_works_with_expected_categories");
+    }
+
+    #[test]
+    fn test_calculate_works_with_missing_strategy() {
+        let data = dmatrix![1.0, 2.0; f64::NAN, 4.0; 3.0, 6.0; 5.0, f64::NAN];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).mean();
+
+        let result = analysis1.calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![3.0, 4.0]);
+
+        let result = analysis1.set_missing_strategy(MissingStrategy::Listwise).calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![2.0, 4.0]);
+
+        let result = analysis1.set_missing_strategy(MissingStrategy::FailFast).calculate();
+        assert!(result.is_err(), "missing values should be rejected with fail-fast strategy in test_calculate_works_with_missing_strategy");
+
+        let complete_data = dmatrix![1.0, 2.0; 3.0, 6.0];
+        let mut analysis2 = analysis();
+        let result = analysis2.for_data(Imputation::No(&complete_data)).mean().set_missing_strategy(MissingStrategy::FailFast).calculate().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_calculate_works_with_group_parallelism() {
+        let data = DMatrix::from_fn(60, 2, |r, c| ((r * 7 + c * 3) % 11) as f64);
+        let groups = DMatrix::from_fn(60, 1, |r, _| (r % 6) as f64);
+        let rep_wgts = DMatrix::from_fn(60, 5, |r, c| if r % 5 == c { 0.0 } else { 1.25 });
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).group_by(Imputation::No(&groups)).mean();
+        let sequential_result = analysis1.calculate().unwrap();
+
+        // groups share the pool of the thread budget with their replications
+        for (group_parallelism, number_threads) in [(0, 0), (4, 0), (0, 2), (4, 2)] {
+            let parallel_result = analysis1.set_group_parallelism(group_parallelism).set_thread_budget(number_threads).calculate().unwrap();
+            assert_eq!(6, parallel_result.len());
+            for (key, result) in sequential_result.iter() {
+                assert_eq!(result.final_estimates(), parallel_result[key].final_estimates());
+                assert_eq!(result.sampling_variances(), parallel_result[key].sampling_variances());
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_returns_ordered_results() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0];
+        let groups = dmatrix![3.0; 1.0; 2.0; 3.0; 1.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).mean().with_pairwise_differences();
+
+        let result = analysis1.calculate().unwrap();
+        let keys : Vec<&Vec<String>> = result.keys().map(|key| key.key()).collect();
+        assert_eq!(vec![
+            &vec!["1".to_string()],
+            &vec!["1".to_string(), "vs".to_string(), "2".to_string()],
+            &vec!["1".to_string(), "vs".to_string(), "3".to_string()],
+            &vec!["2".to_string()],
+            &vec!["2".to_string(), "vs".to_string(), "3".to_string()],
+            &vec!["3".to_string()],
+        ], keys);
+
+        let serialize = |result: &BTreeMap<GroupKey, ReplicatedEstimates>| {
+            let external_result : BTreeMap<&GroupKey, crate::external::ReplicatedEstimates> = result.iter()
+                .map(|(key, value)| (key, crate::external::ReplicatedEstimates::from_internal(value))).collect();
+            rmp_serde::to_vec(&external_result).unwrap()
+        };
+        let serialized = serialize(&result);
+        for _ in 0..5 {
+            assert_eq!(serialized, serialize(&analysis1.calculate().unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_calculate_works_with_quantile_groups() {
+        let data_imp1 = dmatrix![1.0, 10.0; 2.0, 20.0; 3.0, 30.0; 4.0, 40.0; 5.0, 50.0; 6.0, 60.0];
+        let data_imp2 = dmatrix![6.0, 10.0; 5.0, 20.0; 4.0, 30.0; 3.0, 40.0; 2.0, 50.0; 1.0, 60.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data_imp1)).mean().group_by_quantiles(0, 3);
+
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(3, result.len());
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![1.5, 15.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["3"]).unwrap().final_estimates(), dvector![5.5, 55.0]);
+
+        let imputations = vec![&data_imp1, &data_imp2];
+        let mut analysis2 = analysis();
+        analysis2.for_data(Imputation::Yes(&imputations)).mean().group_by_quantiles(0, 2).with_marginals();
+
+        let result = analysis2.calculate().unwrap();
+        assert_eq!(3, result.len());
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![2.0, 35.0]);
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![3.5, 35.0]);
+
+        let result = analysis2.group_by_quantiles(2, 2).calculate();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: quantile grouping column out of range", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calculate_works_with_recoded_groups() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0];
+        let groups = dmatrix![1.0, 1.0; 2.0, 1.0; 3.0, 2.0; 4.0, 2.0; 4.0, 1.0; 5.0, 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups.columns(0, 1).clone_owned())).mean();
+        assert_eq!(5, analysis1.calculate().unwrap().len());
+
+        let result = analysis1.recode_groups(0, &[(2.0, 1.0), (4.0, 3.0), (5.0, 3.0)]).calculate().unwrap();
+        assert_eq!(2, result.len());
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![2.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["3"]).unwrap().final_estimates(), dvector![5.0]);
+
+        let result = analysis1.group_by(Imputation::No(&groups)).recode_groups(1, &[(2.0, 1.0)]).recode_groups(2, &[(1.0, 2.0)]).calculate().unwrap();
+        assert_eq!(5, result.len());
+        assert_approx_eq_iter_f64!(result_for(&result, &["4", "1"]).unwrap().final_estimates(), dvector![3.0]);
+    }
+
+    #[test]
+    fn test_calculate_works_with_appended_data() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0];
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.0, 2.0];
+        let rep_wgts = DMatrix::from_fn(6, 3, |r, c| if r % 3 == c { 0.0 } else { wgt[r] * 1.5 });
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).with_replicate_weights(&rep_wgts).group_by(Imputation::No(&groups)).mean();
+        let expected = analysis1.calculate().unwrap();
+
+        let mut analysis2 = analysis();
+        analysis2.mean();
+        for (start, length) in [(0, 2), (2, 3), (5, 1)] {
+            analysis2.append_data(Imputation::No(&data.rows(start, length).clone_owned())).unwrap()
+                .append_weights(&wgt.rows(start, length).clone_owned()).unwrap()
+                .append_replicate_weights(&rep_wgts.rows(start, length).clone_owned()).unwrap()
+                .append_groups(Imputation::No(&groups.rows(start, length).clone_owned())).unwrap();
+        }
+        let result = analysis2.calculate().unwrap();
+
+        assert_eq!(expected.len(), result.len());
+        for (key, expected_result) in expected.iter() {
+            assert_approx_eq_iter_f64!(expected_result.final_estimates(), result[key].final_estimates());
+            assert_approx_eq_iter_f64!(expected_result.sampling_variances(), result[key].sampling_variances());
+        }
+
+        let data_imp1 = dmatrix![1.0; 2.0];
+        let imputations = vec![&data_imp1, &data_imp1];
+        let result = analysis2.append_data(Imputation::Yes(&imputations));
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: appended data differ in number of imputations", result.err().unwrap().to_string());
+
+        let result = analysis2.append_replicate_weights(&dmatrix![1.0, 1.0]);
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: appended replicate weights differ in number of columns", result.err().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_for_data_owned_and_shared() {
+        let data = Arc::new(vec![dmatrix![1.0; 3.0; 5.0; 2.0], dmatrix![2.0; 4.0; 6.0; 3.0]]);
+        let groups = Arc::new(vec![dmatrix![1.0; 1.0; 2.0; 2.0]]);
+
+        let mut analysis1 = analysis();
+        analysis1.for_data_shared(data.clone()).group_by_shared(groups.clone()).mean();
+        assert!(Arc::ptr_eq(&data, analysis1.x.as_ref().unwrap()));
+        assert!(Arc::ptr_eq(&groups, analysis1.groups.as_ref().unwrap()));
+
+        let analysis2 = analysis1.clone();
+        assert!(Arc::ptr_eq(&data, analysis2.x.as_ref().unwrap()));
+
+        let result1 = analysis1.calculate().unwrap();
+        assert_approx_eq_iter_f64!(result_for(&result1, &["1"]).unwrap().final_estimates(), dvector![2.5]);
+        assert_approx_eq_iter_f64!(result_for(&result1, &["2"]).unwrap().final_estimates(), dvector![4.0]);
+
+        let mut analysis3 = analysis();
+        let result3 = analysis3.for_data_owned(data.to_vec()).group_by_owned(groups.to_vec()).mean().calculate().unwrap();
+        assert_approx_eq_iter_f64!(result_for(&result3, &["1"]).unwrap().final_estimates(), dvector![2.5]);
+        assert_eq!(2, analysis3.x.as_ref().unwrap().len());
+    }
+
+    #[test]
+    fn test_for_data_f32() {
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data_f32(vec![dmatrix![1.0f32; 3.0; 5.5; 2.5]]).group_by_f32(vec![dmatrix![1.0f32; 1.0; 2.0; 2.0]]).mean().calculate().unwrap();
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![2.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["2"]).unwrap().final_estimates(), dvector![4.0]);
+    }
+
+    #[test]
+    fn test_calculate_works_with_unweighted_estimates() {
+        let data1 = dmatrix![1.0; 3.0; 5.0; 2.0];
+        let data2 = dmatrix![2.0; 3.0; 5.0; 2.0];
+        let wgt = dvector![1.0, 2.0, 1.0, 4.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::Yes(&vec![&data1, &data2])).set_weights(&wgt).group_by(Imputation::No(&groups)).mean();
+
+        let result = analysis1.calculate().unwrap();
+        assert!(result_for(&result, &["1"]).unwrap().unweighted_estimates().is_none());
+
+        let result = analysis1.with_unweighted_estimates().calculate().unwrap();
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![3.125]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().unweighted_estimates().unwrap(), dvector![9.5 / 3.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["2"]).unwrap().unweighted_estimates().unwrap(), dvector![2.0]);
+
+        let result = analysis1.set_suppression(2, 0.0).calculate().unwrap();
+        assert!(result_for(&result, &["2"]).unwrap().unweighted_estimates().unwrap()[0].is_nan());
+    }
+
+    #[test]
+    fn test_case_counts() {
+        let data1 = dmatrix![1.0, f64::NAN; 3.0, 2.0; 5.0, 2.0; 2.0, 1.0];
+        let data2 = dmatrix![2.0, 1.0; 3.0, 2.0; f64::NAN, 2.0; 2.0, 1.0];
+        let wgt = dvector![1.0, 2.0, 1.0, 4.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::Yes(&vec![&data1, &data2])).set_weights(&wgt).group_by(Imputation::No(&groups)).mean().with_marginals().calculate().unwrap();
+
+        let group1 = result_for(&result, &["1"]).unwrap();
+        assert_eq!(Some(3), group1.number_cases());
+        assert_eq!(Some(4.0), group1.weight_sum());
+        assert_approx_eq_iter_f64!(group1.valid_cases().unwrap(), dvector![2.5, 2.5]);
+
+        let group2 = result_for(&result, &["2"]).unwrap();
+        assert_eq!(Some(1), group2.number_cases());
+        assert_eq!(Some(4.0), group2.weight_sum());
+
+        let overall = &result[&GroupKey::overall()];
+        assert_eq!(Some(4), overall.number_cases());
+        assert_eq!(Some(8.0), overall.weight_sum());
+        assert_approx_eq_iter_f64!(overall.valid_cases().unwrap(), dvector![3.5, 3.5]);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let data1 = dmatrix![1.0, f64::NAN; 3.0, f64::NAN; 5.0, 2.0];
+        let data2 = dmatrix![1.0, f64::NAN; 3.0, 1.0; 5.0, 2.0];
+        let groups = dmatrix![1.0; 1.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::Yes(&vec![&data1, &data2])).group_by(Imputation::No(&groups)).mean();
+
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(&vec!["group '1' has fewer than 30 cases".to_string(), "column x2 all NaN in imputation 1".to_string()], result_for(&result, &["1"]).unwrap().warnings());
+        assert_eq!(&vec!["group '2' has fewer than 30 cases".to_string()], result_for(&result, &["2"]).unwrap().warnings());
+
+        let result = analysis1.set_variable_names(&["ASRREA", "ASBGSES"]).calculate().unwrap();
+        assert_eq!("column ASBGSES all NaN in imputation 1", result_for(&result, &["1"]).unwrap().warnings()[1]);
+
+        let data = DMatrix::from_fn(30, 1, |r, _| r as f64);
+        let result = analysis().for_data(Imputation::No(&data)).mean().calculate().unwrap();
+        assert!(result[&GroupKey::overall()].warnings().is_empty());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let groups = dmatrix![1.0; 1.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&dmatrix![1.0; 3.0; 5.0])).group_by(Imputation::No(&groups)).mean().with_pairwise_differences();
+        analysis1.set_metadata("survey", "PIRLS").set_metadata("cycle", "2016").set_metadata("cycle", "2021");
+
+        let result = analysis1.calculate().unwrap();
+        assert_eq!(3, result.len());
+        for estimates in result.values() {
+            assert_eq!(2, estimates.metadata().len());
+            assert_eq!("PIRLS", estimates.metadata()["survey"]);
+            assert_eq!("2021", estimates.metadata()["cycle"]);
+        }
+
+        let restored = analysis_from_state(analysis1.state());
+        assert_eq!(analysis1.metadata, restored.metadata);
+    }
+
+    #[test]
+    fn test_compare() {
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0; 3.0; 3.0];
+        let rep_wgts = dmatrix![
+            0.0, 2.0, 1.0, 1.0, 1.0, 1.0;
+            2.0, 0.0, 1.0, 1.0, 1.0, 1.0;
+            1.0, 1.0, 0.0, 2.0, 1.0, 1.0;
+            1.0, 1.0, 2.0, 0.0, 1.0, 1.0;
+            1.0, 1.0, 1.0, 1.0, 0.0, 2.0;
+            1.0, 1.0, 1.0, 1.0, 2.0, 0.0;
+        ];
+
+        let mut analysis1 = analysis();
+        let results_a = analysis1.for_data(Imputation::No(&dmatrix![1.0; 3.0; 5.0; 7.0; 2.0; 2.0])).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(0.5).group_by(Imputation::No(&groups)).mean().calculate().unwrap();
+        let mut analysis2 = analysis();
+        let results_b = analysis2.for_data(Imputation::No(&dmatrix![1.0; 1.0; 4.0; 6.0])).with_replicate_weights(&rep_wgts.view((0, 0), (4, 4)).clone_owned()).set_variance_adjustment_factor(0.5).group_by(Imputation::No(&groups.rows(0, 4).clone_owned())).mean().calculate().unwrap();
+
+        let comparisons = compare(&results_a, &results_b);
+        assert_eq!(2, comparisons.len());
+
+        let comparison1 = result_for(&comparisons, &["1"]).unwrap();
+        assert_approx_eq_iter_f64!(comparison1.differences(), dvector![1.0]);
+        assert_approx_eq_iter_f64!(comparison1.standard_errors(), dvector![1.0]);
+
+        let comparison2 = result_for(&comparisons, &["2"]).unwrap();
+        assert_approx_eq_iter_f64!(comparison2.differences(), dvector![1.0]);
+        assert_approx_eq_iter_f64!(comparison2.standard_errors(), dvector![2.0_f64.sqrt()]);
+        assert_eq!(vec![false], comparison2.significant());
+    }
+
+    #[test]
+    fn test_calculate_reuses_group_splits() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).mean();
+        let result = analysis1.calculate().unwrap();
+        let first_split = analysis1.split_cache.lock().unwrap().as_ref().unwrap().split.clone();
+
+        let mut analysis2 = analysis1.clone();
+        let result2 = analysis2.total().calculate().unwrap();
+        assert!(Arc::ptr_eq(&first_split, &analysis2.split_cache.lock().unwrap().as_ref().unwrap().split));
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![3.0]);
+        assert_approx_eq_iter_f64!(result_for(&result2, &["1"]).unwrap().final_estimates(), dvector![9.0]);
+
+        analysis1.calculate().unwrap();
+        assert!(Arc::ptr_eq(&first_split, &analysis1.split_cache.lock().unwrap().as_ref().unwrap().split));
+
+        let result = analysis1.group_by(Imputation::No(&dmatrix![1.0; 2.0; 1.0; 2.0; 1.0; 2.0])).calculate().unwrap();
+        assert!(!Arc::ptr_eq(&first_split, &analysis1.split_cache.lock().unwrap().as_ref().unwrap().split));
+        assert_approx_eq_iter_f64!(result_for(&result, &["1"]).unwrap().final_estimates(), dvector![10.0 / 3.0]);
+    }
+
+    #[test]
+    fn test_pairwise_differences() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 2.0; 8.0; 4.0; 4.0; 4.0];
+        let rep_wgts = dmatrix![
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+        ];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0; 3.0; 3.0; 3.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).group_by(Imputation::No(&groups)).mean().calculate().unwrap();
+
+        let differences = pairwise_differences(&result).unwrap();
+        assert_eq!(3, differences.len());
+
+        let difference_1_vs_2 = result_for(&differences, &["1", "vs", "2"]).unwrap();
+        assert_eq!("mean_x1", difference_1_vs_2.parameter_names()[0]);
+        assert_approx_eq_iter_f64!(difference_1_vs_2.final_estimates(), dvector![-1.0]);
+        assert_approx_eq_iter_f64!(difference_1_vs_2.standard_errors(), dvector![2.0_f64.sqrt()]);
+
+        assert_approx_eq_iter_f64!(result_for(&differences, &["2", "vs", "3"]).unwrap().final_estimates(), dvector![0.0]);
+        assert_approx_eq_iter_f64!(result_for(&differences, &["2", "vs", "3"]).unwrap().standard_errors(), result_for(&result, &["2"]).unwrap().standard_errors());
+
+        assert!(result_for(&differences, &["2", "vs", "1"]).is_none());
+
+        // groups are paired in the order of their values, so group 2 comes before group 10
+        let groups = dmatrix![2.0; 2.0; 2.0; 10.0; 10.0; 10.0; 3.0; 3.0; 3.0];
+        let result = analysis1.group_by(Imputation::No(&groups)).calculate().unwrap();
+
+        let differences = pairwise_differences(&result).unwrap();
+        let keys : Vec<&Vec<String>> = differences.keys().map(|key| key.key()).collect();
+        assert_eq!(vec![&vec!["2".to_string(), "vs".to_string(), "3".to_string()], &vec!["2".to_string(), "vs".to_string(), "10".to_string()], &vec!["3".to_string(), "vs".to_string(), "10".to_string()]], keys);
+        assert_approx_eq_iter_f64!(result_for(&differences, &["2", "vs", "10"]).unwrap().final_estimates(), dvector![-1.0]);
+    }
+
+    #[test]
+    fn test_calculate_works_with_pairwise_differences() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 2.0; 8.0; 4.0; 4.0; 4.0];
+        let rep_wgts = dmatrix![
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+            0.0, 1.0, 1.0;
+            1.0, 0.0, 1.0;
+            1.0, 1.0, 0.0;
+        ];
+        let groups = dmatrix![1.0; 1.0; 1.0; 2.0; 2.0; 2.0; 3.0; 3.0; 3.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).with_replicate_weights(&rep_wgts).group_by(Imputation::No(&groups)).mean().with_pairwise_differences().calculate().unwrap();
+
+        assert_eq!(6, result.len());
+        assert_eq!("mean_x1", result_for(&result, &["1"]).unwrap().parameter_names()[0]);
+
+        assert_eq!("mean_x1_diff_1_vs_2", result_for(&result, &["1", "vs", "2"]).unwrap().parameter_names()[0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["1", "vs", "2"]).unwrap().final_estimates(), dvector![-1.0]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["1", "vs", "2"]).unwrap().standard_errors(), dvector![2.0_f64.sqrt()]);
+
+        assert_eq!("mean_x1_diff_1_vs_3", result_for(&result, &["1", "vs", "3"]).unwrap().parameter_names()[0]);
+    }
+
+    #[test]
+    fn test_calculate_pairwise_differences_with_differing_categories() {
+        let data = dmatrix![1.0; 2.0; 1.0; 3.0];
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).group_by(Imputation::No(&groups)).frequencies().with_pairwise_differences();
+        let result = analysis1.calculate();
+        assert_eq!("Inconsistency in analysis: parameters differ between groups, so pairwise differences cannot be calculated", result.unwrap_err().to_string());
+
+        let result = analysis1.set_expected_categories(&[1.0, 2.0, 3.0]).calculate().unwrap();
+        assert_eq!("frequency_x1_cat3_diff_1_vs_2", result_for(&result, &["1", "vs", "2"]).unwrap().parameter_names()[2]);
+        assert_approx_eq_iter_f64!(result_for(&result, &["1", "vs", "2"]).unwrap().final_estimates(), dvector![0.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_pairwise_differences_without_groups() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).mean().calculate().unwrap();
+
+        assert_eq!(0, pairwise_differences(&result).unwrap().len());
+    }
+
+    #[test]
+    fn test_analysis_plan() {
+        let data = dmatrix![1.0, 2.0, 1.0; 3.0, 4.0, 1.0; 5.0, 9.0, 2.0; 7.0, 1.0, 2.0];
+        let wgt = dvector![1.0, 1.0, 2.0, 2.0];
+
+        let mut shared = analysis();
+        shared.for_data(Imputation::No(&data)).set_weights(&wgt).set_variable_names(&["ASRREA", "ASRLIT", "ITSEX"]);
+
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0];
+        let mut plan = analysis_plan(&shared);
+        plan.add("means", &[0, 1], |analysis| { analysis.mean(); })
+            .add("totals by group", &[0], move |analysis| { analysis.total().group_by(Imputation::No(&groups)); })
+            .add("everything", &[], |analysis| { analysis.mean(); });
+        assert_eq!(vec!["means".to_string(), "totals by group".to_string(), "everything".to_string()], plan.names());
+
+        let results = plan.execute().unwrap();
+        assert_eq!(3, results.len());
+
+        let means = &results["means"][&GroupKey::overall()];
+        assert_eq!(&vec!["mean_ASRREA".to_string(), "mean_ASRLIT".to_string()], means.parameter_names());
+        assert_approx_eq_iter_f64!(means.final_estimates(), dvector![28.0 / 6.0, 26.0 / 6.0]);
+
+        let totals = &results["totals by group"];
+        assert_approx_eq_iter_f64!(result_for(totals, &["1"]).unwrap().final_estimates(), dvector![4.0]);
+        assert_approx_eq_iter_f64!(result_for(totals, &["2"]).unwrap().final_estimates(), dvector![24.0]);
+
+        assert_eq!(3, results["everything"][&GroupKey::overall()].parameter_names().len());
+
+        let mut plan = analysis_plan(&shared);
+        plan.add("means", &[3], |analysis| { analysis.mean(); });
+        let result = plan.execute();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: selected column out of range in analysis plan", result.unwrap_err().to_string());
+
+        let mut plan = analysis_plan(&shared);
+        plan.add("means", &[], |analysis| { analysis.mean(); }).add("means", &[], |analysis| { analysis.total(); });
+        let result = plan.execute();
+        assert!(result.is_err());
+        assert_eq!("Inconsistency in analysis: duplicate analysis name means in analysis plan", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let data = dmatrix![1.0, 2.0; 3.0, 4.0; 5.0, 9.0; 7.0, 1.0];
+        let wgt = dvector![1.0, 1.0, 2.0, 2.0];
+        let rep_wgts = dmatrix![
+            0.0, 2.0;
+            2.0, 0.0;
+            2.0, 2.0;
+            2.0, 2.0;
+        ];
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0];
+
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).with_replicate_weights(&rep_wgts).set_variance_method(VarianceMethod::JK2)
+            .group_by(Imputation::No(&groups)).set_group_labels(0, &[(1.0, "female")]).set_variable_names(&["ASRREA", "ASRLIT"])
+            .mean().also().percentile_ranks(&[2.0]).set_confidence_level(0.9);
+
+        let path = "/tmp/replicest_test_save_and_restore";
+        analysis1.save(path).unwrap();
+        let mut analysis2 = restore_analysis(path).unwrap();
+        let _ = remove_file(path);
+
+        assert_eq!(analysis1.summary(), analysis2.summary());
+
+        let result1 = analysis1.calculate().unwrap();
+        let result2 = analysis2.calculate().unwrap();
+        assert_eq!(result1.len(), result2.len());
+        for (key, estimates1) in result1.iter() {
+            let estimates2 = &result2[key];
+            assert_eq!(estimates1.parameter_names(), estimates2.parameter_names());
+            assert_approx_eq_iter_f64!(estimates1.final_estimates(), estimates2.final_estimates());
+            assert_approx_eq_iter_f64!(estimates1.standard_errors(), estimates2.standard_errors());
+            assert_approx_eq_iter_f64!(estimates1.confidence_intervals_lower(), estimates2.confidence_intervals_lower());
+        }
+
+        assert!(restore_analysis("/tmp/replicest_test_file_that_does_not_exist").is_err());
+    }
+
+    #[test]
+    fn test_analysis_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).mean();
+        assert_send(&analysis1);
+
+        let handle = std::thread::spawn(move || analysis1.calculate().unwrap());
+        let result = handle.join().unwrap();
+        assert_approx_eq_iter_f64!(result[&GroupKey::overall()].final_estimates(), dvector![3.0]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_copy_is_clone() {
+        let mut analysis1 = analysis();
+        analysis1.set_weights(&dvector![1.0, 2.0]).mean();
+        assert_eq!(analysis1.clone().summary(), analysis1.copy().summary());
+    }
+
+    #[test]
+    fn test_summary_struct() {
+        let data = dmatrix![1.0, 2.0; 3.0, 4.0; 5.0, 9.0; 2.0, 2.0];
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0];
+        let wgt = dvector![1.0, 2.0, 1.0, 2.0];
+        let rep_wgts = dmatrix![0.0, 2.0; 4.0, 0.0; 1.0, 1.0; 2.0, 2.0];
+
+        let mut analysis1 = analysis();
+        let summary = analysis1.summary_struct();
+        assert!(summary.estimates.is_empty());
+        assert_eq!(0, summary.number_datasets);
+        assert_eq!(None, summary.number_cases);
+        assert_eq!(None, summary.weight_sum);
+        assert_eq!(1.0, summary.variance_adjustment_factor);
+
+        analysis1.for_data(Imputation::No(&data)).set_weights(&wgt).with_replicate_weights(&rep_wgts).set_variance_adjustment_factor(0.5)
+            .group_by(Imputation::No(&groups)).set_group_names(&["gender"]).mean().also().total();
+        let summary = analysis1.summary_struct();
+        assert_eq!(vec!["mean".to_string(), "total".to_string()], summary.estimates);
+        assert_eq!(1, summary.number_datasets);
+        assert_eq!(Some(4), summary.number_cases);
+        assert_eq!(Some(4), summary.number_weights);
+        assert_eq!(Some(6.0), summary.weight_sum);
+        assert_eq!(Some(2), summary.number_replicates);
+        assert_eq!(0.5, summary.variance_adjustment_factor);
+        assert_eq!(Some(1), summary.number_grouping_columns);
+        assert_eq!(Some(vec!["gender".to_string()]), summary.group_names);
+    }
+
     #[test]
     fn test_copying() {
         let wgts = dvector![1.1, 1.5, 1.3, 1.7, 1.7, 1.0];
@@ -643,24 +3787,24 @@ mod tests {
         let mut base_analysis = analysis();
         base_analysis.set_weights(&wgts);
 
-        let mut analysis1 = base_analysis.copy();
+        let mut analysis1 = base_analysis.clone();
         analysis1.mean();
 
         assert_eq!("none (no data; 6 weights of sum 8.3; no replicate weights)", base_analysis.summary());
         assert_eq!("mean (no data; 6 weights of sum 8.3; no replicate weights)", analysis1.summary());
-        assert_eq!(2, Rc::strong_count(base_analysis.wgt.as_ref().unwrap()));
+        assert_eq!(2, Arc::strong_count(base_analysis.wgt.as_ref().unwrap()));
 
         let new_wgts = dvector![2.1, 2.5, 2.3, 2.7, 2.7, 2.0];
         analysis1.set_weights(&new_wgts);
 
         assert_eq!("none (no data; 6 weights of sum 8.3; no replicate weights)", base_analysis.summary());
         assert_eq!("mean (no data; 6 weights of sum 14.3; no replicate weights)", analysis1.summary());
-        assert_eq!(1, Rc::strong_count(base_analysis.wgt.as_ref().unwrap()));
+        assert_eq!(1, Arc::strong_count(base_analysis.wgt.as_ref().unwrap()));
 
-        let mut analysis2 = analysis1.copy();
+        let mut analysis2 = analysis1.clone();
 
         assert_eq!("mean (no data; 6 weights of sum 14.3; no replicate weights)", analysis2.summary());
-        assert_eq!(2, Rc::strong_count(analysis2.wgt.as_ref().unwrap()));
+        assert_eq!(2, Arc::strong_count(analysis2.wgt.as_ref().unwrap()));
 
         let data = dmatrix![
             537.0, 456.2, 501.7;
@@ -668,14 +3812,14 @@ mod tests {
             611.0, 501.9, 588.2;
         ];
         analysis2.for_data(Imputation::No(&data));
-        let analysis3 = analysis2.copy();
+        let analysis3 = analysis2.clone();
 
         assert_eq!("mean (1 datasets with 3 cases; 6 weights of sum 14.3; no replicate weights)", analysis3.summary());
-        assert_eq!(3, Rc::strong_count(analysis2.wgt.as_ref().unwrap()));
+        assert_eq!(3, Arc::strong_count(analysis2.wgt.as_ref().unwrap()));
 
         analysis1.set_weights(&wgts);
 
-        assert_eq!(2, Rc::strong_count(analysis2.wgt.as_ref().unwrap()));
+        assert_eq!(2, Arc::strong_count(analysis2.wgt.as_ref().unwrap()));
     }
 
     #[test]
@@ -705,12 +3849,12 @@ mod tests {
         let mut analysis1 = analysis();
         analysis1.for_data(Imputation::Yes(&imp_data)).set_weights(&wgt).mean();
 
-        let mut analysis2 = analysis1.copy();
+        let mut analysis2 = analysis1.clone();
 
         assert_eq!(1, analysis1.calculate().unwrap().len());
         assert_eq!(1, analysis2.calculate().unwrap().len());
 
-        let mut analysis3 = analysis1.copy();
+        let mut analysis3 = analysis1.clone();
 
         assert_eq!(1, analysis3.calculate().unwrap().len());
     }
@@ -0,0 +1,65 @@
+use std::env::var;
+use std::error::Error;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::net::{UnixDatagram, UnixListener};
+
+// the first file descriptor systemd passes on (SD_LISTEN_FDS_START)
+const LISTEN_FDS_START: RawFd = 3;
+
+// names to give the sockets with FileDescriptorName=, otherwise they are expected in this order
+pub const MESSAGE_SOCKET_NAME: &str = "replicest_server";
+pub const DATA_SOCKET_NAME: &str = "replicest_server_data";
+
+// the message and the data socket if the server was started by systemd socket activation
+pub fn inherited_sockets() -> Result<Option<(UnixDatagram, UnixListener)>, Box<dyn Error>> {
+    let file_descriptors = listen_fds(
+        var("LISTEN_PID").ok().as_deref(),
+        var("LISTEN_FDS").ok().as_deref(),
+        var("LISTEN_FDNAMES").ok().as_deref(),
+        std::process::id(),
+    )?;
+
+    Ok(file_descriptors.map(|(message_fd, data_fd)| {
+        // SAFETY: systemd hands over these file descriptors to this process only, and they are taken over exactly once
+        unsafe { (UnixDatagram::from_raw_fd(message_fd), UnixListener::from_raw_fd(data_fd)) }
+    }))
+}
+
+fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, listen_fdnames: Option<&str>, pid: u32) -> Result<Option<(RawFd, RawFd)>, Box<dyn Error>> {
+    // the variables may have been inherited from a parent started by systemd
+    match listen_pid.and_then(|listen_pid| listen_pid.parse::<u32>().ok()) {
+        Some(listen_pid) if listen_pid == pid => {}
+        _ => return Ok(None),
+    }
+
+    let count = listen_fds.and_then(|listen_fds| listen_fds.parse::<RawFd>().ok()).ok_or("invalid LISTEN_FDS")?;
+    if count != 2 {
+        return Err(format!("expected the message and the data socket from systemd but received {} sockets", count).into());
+    }
+
+    let names : Vec<&str> = listen_fdnames.map(|names| names.split(':').collect()).unwrap_or_default();
+    let position = |name: &str| names.iter().position(|candidate| *candidate == name).map(|i| LISTEN_FDS_START + i as RawFd);
+
+    match (position(MESSAGE_SOCKET_NAME), position(DATA_SOCKET_NAME)) {
+        (Some(message_fd), Some(data_fd)) => Ok(Some((message_fd, data_fd))),
+        _ => Ok(Some((LISTEN_FDS_START, LISTEN_FDS_START + 1))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fds() {
+        assert_eq!(None, listen_fds(None, None, None, 42).unwrap());
+        assert_eq!(None, listen_fds(Some("41"), Some("2"), None, 42).unwrap());
+
+        assert_eq!(Some((3, 4)), listen_fds(Some("42"), Some("2"), None, 42).unwrap());
+        assert_eq!(Some((3, 4)), listen_fds(Some("42"), Some("2"), Some("replicest.socket:replicest.socket"), 42).unwrap());
+        assert_eq!(Some((4, 3)), listen_fds(Some("42"), Some("2"), Some("replicest_server_data:replicest_server"), 42).unwrap());
+
+        assert!(listen_fds(Some("42"), Some("1"), None, 42).is_err());
+        assert!(listen_fds(Some("42"), None, None, 42).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{metadata, read_to_string};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// with a token configured, clients have to send "auth <token>" before any other message
+#[derive(Clone, Default)]
+pub struct Authentication {
+    token: Option<Arc<String>>,
+}
+
+impl Authentication {
+    pub fn from_token_file(path: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let token = match path {
+            None => None,
+            Some(path) => {
+                let token = read_to_string(path)?.trim().to_string();
+                if token.is_empty() {
+                    return Err(format!("token file {} is empty", path).into());
+                }
+                Some(Arc::new(token))
+            }
+        };
+
+        Ok(Authentication { token })
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.token.is_some()
+    }
+
+    pub fn accepts(&self, token: &str) -> bool {
+        match self.token.as_ref() {
+            None => true,
+            Some(expected) => constant_time_eq(expected.as_bytes(), token.as_bytes()),
+        }
+    }
+
+    // the response to an auth message, or None for any other message
+    pub fn authenticate(&self, message: &str) -> Option<(bool, &'static str)> {
+        let token = message.strip_prefix("auth ")?;
+        if self.accepts(token) {
+            Some((true, "authenticated"))
+        } else {
+            Some((false, "error: invalid token"))
+        }
+    }
+}
+
+// clients of the message socket are recognized by the path their socket is bound to, and only as long as the
+// socket file they authenticated with is bound there, so that a process binding the path later has to authenticate again
+#[derive(Default)]
+pub struct AuthenticatedClients {
+    socket_files: BTreeMap<PathBuf, SocketFile>,
+}
+
+impl AuthenticatedClients {
+    pub fn insert(&mut self, client_path: &Path) {
+        if let Some(file) = socket_file(client_path) {
+            self.socket_files.insert(client_path.to_path_buf(), file);
+        }
+    }
+
+    pub fn contains(&mut self, client_path: &Path) -> bool {
+        let bound = self.socket_files.get(client_path).is_some_and(|&file| socket_file(client_path) == Some(file));
+        if !bound {
+            self.socket_files.remove(client_path);
+        }
+        bound
+    }
+
+    // forgets the clients whose sockets are gone
+    pub fn remove_unbound(&mut self) -> Vec<PathBuf> {
+        let unbound : Vec<PathBuf> = self.socket_files.iter()
+            .filter(|(client_path, &file)| socket_file(client_path) != Some(file))
+            .map(|(client_path, _)| client_path.clone())
+            .collect();
+
+        for client_path in unbound.iter() {
+            self.socket_files.remove(client_path);
+        }
+
+        unbound
+    }
+}
+
+// inodes of removed files may be reused, but not within the same change time
+type SocketFile = (u64, i64, i64);
+
+fn socket_file(client_path: &Path) -> Option<SocketFile> {
+    metadata(client_path).ok().map(|metadata| (metadata.ino(), metadata.ctime(), metadata.ctime_nsec()))
+}
+
+// comparing all bytes does not reveal how much of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{remove_file, write};
+    use std::os::unix::net::UnixDatagram;
+    use super::*;
+
+    #[test]
+    fn test_authentication() {
+        let authentication = Authentication::from_token_file(None).unwrap();
+        assert!(!authentication.is_required());
+        assert!(authentication.accepts("anything"));
+
+        let path = "/tmp/replicest_server_test_authentication_token";
+        write(path, "s3cr3t\n").unwrap();
+        let authentication = Authentication::from_token_file(Some(path)).unwrap();
+        assert!(authentication.is_required());
+        assert!(authentication.accepts("s3cr3t"));
+        assert!(!authentication.accepts("s3cr3"));
+        assert!(!authentication.accepts("s3cr3t!"));
+
+        assert_eq!(Some((true, "authenticated")), authentication.authenticate("auth s3cr3t"));
+        assert_eq!(Some((false, "error: invalid token")), authentication.authenticate("auth guess"));
+        assert!(authentication.authenticate("mean").is_none());
+
+        write(path, "\n").unwrap();
+        assert!(Authentication::from_token_file(Some(path)).is_err());
+
+        let _ = remove_file(path);
+    }
+
+    #[test]
+    fn test_authenticated_clients() {
+        let client_path = Path::new("/tmp/replicest_server_test_authenticated_clients");
+        let _ = remove_file(client_path);
+
+        let mut authenticated_clients = AuthenticatedClients::default();
+        authenticated_clients.insert(client_path);
+        assert!(!authenticated_clients.contains(client_path));
+
+        let client = UnixDatagram::bind(client_path).unwrap();
+        authenticated_clients.insert(client_path);
+        assert!(authenticated_clients.contains(client_path));
+        assert!(authenticated_clients.remove_unbound().is_empty());
+
+        // another socket bound to the same path is not authenticated, file times may be as coarse as a clock tick
+        drop(client);
+        remove_file(client_path).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let client = UnixDatagram::bind(client_path).unwrap();
+        assert!(!authenticated_clients.contains(client_path));
+
+        authenticated_clients.insert(client_path);
+        drop(client);
+        remove_file(client_path).unwrap();
+        assert_eq!(vec![client_path.to_path_buf()], authenticated_clients.remove_unbound());
+        assert!(!authenticated_clients.contains(client_path));
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+
+// increased whenever the messages or the format of the data change in a way clients have to know about
+pub const PROTOCOL_VERSION: u32 = 2;
+
+const STATISTICS: [&str; 31] = [
+    "mean",
+    "total",
+    "linear regression",
+    "linear regression with residual diagnostics",
+    "poisson regression",
+    "effect size",
+    "t test",
+    "two sample t test",
+    "eta squared",
+    "mantel haenszel dif",
+    "odds ratio",
+    "gini",
+    "concentration",
+    "percentile shares",
+    "theil",
+    "atkinson",
+    "benchmark levels",
+    "percentile ranks",
+    "binned frequencies",
+    "binned frequencies equal width",
+    "polychoric correlation",
+    "polyserial correlation",
+    "frequencies",
+    "frequencies with percentages",
+    "agreement",
+    "crosstab",
+    "range",
+    "missingness",
+    "standardized mean",
+    "principal components",
+    "proportions above",
+];
+
+const OPTIONS: [&str; 9] = [
+    "also",
+    "group by",
+    "group names",
+    "group labels",
+    "variable names",
+    "metadata",
+    "set variance adjustment factor",
+    "set confidence level",
+    "set output format",
+];
+
+const DATA_FORMATS: [&str; 4] = ["f64", "arrow", "csv", "parquet"];
+
+const RESULT_FORMATS: [&str; 3] = ["msgpack", "json", "csv"];
+
+const COMPRESSIONS: [&str; 1] = ["zstd"];
+
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    pub version: &'static str,
+    pub protocol_version: u32,
+    pub statistics: Vec<&'static str>,
+    pub options: Vec<&'static str>,
+    pub data_formats: Vec<&'static str>,
+    pub result_formats: Vec<&'static str>,
+    pub sessions: bool,
+    pub byte_order: &'static str,
+    pub framed_data: bool,
+    pub named_columns: bool,
+    pub compressions: Vec<&'static str>,
+}
+
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        protocol_version: PROTOCOL_VERSION,
+        statistics: STATISTICS.to_vec(),
+        options: OPTIONS.to_vec(),
+        data_formats: DATA_FORMATS.to_vec(),
+        result_formats: RESULT_FORMATS.to_vec(),
+        sessions: true,
+        byte_order: "little",
+        framed_data: true,
+        named_columns: true,
+        compressions: COMPRESSIONS.to_vec(),
+    }
+}
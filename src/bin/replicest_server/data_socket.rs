@@ -0,0 +1,270 @@
+use std::error::Error;
+use std::io::ErrorKind;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+use tracing::info;
+use crate::auth::Authentication;
+use crate::DataSource;
+
+// connections starting with this marker carry any number of payloads of the same command, each preceded by its length as little-endian u64
+pub const FRAME_MARKER: &[u8; 8] = b"RPLFRAME";
+
+// with a token configured, connections have to start with the token and a newline
+const MAX_TOKEN_LINE_LENGTH: usize = 4096;
+
+// the default for --max-payload-size
+pub const DEFAULT_MAX_PAYLOAD_SIZE: u64 = 1 << 30;
+
+pub struct DataSocket {
+    listener: UnixListener,
+    // larger payloads are rejected before any memory is allocated for them
+    max_payload_size: u64,
+    authentication: Authentication,
+}
+
+impl DataSocket {
+    pub fn bind(path: impl AsRef<Path>, max_payload_size: u64, authentication: Authentication) -> Result<Self, Box<dyn Error>> {
+        Ok(DataSocket { listener: UnixListener::bind(path)?, max_payload_size, authentication })
+    }
+
+    pub fn from_std(listener: std::os::unix::net::UnixListener, max_payload_size: u64, authentication: Authentication) -> Result<Self, Box<dyn Error>> {
+        Ok(DataSocket { listener: UnixListener::from_std(listener)?, max_payload_size, authentication })
+    }
+
+    // every command receives its data with a receiver of its own, so that the payloads
+    // of a framed connection cannot be taken by another command or session
+    pub fn receiver(&self) -> DataReceiver<'_> {
+        DataReceiver { data_socket: self, framed_connection: Mutex::new(None) }
+    }
+}
+
+pub struct DataReceiver<'a> {
+    data_socket: &'a DataSocket,
+    // a framed connection is kept open for the payloads following the first one, and closed with the receiver
+    framed_connection: Mutex<Option<UnixStream>>,
+}
+
+impl DataReceiver<'_> {
+    async fn receive(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let max_payload_size = self.data_socket.max_payload_size;
+        let mut framed_connection = self.framed_connection.lock().await;
+
+        if let Some(connection) = framed_connection.as_mut() {
+            match read_frame(connection, max_payload_size).await {
+                Ok(Some(payload)) => return Ok(payload),
+                Ok(None) => *framed_connection = None,
+                Err(err) => {
+                    // the rest of the connection cannot be read as frames anymore
+                    *framed_connection = None;
+                    return Err(err);
+                }
+            }
+        }
+
+        let (mut connection, _) = self.data_socket.listener.accept().await?;
+
+        let authentication = &self.data_socket.authentication;
+        if authentication.is_required() && !authentication.accepts(&read_token_line(&mut connection).await?) {
+            return Err("data connection not authenticated".into());
+        }
+
+        let mut buffer = Vec::new();
+        let _ = (&mut connection).take(FRAME_MARKER.len() as u64).read_to_end(&mut buffer).await?;
+
+        if buffer == FRAME_MARKER {
+            let payload = read_frame(&mut connection, max_payload_size).await?.ok_or("framed connection closed before the first payload")?;
+            *framed_connection = Some(connection);
+            return Ok(payload);
+        }
+
+        let _ = connection.take(max_payload_size + 1 - buffer.len() as u64).read_to_end(&mut buffer).await?;
+        if buffer.len() as u64 > max_payload_size {
+            return Err(payload_too_large(max_payload_size));
+        }
+        Ok(buffer)
+    }
+}
+
+impl DataSource for DataReceiver<'_> {
+    fn receive_data(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        // messages are handled on blocking threads, from where the runtime is asked to do the reception
+        let buffer = tokio::runtime::Handle::current().block_on(self.receive())?;
+        info!(bytes = buffer.len(), "received data");
+        Ok(buffer)
+    }
+
+    fn max_payload_size(&self) -> u64 {
+        self.data_socket.max_payload_size
+    }
+}
+
+async fn read_frame(connection: &mut UnixStream, max_payload_size: u64) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let mut length = [0; 8];
+    match connection.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+
+    let length = u64::from_le_bytes(length);
+    if length > max_payload_size {
+        return Err(payload_too_large(max_payload_size));
+    }
+
+    let mut payload = vec![0; length as usize];
+    connection.read_exact(&mut payload).await?;
+    Ok(Some(payload))
+}
+
+async fn read_token_line(connection: &mut UnixStream) -> Result<String, Box<dyn Error>> {
+    let mut line = Vec::new();
+    loop {
+        match connection.read_u8().await {
+            Ok(b'\n') => return Ok(String::from_utf8_lossy(&line).into_owned()),
+            Ok(byte) if line.len() < MAX_TOKEN_LINE_LENGTH => line.push(byte),
+            Ok(_) => return Err("data connection not authenticated".into()),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Err("data connection not authenticated".into()),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn payload_too_large(max_payload_size: u64) -> Box<dyn Error> {
+    format!("payload exceeds the maximum size of {} bytes", max_payload_size).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{remove_file, write};
+    use std::sync::Arc;
+    use tokio::io::AsyncWriteExt;
+    use super::*;
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        [&(payload.len() as u64).to_le_bytes(), payload].concat()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_receive_data() {
+        let data_socket_addr = "/tmp/replicest_server_test_data_socket_receive_data".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = Arc::new(DataSocket::bind(&data_socket_addr, DEFAULT_MAX_PAYLOAD_SIZE, Authentication::default()).unwrap());
+
+        let data_socket_of_command = Arc::clone(&data_socket);
+        let received = tokio::task::spawn_blocking(move || {
+            let receiver = data_socket_of_command.receiver();
+            (0..4).map(|_| receiver.receive_data().unwrap()).collect::<Vec<Vec<u8>>>()
+        });
+
+        let mut client = UnixStream::connect(&data_socket_addr).await.unwrap();
+        client.write_all(b"abc").await.unwrap();
+        drop(client);
+
+        let mut client = UnixStream::connect(&data_socket_addr).await.unwrap();
+        client.write_all(FRAME_MARKER).await.unwrap();
+        client.write_all(&frame(b"first payload")).await.unwrap();
+        client.write_all(&frame(b"")).await.unwrap();
+        client.write_all(&frame(b"third payload")).await.unwrap();
+        drop(client);
+
+        let received = received.await.unwrap();
+        assert_eq!(vec![b"abc".to_vec(), b"first payload".to_vec(), b"".to_vec(), b"third payload".to_vec()], received);
+
+        // payloads left on the framed connection of a command are not received by the next one
+        let data_socket_of_command = Arc::clone(&data_socket);
+        let received = tokio::task::spawn_blocking(move || data_socket_of_command.receiver().receive_data().unwrap());
+
+        let mut client = UnixStream::connect(&data_socket_addr).await.unwrap();
+        client.write_all(FRAME_MARKER).await.unwrap();
+        client.write_all(&frame(b"payload of the command")).await.unwrap();
+        client.write_all(&frame(b"payload left over")).await.unwrap();
+
+        assert_eq!(b"payload of the command".to_vec(), received.await.unwrap());
+
+        let data_socket_of_command = Arc::clone(&data_socket);
+        let received = tokio::task::spawn_blocking(move || data_socket_of_command.receiver().receive_data().unwrap());
+
+        let mut other_client = UnixStream::connect(&data_socket_addr).await.unwrap();
+        other_client.write_all(b"unframed after framed").await.unwrap();
+        drop(other_client);
+
+        assert_eq!(b"unframed after framed".to_vec(), received.await.unwrap());
+        drop(client);
+
+        let _ = remove_file(&data_socket_addr);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_receive_data_rejects_large_payloads() {
+        let data_socket_addr = "/tmp/replicest_server_test_data_socket_large_payloads".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = Arc::new(DataSocket::bind(&data_socket_addr, 8, Authentication::default()).unwrap());
+
+        let data_socket_of_command = Arc::clone(&data_socket);
+        let received = tokio::task::spawn_blocking(move || {
+            let receiver = data_socket_of_command.receiver();
+            (0..3).map(|_| receiver.receive_data().map_err(|err| err.to_string())).collect::<Vec<Result<Vec<u8>, String>>>()
+        });
+
+        let mut client = UnixStream::connect(&data_socket_addr).await.unwrap();
+        client.write_all(b"more than eight bytes").await.unwrap();
+        drop(client);
+
+        let mut client = UnixStream::connect(&data_socket_addr).await.unwrap();
+        client.write_all(FRAME_MARKER).await.unwrap();
+        client.write_all(&frame(b"in limit")).await.unwrap();
+        client.write_all(&u64::MAX.to_le_bytes()).await.unwrap();
+        drop(client);
+
+        let received = received.await.unwrap();
+        assert_eq!(vec![
+            Err("payload exceeds the maximum size of 8 bytes".to_string()),
+            Ok(b"in limit".to_vec()),
+            Err("payload exceeds the maximum size of 8 bytes".to_string()),
+        ], received);
+
+        let _ = remove_file(&data_socket_addr);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_receive_data_requires_token() {
+        let token_file = "/tmp/replicest_server_test_data_socket_token";
+        write(token_file, "s3cr3t\n").unwrap();
+        let authentication = Authentication::from_token_file(Some(token_file)).unwrap();
+
+        let data_socket_addr = "/tmp/replicest_server_test_data_socket_requires_token".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = Arc::new(DataSocket::bind(&data_socket_addr, DEFAULT_MAX_PAYLOAD_SIZE, authentication).unwrap());
+
+        let data_socket_of_command = Arc::clone(&data_socket);
+        let received = tokio::task::spawn_blocking(move || {
+            let receiver = data_socket_of_command.receiver();
+            (0..4).map(|_| receiver.receive_data().map_err(|err| err.to_string())).collect::<Vec<Result<Vec<u8>, String>>>()
+        });
+
+        for payload in [b"abc".to_vec(), b"guess\nabc".to_vec(), b"s3cr3t\nabc".to_vec()] {
+            let mut client = UnixStream::connect(&data_socket_addr).await.unwrap();
+            client.write_all(&payload).await.unwrap();
+            drop(client);
+        }
+
+        let mut client = UnixStream::connect(&data_socket_addr).await.unwrap();
+        client.write_all(b"s3cr3t\n").await.unwrap();
+        client.write_all(FRAME_MARKER).await.unwrap();
+        client.write_all(&frame(b"framed")).await.unwrap();
+        drop(client);
+
+        let received = received.await.unwrap();
+        assert_eq!(vec![
+            Err("data connection not authenticated".to_string()),
+            Err("data connection not authenticated".to_string()),
+            Ok(b"abc".to_vec()),
+            Ok(b"framed".to_vec()),
+        ], received);
+
+        let _ = remove_file(&data_socket_addr);
+        let _ = remove_file(token_file);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+use nalgebra::{DMatrix, DVector};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status, Streaming};
+use tonic::transport::Server;
+use tracing::info;
+use replicest::analysis::{analysis, Analysis};
+use replicest::Estimate;
+use crate::{parse_atkinson_message, parse_benchmark_levels_message, parse_binned_frequencies_equal_width_message, parse_binned_frequencies_message,
+            parse_concentration_message, parse_linear_regression_diagnostics_message, parse_percentile_ranks_message, parse_proportions_above_message,
+            parse_t_test_message};
+use crate::auth::Authentication;
+use crate::jobs::Jobs;
+
+pub mod proto {
+    tonic::include_proto!("replicest");
+}
+
+use proto::replicest_server::{Replicest, ReplicestServer};
+use proto::{CalculateRequest, CalculateResponse, DataChunk, EstimateRequest, GroupResult};
+
+// the service holds a single analysis, so it serves one client at a time,
+// clients needing separate analyses have to use separate servers
+struct ReplicestService {
+    analysis: Mutex<Analysis>,
+    jobs: Jobs,
+}
+
+// only the estimates of the message protocol can be set, commands touching files or other state are not served
+fn parse_estimate(message: &str) -> Option<Estimate> {
+    match message {
+        "mean" => Some(Estimate::Mean),
+        "standardized mean" => Some(Estimate::StandardizedMean),
+        "total" => Some(Estimate::Total),
+        "missingness" => Some(Estimate::Missingness),
+        "frequencies" => Some(Estimate::Frequencies { percentages: false }),
+        "frequencies with percentages" => Some(Estimate::Frequencies { percentages: true }),
+        "crosstab" => Some(Estimate::Crosstab),
+        "agreement" => Some(Estimate::Agreement),
+        "range" => Some(Estimate::Range),
+        "linear regression" => Some(Estimate::LinearRegression { residual_diagnostics: false, number_residual_bins: 0 }),
+        str if str.starts_with("linear regression with residual diagnostics") => parse_linear_regression_diagnostics_message(str)
+            .map(|number_residual_bins| Estimate::LinearRegression { residual_diagnostics: true, number_residual_bins: number_residual_bins as u32 }),
+        "poisson regression" => Some(Estimate::PoissonRegression),
+        "effect size" => Some(Estimate::EffectSize),
+        str if str.starts_with("t test") => parse_t_test_message(str).map(|mu| Estimate::TTest { mu }),
+        "two sample t test" => Some(Estimate::TwoSampleTTest),
+        "eta squared" => Some(Estimate::EtaSquared),
+        "mantel haenszel dif" => Some(Estimate::MantelHaenszelDif),
+        "odds ratio" => Some(Estimate::OddsRatio),
+        "gini" => Some(Estimate::Gini),
+        str if str.starts_with("concentration") => parse_concentration_message(str).map(|number_ordinates| Estimate::Concentration { number_ordinates: number_ordinates as u32 }),
+        "theil" => Some(Estimate::Theil),
+        str if str.starts_with("atkinson") => parse_atkinson_message(str).map(|epsilon| Estimate::Atkinson { epsilon }),
+        str if str.starts_with("benchmark levels") => parse_benchmark_levels_message(str).map(|cutpoints| Estimate::BenchmarkLevels { cutpoints }),
+        str if str.starts_with("percentile ranks") => parse_percentile_ranks_message(str).map(|values| Estimate::PercentileRanks { values }),
+        str if str.starts_with("binned frequencies equal width") => parse_binned_frequencies_equal_width_message(str)
+            .map(|number_bins| Estimate::EqualWidthBinnedFrequencies { number_bins: number_bins as u32 }),
+        str if str.starts_with("binned frequencies") => parse_binned_frequencies_message(str).map(|breaks| Estimate::BinnedFrequencies { breaks }),
+        "polychoric correlation" => Some(Estimate::PolychoricCorrelation),
+        "polyserial correlation" => Some(Estimate::PolyserialCorrelation),
+        "principal components" => Some(Estimate::PrincipalComponents),
+        str if str.starts_with("proportions above") => parse_proportions_above_message(str).map(|cutpoints| Estimate::ProportionsAbove { cutpoints }),
+        _ => None,
+    }
+}
+
+async fn receive_imputations(mut stream: Streaming<DataChunk>) -> Result<Vec<DMatrix<f64>>, Status> {
+    let mut imputations : BTreeMap<u32, (usize, Vec<f64>)> = BTreeMap::new();
+
+    while let Some(chunk) = stream.message().await? {
+        let columns = chunk.columns as usize;
+        if columns == 0 || chunk.values.len() % columns != 0 {
+            return Err(Status::invalid_argument("chunk does not consist of complete rows"));
+        }
+
+        let (imputation_columns, values) = imputations.entry(chunk.imputation).or_insert((columns, Vec::new()));
+        if *imputation_columns != columns {
+            return Err(Status::invalid_argument("unequal number of columns within an imputation"));
+        }
+        values.extend(chunk.values);
+    }
+
+    if imputations.is_empty() {
+        return Err(Status::invalid_argument("no data received"));
+    }
+
+    Ok(imputations.into_values().map(|(columns, values)| DMatrix::from_row_slice(values.len() / columns, columns, &values)).collect())
+}
+
+fn status(message: &str) -> Response<proto::Status> {
+    Response::new(proto::Status { message: message.to_string() })
+}
+
+#[tonic::async_trait]
+impl Replicest for ReplicestService {
+    async fn set_data(&self, request: Request<Streaming<DataChunk>>) -> Result<Response<proto::Status>, Status> {
+        let imputations = receive_imputations(request.into_inner()).await?;
+        self.analysis.lock().unwrap().for_data_owned(imputations);
+        Ok(status("received data"))
+    }
+
+    async fn set_weights(&self, request: Request<Streaming<DataChunk>>) -> Result<Response<proto::Status>, Status> {
+        let imputations = receive_imputations(request.into_inner()).await?;
+        if imputations.len() != 1 || imputations[0].ncols() != 1 {
+            return Err(Status::invalid_argument("weights have to be a single column"));
+        }
+
+        self.analysis.lock().unwrap().set_weights(&DVector::from_column_slice(imputations[0].as_slice()));
+        Ok(status("received weights"))
+    }
+
+    async fn set_replicate_weights(&self, request: Request<Streaming<DataChunk>>) -> Result<Response<proto::Status>, Status> {
+        let imputations = receive_imputations(request.into_inner()).await?;
+        if imputations.len() != 1 {
+            return Err(Status::invalid_argument("replicate weights cannot have imputations"));
+        }
+
+        self.analysis.lock().unwrap().with_replicate_weights(&imputations[0]);
+        Ok(status("received replicate weights"))
+    }
+
+    async fn set_estimate(&self, request: Request<EstimateRequest>) -> Result<Response<proto::Status>, Status> {
+        let message = request.into_inner().estimate;
+        let estimate = parse_estimate(&message).ok_or_else(|| Status::invalid_argument(format!("not an estimate: {}", message)))?;
+
+        self.analysis.lock().unwrap().set_estimate(estimate);
+        Ok(status(&format!("set analysis to {}", message)))
+    }
+
+    async fn calculate(&self, _request: Request<CalculateRequest>) -> Result<Response<CalculateResponse>, Status> {
+        let _job = self.jobs.start().map_err(Status::unavailable)?;
+
+        // calculations are CPU bound and must not stall the other tasks of the runtime
+        let started = Instant::now();
+        let results = tokio::task::block_in_place(|| self.analysis.lock().unwrap().set_monitor(&self.jobs.monitor()).calculate())
+            .map_err(|err| Status::failed_precondition(err.to_string()))?;
+        info!(front_end = "grpc", milliseconds = started.elapsed().as_millis() as u64, "calculation finished");
+
+        let results = results.iter().map(|(key, estimates)| GroupResult {
+            key: key.key().clone(),
+            parameter_names: estimates.parameter_names().clone(),
+            final_estimates: estimates.final_estimates().as_slice().to_vec(),
+            sampling_variances: estimates.sampling_variances().as_slice().to_vec(),
+            imputation_variances: estimates.imputation_variances().as_slice().to_vec(),
+            standard_errors: estimates.standard_errors().as_slice().to_vec(),
+            degrees_of_freedom: estimates.degrees_of_freedom().as_slice().to_vec(),
+            p_values: estimates.p_values().as_slice().to_vec(),
+        }).collect();
+
+        Ok(Response::new(CalculateResponse { results }))
+    }
+}
+
+pub fn serve(address: SocketAddr, jobs: Jobs, authentication: Authentication) -> Result<(), Box<dyn Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        serve_with_listener(listener, jobs, authentication).await
+    })
+}
+
+async fn serve_with_listener(listener: tokio::net::TcpListener, jobs: Jobs, authentication: Authentication) -> Result<(), Box<dyn Error>> {
+    let service = ReplicestService { analysis: Mutex::new(analysis()), jobs };
+
+    // with a token configured, every call has to carry it as "authorization: Bearer <token>",
+    // tonic prescribes the unboxed Status as error of an interceptor
+    #[allow(clippy::result_large_err)]
+    let interceptor = move |request: Request<()>| {
+        let token = request.metadata().get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or("");
+        if authentication.accepts(token) {
+            Ok(request)
+        } else {
+            Err(Status::unauthenticated("invalid token"))
+        }
+    };
+
+    Server::builder()
+        .add_service(ReplicestServer::with_interceptor(service, interceptor))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::replicest_client::ReplicestClient;
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_grpc_service() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { serve_with_listener(listener, Jobs::new(), Authentication::default()).await.unwrap() });
+
+        let mut client = ReplicestClient::connect(format!("http://{}", address)).await.unwrap();
+
+        let chunks = vec![
+            DataChunk { imputation: 0, columns: 2, values: vec![1.0, 2.0, 3.0, 4.0] },
+            DataChunk { imputation: 0, columns: 2, values: vec![5.0, 6.0] },
+        ];
+        let response = client.set_data(tokio_stream::iter(chunks)).await.unwrap();
+        assert_eq!("received data", response.into_inner().message);
+
+        let chunks = vec![DataChunk { imputation: 0, columns: 1, values: vec![1.0, 1.0, 2.0] }];
+        let response = client.set_weights(tokio_stream::iter(chunks)).await.unwrap();
+        assert_eq!("received weights", response.into_inner().message);
+
+        let response = client.calculate(CalculateRequest {}).await;
+        assert!(response.is_err());
+
+        let response = client.set_estimate(EstimateRequest { estimate: "mean".to_string() }).await.unwrap();
+        assert_eq!("set analysis to mean", response.into_inner().message);
+
+        for message in ["save /tmp/analysis.json", "load csv /tmp/data.csv", "calculate", "t test", "percentile shares 0-50"] {
+            let response = client.set_estimate(EstimateRequest { estimate: message.to_string() }).await;
+            assert_eq!(tonic::Code::InvalidArgument, response.err().unwrap().code());
+        }
+
+        let response = client.calculate(CalculateRequest {}).await.unwrap().into_inner();
+        assert_eq!(1, response.results.len());
+        assert_eq!(vec!["overall".to_string()], response.results[0].key);
+        assert_eq!(vec![3.5, 4.5], response.results[0].final_estimates);
+
+        let chunks = vec![DataChunk { imputation: 0, columns: 2, values: vec![1.0, 2.0, 3.0] }];
+        let response = client.set_data(tokio_stream::iter(chunks)).await;
+        assert_eq!(tonic::Code::InvalidArgument, response.err().unwrap().code());
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use replicest::replication::{CancellationToken, ReplicationMonitor};
+
+#[derive(Default)]
+struct JobsState {
+    running: usize,
+    closed: bool,
+}
+
+// calculations of all front-ends are registered here, so that a shutdown can wait for or cancel them
+#[derive(Clone, Default)]
+pub struct Jobs {
+    state: Arc<(Mutex<JobsState>, Condvar)>,
+    cancellation_token: CancellationToken,
+}
+
+pub struct Job {
+    jobs: Jobs,
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let (state, finished) = self.jobs.state.as_ref();
+        state.lock().unwrap().running -= 1;
+        finished.notify_all();
+    }
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Jobs::default()
+    }
+
+    pub fn start(&self) -> Result<Job, String> {
+        let mut state = self.state.0.lock().unwrap();
+        if state.closed {
+            return Err("server is shutting down".to_string());
+        }
+        state.running += 1;
+        Ok(Job { jobs: self.clone() })
+    }
+
+    pub fn monitor(&self) -> ReplicationMonitor {
+        let mut monitor = ReplicationMonitor::default();
+        monitor.set_cancellation_token(&self.cancellation_token);
+        monitor
+    }
+
+    pub fn close(&self) {
+        self.state.0.lock().unwrap().closed = true;
+    }
+
+    pub fn cancel(&self) {
+        self.close();
+        self.cancellation_token.cancel();
+    }
+
+    pub fn drain(&self, timeout: Duration) -> bool {
+        let (state, finished) = self.state.as_ref();
+        let (state, _) = finished.wait_timeout_while(state.lock().unwrap(), timeout, |state| state.running > 0).unwrap();
+        state.running == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use nalgebra::dmatrix;
+    use replicest::analysis::{analysis, Imputation};
+    use super::*;
+
+    #[test]
+    fn test_drain() {
+        let jobs = Jobs::new();
+        assert!(jobs.drain(Duration::ZERO));
+
+        let job = jobs.start().unwrap();
+        jobs.close();
+        assert_eq!("server is shutting down", jobs.start().err().unwrap());
+        assert!(!jobs.drain(Duration::from_millis(10)));
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            drop(job);
+        });
+        assert!(jobs.drain(Duration::from_secs(5)));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_cancel() {
+        let jobs = Jobs::new();
+        let monitor = jobs.monitor();
+
+        jobs.cancel();
+        assert!(jobs.start().is_err());
+
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let mut analysis1 = analysis();
+        analysis1.for_data(Imputation::No(&data)).mean().set_monitor(&monitor);
+        assert_eq!("Calculation was cancelled", analysis1.calculate().unwrap_err().to_string());
+    }
+}
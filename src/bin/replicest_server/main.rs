@@ -0,0 +1,3180 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{remove_file, set_permissions, OpenOptions, Permissions};
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::fs::{chown, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use nalgebra::{DMatrix, DVector};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn, Level};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tungstenite::{accept, Message, WebSocket};
+use users::{get_current_uid, get_group_by_name};
+use replicest::analysis::*;
+use replicest::errors::DataLengthError;
+use replicest::export::{results_to_csv, results_to_json};
+use replicest::import::{arrow_ipc_to_matrix, read_csv, read_parquet, CsvOptions};
+use replicest::estimates::{FrequenciesOptions, LinregOptions, ILSA_BENCHMARKS};
+use replicest::ReplicatedEstimates;
+use crate::activation::inherited_sockets;
+use crate::auth::{AuthenticatedClients, Authentication};
+use crate::capabilities::capabilities;
+use crate::data_socket::{DataSocket, DEFAULT_MAX_PAYLOAD_SIZE};
+use crate::jobs::Jobs;
+use crate::script::run_script;
+use crate::session::{execute, Route, SharedAnalysis, Sessions};
+
+mod activation;
+mod auth;
+mod capabilities;
+mod data_socket;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod jobs;
+mod script;
+mod session;
+
+struct ServerOptions {
+    websocket_address: Option<String>,
+    grpc_address: Option<String>,
+    session_timeout: Option<Duration>,
+    log_level: Level,
+    log_file: Option<String>,
+    socket_mode: Option<u32>,
+    socket_group: Option<u32>,
+    token_file: Option<String>,
+    max_payload_size: u64,
+    script: Option<String>,
+    output: Option<String>,
+}
+
+// how often idle sessions are looked for
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// running calculations are cancelled if they do not finish within this time after a shutdown
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn parse_arguments(args: &[String]) -> Result<ServerOptions, String> {
+    let mut options = ServerOptions {
+        websocket_address: None,
+        grpc_address: None,
+        session_timeout: None,
+        log_level: Level::INFO,
+        log_file: None,
+        socket_mode: None,
+        socket_group: None,
+        token_file: None,
+        max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        script: None,
+        output: None,
+    };
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--websocket" => {
+                options.websocket_address = Some(args.next().ok_or("missing address for --websocket")?.clone());
+            }
+            "--grpc" => {
+                options.grpc_address = Some(args.next().ok_or("missing address for --grpc")?.clone());
+            }
+            "--session-timeout" => {
+                let seconds = args.next().ok_or("missing seconds for --session-timeout")?;
+                match seconds.parse::<u64>() {
+                    Ok(seconds) if seconds > 0 => options.session_timeout = Some(Duration::from_secs(seconds)),
+                    _ => return Err(format!("invalid seconds for --session-timeout: {}", seconds)),
+                }
+            }
+            "--log-level" => {
+                let level = args.next().ok_or("missing level for --log-level")?;
+                options.log_level = level.parse::<Level>().map_err(|_| format!("invalid level for --log-level: {}", level))?;
+            }
+            "--log-file" => {
+                options.log_file = Some(args.next().ok_or("missing path for --log-file")?.clone());
+            }
+            "--socket-mode" => {
+                let mode = args.next().ok_or("missing mode for --socket-mode")?;
+                match u32::from_str_radix(mode, 8) {
+                    Ok(mode) if mode <= 0o777 => options.socket_mode = Some(mode),
+                    _ => return Err(format!("invalid octal mode for --socket-mode: {}", mode)),
+                }
+            }
+            "--socket-group" => {
+                let group = args.next().ok_or("missing group for --socket-group")?;
+                let group_id = match group.parse::<u32>() {
+                    Ok(group_id) => group_id,
+                    Err(_) => get_group_by_name(group).ok_or(format!("unknown group for --socket-group: {}", group))?.gid(),
+                };
+                options.socket_group = Some(group_id);
+            }
+            "--token-file" => {
+                options.token_file = Some(args.next().ok_or("missing path for --token-file")?.clone());
+            }
+            "--max-payload-size" => {
+                let bytes = args.next().ok_or("missing bytes for --max-payload-size")?;
+                match bytes.parse::<u64>() {
+                    Ok(bytes) if bytes > 0 => options.max_payload_size = bytes,
+                    _ => return Err(format!("invalid bytes for --max-payload-size: {}", bytes)),
+                }
+            }
+            "--script" => {
+                options.script = Some(args.next().ok_or("missing path for --script")?.clone());
+            }
+            "--output" => {
+                options.output = Some(args.next().ok_or("missing path for --output")?.clone());
+            }
+            _ => {
+                return Err(format!("unknown argument {}", arg));
+            }
+        }
+    }
+
+    if options.script.is_some() != options.output.is_some() {
+        return Err("--script and --output have to be given together".to_string());
+    }
+
+    Ok(options)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_arguments(&std::env::args().collect::<Vec<String>>())?;
+    run(options)
+}
+
+fn run(options: ServerOptions) -> Result<(), Box<dyn Error>> {
+    setup_logging(&options)?;
+
+    // a script is run instead of serving any clients
+    if let (Some(script), Some(output)) = (options.script.as_ref(), options.output.as_ref()) {
+        return run_script(script, output, &Jobs::new());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(serve(options));
+
+    // receptions still waiting for data must not keep the server from exiting
+    runtime.shutdown_background();
+
+    result
+}
+
+fn setup_logging(options: &ServerOptions) -> Result<(), Box<dyn Error>> {
+    let writer = match options.log_file.as_ref() {
+        None => BoxMakeWriter::new(std::io::stdout),
+        Some(path) => BoxMakeWriter::new(Mutex::new(OpenOptions::new().create(true).append(true).open(path)?)),
+    };
+
+    // a subscriber is already set when the server is started a second time within the same process
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(options.log_level)
+        .with_writer(writer)
+        .with_ansi(options.log_file.is_none())
+        .try_init();
+
+    Ok(())
+}
+
+async fn serve(options: ServerOptions) -> Result<(), Box<dyn Error>> {
+    let authentication = Authentication::from_token_file(options.token_file.as_deref())?;
+
+    let (message_socket, data_socket, _socket_files) = setup_sockets(&options, &authentication)?;
+    let message_socket = Arc::new(message_socket);
+    let data_socket = Arc::new(data_socket);
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+    let jobs = Jobs::new();
+
+    if let Some(websocket_address) = options.websocket_address {
+        let listener = bind_websocket(&websocket_address, &authentication)?;
+        let session_timeout = options.session_timeout;
+        let jobs = jobs.clone();
+        let authentication = authentication.clone();
+        thread::spawn(move || serve_websocket(listener, session_timeout, jobs, authentication));
+    }
+
+    if let Some(grpc_address) = options.grpc_address {
+        start_grpc(grpc_address.parse()?, &jobs, &authentication)?;
+    }
+
+    let mut sessions = Sessions::new(options.session_timeout, &jobs);
+
+    // the latest task of every session, which the next one waits for to keep the order of messages
+    let mut session_tasks : BTreeMap<String, JoinHandle<()>> = BTreeMap::new();
+
+    let mut authenticated_clients = AuthenticatedClients::default();
+
+    loop {
+        let mut buffer = [0; 1024];
+
+        tokio::select! {
+            received = message_socket.recv_from(&mut buffer) => {
+                let client_addr = match received {
+                    Ok((_, client_addr)) => client_addr,
+                    Err(_) => break,
+                };
+
+                // replies can only be sent to clients bound to a path
+                let client_path = match client_addr.as_pathname() {
+                    None => continue,
+                    Some(client_path) => client_path.to_path_buf(),
+                };
+
+                let message = trim_buffer(&buffer);
+
+                // the token must not end up in the log
+                if let Some((accepted, response)) = authentication.authenticate(&message) {
+                    info!(front_end = "unix socket", accepted, "received authentication");
+                    if accepted {
+                        authenticated_clients.insert(&client_path);
+                    }
+                    message_socket.send_to(response.as_bytes(), &client_path).await?;
+                    continue;
+                }
+
+                info!(front_end = "unix socket", %message, "received command");
+
+                if authentication.is_required() && !authenticated_clients.contains(&client_path) {
+                    send_responses(&message_socket, into_responses(Err("not authenticated".into())), &client_path).await?;
+                    continue;
+                }
+
+                if message == "shutdown" || message == "shutdown cancel" {
+                    let jobs = jobs.clone();
+                    let cancel = message == "shutdown cancel";
+                    tokio::task::spawn_blocking(move || shut_down(&jobs, cancel)).await?;
+                    message_socket.send_to(b"shutting down", &client_path).await?;
+                    break;
+                }
+
+                match sessions.route(&message) {
+                    Route::Response(response) => {
+                        let responses = into_responses(response);
+                        send_responses(&message_socket, responses, &client_path).await?;
+                    }
+                    Route::Session(name, analysis, command) => {
+                        let task = tokio::spawn(handle_in_session(
+                            session_tasks.remove(&name),
+                            analysis,
+                            command.to_string(),
+                            Arc::clone(&data_socket),
+                            Arc::clone(&message_socket),
+                            client_path,
+                            jobs.clone(),
+                        ));
+                        session_tasks.insert(name, task);
+                    }
+                }
+            }
+            _ = poll.tick() => {
+                for name in sessions.expire_idle(Instant::now()) {
+                    info!(session = %name, "freed idle session");
+                }
+                session_tasks.retain(|_, task| !task.is_finished());
+                for client_path in authenticated_clients.remove_unbound() {
+                    info!(client = %client_path.display(), "forgot authentication of departed client");
+                }
+            }
+            _ = terminate.recv() => {
+                info!("received termination signal");
+                let jobs = jobs.clone();
+                tokio::task::spawn_blocking(move || shut_down(&jobs, false)).await?;
+                break;
+            }
+            _ = interrupt.recv() => {
+                info!("received interrupt signal");
+                let jobs = jobs.clone();
+                tokio::task::spawn_blocking(move || shut_down(&jobs, false)).await?;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_in_session(previous_task: Option<JoinHandle<()>>, analysis: SharedAnalysis, command: String, data_socket: Arc<DataSocket>, message_socket: Arc<tokio::net::UnixDatagram>, client_path: PathBuf, jobs: Jobs) {
+    if let Some(previous_task) = previous_task {
+        let _ = previous_task.await;
+    }
+
+    // waiting for data and calculating must not occupy the workers of the runtime
+    let responses = tokio::task::spawn_blocking(move || {
+        into_responses(execute(&command, &mut analysis.lock().unwrap(), &data_socket.receiver(), &jobs))
+    }).await.unwrap_or_else(|err| vec!(format!("error: {}", err).into_bytes()));
+
+    if let Err(err) = send_responses(&message_socket, responses, &client_path).await {
+        error!(error = %err, "sending response failed");
+    }
+}
+
+fn into_responses(response: Result<Vec<Vec<u8>>, Box<dyn Error>>) -> Vec<Vec<u8>> {
+    let responses = response.unwrap_or_else(|err| vec!(format!("error: {}", err).into_bytes()));
+
+    // some failures are reported by the status line only
+    if let Some(status) = responses.first().filter(|status| status.starts_with(b"error") || status.starts_with(b"bad request")) {
+        warn!(status = %String::from_utf8_lossy(status), "command failed");
+    }
+
+    responses
+}
+
+async fn send_responses(message_socket: &tokio::net::UnixDatagram, responses: Vec<Vec<u8>>, client_path: &Path) -> std::io::Result<()> {
+    for response_data in responses {
+        message_socket.send_to(&response_data, client_path).await?;
+    }
+    Ok(())
+}
+
+fn shut_down(jobs: &Jobs, cancel: bool) {
+    if cancel {
+        jobs.cancel();
+    } else {
+        jobs.close();
+    }
+
+    if !jobs.drain(SHUTDOWN_DRAIN_TIMEOUT) {
+        warn!(seconds = SHUTDOWN_DRAIN_TIMEOUT.as_secs(), "cancelling calculations still running");
+        jobs.cancel();
+        jobs.drain(SHUTDOWN_DRAIN_TIMEOUT);
+    }
+}
+
+// the socket files are removed however the server stops, including on errors
+struct SocketFiles {
+    paths: Vec<String>,
+}
+
+impl Drop for SocketFiles {
+    fn drop(&mut self) {
+        for path in self.paths.iter() {
+            let _ = remove_file(path);
+        }
+    }
+}
+
+fn setup_sockets(options: &ServerOptions, authentication: &Authentication) -> Result<(tokio::net::UnixDatagram, DataSocket, SocketFiles), Box<dyn Error>> {
+    if let Some((message_socket, data_listener)) = inherited_sockets()? {
+        info!("using sockets passed by systemd");
+        message_socket.set_nonblocking(true)?;
+        data_listener.set_nonblocking(true)?;
+        // the socket files belong to systemd, which keeps them for the next activation
+        return Ok((tokio::net::UnixDatagram::from_std(message_socket)?, DataSocket::from_std(data_listener, options.max_payload_size, authentication.clone())?, SocketFiles { paths: Vec::new() }));
+    }
+
+    let user_id = get_current_uid();
+
+    let message_socket_addr = format!("/run/user/{}/replicest_server", user_id);
+    let _ = remove_file(&message_socket_addr);
+    let message_socket = tokio::net::UnixDatagram::bind(&message_socket_addr)?;
+    let mut socket_files = SocketFiles { paths: vec![message_socket_addr.clone()] };
+    restrict_socket_file(&message_socket_addr, options)?;
+
+    let data_socket_addr = format!("/run/user/{}/replicest_server_data", user_id);
+    let _ = remove_file(&data_socket_addr);
+    let data_socket = DataSocket::bind(&data_socket_addr, options.max_payload_size, authentication.clone())?;
+    socket_files.paths.push(data_socket_addr.clone());
+    restrict_socket_file(&data_socket_addr, options)?;
+
+    Ok((message_socket, data_socket, socket_files))
+}
+
+// without these options, who may connect is decided by the umask and the permissions of the runtime directory
+fn restrict_socket_file(path: &str, options: &ServerOptions) -> Result<(), Box<dyn Error>> {
+    if let Some(group_id) = options.socket_group {
+        chown(path, None, Some(group_id))?;
+    }
+    if let Some(mode) = options.socket_mode {
+        set_permissions(path, Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+fn start_grpc(address: std::net::SocketAddr, jobs: &Jobs, authentication: &Authentication) -> Result<(), Box<dyn Error>> {
+    let jobs = jobs.clone();
+    let authentication = authentication.clone();
+    thread::spawn(move || {
+        if let Err(err) = grpc::serve(address, jobs, authentication) {
+            error!(error = %err, "gRPC service failed");
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "grpc"))]
+fn start_grpc(_address: std::net::SocketAddr, _jobs: &Jobs, _authentication: &Authentication) -> Result<(), Box<dyn Error>> {
+    Err("replicest_server was built without the grpc feature".into())
+}
+
+// websocket clients may name files to load and save, so clients from other hosts are only served with a token
+fn bind_websocket(address: &str, authentication: &Authentication) -> Result<TcpListener, Box<dyn Error>> {
+    let listener = TcpListener::bind(address)?;
+    if !authentication.is_required() && !listener.local_addr()?.ip().is_loopback() {
+        return Err("--websocket on an address other than loopback requires --token-file".into());
+    }
+    Ok(listener)
+}
+
+fn serve_websocket(listener: TcpListener, session_timeout: Option<Duration>, jobs: Jobs, authentication: Authentication) {
+    // every connection works on sessions of its own
+    for stream in listener.incoming().flatten() {
+        let jobs = jobs.clone();
+        let authentication = authentication.clone();
+        thread::spawn(move || {
+            if let Err(err) = handle_websocket_connection(stream, session_timeout, &jobs, &authentication) {
+                warn!(error = %err, "WebSocket connection failed");
+            }
+        });
+    }
+}
+
+fn handle_websocket_connection(stream: TcpStream, session_timeout: Option<Duration>, jobs: &Jobs, authentication: &Authentication) -> Result<(), Box<dyn Error>> {
+    let websocket = RefCell::new(accept(stream)?);
+    let mut sessions = Sessions::new(session_timeout, jobs);
+    let mut authenticated = !authentication.is_required();
+
+    loop {
+        let message = websocket.borrow_mut().read()?;
+        let message = match message {
+            Message::Text(text) => text.trim_end().to_string(),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        if let Some((accepted, response)) = authentication.authenticate(&message) {
+            info!(front_end = "websocket", accepted, "received authentication");
+            authenticated |= accepted;
+            websocket.borrow_mut().send(Message::text(response))?;
+            continue;
+        }
+
+        info!(front_end = "websocket", %message, "received command");
+
+        if !authenticated {
+            websocket.borrow_mut().send(Message::text("error: not authenticated"))?;
+            continue;
+        }
+
+        // a connection is only woken up by its client, so idle sessions are freed on its next message
+        sessions.expire_idle(Instant::now());
+
+        let responses = into_responses(sessions.handle(message, &websocket));
+
+        // the status line is sent as text, any payload following it as binary
+        for (i, response_data) in responses.into_iter().enumerate() {
+            let response = match String::from_utf8(response_data) {
+                Ok(text) if i == 0 => Message::text(text),
+                Ok(text) => Message::binary(text.into_bytes()),
+                Err(err) => Message::binary(err.into_bytes()),
+            };
+            websocket.borrow_mut().send(response)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn trim_buffer(buffer: &[u8]) -> String {
+    let message = String::from_utf8(buffer.to_vec()).unwrap_or("".to_string());
+    let message = message.trim_end_matches(char::from(0));
+    message.trim_end().to_string()
+}
+
+trait DataSource {
+    fn receive_data(&self) -> Result<Vec<u8>, Box<dyn Error>>;
+
+    // also the limit for decompressed payloads
+    fn max_payload_size(&self) -> u64 {
+        DEFAULT_MAX_PAYLOAD_SIZE
+    }
+}
+
+impl DataSource for UnixListener {
+    fn receive_data(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let (mut socket, _) = self.accept()?;
+        let mut buffer = Vec::new();
+        let _ = socket.read_to_end(&mut buffer)?;
+        info!(bytes = buffer.len(), "received data");
+        Ok(buffer)
+    }
+}
+
+impl DataSource for RefCell<WebSocket<TcpStream>> {
+    fn receive_data(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        // data arrives as the next binary message on the same connection
+        loop {
+            match self.borrow_mut().read()? {
+                Message::Binary(data) => {
+                    info!(bytes = data.len(), "received data");
+                    return Ok(data.to_vec());
+                }
+                Message::Close(_) => return Err(Box::new(tungstenite::Error::ConnectionClosed)),
+                _ => continue,
+            }
+        }
+    }
+}
+
+// the payloads for these messages may be compressed with zstd, which is announced by a trailing "compressed"
+const DATA_MESSAGES: [&str; 5] = ["data", "append", "weights", "replicate weights", "group by"];
+
+struct CompressedDataSource<'a, D: DataSource>(&'a D);
+
+impl<D: DataSource> DataSource for CompressedDataSource<'_, D> {
+    fn receive_data(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let buffer = decompress(self.0.receive_data()?.as_slice(), self.0.max_payload_size())?;
+        info!(bytes = buffer.len(), "decompressed data");
+        Ok(buffer)
+    }
+
+    fn max_payload_size(&self) -> u64 {
+        self.0.max_payload_size()
+    }
+}
+
+// a small compressed payload can expand to any size, so decompression stops right after the limit
+fn decompress(compressed: &[u8], max_payload_size: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buffer = Vec::new();
+    let _ = zstd::stream::read::Decoder::new(compressed)?.take(max_payload_size + 1).read_to_end(&mut buffer)?;
+    if buffer.len() as u64 > max_payload_size {
+        return Err(format!("decompressed payload exceeds the maximum size of {} bytes", max_payload_size).into());
+    }
+    Ok(buffer)
+}
+
+fn handle_message(message: String, analysis: &mut Analysis, data_socket: &impl DataSource) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    match message.strip_suffix(" compressed") {
+        Some(uncompressed_message) if DATA_MESSAGES.iter().any(|prefix| uncompressed_message.starts_with(prefix)) => {
+            handle_uncompressed_message(uncompressed_message.to_string(), analysis, &CompressedDataSource(data_socket))
+        }
+        _ => {
+            handle_uncompressed_message(message, analysis, data_socket)
+        }
+    }
+}
+
+fn handle_uncompressed_message(message: String, analysis: &mut Analysis, data_socket: &impl DataSource) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    match message.as_str() {
+        str if str.starts_with("append data") => {
+            let message_arguments = parse_data_message(str.trim_start_matches("append "));
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: append data <number_imputations> <number_columns> [named]".into()))
+                }
+                Some((number_imputations, number_columns, named)) => {
+                    let column_names = if named { Some(listen_for_column_names(data_socket, number_columns)?) } else { None };
+                    let mut data : Vec<DMatrix<f64>> = Vec::new();
+
+                    for _ in 0..number_imputations {
+                        data.push(listen_for_data(data_socket, number_columns)?);
+                    }
+
+                    let imp_data : Vec<&DMatrix<f64>> = Vec::from_iter(data.iter());
+                    match analysis.append_data(Imputation::Yes(&imp_data)) {
+                        Ok(_) => {
+                            if let Some(column_names) = column_names {
+                                analysis.set_variable_names(&column_names.iter().map(|name| name.as_str()).collect::<Vec<&str>>());
+                            }
+                            Ok(vec!(b"appended data".into()))
+                        }
+                        Err(err) => Ok(vec!([b"error appending data: ", err.to_string().as_bytes()].concat())),
+                    }
+                }
+            }
+        }
+        "append weights" => {
+            let data = listen_for_data(data_socket, 1)?;
+            let weight_vector : DVector<f64> = DVector::<f64>::from_iterator(data.nrows(), data.iter().cloned());
+            analysis.append_weights(&weight_vector)?;
+            Ok(vec!(b"appended weights".into()))
+        }
+        str if str.starts_with("append replicate weights") => {
+            let message_arguments = parse_replicate_weights_message(str.trim_start_matches("append "));
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: append replicate weights <number_columns>".into()))
+                }
+                Some(number_columns) => {
+                    let replicate_weights = listen_for_data(data_socket, number_columns)?;
+                    match analysis.append_replicate_weights(&replicate_weights) {
+                        Ok(_) => Ok(vec!(b"appended replicate weights".into())),
+                        Err(err) => Ok(vec!([b"error appending replicate weights: ", err.to_string().as_bytes()].concat())),
+                    }
+                }
+            }
+        }
+        str if str.starts_with("load csv") => {
+            let message_arguments = parse_load_csv_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: load csv <path> [delimiter=<character>] [header=<true|false>] [columns=<column>,...] [into=<data|weights|replicate_weights|groups>]".into()))
+                }
+                Some((path, options, target)) => {
+                    let (data, column_names) = read_csv(path, &options)?;
+                    let column_names : Vec<&str> = column_names.iter().map(|name| name.as_str()).collect();
+
+                    match target {
+                        "weights" => {
+                            if data.ncols() != 1 {
+                                return Err("weights have to be a single column".into());
+                            }
+                            analysis.set_weights(&data.column(0).clone_owned());
+                            Ok(vec!(b"loaded weights".into()))
+                        }
+                        "replicate_weights" => {
+                            analysis.with_replicate_weights(&data);
+                            Ok(vec!(b"loaded replicate weights".into()))
+                        }
+                        "groups" => {
+                            analysis.group_by_owned(vec![data]);
+                            if options.header {
+                                analysis.set_group_names(&column_names);
+                            }
+                            Ok(vec!(b"loaded grouping columns".into()))
+                        }
+                        _ => {
+                            analysis.for_data_owned(vec![data]);
+                            if options.header {
+                                analysis.set_variable_names(&column_names);
+                            }
+                            Ok(vec!(b"loaded data".into()))
+                        }
+                    }
+                }
+            }
+        }
+        str if str.starts_with("load parquet") => {
+            let message_arguments = parse_load_parquet_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: load parquet <path> [<column> ...]".into()))
+                }
+                Some((path, columns)) => {
+                    let (data, variable_names) = read_parquet(path, &columns)?;
+                    analysis.for_data_owned(vec![data]);
+                    analysis.set_variable_names(&variable_names.iter().map(|name| name.as_str()).collect::<Vec<&str>>());
+                    Ok(vec!(b"loaded data".into()))
+                }
+            }
+        }
+        str if str.starts_with("data arrow") => {
+            let message_arguments = parse_arrow_data_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: data arrow <number_imputations>".into()))
+                }
+                Some(number_imputations) => {
+                    let mut data : Vec<DMatrix<f64>> = Vec::new();
+                    let mut variable_names : Vec<String> = Vec::new();
+
+                    for _ in 0..number_imputations {
+                        let (matrix, column_names) = arrow_ipc_to_matrix(data_socket.receive_data()?)?;
+                        if !data.is_empty() && column_names != variable_names {
+                            return Err("imputations differ in their columns".into());
+                        }
+                        data.push(matrix);
+                        variable_names = column_names;
+                    }
+
+                    analysis.for_data_owned(data);
+                    analysis.set_variable_names(&variable_names.iter().map(|name| name.as_str()).collect::<Vec<&str>>());
+                    Ok(vec!(b"received data".into()))
+                }
+            }
+        }
+        str if str.starts_with("data") => {
+            let message_arguments = parse_data_message(&str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: data <number_imputations> <number_columns> [named]".into()))
+                }
+                Some((number_imputations, number_columns, named)) => {
+                    let column_names = if named { Some(listen_for_column_names(data_socket, number_columns)?) } else { None };
+                    let mut data : Vec<DMatrix<f64>> = Vec::new();
+
+                    for _ in 0..number_imputations {
+                        data.push(listen_for_data(data_socket, number_columns)?);
+                    }
+
+                    match number_imputations {
+                        1 => {
+                            analysis.for_data(Imputation::No(&data[0]));
+                        }
+                        _ => {
+                            let imp_data : Vec<&DMatrix<f64>> = Vec::from_iter(data.iter().map(|v| v));
+                            analysis.for_data(Imputation::Yes(&imp_data));
+                        }
+                    }
+
+                    if let Some(column_names) = column_names {
+                        analysis.set_variable_names(&column_names.iter().map(|name| name.as_str()).collect::<Vec<&str>>());
+                    }
+
+                    Ok(vec!(b"received data".into()))
+                }
+            }
+        }
+        "weights arrow" => {
+            let (data, _) = arrow_ipc_to_matrix(data_socket.receive_data()?)?;
+            if data.ncols() != 1 {
+                return Err("weights have to be a single column".into());
+            }
+            analysis.set_weights(&data.column(0).clone_owned());
+            Ok(vec!(b"received weights".into()))
+        }
+        "replicate weights arrow" => {
+            let (replicate_weights, _) = arrow_ipc_to_matrix(data_socket.receive_data()?)?;
+            analysis.with_replicate_weights(&replicate_weights);
+            Ok(vec!(b"received replicate weights".into()))
+        }
+        "weights" => {
+            let data = listen_for_data(data_socket, 1)?;
+            let weight_vector : DVector<f64> = DVector::<f64>::from_iterator(data.nrows(), data.iter().map(|v| v.clone()));
+            analysis.set_weights(&weight_vector);
+            Ok(vec!(b"received weights".into()))
+        }
+        str if str.starts_with("replicate weights") => {
+            let message_arguments = parse_replicate_weights_message(&str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: replicate weights <number_columns>".into()))
+                }
+                Some(number_columns) => {
+                    let replicate_weights = listen_for_data(data_socket, number_columns)?;
+                    analysis.with_replicate_weights(&replicate_weights);
+                    Ok(vec!(b"received replicate weights".into()))
+                }
+            }
+        }
+        str if str.starts_with("save") => {
+            match str.strip_prefix("save ").filter(|path| !path.is_empty()) {
+                None => {
+                    Ok(vec!(b"bad request - usage: save <path>".into()))
+                }
+                Some(path) => {
+                    analysis.save(path)?;
+                    Ok(vec!(b"saved analysis".into()))
+                }
+            }
+        }
+        str if str.starts_with("restore") => {
+            match str.strip_prefix("restore ").filter(|path| !path.is_empty()) {
+                None => {
+                    Ok(vec!(b"bad request - usage: restore <path>".into()))
+                }
+                Some(path) => {
+                    *analysis = restore_analysis(path)?;
+                    Ok(vec!(b"restored analysis".into()))
+                }
+            }
+        }
+        str if str.starts_with("group by") => {
+            let message_arguments = parse_group_by_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: group by <number_columns> [named]".into()))
+                }
+                Some((number_columns, named)) => {
+                    let column_names = if named { Some(listen_for_column_names(data_socket, number_columns)?) } else { None };
+                    let groups = listen_for_data(data_socket, number_columns)?;
+                    analysis.group_by(Imputation::No(&groups));
+                    if let Some(column_names) = column_names {
+                        analysis.set_group_names(&column_names.iter().map(|name| name.as_str()).collect::<Vec<&str>>());
+                    }
+                    Ok(vec!(b"received grouping columns".into()))
+                }
+            }
+        }
+        str if str.starts_with("group names") => {
+            let message_arguments = parse_group_names_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: group names <name> ...".into()))
+                }
+                Some(group_names) => {
+                    analysis.set_group_names(&group_names);
+                    Ok(vec!(b"set group names".into()))
+                }
+            }
+        }
+        str if str.starts_with("group labels") => {
+            let message_arguments = parse_group_labels_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: group labels <column> <value>=<label> ...".into()))
+                }
+                Some((column, labels)) => {
+                    analysis.set_group_labels(column, &labels);
+                    Ok(vec!(b"set group labels".into()))
+                }
+            }
+        }
+        str if str.starts_with("variable names") => {
+            let message_arguments = parse_variable_names_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: variable names <name> ...".into()))
+                }
+                Some(variable_names) => {
+                    analysis.set_variable_names(&variable_names);
+                    Ok(vec!(b"set variable names".into()))
+                }
+            }
+        }
+        str if str.starts_with("metadata") => {
+            let message_arguments = parse_metadata_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: metadata <key> <value>".into()))
+                }
+                Some((key, value)) => {
+                    analysis.set_metadata(key, value);
+                    Ok(vec!(b"set metadata".into()))
+                }
+            }
+        }
+        str if str.starts_with("set variance adjustment factor") => {
+            let message_arguments = parse_set_variance_adjustment_factor_message(&str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: set variance adjustment factor <factor>".into()))
+                }
+                Some(factor) => {
+                    analysis.set_variance_adjustment_factor(factor);
+                    Ok(vec!(b"set variance adjustment factor".into()))
+                }
+            }
+        }
+        str if str.starts_with("set confidence level") => {
+            let message_arguments = parse_set_confidence_level_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: set confidence level <level>".into()))
+                }
+                Some(confidence_level) => {
+                    analysis.set_confidence_level(confidence_level);
+                    Ok(vec!(b"set confidence level".into()))
+                }
+            }
+        }
+        "also" => {
+            analysis.also();
+            Ok(vec!(b"keeping previous analysis".into()))
+        }
+        "mean" => {
+            analysis.mean();
+            Ok(vec!(b"set analysis to mean".into()))
+        }
+        "total" => {
+            analysis.total();
+            Ok(vec!(b"set analysis to total".into()))
+        }
+        "linear regression" => {
+            analysis.linreg();
+            Ok(vec!(b"set analysis to linear regression".into()))
+        }
+        str if str.starts_with("linear regression with residual diagnostics") => {
+            let message_arguments = parse_linear_regression_diagnostics_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: linear regression with residual diagnostics <number of bins>".into()))
+                }
+                Some(number_residual_bins) => {
+                    analysis.linreg_with_options(&LinregOptions { residual_diagnostics: true, number_residual_bins, ..Default::default() });
+                    Ok(vec!(b"set analysis to linear regression".into()))
+                }
+            }
+        }
+        "poisson regression" => {
+            analysis.poisson_regression();
+            Ok(vec!(b"set analysis to poisson regression".into()))
+        }
+        "effect size" => {
+            analysis.effect_size();
+            Ok(vec!(b"set analysis to effect size".into()))
+        }
+        str if str.starts_with("t test") => {
+            let message_arguments = parse_t_test_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: t test <mu>".into()))
+                }
+                Some(mu) => {
+                    analysis.t_test(mu);
+                    Ok(vec!(b"set analysis to t test".into()))
+                }
+            }
+        }
+        "two sample t test" => {
+            analysis.two_sample_t_test();
+            Ok(vec!(b"set analysis to two sample t test".into()))
+        }
+        "eta squared" => {
+            analysis.eta_squared();
+            Ok(vec!(b"set analysis to eta squared".into()))
+        }
+        "mantel haenszel dif" => {
+            analysis.mantel_haenszel_dif();
+            Ok(vec!(b"set analysis to mantel haenszel dif".into()))
+        }
+        "odds ratio" => {
+            analysis.odds_ratio();
+            Ok(vec!(b"set analysis to odds ratio".into()))
+        }
+        "gini" => {
+            analysis.gini();
+            Ok(vec!(b"set analysis to gini".into()))
+        }
+        str if str.starts_with("concentration") => {
+            let message_arguments = parse_concentration_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: concentration <number of ordinates>".into()))
+                }
+                Some(number_ordinates) => {
+                    analysis.concentration(number_ordinates);
+                    Ok(vec!(b"set analysis to concentration".into()))
+                }
+            }
+        }
+        str if str.starts_with("percentile shares") => {
+            let message_arguments = parse_percentile_shares_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: percentile shares <lower>-<upper> [<lower>-<upper> ...]".into()))
+                }
+                Some(ranges) => {
+                    analysis.percentile_shares(&ranges);
+                    Ok(vec!(b"set analysis to percentile shares".into()))
+                }
+            }
+        }
+        "theil" => {
+            analysis.theil();
+            Ok(vec!(b"set analysis to theil".into()))
+        }
+        str if str.starts_with("atkinson") => {
+            let message_arguments = parse_atkinson_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: atkinson <epsilon>".into()))
+                }
+                Some(epsilon) => {
+                    analysis.atkinson(epsilon);
+                    Ok(vec!(b"set analysis to atkinson".into()))
+                }
+            }
+        }
+        str if str.starts_with("benchmark levels") => {
+            let message_arguments = parse_benchmark_levels_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: benchmark levels [<cutpoint> ...]".into()))
+                }
+                Some(cutpoints) => {
+                    analysis.benchmark_levels(&cutpoints);
+                    Ok(vec!(b"set analysis to benchmark levels".into()))
+                }
+            }
+        }
+        str if str.starts_with("percentile ranks") => {
+            let message_arguments = parse_percentile_ranks_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: percentile ranks <value> [<value> ...]".into()))
+                }
+                Some(values) => {
+                    analysis.percentile_ranks(&values);
+                    Ok(vec!(b"set analysis to percentile ranks".into()))
+                }
+            }
+        }
+        str if str.starts_with("binned frequencies equal width") => {
+            let message_arguments = parse_binned_frequencies_equal_width_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: binned frequencies equal width <number_bins>".into()))
+                }
+                Some(number_bins) => {
+                    analysis.equal_width_binned_frequencies(number_bins);
+                    Ok(vec!(b"set analysis to binned frequencies".into()))
+                }
+            }
+        }
+        str if str.starts_with("binned frequencies") => {
+            let message_arguments = parse_binned_frequencies_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: binned frequencies <break> <break> [<break> ...]".into()))
+                }
+                Some(breaks) => {
+                    analysis.binned_frequencies(&breaks);
+                    Ok(vec!(b"set analysis to binned frequencies".into()))
+                }
+            }
+        }
+        "polychoric correlation" => {
+            analysis.polychoric_correlation();
+            Ok(vec!(b"set analysis to polychoric correlation".into()))
+        }
+        "polyserial correlation" => {
+            analysis.polyserial_correlation();
+            Ok(vec!(b"set analysis to polyserial correlation".into()))
+        }
+        "frequencies" => {
+            analysis.frequencies();
+            Ok(vec!(b"set analysis to frequencies".into()))
+        }
+        "frequencies with percentages" => {
+            analysis.frequencies_with_options(&FrequenciesOptions { percentages: true, ..Default::default() });
+            Ok(vec!(b"set analysis to frequencies".into()))
+        }
+        "agreement" => {
+            analysis.agreement();
+            Ok(vec!(b"set analysis to agreement".into()))
+        }
+        "crosstab" => {
+            analysis.crosstab();
+            Ok(vec!(b"set analysis to crosstab".into()))
+        }
+        "range" => {
+            analysis.range();
+            Ok(vec!(b"set analysis to range".into()))
+        }
+        "missingness" => {
+            analysis.missingness();
+            Ok(vec!(b"set analysis to missingness".into()))
+        }
+        "standardized mean" => {
+            analysis.standardized_mean();
+            Ok(vec!(b"set analysis to standardized mean".into()))
+        }
+        "principal components" => {
+            analysis.principal_components();
+            Ok(vec!(b"set analysis to principal components".into()))
+        }
+        str if str.starts_with("proportions above") => {
+            let message_arguments = parse_proportions_above_message(str);
+
+            match message_arguments {
+                None => {
+                    Ok(vec!(b"bad request - usage: proportions above <cutpoint> [<cutpoint> ...]".into()))
+                }
+                Some(cutpoints) => {
+                    analysis.proportions_above(&cutpoints);
+                    Ok(vec!(b"set analysis to proportions above".into()))
+                }
+            }
+        }
+        "hello" | "capabilities" => {
+            Ok(vec!(b"capabilities".into(), serde_json::to_string(&capabilities())?.into_bytes()))
+        }
+        "summary" => {
+            Ok(vec!(analysis.summary().into_bytes()))
+        }
+        "summary json" => {
+            Ok(vec!(b"summary".into(), serde_json::to_string(&analysis.summary_struct())?.into_bytes()))
+        }
+        "calculate json" => {
+            match analysis.calculate() {
+                Ok(result_data) => {
+                    match results_to_json(&result_data) {
+                        Ok(json) => {
+                            Ok(vec!(b"calculation complete".into(), json.into_bytes()))
+                        }
+                        Err(err) => {
+                            Ok(vec!([b"error serializing calculation result: ", err.to_string().as_bytes()].concat()))
+                        }
+                    }
+                }
+                Err(err) => {
+                    Ok(vec!([b"error calculating: ", err.to_string().as_bytes()].concat()))
+                }
+            }
+        }
+        "calculate csv" => {
+            match analysis.calculate() {
+                Ok(result_data) => {
+                    Ok(vec!(b"calculation complete".into(), results_to_csv(&result_data).into_bytes()))
+                }
+                Err(err) => {
+                    Ok(vec!([b"error calculating: ", err.to_string().as_bytes()].concat()))
+                }
+            }
+        }
+        "calculate" => {
+            let result = analysis.calculate();
+            match result {
+                Ok(result_data) => {
+                    let mut result_data_external : BTreeMap<GroupKey, ReplicatedEstimates> = BTreeMap::new();
+                    for (key, value) in result_data.iter() {
+                        result_data_external.insert(key.clone(), ReplicatedEstimates::from_internal(value));
+                    }
+                    let serialization = rmp_serde::to_vec(&result_data_external);
+
+                    match serialization {
+                        Ok(serialized_data) => {
+                            Ok(vec!(b"calculation complete".try_into().unwrap(), serialized_data))
+                        }
+                        Err(err) => {
+                            Ok(vec!([b"error serializing calculation result: ", err.to_string().as_bytes()].concat().into()))
+                        }
+                    }
+                }
+                Err(err) => {
+                    Ok(vec!([b"error calculating: ", err.to_string().as_bytes()].concat().into()))
+                }
+            }
+        }
+        _ => {
+            Ok(vec!(b"unknown".into()))
+        }
+    }
+}
+
+fn parse_data_message(message: &str) -> Option<(usize, usize, bool)> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, number_imputations, number_columns] if number_imputations.parse::<usize>().is_ok() && number_columns.parse::<usize>().is_ok() => {
+            Some((number_imputations.parse::<usize>().unwrap(), number_columns.parse::<usize>().unwrap(), false))
+        }
+        [_, number_imputations, number_columns, "named"] if number_imputations.parse::<usize>().is_ok() && number_columns.parse::<usize>().is_ok() => {
+            Some((number_imputations.parse::<usize>().unwrap(), number_columns.parse::<usize>().unwrap(), true))
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_load_csv_message(message: &str) -> Option<(&str, CsvOptions, &str)> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, path, settings @ ..] if !path.is_empty() => {
+            let mut options = CsvOptions::default();
+            let mut target = "data";
+
+            for setting in settings {
+                match setting.split_once('=') {
+                    Some(("delimiter", "tab")) => options.delimiter = b'\t',
+                    Some(("delimiter", delimiter)) if delimiter.len() == 1 => options.delimiter = delimiter.as_bytes()[0],
+                    Some(("header", header)) if header.parse::<bool>().is_ok() => options.header = header.parse::<bool>().unwrap(),
+                    Some(("columns", columns)) if columns.split(',').all(|column| !column.is_empty()) => {
+                        options.columns = columns.split(',').map(|column| column.to_string()).collect();
+                    }
+                    Some(("into", into)) if ["data", "weights", "replicate_weights", "groups"].contains(&into) => target = into,
+                    _ => return None,
+                }
+            }
+
+            Some((path, options, target))
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_load_parquet_message(message: &str) -> Option<(&str, Vec<&str>)> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, path, columns @ ..] if !path.is_empty() && columns.iter().all(|column| !column.is_empty()) => {
+            Some((path, columns.to_vec()))
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_arrow_data_message(message: &str) -> Option<usize> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, number_imputations] if number_imputations.parse::<usize>().is_ok_and(|n| n > 0) => {
+            Some(number_imputations.parse::<usize>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_replicate_weights_message(message: &str) -> Option<usize> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, number_columns] if number_columns.parse::<usize>().is_ok() => {
+            Some(number_columns.parse::<usize>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_group_by_message(message: &str) -> Option<(usize, bool)> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, number_columns] if number_columns.parse::<usize>().is_ok_and(|n| n > 0) => {
+            Some((number_columns.parse::<usize>().unwrap(), false))
+        }
+        [_, _, number_columns, "named"] if number_columns.parse::<usize>().is_ok_and(|n| n > 0) => {
+            Some((number_columns.parse::<usize>().unwrap(), true))
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_group_names_message(message: &str) -> Option<Vec<&str>> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, group_names @ ..] if !group_names.is_empty() && group_names.iter().all(|name| !name.is_empty()) => {
+            Some(group_names.to_vec())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_group_labels_message(message: &str) -> Option<(usize, Vec<(f64, &str)>)> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, column, labels @ ..] if column.parse::<usize>().is_ok() && !labels.is_empty() => {
+            let mut parsed_labels : Vec<(f64, &str)> = Vec::new();
+            for label in labels {
+                match label.split_once('=') {
+                    Some((value, label)) if value.parse::<f64>().is_ok() && !label.is_empty() => {
+                        parsed_labels.push((value.parse::<f64>().unwrap(), label));
+                    }
+                    _ => {
+                        return None;
+                    }
+                }
+            }
+            Some((column.parse::<usize>().unwrap(), parsed_labels))
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_variable_names_message(message: &str) -> Option<Vec<&str>> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, variable_names @ ..] if !variable_names.is_empty() && variable_names.iter().all(|name| !name.is_empty()) => {
+            Some(variable_names.to_vec())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_metadata_message(message: &str) -> Option<(&str, &str)> {
+    // the value is the remainder of the message and may contain spaces
+    match message.strip_prefix("metadata ").and_then(|arguments| arguments.split_once(' ')) {
+        Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+            Some((key, value))
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_set_variance_adjustment_factor_message(message: &str) -> Option<f64> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, _, _, factor] if factor.parse::<f64>().is_ok() => {
+            Some(factor.parse::<f64>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_set_confidence_level_message(message: &str) -> Option<f64> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, _, level] if level.parse::<f64>().is_ok_and(|l| l > 0.0 && l < 1.0) => {
+            Some(level.parse::<f64>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_linear_regression_diagnostics_message(message: &str) -> Option<usize> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, _, _, _, number_bins] if number_bins.parse::<usize>().is_ok_and(|n| n > 0) => {
+            Some(number_bins.parse::<usize>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_t_test_message(message: &str) -> Option<f64> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, mu] if mu.parse::<f64>().is_ok() => {
+            Some(mu.parse::<f64>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_concentration_message(message: &str) -> Option<usize> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, number_ordinates] if number_ordinates.parse::<usize>().is_ok_and(|n| n > 0) => {
+            Some(number_ordinates.parse::<usize>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_percentile_shares_message(message: &str) -> Option<Vec<(f64, f64)>> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, ranges @ ..] if !ranges.is_empty() => {
+            let mut parsed_ranges : Vec<(f64, f64)> = Vec::new();
+            for range in ranges {
+                match range.split_once("-").map(|(lower, upper)| (lower.parse::<f64>(), upper.parse::<f64>())) {
+                    Some((Ok(lower), Ok(upper))) if 0.0 <= lower && lower < upper && upper <= 100.0 => {
+                        parsed_ranges.push((lower, upper));
+                    }
+                    _ => {
+                        return None;
+                    }
+                }
+            }
+            Some(parsed_ranges)
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_atkinson_message(message: &str) -> Option<f64> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, epsilon] if epsilon.parse::<f64>().is_ok() => {
+            Some(epsilon.parse::<f64>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_benchmark_levels_message(message: &str) -> Option<Vec<f64>> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _] => {
+            Some(ILSA_BENCHMARKS.to_vec())
+        }
+        [_, _, cutpoints @ ..] if cutpoints.iter().all(|v| v.parse::<f64>().is_ok()) => {
+            let cutpoints : Vec<f64> = cutpoints.iter().map(|v| v.parse::<f64>().unwrap()).collect();
+            if cutpoints.windows(2).all(|w| w[0] < w[1]) { Some(cutpoints) } else { None }
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_percentile_ranks_message(message: &str) -> Option<Vec<f64>> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, values @ ..] if !values.is_empty() && values.iter().all(|v| v.parse::<f64>().is_ok()) => {
+            Some(values.iter().map(|v| v.parse::<f64>().unwrap()).collect())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_binned_frequencies_message(message: &str) -> Option<Vec<f64>> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, breaks @ ..] if breaks.len() > 1 && breaks.iter().all(|b| b.parse::<f64>().is_ok()) => {
+            Some(breaks.iter().map(|b| b.parse::<f64>().unwrap()).collect())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_binned_frequencies_equal_width_message(message: &str) -> Option<usize> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, _, _, number_bins] if number_bins.parse::<usize>().is_ok_and(|n| n > 0) => {
+            Some(number_bins.parse::<usize>().unwrap())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+fn parse_proportions_above_message(message: &str) -> Option<Vec<f64>> {
+    let message_components : Vec<&str> = message.split(" ").collect();
+
+    match message_components.as_slice() {
+        [_, _, cutpoints @ ..] if !cutpoints.is_empty() && cutpoints.iter().all(|c| c.parse::<f64>().is_ok()) => {
+            Some(cutpoints.iter().map(|c| c.parse::<f64>().unwrap()).collect())
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+// column names are sent as a JSON array ahead of the matrices
+fn listen_for_column_names(data_socket: &impl DataSource, columns: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let column_names : Vec<String> = serde_json::from_slice(&data_socket.receive_data()?)?;
+    if column_names.len() != columns {
+        return Err(format!("expected {} column names but received {}", columns, column_names.len()).into());
+    }
+    Ok(column_names)
+}
+
+fn listen_for_data(data_socket: &impl DataSource, columns: usize) -> Result<DMatrix<f64>, Box<dyn Error>> {
+    let buffer = data_socket.receive_data()?;
+
+    let data = u8_to_f64_vec(buffer, columns)?;
+    let rows = data.len() / columns;
+
+    Ok(DMatrix::from_vec(rows, columns, data))
+}
+
+fn u8_to_f64_vec(u8_data: Vec<u8>, columns: usize) -> Result<Vec<f64>, Box<dyn Error>> {
+    if u8_data.len() % (8 * columns) != 0 {
+        return Err(Box::new(DataLengthError::new()));
+    }
+    let rows = u8_data.len() / (8 * columns);
+
+    let mut data = Vec::new();
+
+    for i in 0..columns * rows {
+        let bytes : [u8; 8] = u8_data[i*8..(i + 1) * 8].try_into().unwrap();
+
+        // the protocol is little-endian whatever the architecture of server and client
+        data.push(f64::from_le_bytes(bytes))
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+    use std::fs::exists;
+    use std::io::Write;
+    use std::ops::Deref;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::net::{UnixDatagram, UnixStream};
+    use std::sync::Arc;
+    use users::get_current_gid;
+    use super::*;
+    use std::time::Duration;
+    use nalgebra::{dmatrix, dvector};
+
+    #[tokio::test]
+    #[serial]
+    async fn test_setup_sockets() {
+        let user_id = get_current_uid();
+        let options = parse_arguments(&["replicest_server".to_string()]).unwrap();
+
+        let sockets = setup_sockets(&options, &Authentication::default());
+        assert!(sockets.is_ok());
+        assert!(exists(format!("/run/user/{}/replicest_server", user_id)).unwrap_or(false));
+        assert!(exists(format!("/run/user/{}/replicest_server_data", user_id)).unwrap_or(false));
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--socket-mode".to_string(), "660".to_string(), "--socket-group".to_string(), get_current_gid().to_string()]).unwrap();
+        let sockets = setup_sockets(&options, &Authentication::default());
+        assert!(sockets.is_ok());
+        for path in [format!("/run/user/{}/replicest_server", user_id), format!("/run/user/{}/replicest_server_data", user_id)] {
+            let metadata = std::fs::metadata(path).unwrap();
+            assert_eq!(0o660, metadata.permissions().mode() & 0o777);
+            assert_eq!(get_current_gid(), metadata.gid());
+        }
+
+        drop(sockets);
+        assert!(!exists(format!("/run/user/{}/replicest_server", user_id)).unwrap_or(true));
+        assert!(!exists(format!("/run/user/{}/replicest_server_data", user_id)).unwrap_or(true));
+    }
+
+    #[test]
+    #[serial]
+    fn test_message_socket_general_commands() {
+        let client_addr = "/tmp/replicest_server_test_message_socket_general_commands".to_string();
+        let _ = remove_file(&client_addr);
+        let client = UnixDatagram::bind(&client_addr).unwrap();
+
+        let handle = thread::spawn(|| {
+            let return_value = main();
+            assert!(return_value.is_ok());
+        });
+
+        thread::sleep(Duration::from_secs(1));
+
+        let user_id = get_current_uid();
+        let socket_addr = format!("/run/user/{}/replicest_server", user_id);
+        client.connect(&socket_addr).unwrap();
+
+        client.send(b"clear").unwrap();
+
+        let mut buffer = [0; 1024];
+        let _ = client.recv(&mut buffer);
+        let message = trim_buffer(&buffer);
+
+        assert_eq!("cleared", message);
+
+        client.send(b"shutdown").unwrap();
+
+        let mut buffer = [0; 1024];
+        let _ = client.recv(&mut buffer);
+        let message = trim_buffer(&buffer);
+
+        assert_eq!("shutting down", message);
+
+        handle.join().unwrap();
+        assert!(!exists(&socket_addr).unwrap_or(true));
+        assert!(!exists(format!("/run/user/{}/replicest_server_data", user_id)).unwrap_or(true));
+        let _ = remove_file(&client_addr);
+    }
+
+    #[test]
+    #[serial]
+    fn test_message_socket_authentication() {
+        let token_file = "/tmp/replicest_server_test_message_socket_authentication_token".to_string();
+        std::fs::write(&token_file, "s3cr3t\n").unwrap();
+
+        let client_addr = "/tmp/replicest_server_test_message_socket_authentication".to_string();
+        let _ = remove_file(&client_addr);
+        let client = UnixDatagram::bind(&client_addr).unwrap();
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--token-file".to_string(), token_file.clone()]).unwrap();
+        let handle = thread::spawn(move || {
+            let return_value = run(options);
+            assert!(return_value.is_ok());
+        });
+
+        thread::sleep(Duration::from_secs(1));
+
+        let user_id = get_current_uid();
+        client.connect(format!("/run/user/{}/replicest_server", user_id)).unwrap();
+
+        let exchange = |message: &[u8]| {
+            client.send(message).unwrap();
+            let mut buffer = [0; 1024];
+            let _ = client.recv(&mut buffer);
+            trim_buffer(&buffer)
+        };
+
+        assert_eq!("error: not authenticated", exchange(b"clear"));
+        assert_eq!("error: not authenticated", exchange(b"shutdown"));
+        assert_eq!("error: invalid token", exchange(b"auth guess"));
+        assert_eq!("error: not authenticated", exchange(b"clear"));
+        assert_eq!("authenticated", exchange(b"auth s3cr3t"));
+        assert_eq!("cleared", exchange(b"clear"));
+
+        // a client binding the same path afterwards has to authenticate again
+        drop(client);
+        remove_file(&client_addr).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        let client = UnixDatagram::bind(&client_addr).unwrap();
+        client.connect(format!("/run/user/{}/replicest_server", user_id)).unwrap();
+        let exchange = |message: &[u8]| {
+            client.send(message).unwrap();
+            let mut buffer = [0; 1024];
+            let _ = client.recv(&mut buffer);
+            trim_buffer(&buffer)
+        };
+
+        assert_eq!("error: not authenticated", exchange(b"clear"));
+        assert_eq!("authenticated", exchange(b"auth s3cr3t"));
+        assert_eq!("shutting down", exchange(b"shutdown"));
+
+        handle.join().unwrap();
+        let _ = remove_file(&client_addr);
+        let _ = remove_file(&token_file);
+    }
+
+    #[test]
+    #[serial]
+    fn test_message_socket_keeps_answering_while_waiting_for_data() {
+        let client_addr = "/tmp/replicest_server_test_message_socket_keeps_answering".to_string();
+        let _ = remove_file(&client_addr);
+        let client = UnixDatagram::bind(&client_addr).unwrap();
+
+        let handle = thread::spawn(|| {
+            let return_value = main();
+            assert!(return_value.is_ok());
+        });
+
+        thread::sleep(Duration::from_secs(1));
+
+        let user_id = get_current_uid();
+        client.connect(format!("/run/user/{}/replicest_server", user_id)).unwrap();
+
+        client.send(b"data 1 2").unwrap();
+        client.send(b"session create other").unwrap();
+
+        let mut buffer = [0; 1024];
+        let _ = client.recv(&mut buffer);
+        assert_eq!("created session other", trim_buffer(&buffer));
+
+        let mut data_client = UnixStream::connect(format!("/run/user/{}/replicest_server_data", user_id)).unwrap();
+        let data : Vec<u8> = [1.0_f64, 3.0, 2.0, 4.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        data_client.write_all(&data).unwrap();
+        drop(data_client);
+
+        let mut buffer = [0; 1024];
+        let _ = client.recv(&mut buffer);
+        assert_eq!("received data", trim_buffer(&buffer));
+
+        client.send(b"shutdown").unwrap();
+
+        let mut buffer = [0; 1024];
+        let _ = client.recv(&mut buffer);
+        assert_eq!("shutting down", trim_buffer(&buffer));
+
+        handle.join().unwrap();
+        let _ = remove_file(&client_addr);
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let options = parse_arguments(&["replicest_server".to_string()]).unwrap();
+        assert!(options.websocket_address.is_none());
+        assert_eq!(Level::INFO, options.log_level);
+        assert!(options.log_file.is_none());
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--websocket".to_string(), "127.0.0.1:9001".to_string()]).unwrap();
+        assert_eq!(Some("127.0.0.1:9001".to_string()), options.websocket_address);
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--grpc".to_string(), "127.0.0.1:50051".to_string()]).unwrap();
+        assert_eq!(Some("127.0.0.1:50051".to_string()), options.grpc_address);
+        assert!(options.session_timeout.is_none());
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--session-timeout".to_string(), "600".to_string()]).unwrap();
+        assert_eq!(Some(Duration::from_secs(600)), options.session_timeout);
+        assert!(parse_arguments(&["replicest_server".to_string(), "--session-timeout".to_string(), "0".to_string()]).is_err());
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--log-level".to_string(), "debug".to_string(), "--log-file".to_string(), "/tmp/replicest_server.log".to_string()]).unwrap();
+        assert_eq!(Level::DEBUG, options.log_level);
+        assert_eq!(Some("/tmp/replicest_server.log".to_string()), options.log_file);
+        assert!(parse_arguments(&["replicest_server".to_string(), "--log-level".to_string(), "chatty".to_string()]).is_err());
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--socket-mode".to_string(), "0660".to_string(), "--socket-group".to_string(), "0".to_string(), "--token-file".to_string(), "/etc/replicest/token".to_string()]).unwrap();
+        assert_eq!(Some(0o660), options.socket_mode);
+        assert_eq!(Some(0), options.socket_group);
+        assert_eq!(Some("/etc/replicest/token".to_string()), options.token_file);
+        assert!(parse_arguments(&["replicest_server".to_string(), "--socket-mode".to_string(), "999".to_string()]).is_err());
+        assert!(parse_arguments(&["replicest_server".to_string(), "--socket-mode".to_string(), "1777".to_string()]).is_err());
+        assert!(parse_arguments(&["replicest_server".to_string(), "--socket-group".to_string(), "no_such_group_for_replicest".to_string()]).is_err());
+
+        assert_eq!(DEFAULT_MAX_PAYLOAD_SIZE, parse_arguments(&["replicest_server".to_string()]).unwrap().max_payload_size);
+        let options = parse_arguments(&["replicest_server".to_string(), "--max-payload-size".to_string(), "1048576".to_string()]).unwrap();
+        assert_eq!(1048576, options.max_payload_size);
+        assert!(parse_arguments(&["replicest_server".to_string(), "--max-payload-size".to_string(), "0".to_string()]).is_err());
+
+        let options = parse_arguments(&["replicest_server".to_string(), "--script".to_string(), "analysis.txt".to_string(), "--output".to_string(), "results.csv".to_string()]).unwrap();
+        assert_eq!(Some("analysis.txt".to_string()), options.script);
+        assert_eq!(Some("results.csv".to_string()), options.output);
+        assert!(parse_arguments(&["replicest_server".to_string(), "--script".to_string(), "analysis.txt".to_string()]).is_err());
+        assert!(parse_arguments(&["replicest_server".to_string(), "--output".to_string(), "results.csv".to_string()]).is_err());
+
+        assert!(parse_arguments(&["replicest_server".to_string(), "--websocket".to_string()]).is_err());
+        assert!(parse_arguments(&["replicest_server".to_string(), "--unknown".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve_websocket(listener, None, Jobs::new(), Authentication::default()));
+
+        let (mut client, _) = tungstenite::connect(format!("ws://{}", address)).unwrap();
+
+        client.send(Message::text("data 1 2")).unwrap();
+        let data : Vec<u8> = [1.0_f64, 3.0, 2.0, 4.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        client.send(Message::binary(data)).unwrap();
+        assert_eq!(Message::text("received data"), client.read().unwrap());
+
+        client.send(Message::text("mean")).unwrap();
+        assert_eq!(Message::text("set analysis to mean"), client.read().unwrap());
+
+        client.send(Message::text("calculate")).unwrap();
+        assert_eq!(Message::text("calculation complete"), client.read().unwrap());
+        let result = match client.read().unwrap() {
+            Message::Binary(result_data) => rmp_serde::from_slice::<BTreeMap<GroupKey, ReplicatedEstimates>>(&result_data).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert_eq!(vec![2.0, 3.0], result[&GroupKey::overall()].final_estimates);
+
+        client.send(Message::text("session create other")).unwrap();
+        assert_eq!(Message::text("created session other"), client.read().unwrap());
+
+        client.send(Message::text("@other calculate")).unwrap();
+        assert_eq!(Message::text("error calculating: Analysis is missing some element: estimate"), client.read().unwrap());
+
+        client.send(Message::text("clear")).unwrap();
+        assert_eq!(Message::text("cleared"), client.read().unwrap());
+
+        client.send(Message::text("calculate")).unwrap();
+        assert_eq!(Message::text("error calculating: Analysis is missing some element: estimate"), client.read().unwrap());
+
+        client.close(None).unwrap();
+    }
+
+    #[test]
+    fn test_bind_websocket() {
+        assert!(bind_websocket("127.0.0.1:0", &Authentication::default()).is_ok());
+
+        let result = bind_websocket("0.0.0.0:0", &Authentication::default());
+        assert_eq!("--websocket on an address other than loopback requires --token-file", result.err().unwrap().to_string());
+
+        let token_file = "/tmp/replicest_server_test_bind_websocket_token".to_string();
+        std::fs::write(&token_file, "s3cr3t\n").unwrap();
+        assert!(bind_websocket("0.0.0.0:0", &Authentication::from_token_file(Some(&token_file)).unwrap()).is_ok());
+        let _ = remove_file(&token_file);
+    }
+
+    #[test]
+    fn test_u8_to_vec() {
+        let result = u8_to_f64_vec(b"abcabcabcabcabcabcabcabc".try_into().unwrap(), 3);
+        assert!(result.is_ok());
+
+        let floats = vec![1.5, 2.0, -3.2, 14.44, -7.1, f64::NAN];
+
+        let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+        let bytes = Vec::from(bytes.as_flattened());
+
+        let result = u8_to_f64_vec(bytes, 2).unwrap();
+
+        for (i, &v) in floats.iter().enumerate() {
+            if v.is_nan() {
+                assert!(result[i].is_nan());
+            } else {
+                assert_eq!(v, result[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_u8_to_f64_vec_wrong_length() {
+        let result = u8_to_f64_vec(b"abcdeabcdeabcdeabcdeabcde".try_into().unwrap(), 3);
+        assert!(result.is_err());
+        assert_eq!("Length of data was not a multiple of 8 * columns", result.err().unwrap().deref().to_string())
+    }
+
+    #[test]
+    fn test_trim_buffer() {
+        let mut buf = [0; 1024];
+        buf[0] = 0x61;
+        buf[1] = 0x62;
+        buf[2] = 0x63;
+        buf[3] = 0x20;
+        let result = trim_buffer(&buf);
+
+        assert_eq!("abc", result);
+    }
+
+    #[test]
+    fn test_listen_for_data() {
+        let data_socket_addr = "/tmp/replicest_server_test_listen_for_data".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let return_value = listen_for_data(&data_socket, 2);
+            assert!(return_value.is_ok());
+
+            let expected = dmatrix![
+                1.5, 14.44;
+                2.0, -7.1;
+                -3.2, f64::NAN;
+            ];
+
+            let result = return_value.unwrap();
+
+            assert_eq!(0,result.iter().enumerate().filter(|(i, &v)| (expected[(i % 3, i / 3)] - v).abs() > 1e-10).count())
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        let mut client = UnixStream::connect("/tmp/replicest_server_test_listen_for_data").unwrap();
+
+        let floats = vec![1.5, 2.0, -3.2, 14.44, -7.1, f64::NAN];
+        let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+        let bytes = Vec::from(bytes.as_flattened());
+
+        let _ = client.write_all(&bytes);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_listen_for_data_wrong_length() {
+        let data_socket_addr = "/tmp/replicest_server_test_listen_for_data_wrong_length".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let return_value = listen_for_data(&data_socket, 10);
+            assert!(return_value.is_err());
+            assert_eq!("Length of data was not a multiple of 8 * columns", return_value.err().unwrap().deref().to_string());
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        let mut client = UnixStream::connect("/tmp/replicest_server_test_listen_for_data_wrong_length").unwrap();
+
+        let floats = vec![1.5, 2.0, -3.2, 14.44, -7.1, f64::NAN];
+        let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+        let bytes = Vec::from(bytes.as_flattened());
+
+        let _ = client.write_all(&bytes);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_message_weights() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_weights".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            let return_value = handle_message("weights".to_string(), &mut current_analysis, &data_socket);
+            assert!(return_value.is_ok());
+            assert_eq!(Vec::from(b"received weights"), return_value.unwrap()[0]);
+            assert_eq!("none (no data; 6 weights of sum 30.540000000000003; no replicate weights)", current_analysis.summary());
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_weights").unwrap();
+
+        let floats = vec![1.5, 2.0, 3.2, 14.44, 7.1, 2.3];
+        let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+        let bytes = Vec::from(bytes.as_flattened());
+
+        let _ = client.write_all(&bytes);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_parse_data_message() {
+        let wrong_message = "data";
+        assert!(parse_data_message(wrong_message).is_none());
+
+        let wrong_message = "data a 1";
+        assert!(parse_data_message(wrong_message).is_none());
+
+        let message = "data 5 15";
+        let result = parse_data_message(message);
+
+        assert!(result.is_some());
+        assert_eq!((5, 15, false), result.unwrap());
+
+        assert_eq!(Some((5, 15, true)), parse_data_message("data 5 15 named"));
+        assert!(parse_data_message("data 5 15 unnamed").is_none());
+    }
+
+    #[test]
+    fn test_parse_replicate_weights_message() {
+        let wrong_message = "replicate weights";
+        assert!(parse_replicate_weights_message(wrong_message).is_none());
+
+        let wrong_message = "replicate weights abc";
+        assert!(parse_replicate_weights_message(wrong_message).is_none());
+
+        let message = "replicate weights 80";
+        let result = parse_replicate_weights_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(80, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_group_by_message() {
+        let wrong_message = "group by 0";
+        assert!(parse_group_by_message(wrong_message).is_none());
+
+        let message = "group by 2";
+        let result = parse_group_by_message(message);
+
+        assert!(result.is_some());
+        assert_eq!((2, false), result.unwrap());
+
+        assert_eq!(Some((2, true)), parse_group_by_message("group by 2 named"));
+    }
+
+    #[test]
+    fn test_parse_group_names_message() {
+        let wrong_message = "group names";
+        assert!(parse_group_names_message(wrong_message).is_none());
+
+        let message = "group names gender track";
+        let result = parse_group_names_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(vec!["gender", "track"], result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_group_labels_message() {
+        let wrong_message = "group labels 0";
+        assert!(parse_group_labels_message(wrong_message).is_none());
+
+        let wrong_message = "group labels 0 1=female 2";
+        assert!(parse_group_labels_message(wrong_message).is_none());
+
+        let message = "group labels 0 1=female 2=male";
+        let result = parse_group_labels_message(message);
+
+        assert!(result.is_some());
+        assert_eq!((0, vec![(1.0, "female"), (2.0, "male")]), result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_variable_names_message() {
+        let wrong_message = "variable names";
+        assert!(parse_variable_names_message(wrong_message).is_none());
+
+        let message = "variable names ASRREA ASBGSES";
+        let result = parse_variable_names_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(vec!["ASRREA", "ASBGSES"], result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_metadata_message() {
+        assert!(parse_metadata_message("metadata").is_none());
+        assert!(parse_metadata_message("metadata survey").is_none());
+
+        let result = parse_metadata_message("metadata survey PIRLS 2021");
+        assert_eq!(Some(("survey", "PIRLS 2021")), result);
+    }
+
+    #[test]
+    fn test_parse_set_variance_adjustment_factor_message() {
+        let wrong_message = "set variance adjustment factor";
+        assert!(parse_set_variance_adjustment_factor_message(wrong_message).is_none());
+
+        let wrong_message = "set variance adjustment factor abc";
+        assert!(parse_set_variance_adjustment_factor_message(wrong_message).is_none());
+
+        let message = "set variance adjustment factor 0.25";
+        let result = parse_set_variance_adjustment_factor_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(0.25, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_set_confidence_level_message() {
+        let wrong_message = "set confidence level";
+        assert!(parse_set_confidence_level_message(wrong_message).is_none());
+
+        let wrong_message = "set confidence level 95";
+        assert!(parse_set_confidence_level_message(wrong_message).is_none());
+
+        let message = "set confidence level 0.99";
+        let result = parse_set_confidence_level_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(0.99, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_linear_regression_diagnostics_message() {
+        let wrong_message = "linear regression with residual diagnostics";
+        assert!(parse_linear_regression_diagnostics_message(wrong_message).is_none());
+
+        let wrong_message = "linear regression with residual diagnostics 0";
+        assert!(parse_linear_regression_diagnostics_message(wrong_message).is_none());
+
+        let message = "linear regression with residual diagnostics 4";
+        let result = parse_linear_regression_diagnostics_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(4, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_t_test_message() {
+        let wrong_message = "t test";
+        assert!(parse_t_test_message(wrong_message).is_none());
+
+        let wrong_message = "t test abc";
+        assert!(parse_t_test_message(wrong_message).is_none());
+
+        let message = "t test 500";
+        let result = parse_t_test_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(500.0, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_concentration_message() {
+        let wrong_message = "concentration";
+        assert!(parse_concentration_message(wrong_message).is_none());
+
+        let wrong_message = "concentration 0";
+        assert!(parse_concentration_message(wrong_message).is_none());
+
+        let message = "concentration 10";
+        let result = parse_concentration_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(10, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_percentile_shares_message() {
+        let wrong_message = "percentile shares";
+        assert!(parse_percentile_shares_message(wrong_message).is_none());
+
+        let wrong_message = "percentile shares 0-20 50";
+        assert!(parse_percentile_shares_message(wrong_message).is_none());
+
+        let wrong_message = "percentile shares 90-80";
+        assert!(parse_percentile_shares_message(wrong_message).is_none());
+
+        let message = "percentile shares 0-20 90-100";
+        let result = parse_percentile_shares_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(vec![(0.0, 20.0), (90.0, 100.0)], result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_benchmark_levels_message() {
+        let wrong_message = "benchmark levels 400 abc";
+        assert!(parse_benchmark_levels_message(wrong_message).is_none());
+
+        let wrong_message = "benchmark levels 475 400";
+        assert!(parse_benchmark_levels_message(wrong_message).is_none());
+
+        let message = "benchmark levels";
+        assert_eq!(vec![400.0, 475.0, 550.0, 625.0], parse_benchmark_levels_message(message).unwrap());
+
+        let message = "benchmark levels 420 482";
+        assert_eq!(vec![420.0, 482.0], parse_benchmark_levels_message(message).unwrap());
+    }
+
+    #[test]
+    fn test_parse_atkinson_message() {
+        let wrong_message = "atkinson";
+        assert!(parse_atkinson_message(wrong_message).is_none());
+
+        let wrong_message = "atkinson abc";
+        assert!(parse_atkinson_message(wrong_message).is_none());
+
+        let message = "atkinson 0.5";
+        let result = parse_atkinson_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(0.5, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_percentile_ranks_message() {
+        let wrong_message = "percentile ranks";
+        assert!(parse_percentile_ranks_message(wrong_message).is_none());
+
+        let wrong_message = "percentile ranks 500 x";
+        assert!(parse_percentile_ranks_message(wrong_message).is_none());
+
+        let message = "percentile ranks 400 500.5";
+        let result = parse_percentile_ranks_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(vec![400.0, 500.5], result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_binned_frequencies_message() {
+        let wrong_message = "binned frequencies 400";
+        assert!(parse_binned_frequencies_message(wrong_message).is_none());
+
+        let wrong_message = "binned frequencies 400 x";
+        assert!(parse_binned_frequencies_message(wrong_message).is_none());
+
+        let message = "binned frequencies 400 500 600";
+        let result = parse_binned_frequencies_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(vec![400.0, 500.0, 600.0], result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_binned_frequencies_equal_width_message() {
+        let wrong_message = "binned frequencies equal width";
+        assert!(parse_binned_frequencies_equal_width_message(wrong_message).is_none());
+
+        let wrong_message = "binned frequencies equal width 0";
+        assert!(parse_binned_frequencies_equal_width_message(wrong_message).is_none());
+
+        let message = "binned frequencies equal width 10";
+        let result = parse_binned_frequencies_equal_width_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(10, result.unwrap());
+    }
+
+    #[test]
+    fn test_parse_proportions_above_message() {
+        let wrong_message = "proportions above";
+        assert!(parse_proportions_above_message(wrong_message).is_none());
+
+        let wrong_message = "proportions above 400 abc";
+        assert!(parse_proportions_above_message(wrong_message).is_none());
+
+        let message = "proportions above 400 475 550 625";
+        let result = parse_proportions_above_message(message);
+
+        assert!(result.is_some());
+        assert_eq!(vec![400.0, 475.0, 550.0, 625.0], result.unwrap());
+    }
+
+    #[test]
+    fn test_handle_message_data_without_imputation() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_data_without_imputation".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            let return_value = handle_message("data 1 3".to_string(), &mut current_analysis, &data_socket);
+            assert!(return_value.is_ok());
+            assert_eq!(Vec::from(b"received data"), return_value.unwrap()[0]);
+            assert_eq!("none (1 datasets with 2 cases; wgt missing; no replicate weights)", current_analysis.summary());
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_data_without_imputation").unwrap();
+
+        let floats = vec![1.5, 2.0, 3.2, 14.44, 7.1, 2.3];
+        let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+        let bytes = Vec::from(bytes.as_flattened());
+
+        let _ = client.write_all(&bytes);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_message_data_compressed() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_data_compressed".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            let return_value = handle_message("data 1 3 compressed".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"received data"), return_value.unwrap()[0]);
+            let return_value = handle_message("weights compressed".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"received weights"), return_value.unwrap()[0]);
+            assert_eq!("none (1 datasets with 2 cases; 2 weights of sum 3; no replicate weights)", current_analysis.summary());
+
+            let return_value = handle_message("metadata comment data compressed".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"set metadata"), return_value.unwrap()[0]);
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        for floats in [vec![1.5, 2.0, 3.2, 14.44, 7.1, 2.3], vec![1.0, 2.0]] {
+            let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_data_compressed").unwrap();
+            let bytes : Vec<u8> = floats.iter().flat_map(|v: &f64| v.to_le_bytes()).collect();
+            let _ = client.write_all(&zstd::encode_all(bytes.as_slice(), 0).unwrap());
+            drop(client);
+        }
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_decompress() {
+        let bytes = vec![7; 1000];
+        let compressed = zstd::encode_all(bytes.as_slice(), 0).unwrap();
+        assert!(compressed.len() < 100);
+
+        assert_eq!(bytes, decompress(&compressed, 1000).unwrap());
+        assert_eq!("decompressed payload exceeds the maximum size of 999 bytes", decompress(&compressed, 999).unwrap_err().to_string());
+        assert!(decompress(b"not zstd", 1000).is_err());
+    }
+
+    #[test]
+    fn test_handle_message_data_named() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_data_named".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            let return_value = handle_message("data 1 2 named".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"received data"), return_value.unwrap()[0]);
+            let return_value = handle_message("group by 1 named".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"received grouping columns"), return_value.unwrap()[0]);
+
+            let result = current_analysis.mean().calculate().unwrap();
+            let (key, estimates) = result.iter().find(|(key, _)| key.labels() == &vec!["1".to_string()]).unwrap();
+            assert_eq!(&vec!["ITSEX".to_string()], key.variable_names());
+            assert_eq!(&vec!["mean_ASRREA".to_string(), "mean_ASBGSES".to_string()], estimates.parameter_names());
+
+            let return_value = handle_message("data 1 3 named".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!("expected 3 column names but received 2", return_value.err().unwrap().to_string());
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        let floats = [500.0_f64, 520.0, 9.5, 10.5];
+        let payloads : Vec<Vec<u8>> = vec![
+            b"[\"ASRREA\",\"ASBGSES\"]".to_vec(),
+            floats.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            b"[\"ITSEX\"]".to_vec(),
+            [1.0_f64, 2.0].iter().flat_map(|v| v.to_le_bytes()).collect(),
+            b"[\"ASRREA\",\"ASBGSES\"]".to_vec(),
+        ];
+        for payload in payloads {
+            let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_data_named").unwrap();
+            let _ = client.write_all(&payload);
+            drop(client);
+        }
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_message_append_data() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_append_data".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            for _ in 0..2 {
+                let return_value = handle_message("append data 1 3".to_string(), &mut current_analysis, &data_socket);
+                assert_eq!(Vec::from(b"appended data"), return_value.unwrap()[0]);
+            }
+            assert_eq!("none (1 datasets with 4 cases; wgt missing; no replicate weights)", current_analysis.summary());
+
+            let return_value = handle_message("append data 1 2".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"error appending data: Inconsistency in analysis: appended data differ in number of columns"), return_value.unwrap()[0]);
+
+            let return_value = handle_message("append data 1".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"bad request - usage: append data <number_imputations> <number_columns> [named]"), return_value.unwrap()[0]);
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        for floats in [vec![1.5, 2.0, 3.2, 14.44, 7.1, 2.3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], vec![1.0, 2.0]] {
+            let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_append_data").unwrap();
+            let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+            let bytes = Vec::from(bytes.as_flattened());
+            let _ = client.write_all(&bytes);
+            drop(client);
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_message_data_with_imputation() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_data_with_imputation".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            let return_value = handle_message("data 2 3".to_string(), &mut current_analysis, &data_socket);
+            assert!(return_value.is_ok());
+            assert_eq!(Vec::from(b"received data"), return_value.unwrap()[0]);
+            assert_eq!("none (2 datasets with 2 cases; wgt missing; no replicate weights)", current_analysis.summary());
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        for _ in 0..2 {
+            let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_data_with_imputation").unwrap();
+
+            let floats = vec![1.5, 2.0, 3.2, 14.44, 7.1, 2.3];
+            let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+            let bytes = Vec::from(bytes.as_flattened());
+
+            let _ = client.write_all(&bytes);
+
+            drop(client);
+        }
+
+        handle.join().unwrap();
+    }
+
+    fn arrow_stream(columns: Vec<(&str, arrow_array::ArrayRef)>) -> Vec<u8> {
+        let batch = arrow_array::RecordBatch::try_from_iter(columns).unwrap();
+        let mut buffer : Vec<u8> = Vec::new();
+        let mut writer = arrow_ipc::writer::StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        buffer
+    }
+
+    #[test]
+    fn test_parse_load_csv_message() {
+        assert!(parse_load_csv_message("load csv").is_none());
+        assert!(parse_load_csv_message("load csv /tmp/pirls.csv delimiter=ab").is_none());
+        assert!(parse_load_csv_message("load csv /tmp/pirls.csv into=somewhere").is_none());
+
+        let (path, options, target) = parse_load_csv_message("load csv /tmp/pirls.csv").unwrap();
+        assert_eq!("/tmp/pirls.csv", path);
+        assert_eq!(CsvOptions::default(), options);
+        assert_eq!("data", target);
+
+        let (_, options, target) = parse_load_csv_message("load csv /tmp/pirls.csv delimiter=tab header=false columns=x1,x3 into=replicate_weights").unwrap();
+        assert_eq!(CsvOptions { delimiter: b'\t', header: false, columns: vec!["x1".to_string(), "x3".to_string()] }, options);
+        assert_eq!("replicate_weights", target);
+    }
+
+    #[test]
+    fn test_handle_message_load_csv() {
+        let path = "/tmp/replicest_server_test_handle_message_load_csv.csv";
+        std::fs::write(path, "ASRREA;ITSEX;TOTWGT\n500;1;1\n520;2;3\n").unwrap();
+
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_load_csv".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+        let return_value = handle_message(format!("load csv {} delimiter=; columns=ASRREA", path), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"loaded data"), return_value.unwrap()[0]);
+        let return_value = handle_message(format!("load csv {} delimiter=; columns=TOTWGT into=weights", path), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"loaded weights"), return_value.unwrap()[0]);
+        let return_value = handle_message(format!("load csv {} delimiter=; columns=ITSEX into=groups", path), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"loaded grouping columns"), return_value.unwrap()[0]);
+        assert_eq!("none by 1 grouping columns (1 datasets with 2 cases; 2 weights of sum 4; no replicate weights)", current_analysis.summary());
+
+        let result = current_analysis.mean().calculate().unwrap();
+        assert_eq!(&vec!["mean_ASRREA".to_string()], result_for(&result, &["1"]).unwrap().parameter_names());
+
+        let return_value = handle_message(format!("load csv {} delimiter=; into=weights", path), &mut current_analysis, &data_socket);
+        assert!(return_value.is_err());
+
+        let _ = remove_file(path);
+    }
+
+    #[test]
+    fn test_parse_load_parquet_message() {
+        assert!(parse_load_parquet_message("load parquet").is_none());
+        assert_eq!(Some(("/tmp/pirls.parquet", vec![])), parse_load_parquet_message("load parquet /tmp/pirls.parquet"));
+        assert_eq!(Some(("/tmp/pirls.parquet", vec!["ASRREA", "ITSEX"])), parse_load_parquet_message("load parquet /tmp/pirls.parquet ASRREA ITSEX"));
+    }
+
+    #[test]
+    fn test_handle_message_load_parquet() {
+        let path = "/tmp/replicest_server_test_handle_message_load_parquet.parquet";
+        let batch = arrow_array::RecordBatch::try_from_iter(vec![
+            ("ASRREA", Arc::new(arrow_array::Float64Array::from(vec![500.0, 520.0])) as arrow_array::ArrayRef),
+            ("ITSEX", Arc::new(arrow_array::Int32Array::from(vec![1, 2])) as arrow_array::ArrayRef),
+        ]).unwrap();
+        let mut writer = parquet::arrow::ArrowWriter::try_new(std::fs::File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_load_parquet".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+        let return_value = handle_message(format!("load parquet {} ASRREA", path), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"loaded data"), return_value.unwrap()[0]);
+        assert_eq!("none (1 datasets with 2 cases; wgt missing; no replicate weights)", current_analysis.summary());
+
+        let result = current_analysis.mean().calculate().unwrap();
+        assert_eq!(&vec!["mean_ASRREA".to_string()], result[&GroupKey::overall()].parameter_names());
+
+        let return_value = handle_message(format!("load parquet {} ASBGSES", path), &mut current_analysis, &data_socket);
+        assert!(return_value.is_err());
+
+        let _ = remove_file(path);
+    }
+
+    #[test]
+    fn test_parse_arrow_data_message() {
+        assert!(parse_arrow_data_message("data arrow").is_none());
+        assert!(parse_arrow_data_message("data arrow 0").is_none());
+        assert_eq!(Some(2), parse_arrow_data_message("data arrow 2"));
+    }
+
+    #[test]
+    fn test_handle_message_data_arrow() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_data_arrow".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            let return_value = handle_message("data arrow 2".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"received data"), return_value.unwrap()[0]);
+
+            let return_value = handle_message("weights arrow".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"received weights"), return_value.unwrap()[0]);
+            assert_eq!("none (2 datasets with 2 cases; 2 weights of sum 3; no replicate weights)", current_analysis.summary());
+
+            let result = current_analysis.mean().calculate().unwrap();
+            assert_eq!(&vec!["mean_PV1".to_string(), "mean_SES".to_string()], result[&GroupKey::overall()].parameter_names());
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        for (pv, ses) in [(vec![500.0, 520.0], vec![1.0, 2.0]), (vec![510.0, 530.0], vec![1.0, 2.0])] {
+            let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_data_arrow").unwrap();
+            let _ = client.write_all(&arrow_stream(vec![
+                ("PV1", Arc::new(arrow_array::Float64Array::from(pv)) as arrow_array::ArrayRef),
+                ("SES", Arc::new(arrow_array::Float64Array::from(ses)) as arrow_array::ArrayRef),
+            ]));
+        }
+
+        let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_data_arrow").unwrap();
+        let _ = client.write_all(&arrow_stream(vec![("TOTWGT", Arc::new(arrow_array::Float64Array::from(vec![1.0, 2.0])) as arrow_array::ArrayRef)]));
+        drop(client);
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_message_group_by_with_labels() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_group_by_with_labels".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            current_analysis.for_data(Imputation::No(&dmatrix![1.0; 3.0; 5.0])).mean();
+
+            let return_value = handle_message("group by 1".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"received grouping columns"), return_value.unwrap()[0]);
+
+            let return_value = handle_message("group labels 0 1=female 2=male".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"set group labels"), return_value.unwrap()[0]);
+
+            let return_value = handle_message("group names gender".to_string(), &mut current_analysis, &data_socket);
+            assert_eq!(Vec::from(b"set group names"), return_value.unwrap()[0]);
+
+            let responses = handle_message("calculate".to_string(), &mut current_analysis, &data_socket).unwrap();
+            let result = rmp_serde::from_slice::<BTreeMap<GroupKey, ReplicatedEstimates>>(responses[1].as_slice()).unwrap();
+            let (key, estimates) = result.iter().find(|(key, _)| key.labels() == &vec!["female".to_string()]).unwrap();
+            assert_eq!(&vec!["gender".to_string()], key.variable_names());
+            assert_eq!(&vec![1.0], key.values());
+            assert_eq!(vec![2.0], estimates.final_estimates);
+            assert_eq!(vec![5.0], result_for(&result, &["male"]).unwrap().final_estimates);
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_group_by_with_labels").unwrap();
+        let floats = [1.0, 1.0, 2.0];
+        let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+        let _ = client.write_all(bytes.as_flattened());
+        drop(client);
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_message_replicate_weights_with_error() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_replicate_weights_with_error".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("replicate weights x".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"bad request - usage: replicate weights <number_columns>"), return_value.unwrap()[0]);
+    }
+
+    #[test]
+    fn test_handle_message_replicate_weights() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_replicate_weights".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut current_analysis = analysis();
+            let return_value = handle_message("replicate weights 3".to_string(), &mut current_analysis, &data_socket);
+            assert!(return_value.is_ok());
+            assert_eq!(Vec::from(b"received replicate weights"), return_value.unwrap()[0]);
+            assert_eq!("none (no data; wgt missing; 3 replicate weights)", current_analysis.summary());
+        });
+
+        thread::sleep(Duration::from_millis(200));
+
+        let mut client = UnixStream::connect("/tmp/replicest_server_test_handle_message_replicate_weights").unwrap();
+
+        let floats = vec![1.5, 2.0, 3.2, 14.44, 7.1, 2.3];
+        let bytes = Vec::from_iter(floats.iter().map(|&v| f64::to_le_bytes(v)));
+        let bytes = Vec::from(bytes.as_flattened());
+
+        let _ = client.write_all(&bytes);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_message_set_confidence_level() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_set_confidence_level".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("set confidence level 1.5".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"bad request - usage: set confidence level <level>"), return_value.unwrap()[0]);
+
+        let return_value = handle_message("set confidence level 0.99".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set confidence level"), return_value.unwrap()[0]);
+    }
+
+    #[test]
+    fn test_handle_message_set_variance_adjustment_factor_with_error() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_set_variance_adjustment_factor_with_error".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("set variance adjustment factor".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"bad request - usage: set variance adjustment factor <factor>"), return_value.unwrap()[0]);
+    }
+
+    #[test]
+    fn test_handle_message_set_variance_adjustment_factor() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_set_variance_adjustment_factor".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+        current_analysis.with_replicate_weights(&dmatrix![
+            1.0, 2.0, 3.0;
+            4.0, 5.0, 6.0;
+            7.0, 8.0, 9.0;
+        ]);
+
+        let return_value = handle_message("set variance adjustment factor 0.5000".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"set variance adjustment factor"), return_value.unwrap()[0]);
+        assert_eq!("none (no data; wgt missing; 3 replicate weights, factor 0.5)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_total() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_total".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("total".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"set analysis to total"), return_value.unwrap()[0]);
+        assert_eq!("total (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_save_and_restore() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_save_and_restore".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+        let state_path = "/tmp/replicest_server_test_handle_message_save_and_restore_state";
+
+        let mut current_analysis = analysis();
+        current_analysis.for_data(Imputation::No(&dmatrix![1.0; 3.0])).total();
+
+        let return_value = handle_message("save".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"bad request - usage: save <path>"), return_value.unwrap()[0]);
+
+        let return_value = handle_message(format!("save {}", state_path), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"saved analysis"), return_value.unwrap()[0]);
+
+        let mut restored_analysis = analysis();
+        let return_value = handle_message(format!("restore {}", state_path), &mut restored_analysis, &data_socket);
+        assert_eq!(Vec::from(b"restored analysis"), return_value.unwrap()[0]);
+        assert_eq!("total (1 datasets with 2 cases; wgt missing; no replicate weights)", restored_analysis.summary());
+
+        let _ = remove_file(state_path);
+        let return_value = handle_message(format!("restore {}", state_path), &mut restored_analysis, &data_socket);
+        assert!(return_value.is_err());
+    }
+
+    #[test]
+    fn test_handle_message_calculate_csv() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_calculate_csv".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+        current_analysis.for_data(Imputation::No(&dmatrix![1.0; 3.0])).mean();
+
+        let responses = handle_message("calculate csv".to_string(), &mut current_analysis, &data_socket).unwrap();
+        assert_eq!(2, responses.len());
+        assert_eq!(Vec::from(b"calculation complete"), responses[0]);
+        assert_eq!(Vec::from(b"parameter,estimate,standard_error\nmean_x1,2,0\n"), responses[1]);
+    }
+
+    #[test]
+    fn test_handle_message_calculate_json() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_calculate_json".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+        current_analysis.for_data(Imputation::No(&dmatrix![1.0; 3.0])).mean();
+
+        let responses = handle_message("calculate json".to_string(), &mut current_analysis, &data_socket).unwrap();
+        assert_eq!(2, responses.len());
+        assert_eq!(Vec::from(b"calculation complete"), responses[0]);
+        let json = String::from_utf8(responses[1].clone()).unwrap();
+        assert!(json.starts_with("[{\"key\":[\"overall\"],\"groups\":{},\"compared_to\":null,\"estimates\":{\"parameter_names\":[\"mean_x1\"]"));
+
+        let mut empty_analysis = analysis();
+        let responses = handle_message("calculate json".to_string(), &mut empty_analysis, &data_socket).unwrap();
+        assert_eq!(Vec::from(b"error calculating: Analysis is missing some element: estimate"), responses[0]);
+    }
+
+    #[test]
+    fn test_handle_message_also() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_also".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let _ = handle_message("mean".to_string(), &mut current_analysis, &data_socket);
+        let return_value = handle_message("also".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"keeping previous analysis"), return_value.unwrap()[0]);
+        let _ = handle_message("total".to_string(), &mut current_analysis, &data_socket);
+
+        assert_eq!("mean and total (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_linear_regression() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_linear_regression".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("linear regression".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to linear regression"), return_value.unwrap()[0]);
+        assert_eq!("linear regression (no data; wgt missing; no replicate weights)", current_analysis.summary());
+
+        let return_value = handle_message("linear regression with residual diagnostics x".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"bad request - usage: linear regression with residual diagnostics <number of bins>"), return_value.unwrap()[0]);
+
+        let return_value = handle_message("linear regression with residual diagnostics 5".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to linear regression"), return_value.unwrap()[0]);
+    }
+
+    #[test]
+    fn test_handle_message_poisson_regression() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_poisson_regression".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("poisson regression".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"set analysis to poisson regression"), return_value.unwrap()[0]);
+        assert_eq!("poisson regression (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_effect_size() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_effect_size".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("effect size".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"set analysis to effect size"), return_value.unwrap()[0]);
+        assert_eq!("effect size (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_inequality_measures() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_inequality_measures".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("gini".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to gini"), return_value.unwrap()[0]);
+        assert_eq!("gini (no data; wgt missing; no replicate weights)", current_analysis.summary());
+
+        let return_value = handle_message("theil".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to theil"), return_value.unwrap()[0]);
+        assert_eq!("theil (no data; wgt missing; no replicate weights)", current_analysis.summary());
+
+        let return_value = handle_message("atkinson 1".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to atkinson"), return_value.unwrap()[0]);
+        assert_eq!("atkinson (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_polychoric_and_polyserial_correlation() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_polychoric_and_polyserial_correlation".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("polychoric correlation".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to polychoric correlation"), return_value.unwrap()[0]);
+        assert_eq!("polychoric correlation (no data; wgt missing; no replicate weights)", current_analysis.summary());
+
+        let return_value = handle_message("polyserial correlation".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to polyserial correlation"), return_value.unwrap()[0]);
+        assert_eq!("polyserial correlation (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_frequencies() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_frequencies".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("frequencies".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to frequencies"), return_value.unwrap()[0]);
+        assert_eq!("frequencies (no data; wgt missing; no replicate weights)", current_analysis.summary());
+
+        let return_value = handle_message("frequencies with percentages".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to frequencies"), return_value.unwrap()[0]);
+    }
+
+    #[test]
+    fn test_handle_message_agreement() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_agreement".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("agreement".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to agreement"), return_value.unwrap()[0]);
+        assert_eq!("agreement (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_crosstab() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_crosstab".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("crosstab".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to crosstab"), return_value.unwrap()[0]);
+        assert_eq!("crosstab (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_t_tests() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_t_tests".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("t test x".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"bad request - usage: t test <mu>"), return_value.unwrap()[0]);
+
+        let return_value = handle_message("t test 0.5".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to t test"), return_value.unwrap()[0]);
+        assert_eq!("t test (no data; wgt missing; no replicate weights)", current_analysis.summary());
+
+        let return_value = handle_message("two sample t test".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to two sample t test"), return_value.unwrap()[0]);
+        assert_eq!("two sample t test (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_eta_squared() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_eta_squared".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("eta squared".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to eta squared"), return_value.unwrap()[0]);
+        assert_eq!("eta squared (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_mantel_haenszel_dif() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_mantel_haenszel_dif".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("mantel haenszel dif".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to mantel haenszel dif"), return_value.unwrap()[0]);
+        assert_eq!("mantel haenszel dif (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_odds_ratio() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_odds_ratio".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("odds ratio".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to odds ratio"), return_value.unwrap()[0]);
+        assert_eq!("odds ratio (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_concentration() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_concentration".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("concentration 10".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to concentration"), return_value.unwrap()[0]);
+        assert_eq!("concentration (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_percentile_shares() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_percentile_shares".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("percentile shares 0-20 90-100".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to percentile shares"), return_value.unwrap()[0]);
+        assert_eq!("percentile shares (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_benchmark_levels() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_benchmark_levels".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("benchmark levels".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to benchmark levels"), return_value.unwrap()[0]);
+        assert_eq!("benchmark levels (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_range() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_range".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("range".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to range"), return_value.unwrap()[0]);
+        assert_eq!("range (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_missingness() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_missingness".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("missingness".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to missingness"), return_value.unwrap()[0]);
+        assert_eq!("missingness (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_standardized_mean() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_standardized_mean".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("standardized mean".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to standardized mean"), return_value.unwrap()[0]);
+        assert_eq!("standardized mean (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_principal_components() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_principal_components".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("principal components".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set analysis to principal components"), return_value.unwrap()[0]);
+        assert_eq!("principal components (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_proportions_above() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_proportions_above".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+
+        let return_value = handle_message("proportions above x".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"bad request - usage: proportions above <cutpoint> [<cutpoint> ...]"), return_value.unwrap()[0]);
+
+        let return_value = handle_message("proportions above 400 475".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"set analysis to proportions above"), return_value.unwrap()[0]);
+        assert_eq!("proportions above (no data; wgt missing; no replicate weights)", current_analysis.summary());
+    }
+
+    #[test]
+    fn test_handle_message_capabilities() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_capabilities".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+        let return_value = handle_message("hello".to_string(), &mut current_analysis, &data_socket).unwrap();
+        assert_eq!(Vec::from(b"capabilities"), return_value[0]);
+
+        let capabilities : serde_json::Value = serde_json::from_slice(&return_value[1]).unwrap();
+        assert_eq!(env!("CARGO_PKG_VERSION"), capabilities["version"]);
+        assert_eq!(2, capabilities["protocol_version"]);
+        assert_eq!("little", capabilities["byte_order"]);
+        assert_eq!(true, capabilities["framed_data"]);
+        assert_eq!(true, capabilities["named_columns"]);
+        assert_eq!(serde_json::json!(["zstd"]), capabilities["compressions"]);
+
+        // every advertised statistic is known to the server, even if it is missing arguments here
+        for statistic in capabilities["statistics"].as_array().unwrap() {
+            let return_value = handle_message(statistic.as_str().unwrap().to_string(), &mut current_analysis, &data_socket).unwrap();
+            assert_ne!(Vec::from(b"unknown"), return_value[0]);
+        }
+
+        let return_value = handle_message("capabilities".to_string(), &mut current_analysis, &data_socket).unwrap();
+        assert_eq!(Vec::from(b"capabilities"), return_value[0]);
+    }
+
+    #[test]
+    fn test_handle_message_summary() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_summary".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let data = dmatrix![1.0; 2.0];
+        let mut current_analysis = analysis();
+        current_analysis.for_data(Imputation::No(&data)).mean();
+
+        let return_value = handle_message("summary".to_string(), &mut current_analysis, &data_socket).unwrap();
+        assert_eq!(Vec::from(b"mean (1 datasets with 2 cases; wgt missing; no replicate weights)"), return_value[0]);
+
+        let return_value = handle_message("summary json".to_string(), &mut current_analysis, &data_socket).unwrap();
+        assert_eq!(Vec::from(b"summary"), return_value[0]);
+        let summary : AnalysisSummary = serde_json::from_slice(&return_value[1]).unwrap();
+        assert_eq!(current_analysis.summary_struct(), summary);
+    }
+
+    #[test]
+    fn test_handle_message_calculate_with_error() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_calculate_with_error".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut current_analysis = analysis();
+        current_analysis.mean();
+
+        let return_value = handle_message("calculate".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+        assert_eq!(Vec::from(b"error calculating: Analysis is missing some element: data"), return_value.unwrap()[0]);
+    }
+
+    #[test]
+    fn test_handle_message_calculate() {
+        let data_socket_addr = "/tmp/replicest_server_test_handle_message_calculate".to_string();
+        let _ = remove_file(&data_socket_addr);
+        let data_socket = UnixListener::bind(&data_socket_addr).unwrap();
+
+        let mut imp_data: Vec<&DMatrix<f64>> = Vec::new();
+        let data0 = DMatrix::from_row_slice(3, 4, &[
+            1.0, 4.0, 2.5, -1.0,
+            2.5, 1.75, 4.0, -2.5,
+            3.0, 3.0, 1.0, -3.5,
+        ]);
+        imp_data.push(&data0);
+        let data1 = DMatrix::from_row_slice(3, 4, &[
+            1.2, 4.0, 2.5, -1.0,
+            2.5, 1.75, 3.9, -2.5,
+            2.7, 3.0, 1.0, -3.5,
+        ]);
+        imp_data.push(&data1);
+        let data2 = DMatrix::from_row_slice(3, 4, &[
+            0.8, 4.0, 2.5, -1.0,
+            2.5, 1.75, 4.1, -2.5,
+            3.3, 3.0, 1.0, -3.5,
+        ]);
+        imp_data.push(&data2);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        let mut current_analysis = analysis();
+        current_analysis.for_data(Imputation::Yes(&imp_data)).set_weights(&wgt).mean();
+
+        let return_value = handle_message("variable names a ASRREA c d".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set variable names"), return_value.unwrap()[0]);
+
+        let return_value = handle_message("metadata cycle 2021".to_string(), &mut current_analysis, &data_socket);
+        assert_eq!(Vec::from(b"set metadata"), return_value.unwrap()[0]);
+
+        let return_value = handle_message("calculate".to_string(), &mut current_analysis, &data_socket);
+
+        assert!(return_value.is_ok());
+
+        let responses = return_value.unwrap();
+        assert_eq!(2, responses.len());
+        assert_eq!(Vec::from(b"calculation complete"), responses[0]);
+
+        let result_data = &responses[1];
+        let result = rmp_serde::from_slice::<BTreeMap<GroupKey, ReplicatedEstimates>>(result_data.as_slice());
+        assert!(result.is_ok());
+
+        let replicated_estimates = result.unwrap();
+        assert_eq!(1, replicated_estimates.len());
+        assert!(replicated_estimates.keys().next().unwrap().is_overall());
+
+        let overall_estimates = replicated_estimates.get(&GroupKey::overall()).unwrap();
+        assert_eq!(4, overall_estimates.parameter_names.len());
+        assert_eq!("mean_ASRREA", overall_estimates.parameter_names[1]);
+        assert_eq!(Some(&"2021".to_string()), overall_estimates.metadata.get("cycle"));
+
+        let expected_final_estimates = vec![2.25, 3.125, 2.0, -2.5];
+        let expected_imputation_variances = vec![0.0069444444444443955, 0.0, 0.0002777777777777758, 0.0];
+
+        for (i, value) in expected_final_estimates.iter().enumerate() {
+            assert!(overall_estimates.final_estimates[i] - value < 1e-10);
+        }
+        for (i, value) in expected_imputation_variances.iter().enumerate() {
+            assert!(overall_estimates.imputation_variances[i] - value < 1e-10);
+        }
+    }
+}
\ No newline at end of file
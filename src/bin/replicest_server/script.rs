@@ -0,0 +1,80 @@
+use std::error::Error;
+use std::fs::{read_to_string, File};
+use std::io::{BufWriter, Write};
+use tracing::info;
+use crate::DataSource;
+use crate::jobs::Jobs;
+use crate::session::Sessions;
+
+// there is no client to send data, so a script loads it from files
+struct NoDataSource;
+
+impl DataSource for NoDataSource {
+    fn receive_data(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Err("data has to be loaded from files in a script, e.g. with load csv or load parquet".into())
+    }
+}
+
+// runs the commands of the script line by line and writes the payloads of their responses to the output,
+// stopping at the first command that fails
+pub fn run_script(script_path: &str, output_path: &str, jobs: &Jobs) -> Result<(), Box<dyn Error>> {
+    let script = read_to_string(script_path)?;
+    let mut output = BufWriter::new(File::create(output_path)?);
+    let mut sessions = Sessions::new(None, jobs);
+
+    for (i, line) in script.lines().enumerate() {
+        let command = line.trim();
+        if command.is_empty() || command.starts_with('#') {
+            continue;
+        }
+
+        info!(front_end = "script", line = i + 1, command, "received command");
+
+        let mut responses = sessions.handle(command.to_string(), &NoDataSource)
+            .map_err(|err| format!("line {}: error: {}", i + 1, err))?
+            .into_iter();
+
+        let status = responses.next().unwrap_or_default();
+        if status.starts_with(b"error") || status.starts_with(b"bad request") {
+            return Err(format!("line {}: {}", i + 1, String::from_utf8_lossy(&status)).into());
+        }
+
+        for payload in responses {
+            output.write_all(&payload)?;
+        }
+    }
+
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read, remove_file, write};
+    use super::*;
+
+    #[test]
+    fn test_run_script() {
+        let data_path = "/tmp/replicest_server_test_run_script.csv";
+        write(data_path, "ASRREA;TOTWGT\n500;1\n520;3\n").unwrap();
+
+        let script_path = "/tmp/replicest_server_test_run_script.txt";
+        write(script_path, format!("# weighted mean\nload csv {0} delimiter=; columns=ASRREA\nload csv {0} delimiter=; columns=TOTWGT into=weights\n\nmean\ncalculate csv\n", data_path)).unwrap();
+
+        let output_path = "/tmp/replicest_server_test_run_script.out";
+        run_script(script_path, output_path, &Jobs::new()).unwrap();
+        let output = String::from_utf8(read(output_path).unwrap()).unwrap();
+        assert!(output.starts_with("parameter,estimate,standard_error\n"));
+        assert!(output.contains("mean_ASRREA,515,"));
+
+        write(script_path, "mean\ndata 1 1\ncalculate\n").unwrap();
+        assert_eq!("line 2: error: data has to be loaded from files in a script, e.g. with load csv or load parquet", run_script(script_path, output_path, &Jobs::new()).unwrap_err().to_string());
+
+        write(script_path, "data\n").unwrap();
+        assert!(run_script(script_path, output_path, &Jobs::new()).unwrap_err().to_string().starts_with("line 1: bad request"));
+
+        let _ = remove_file(data_path);
+        let _ = remove_file(script_path);
+        let _ = remove_file(output_path);
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use replicest::analysis::{analysis, Analysis};
+use tracing::info;
+use crate::{handle_message, DataSource};
+use crate::jobs::Jobs;
+
+pub const DEFAULT_SESSION: &str = "default";
+
+pub type SharedAnalysis = Arc<Mutex<Analysis>>;
+
+// the format results of a plain calculate are sent in
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum OutputFormat {
+    #[default]
+    Msgpack,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    fn calculate_command(&self) -> &'static str {
+        match self {
+            OutputFormat::Msgpack => "calculate",
+            OutputFormat::Json => "calculate json",
+            OutputFormat::Csv => "calculate csv",
+        }
+    }
+}
+
+struct Session {
+    analysis: SharedAnalysis,
+    last_access: Instant,
+    output_format: OutputFormat,
+}
+
+impl Session {
+    fn new() -> Self {
+        Session { analysis: Arc::new(Mutex::new(analysis())), last_access: Instant::now(), output_format: OutputFormat::default() }
+    }
+
+    fn set_output_format(&mut self, command: &str) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        let (name, output_format) = match command.strip_prefix("set output format ") {
+            Some(name @ "msgpack") => (name, OutputFormat::Msgpack),
+            Some(name @ "json") => (name, OutputFormat::Json),
+            Some(name @ "csv") => (name, OutputFormat::Csv),
+            _ => return Ok(vec!(b"bad request - usage: set output format <msgpack|json|csv>".into())),
+        };
+        self.output_format = output_format;
+        Ok(vec!(format!("set output format to {}", name).into_bytes()))
+    }
+}
+
+pub enum Route<'a> {
+    // messages concerning the sessions themselves are answered right away
+    Response(Result<Vec<Vec<u8>>, Box<dyn Error>>),
+    Session(String, SharedAnalysis, &'a str),
+}
+
+pub struct Sessions {
+    sessions: BTreeMap<String, Session>,
+    idle_timeout: Option<Duration>,
+    // names of sessions freed for being idle, so that their clients can be told on next contact
+    expired: BTreeSet<String>,
+    jobs: Jobs,
+}
+
+impl Sessions {
+    pub fn new(idle_timeout: Option<Duration>, jobs: &Jobs) -> Self {
+        let mut sessions = BTreeMap::new();
+        sessions.insert(DEFAULT_SESSION.to_string(), Session::new());
+        Sessions { sessions, idle_timeout, expired: BTreeSet::new(), jobs: jobs.clone() }
+    }
+
+    pub fn handle(&mut self, message: String, data_socket: &impl DataSource) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        match self.route(&message) {
+            Route::Response(response) => response,
+            Route::Session(_, analysis, command) => execute(command, &mut analysis.lock().unwrap(), data_socket, &self.jobs),
+        }
+    }
+
+    pub fn route<'a>(&mut self, message: &'a str) -> Route<'a> {
+        let (name, command) = match split_session_prefix(message) {
+            None => {
+                return Route::Response(Ok(vec!(b"bad request - usage: @<session> <command>".into())));
+            }
+            Some(prefix) => prefix
+        };
+
+        if command.starts_with("session") {
+            return Route::Response(self.handle_session_command(command));
+        }
+
+        if self.expired.remove(name) {
+            return Route::Response(Err(format!("session {} was freed after being idle for more than {} seconds", name, self.idle_timeout.unwrap_or_default().as_secs()).into()));
+        }
+
+        match self.sessions.get_mut(name) {
+            None => {
+                Route::Response(Err(format!("unknown session {}", name).into()))
+            }
+            Some(session) => {
+                session.last_access = Instant::now();
+                if command.starts_with("set output format") {
+                    return Route::Response(session.set_output_format(command));
+                }
+                let command = match command {
+                    "calculate" => session.output_format.calculate_command(),
+                    _ => command,
+                };
+                Route::Session(name.to_string(), Arc::clone(&session.analysis), command)
+            }
+        }
+    }
+
+    pub fn expire_idle(&mut self, now: Instant) -> Vec<String> {
+        let idle_timeout = match self.idle_timeout {
+            None => return Vec::new(),
+            Some(idle_timeout) => idle_timeout,
+        };
+
+        let idle_names : Vec<String> = self.sessions.iter()
+            .filter(|(_, session)| now.saturating_duration_since(session.last_access) > idle_timeout)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in idle_names.iter() {
+            // the default session always exists and is only emptied
+            if name == DEFAULT_SESSION {
+                self.sessions.insert(name.clone(), Session::new());
+            } else {
+                self.sessions.remove(name);
+            }
+            self.expired.insert(name.clone());
+        }
+
+        idle_names
+    }
+
+    fn handle_session_command(&mut self, command: &str) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        let command_components : Vec<&str> = command.split(" ").collect();
+
+        match command_components.as_slice() {
+            ["session", "create", name] if is_valid_session_name(name) => {
+                if self.sessions.contains_key(*name) {
+                    return Err(format!("session {} already exists", name).into());
+                }
+                self.expired.remove(*name);
+                self.sessions.insert(name.to_string(), Session::new());
+                Ok(vec!(format!("created session {}", name).into_bytes()))
+            }
+            ["session", "close", name] if *name != DEFAULT_SESSION => {
+                match self.sessions.remove(*name) {
+                    None => Err(format!("unknown session {}", name).into()),
+                    Some(_) => Ok(vec!(format!("closed session {}", name).into_bytes())),
+                }
+            }
+            ["session", "list"] => {
+                let names : Vec<&str> = self.sessions.keys().map(|name| name.as_str()).collect();
+                Ok(vec!(format!("sessions: {}", names.join(" ")).into_bytes()))
+            }
+            _ => {
+                Ok(vec!(b"bad request - usage: session create <name> | session close <name> | session list".into()))
+            }
+        }
+    }
+}
+
+pub fn execute(command: &str, analysis_of_session: &mut Analysis, data_socket: &impl DataSource, jobs: &Jobs) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    match command {
+        "clear" => {
+            *analysis_of_session = analysis();
+            Ok(vec!(b"cleared".into()))
+        }
+        str if str.starts_with("calculate") => {
+            // the monitor is set right before calculating, as restoring replaces the analysis
+            let _job = jobs.start()?;
+            analysis_of_session.set_monitor(&jobs.monitor());
+            let started = Instant::now();
+            let response = handle_message(command.to_string(), analysis_of_session, data_socket);
+            info!(command, milliseconds = started.elapsed().as_millis() as u64, "calculation finished");
+            response
+        }
+        _ => {
+            handle_message(command.to_string(), analysis_of_session, data_socket)
+        }
+    }
+}
+
+fn is_valid_session_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('@')
+}
+
+// commands addressed to a session other than the default one are prefixed by @<session>
+fn split_session_prefix(message: &str) -> Option<(&str, &str)> {
+    match message.strip_prefix('@') {
+        None => Some((DEFAULT_SESSION, message)),
+        Some(prefixed) => {
+            match prefixed.split_once(' ') {
+                Some((name, command)) if is_valid_session_name(name) && !command.is_empty() => Some((name, command)),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use replicest::analysis::GroupKey;
+    use replicest::ReplicatedEstimates;
+    use super::*;
+
+    struct TestDataSource;
+
+    impl DataSource for TestDataSource {
+        fn receive_data(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+            Ok([1.0_f64, 2.0, 3.0].iter().flat_map(|v| v.to_le_bytes()).collect())
+        }
+    }
+
+    #[test]
+    fn test_split_session_prefix() {
+        assert_eq!(Some((DEFAULT_SESSION, "mean")), split_session_prefix("mean"));
+        assert_eq!(Some(("pirls", "data 1 1")), split_session_prefix("@pirls data 1 1"));
+        assert!(split_session_prefix("@pirls").is_none());
+        assert!(split_session_prefix("@ mean").is_none());
+    }
+
+    #[test]
+    fn test_sessions() {
+        let mut sessions = Sessions::new(None, &Jobs::new());
+
+        let response = sessions.handle("session create pirls".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"created session pirls"), response[0]);
+        assert!(sessions.handle("session create pirls".to_string(), &TestDataSource).is_err());
+        let response = sessions.handle("session create".to_string(), &TestDataSource).unwrap();
+        assert!(response[0].starts_with(b"bad request"));
+
+        sessions.handle("@pirls data 1 1".to_string(), &TestDataSource).unwrap();
+        sessions.handle("@pirls mean".to_string(), &TestDataSource).unwrap();
+        sessions.handle("total".to_string(), &TestDataSource).unwrap();
+        assert_eq!("mean (1 datasets with 3 cases; wgt missing; no replicate weights)", sessions.sessions["pirls"].analysis.lock().unwrap().summary());
+        assert_eq!("total (no data; wgt missing; no replicate weights)", sessions.sessions[DEFAULT_SESSION].analysis.lock().unwrap().summary());
+
+        let response = sessions.handle("session list".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"sessions: default pirls"), response[0]);
+
+        let response = sessions.handle("@pirls clear".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"cleared"), response[0]);
+        assert_eq!("none (no data; wgt missing; no replicate weights)", sessions.sessions["pirls"].analysis.lock().unwrap().summary());
+
+        let response = sessions.handle("session close pirls".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"closed session pirls"), response[0]);
+        assert_eq!("unknown session pirls", sessions.handle("@pirls mean".to_string(), &TestDataSource).err().unwrap().to_string());
+        assert!(sessions.handle("session close pirls".to_string(), &TestDataSource).is_err());
+
+        let response = sessions.handle("session close default".to_string(), &TestDataSource).unwrap();
+        assert!(response[0].starts_with(b"bad request"));
+    }
+
+    #[test]
+    fn test_output_format() {
+        let mut sessions = Sessions::new(None, &Jobs::new());
+        sessions.handle("session create pirls".to_string(), &TestDataSource).unwrap();
+        sessions.handle("data 1 1".to_string(), &TestDataSource).unwrap();
+        sessions.handle("mean".to_string(), &TestDataSource).unwrap();
+
+        let response = sessions.handle("set output format csv".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"set output format to csv"), response[0]);
+        let response = sessions.handle("calculate".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"parameter,estimate,standard_error\nmean_x1,2,0\n"), response[1]);
+
+        let response = sessions.handle("set output format json".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"set output format to json"), response[0]);
+        let response = sessions.handle("calculate".to_string(), &TestDataSource).unwrap();
+        assert!(serde_json::from_slice::<serde_json::Value>(&response[1]).is_ok());
+
+        let response = sessions.handle("set output format xml".to_string(), &TestDataSource).unwrap();
+        assert!(response[0].starts_with(b"bad request"));
+        assert_eq!(OutputFormat::Json, sessions.sessions[DEFAULT_SESSION].output_format);
+        assert_eq!(OutputFormat::Msgpack, sessions.sessions["pirls"].output_format);
+
+        sessions.handle("set output format msgpack".to_string(), &TestDataSource).unwrap();
+        let response = sessions.handle("calculate".to_string(), &TestDataSource).unwrap();
+        let result = rmp_serde::from_slice::<BTreeMap<GroupKey, ReplicatedEstimates>>(&response[1]).unwrap();
+        assert_eq!(vec![2.0], result[&GroupKey::overall()].final_estimates);
+    }
+
+    #[test]
+    fn test_expire_idle() {
+        let mut sessions = Sessions::new(Some(Duration::from_secs(60)), &Jobs::new());
+        sessions.handle("session create pirls".to_string(), &TestDataSource).unwrap();
+        sessions.handle("session create timss".to_string(), &TestDataSource).unwrap();
+        sessions.handle("data 1 1".to_string(), &TestDataSource).unwrap();
+        sessions.handle("@pirls data 1 1".to_string(), &TestDataSource).unwrap();
+
+        assert!(sessions.expire_idle(Instant::now()).is_empty());
+
+        sessions.sessions.get_mut("timss").unwrap().last_access = Instant::now() + Duration::from_secs(30);
+        let idle_names = sessions.expire_idle(Instant::now() + Duration::from_secs(61));
+        assert_eq!(vec!["default".to_string(), "pirls".to_string()], idle_names);
+
+        let response = sessions.handle("session list".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"sessions: default timss"), response[0]);
+        assert_eq!("none (no data; wgt missing; no replicate weights)", sessions.sessions[DEFAULT_SESSION].analysis.lock().unwrap().summary());
+
+        assert_eq!("session pirls was freed after being idle for more than 60 seconds", sessions.handle("@pirls mean".to_string(), &TestDataSource).err().unwrap().to_string());
+        assert_eq!("unknown session pirls", sessions.handle("@pirls mean".to_string(), &TestDataSource).err().unwrap().to_string());
+
+        assert!(sessions.handle("mean".to_string(), &TestDataSource).is_err());
+        let response = sessions.handle("mean".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"set analysis to mean"), response[0]);
+
+        let response = sessions.handle("session create pirls".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"created session pirls"), response[0]);
+        let response = sessions.handle("@pirls mean".to_string(), &TestDataSource).unwrap();
+        assert_eq!(Vec::from(b"set analysis to mean"), response[0]);
+
+        assert!(Sessions::new(None, &Jobs::new()).expire_idle(Instant::now() + Duration::from_secs(3600)).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+use nalgebra::{DMatrix, DVector};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum MissingStrategy {
+    #[default]
+    Pairwise,
+    Listwise,
+    FailFast,
+}
+
+pub fn complete_cases(x: &DMatrix<f64>) -> Vec<usize> {
+    (0..x.nrows()).filter(|&r| x.row(r).iter().all(|value| !value.is_nan())).collect()
+}
+
+pub fn listwise_delete(x: &DMatrix<f64>, wgt: &DVector<f64>) -> (DMatrix<f64>, DVector<f64>) {
+    assert_eq!(x.nrows(), wgt.len(), "dimension mismatch of x and wgt in listwise_delete");
+
+    let rows = complete_cases(x);
+    (x.select_rows(&rows), wgt.select_rows(&rows))
+}
+
+pub fn weighted_quantile_groups(values: &DVector<f64>, wgt: &DVector<f64>, number_groups: usize) -> DVector<f64> {
+    assert_eq!(values.len(), wgt.len(), "dimension mismatch of values and wgt in weighted_quantile_groups");
+    assert!(number_groups > 0, "no groups in weighted_quantile_groups");
+
+    let mut order : Vec<usize> = (0..values.len()).filter(|&r| !values[r].is_nan()).collect();
+    order.sort_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap());
+    let total_weight : f64 = order.iter().map(|&r| wgt[r]).sum();
+
+    // the k-th cutpoint is the smallest value at which the cumulative weight reaches k / number_groups of the total
+    let mut cutpoints = Vec::<f64>::new();
+    let mut cumulative_weight = 0.0;
+    for &r in order.iter() {
+        cumulative_weight += wgt[r];
+        while cutpoints.len() < number_groups - 1 && cumulative_weight >= total_weight * (cutpoints.len() + 1) as f64 / number_groups as f64 {
+            cutpoints.push(values[r]);
+        }
+    }
+
+    // tied values always end up in the same group
+    values.map(|value| {
+        if value.is_nan() { f64::NAN } else { (1 + cutpoints.iter().filter(|&&cutpoint| value > cutpoint).count()) as f64 }
+    })
+}
+
+pub fn recode(x: &DMatrix<f64>, column: usize, recodes: &[(f64, f64)]) -> DMatrix<f64> {
+    assert!(column < x.ncols(), "column out of range in recode");
+
+    // every value is recoded at most once, so that chained mappings like 1 -> 2 and 2 -> 3 do not cascade
+    let mut recoded = x.clone();
+    for value in recoded.column_mut(column).iter_mut() {
+        if let Some((_, new_value)) = recodes.iter().find(|(old_value, _)| *old_value == *value || (old_value.is_nan() && value.is_nan())) {
+            *value = *new_value;
+        }
+    }
+    recoded
+}
+
+pub fn append_rows(x: &DMatrix<f64>, rows: &DMatrix<f64>) -> DMatrix<f64> {
+    assert_eq!(x.ncols(), rows.ncols(), "unequal number of columns in append_rows");
+
+    let nrows = x.nrows();
+    DMatrix::from_fn(nrows + rows.nrows(), x.ncols(), |r, c| if r < nrows { x[(r, c)] } else { rows[(r - nrows, c)] })
+}
+
+// imputations are widened one at a time, so that at most one of them exists in both precisions
+pub fn widen(data: Vec<DMatrix<f32>>) -> Vec<DMatrix<f64>> {
+    data.into_iter().map(|mat| mat.cast::<f64>()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{dmatrix, dvector};
+    use super::*;
+
+    #[test]
+    fn test_complete_cases() {
+        let data = dmatrix![1.0, 2.0; f64::NAN, 3.0; 4.0, 5.0; 6.0, f64::NAN];
+        assert_eq!(vec![0, 2], complete_cases(&data));
+
+        let data = dmatrix![1.0; 2.0];
+        assert_eq!(vec![0, 1], complete_cases(&data));
+    }
+
+    #[test]
+    fn test_listwise_delete() {
+        let data = dmatrix![1.0, 2.0; f64::NAN, 3.0; 4.0, 5.0; 6.0, f64::NAN];
+        let wgt = dvector![1.0, 2.0, 3.0, 4.0];
+
+        let (x, wgt) = listwise_delete(&data, &wgt);
+        assert_eq!(dmatrix![1.0, 2.0; 4.0, 5.0], x);
+        assert_eq!(dvector![1.0, 3.0], wgt);
+    }
+
+    #[test]
+    #[should_panic(expected = "dimension mismatch of x and wgt in listwise_delete")]
+    fn test_listwise_delete_dimension_mismatch() {
+        let data = dmatrix![1.0; 2.0];
+        let wgt = dvector![1.0];
+        listwise_delete(&data, &wgt);
+    }
+
+    #[test]
+    fn test_weighted_quantile_groups() {
+        let values = dvector![5.0, 1.0, 3.0, 2.0, 4.0, 6.0, f64::NAN, 7.0, 8.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+
+        let groups = weighted_quantile_groups(&values, &wgt, 4);
+        assert_eq!(vec![3.0, 1.0, 2.0, 1.0, 2.0, 3.0, 4.0, 4.0], groups.iter().filter(|g| !g.is_nan()).cloned().collect::<Vec<f64>>());
+        assert!(groups[6].is_nan());
+
+        let wgt = dvector![1.0, 4.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let groups = weighted_quantile_groups(&values, &wgt, 2);
+        assert_eq!(vec![2.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0], groups.iter().filter(|g| !g.is_nan()).cloned().collect::<Vec<f64>>());
+
+        let groups = weighted_quantile_groups(&dvector![1.0, 1.0, 1.0, 2.0], &dvector![1.0, 1.0, 1.0, 1.0], 2);
+        assert_eq!(dvector![1.0, 1.0, 1.0, 2.0], groups);
+    }
+
+    #[test]
+    fn test_recode() {
+        let groups = dmatrix![1.0, 1.0; 2.0, 1.0; 3.0, 2.0; f64::NAN, 2.0];
+
+        let recoded = recode(&groups, 0, &[(1.0, 2.0), (2.0, 3.0), (f64::NAN, 9.0)]);
+        assert_eq!(dmatrix![2.0, 1.0; 3.0, 1.0; 3.0, 2.0; 9.0, 2.0], recoded);
+
+        let recoded = recode(&groups, 1, &[(2.0, 1.0)]);
+        assert_eq!(dvector![1.0, 1.0, 1.0, 1.0], recoded.column(1));
+        assert!(recoded[(3, 0)].is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "column out of range in recode")]
+    fn test_recode_panic() {
+        recode(&dmatrix![1.0; 2.0], 1, &[(1.0, 2.0)]);
+    }
+
+    #[test]
+    fn test_append_rows() {
+        let x = dmatrix![1.0, 2.0; 3.0, 4.0];
+        assert_eq!(dmatrix![1.0, 2.0; 3.0, 4.0; 5.0, 6.0], append_rows(&x, &dmatrix![5.0, 6.0]));
+        assert_eq!(x, append_rows(&DMatrix::from_row_slice(0, 2, &[]), &x));
+    }
+
+    #[test]
+    #[should_panic(expected = "unequal number of columns in append_rows")]
+    fn test_append_rows_panic() {
+        append_rows(&dmatrix![1.0, 2.0], &dmatrix![1.0]);
+    }
+
+    #[test]
+    fn test_widen() {
+        let widened = widen(vec![dmatrix![1.5f32, 2.0; 0.1, f32::NAN], dmatrix![3.0f32, 4.0; 5.0, 6.0]]);
+        assert_eq!(2, widened.len());
+        assert_eq!(dmatrix![3.0, 4.0; 5.0, 6.0], widened[1]);
+        assert_eq!(1.5, widened[0][(0, 0)]);
+        assert_eq!(0.1f32 as f64, widened[0][(1, 0)]);
+        assert!(widened[0][(1, 1)].is_nan());
+    }
+}
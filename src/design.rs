@@ -0,0 +1,566 @@
+use nalgebra::{DMatrix, DVector};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum To {
+    SampleSize,
+    Sum(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JackknifeScheme {
+    JK1,
+    JK2,
+}
+
+pub fn hadamard_matrix(order: usize) -> DMatrix<f64> {
+    assert!(order > 0 && order.is_power_of_two(), "order not a power of two in hadamard_matrix");
+
+    // Sylvester construction, doubling [H H; H -H] until the order is reached
+    let mut hadamard = DMatrix::<f64>::from_element(1, 1, 1.0);
+    while hadamard.nrows() < order {
+        let size = hadamard.nrows();
+        let mut doubled = DMatrix::<f64>::zeros(2 * size, 2 * size);
+        doubled.view_mut((0, 0), (size, size)).copy_from(&hadamard);
+        doubled.view_mut((0, size), (size, size)).copy_from(&hadamard);
+        doubled.view_mut((size, 0), (size, size)).copy_from(&hadamard);
+        doubled.view_mut((size, size), (size, size)).copy_from(&(-&hadamard));
+        hadamard = doubled;
+    }
+
+    hadamard
+}
+
+pub fn brr_replicate_weights(wgt: &DVector<f64>, strata: &DVector<f64>, psus: &DVector<f64>) -> DMatrix<f64> {
+    assert_eq!(wgt.len(), strata.len(), "dimension mismatch of wgt and strata in brr_replicate_weights");
+    assert_eq!(wgt.len(), psus.len(), "dimension mismatch of wgt and psus in brr_replicate_weights");
+
+    let (stratum_values, psu_values) = psus_by_stratum(strata, psus);
+    assert!(psu_values.iter().all(|values| values.len() == 2), "stratum without exactly two psus in brr_replicate_weights");
+
+    // the first column of the hadamard matrix is constant and therefore not used
+    let number_replicates = (stratum_values.len() + 1).next_power_of_two();
+    let hadamard = hadamard_matrix(number_replicates);
+
+    let mut replicate_weights = DMatrix::<f64>::zeros(wgt.len(), number_replicates);
+    for r in 0..wgt.len() {
+        let stratum_index = stratum_values.iter().position(|&s| s == strata[r]).unwrap();
+        let first_psu = psus[r] == psu_values[stratum_index][0];
+
+        for replicate in 0..number_replicates {
+            let keep_first_psu = hadamard[(replicate, stratum_index + 1)] > 0.0;
+            if keep_first_psu == first_psu {
+                replicate_weights[(r, replicate)] = 2.0 * wgt[r];
+            }
+        }
+    }
+
+    replicate_weights
+}
+
+pub fn brr_variance_adjustment_factor(replicate_weights: &DMatrix<f64>) -> f64 {
+    1.0 / replicate_weights.ncols() as f64
+}
+
+pub fn jackknife_replicate_weights(wgt: &DVector<f64>, strata: &DVector<f64>, psus: &DVector<f64>, scheme: JackknifeScheme) -> DMatrix<f64> {
+    assert_eq!(wgt.len(), strata.len(), "dimension mismatch of wgt and strata in jackknife_replicate_weights");
+    assert_eq!(wgt.len(), psus.len(), "dimension mismatch of wgt and psus in jackknife_replicate_weights");
+
+    let (stratum_values, psu_values) = psus_by_stratum(strata, psus);
+
+    match scheme {
+        JackknifeScheme::JK1 => {
+            // every psu is dropped once and all remaining weights are scaled up by n / (n - 1), so that the sample is treated
+            // as a single stratum, the strata only tell apart psus numbered within strata
+            let units : Vec<(f64, f64)> = stratum_values.iter().zip(psu_values.iter())
+                .flat_map(|(&stratum, values)| values.iter().map(move |&psu| (stratum, psu)))
+                .collect();
+            assert!(units.len() > 1, "less than two psus in jackknife_replicate_weights");
+
+            let scale = units.len() as f64 / (units.len() - 1) as f64;
+            let mut replicate_weights = DMatrix::<f64>::zeros(wgt.len(), units.len());
+            for r in 0..wgt.len() {
+                let unit_index = units.iter().position(|&(s, p)| s == strata[r] && p == psus[r]).unwrap();
+                for replicate in (0..units.len()).filter(|&replicate| replicate != unit_index) {
+                    replicate_weights[(r, replicate)] = scale * wgt[r];
+                }
+            }
+
+            replicate_weights
+        }
+        JackknifeScheme::JK2 => {
+            assert!(psu_values.iter().all(|values| values.len() == 2), "stratum without exactly two psus in jackknife_replicate_weights");
+
+            // each stratum yields two replicates, doubling one psu and dropping the other in turn
+            let mut replicate_weights = DMatrix::<f64>::zeros(wgt.len(), 2 * stratum_values.len());
+            for r in 0..wgt.len() {
+                let stratum_index = stratum_values.iter().position(|&s| s == strata[r]).unwrap();
+                let first_psu = psus[r] == psu_values[stratum_index][0];
+
+                for replicate in 0..replicate_weights.ncols() {
+                    replicate_weights[(r, replicate)] = if replicate / 2 != stratum_index {
+                        wgt[r]
+                    } else if (replicate % 2 == 0) == first_psu {
+                        2.0 * wgt[r]
+                    } else {
+                        0.0
+                    };
+                }
+            }
+
+            replicate_weights
+        }
+    }
+}
+
+pub fn bootstrap_replicate_weights(wgt: &DVector<f64>, strata: &DVector<f64>, psus: &DVector<f64>, number_replicates: usize, seed: u64) -> DMatrix<f64> {
+    assert_eq!(wgt.len(), strata.len(), "dimension mismatch of wgt and strata in bootstrap_replicate_weights");
+    assert_eq!(wgt.len(), psus.len(), "dimension mismatch of wgt and psus in bootstrap_replicate_weights");
+
+    let (stratum_values, psu_values) = psus_by_stratum(strata, psus);
+    assert!(psu_values.iter().all(|values| values.len() >= 2), "stratum with less than two psus in bootstrap_replicate_weights");
+
+    // Rao-Wu-Yue rescaling bootstrap drawing n_h - 1 of the n_h psus with replacement in each stratum
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut factors : Vec<DMatrix<f64>> = Vec::new();
+    for values in psu_values.iter() {
+        let number_psus = values.len();
+        let mut stratum_factors = DMatrix::<f64>::zeros(number_psus, number_replicates);
+        for replicate in 0..number_replicates {
+            for _ in 0..number_psus - 1 {
+                stratum_factors[(rng.gen_range(0..number_psus), replicate)] += number_psus as f64 / (number_psus - 1) as f64;
+            }
+        }
+        factors.push(stratum_factors);
+    }
+
+    let mut replicate_weights = DMatrix::<f64>::zeros(wgt.len(), number_replicates);
+    for r in 0..wgt.len() {
+        let stratum_index = stratum_values.iter().position(|&s| s == strata[r]).unwrap();
+        let psu_index = psu_values[stratum_index].iter().position(|&p| p == psus[r]).unwrap();
+
+        for replicate in 0..number_replicates {
+            replicate_weights[(r, replicate)] = wgt[r] * factors[stratum_index][(psu_index, replicate)];
+        }
+    }
+
+    replicate_weights
+}
+
+pub fn bootstrap_variance_adjustment_factor(replicate_weights: &DMatrix<f64>) -> f64 {
+    1.0 / replicate_weights.ncols() as f64
+}
+
+pub fn srs_replicate_weights(wgt: &DVector<f64>, number_groups: usize) -> DMatrix<f64> {
+    let number_groups = number_groups.min(wgt.len());
+    assert!(number_groups > 1, "less than two groups in srs_replicate_weights");
+
+    // delete-one-group jackknife over interleaved cases, ignoring any clustering or stratification of the design
+    let factor = number_groups as f64 / (number_groups - 1) as f64;
+    DMatrix::<f64>::from_fn(wgt.len(), number_groups, |r, c| if r % number_groups == c { 0.0 } else { wgt[r] * factor })
+}
+
+pub fn srs_variance_adjustment_factor(replicate_weights: &DMatrix<f64>) -> f64 {
+    (replicate_weights.ncols() - 1) as f64 / replicate_weights.ncols() as f64
+}
+
+pub fn normalize_weights(wgt: &DVector<f64>) -> DVector<f64> {
+    normalize_weights_to(wgt, To::SampleSize)
+}
+
+pub fn normalize_weights_to(wgt: &DVector<f64>, to: To) -> DVector<f64> {
+    let sum = wgt.sum();
+    assert!(sum > 0.0, "weights do not sum up to a positive value in normalize_weights");
+
+    let target = match to {
+        To::SampleSize => wgt.len() as f64,
+        To::Sum(target) => target,
+    };
+    assert!(target > 0.0, "target sum not positive in normalize_weights");
+
+    wgt * (target / sum)
+}
+
+pub fn trim_weights(wgt: &DVector<f64>, percentile: f64) -> DVector<f64> {
+    assert!(percentile > 0.0 && percentile <= 1.0, "percentile not within (0, 1] in trim_weights");
+
+    // the cap is the nearest-rank percentile among positive weights, so that cases dropped from a replicate do not lower it
+    let mut positive_weights : Vec<f64> = wgt.iter().filter(|&&w| w > 0.0).cloned().collect();
+    if positive_weights.is_empty() {
+        return wgt.clone();
+    }
+    positive_weights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let rank = ((percentile * positive_weights.len() as f64).ceil() as usize).max(1);
+    let cap = positive_weights[rank - 1];
+
+    // missing weights stay missing instead of becoming the cap
+    wgt.map(|w| if w.is_nan() { w } else { w.min(cap) })
+}
+
+pub fn preprocess_weights(wgt: &DVector<f64>, replicate_weights: &DMatrix<f64>, normalization: Option<To>, trimming_percentile: Option<f64>) -> (DVector<f64>, DMatrix<f64>) {
+    assert_eq!(wgt.len(), replicate_weights.nrows(), "dimension mismatch of wgt and replicate_weights in preprocess_weights");
+
+    // every replicate weight column is treated exactly like the main weights, trimming before normalizing
+    let preprocess = |weights: DVector<f64>| {
+        let weights = match trimming_percentile {
+            Some(percentile) => trim_weights(&weights, percentile),
+            None => weights,
+        };
+        match normalization {
+            Some(to) => normalize_weights_to(&weights, to),
+            None => weights,
+        }
+    };
+
+    let new_wgt = preprocess(wgt.clone());
+    let columns : Vec<DVector<f64>> = replicate_weights.column_iter().map(|column| preprocess(column.clone_owned())).collect();
+    let new_replicate_weights = if columns.is_empty() { replicate_weights.clone() } else { DMatrix::from_columns(&columns) };
+
+    (new_wgt, new_replicate_weights)
+}
+
+pub fn senate_weights(wgt: &DVector<f64>, replicate_weights: &DMatrix<f64>, units: &DVector<f64>, total: f64) -> (DVector<f64>, DMatrix<f64>) {
+    assert_eq!(wgt.len(), replicate_weights.nrows(), "dimension mismatch of wgt and replicate_weights in senate_weights");
+    assert_eq!(wgt.len(), units.len(), "dimension mismatch of wgt and units in senate_weights");
+    assert!(total > 0.0, "total not positive in senate_weights");
+
+    // each unit is rescaled to the same total, units without any weight stay at zero
+    let unit_values = sorted_unique_values(units);
+    let rescale = |weights: DVector<f64>| {
+        let mut new_weights = weights.clone();
+        for &unit in unit_values.iter() {
+            let sum : f64 = weights.iter().zip(units.iter()).filter(|(_, &u)| u == unit).map(|(&w, _)| w).sum();
+            if sum > 0.0 {
+                for (w, &u) in new_weights.iter_mut().zip(units.iter()) {
+                    if u == unit {
+                        *w *= total / sum;
+                    }
+                }
+            }
+        }
+        new_weights
+    };
+
+    let new_wgt = rescale(wgt.clone());
+    let columns : Vec<DVector<f64>> = replicate_weights.column_iter().map(|column| rescale(column.clone_owned())).collect();
+    let new_replicate_weights = if columns.is_empty() { replicate_weights.clone() } else { DMatrix::from_columns(&columns) };
+
+    (new_wgt, new_replicate_weights)
+}
+
+// the conditions asserted when deriving replicate weights, for callers reporting an invalid design as an error instead
+pub fn check_design(wgt: &DVector<f64>, strata: &DVector<f64>, psus: &DVector<f64>, two_psus_per_stratum: bool) -> Result<(), String> {
+    if strata.len() != wgt.len() || psus.len() != wgt.len() {
+        return Err("strata and psus have to be given for every case".to_string());
+    }
+    if strata.iter().chain(psus.iter()).any(|value| value.is_nan()) {
+        return Err("strata and psus cannot be missing".to_string());
+    }
+
+    let (_, psu_values) = psus_by_stratum(strata, psus);
+    if two_psus_per_stratum && psu_values.iter().any(|values| values.len() != 2) {
+        return Err("every stratum has to have exactly two psus".to_string());
+    }
+    if psu_values.iter().map(|values| values.len()).sum::<usize>() < 2 {
+        return Err("design has less than two psus".to_string());
+    }
+
+    Ok(())
+}
+
+fn psus_by_stratum(strata: &DVector<f64>, psus: &DVector<f64>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let stratum_values = sorted_unique_values(strata);
+    let psu_values : Vec<Vec<f64>> = stratum_values.iter().map(|&stratum| {
+        let psus_in_stratum : DVector<f64> = DVector::from_iterator(
+            strata.len(),
+            strata.iter().zip(psus.iter()).map(|(&s, &p)| if s == stratum { p } else { f64::NAN })
+        );
+        sorted_unique_values(&psus_in_stratum)
+    }).collect();
+
+    (stratum_values, psu_values)
+}
+
+fn sorted_unique_values(values: &DVector<f64>) -> Vec<f64> {
+    let mut unique_values : Vec<f64> = values.iter().filter(|v| !v.is_nan()).cloned().collect();
+    unique_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    unique_values.dedup();
+    unique_values
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{dmatrix, dvector, DMatrix};
+    use crate::design::{bootstrap_replicate_weights, bootstrap_variance_adjustment_factor, brr_replicate_weights, brr_variance_adjustment_factor, check_design, hadamard_matrix, jackknife_replicate_weights, normalize_weights, normalize_weights_to, preprocess_weights, senate_weights, srs_replicate_weights, srs_variance_adjustment_factor, trim_weights, JackknifeScheme, To};
+    use crate::estimates::mean;
+    use crate::replication::replicate_estimates;
+
+    #[test]
+    fn test_hadamard_matrix() {
+        assert_eq!(dmatrix![1.0], hadamard_matrix(1));
+        assert_eq!(dmatrix![1.0, 1.0; 1.0, -1.0], hadamard_matrix(2));
+
+        let hadamard = hadamard_matrix(8);
+        assert_eq!(DMatrix::<f64>::identity(8, 8) * 8.0, &hadamard * hadamard.transpose());
+    }
+
+    #[test]
+    #[should_panic(expected = "order not a power of two in hadamard_matrix")]
+    fn test_hadamard_matrix_panic() {
+        hadamard_matrix(6);
+    }
+
+    #[test]
+    fn test_brr_replicate_weights() {
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 3.0, 1.0];
+        let strata = dvector![1.0, 1.0, 1.0, 2.0, 2.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0, 5.0, 4.0, 4.0];
+
+        let replicate_weights = brr_replicate_weights(&wgt, &strata, &psus);
+        assert_eq!(replicate_weights, dmatrix![
+            2.0, 0.0, 2.0, 0.0;
+            0.0, 4.0, 0.0, 4.0;
+            2.0, 0.0, 2.0, 0.0;
+            0.0, 0.0, 2.0, 2.0;
+            6.0, 6.0, 0.0, 0.0;
+            2.0, 2.0, 0.0, 0.0;
+        ]);
+        assert_eq!(0.25, brr_variance_adjustment_factor(&replicate_weights));
+    }
+
+    #[test]
+    #[should_panic(expected = "stratum without exactly two psus in brr_replicate_weights")]
+    fn test_brr_replicate_weights_single_psu() {
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let strata = dvector![1.0, 1.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0];
+
+        brr_replicate_weights(&wgt, &strata, &psus);
+    }
+
+    #[test]
+    #[should_panic(expected = "dimension mismatch of wgt and psus in brr_replicate_weights")]
+    fn test_brr_replicate_weights_dimension_mismatch() {
+        brr_replicate_weights(&dvector![1.0, 1.0], &dvector![1.0, 1.0], &dvector![1.0]);
+    }
+
+    #[test]
+    fn test_jackknife_replicate_weights() {
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 3.0, 1.0];
+        let strata = dvector![1.0, 1.0, 1.0, 2.0, 2.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0, 5.0, 4.0, 4.0];
+
+        let replicate_weights = jackknife_replicate_weights(&wgt, &strata, &psus, JackknifeScheme::JK2);
+        assert_eq!(replicate_weights, dmatrix![
+            2.0, 0.0, 1.0, 1.0;
+            0.0, 4.0, 2.0, 2.0;
+            2.0, 0.0, 1.0, 1.0;
+            1.0, 1.0, 0.0, 2.0;
+            3.0, 3.0, 6.0, 0.0;
+            1.0, 1.0, 2.0, 0.0;
+        ]);
+
+        let replicate_weights = jackknife_replicate_weights(&dvector![1.0, 2.0, 1.0], &dvector![1.0, 1.0, 2.0], &dvector![1.0, 2.0, 1.0], JackknifeScheme::JK1);
+        assert_eq!(replicate_weights, dmatrix![
+            0.0, 1.5, 1.5;
+            3.0, 0.0, 3.0;
+            1.5, 1.5, 0.0;
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "stratum without exactly two psus in jackknife_replicate_weights")]
+    fn test_jackknife_replicate_weights_single_psu() {
+        jackknife_replicate_weights(&dvector![1.0, 1.0, 1.0], &dvector![1.0, 1.0, 2.0], &dvector![1.0, 2.0, 1.0], JackknifeScheme::JK2);
+    }
+
+    #[test]
+    #[should_panic(expected = "less than two psus in jackknife_replicate_weights")]
+    fn test_jackknife_replicate_weights_single_unit() {
+        jackknife_replicate_weights(&dvector![1.0, 1.0], &dvector![1.0, 1.0], &dvector![1.0, 1.0], JackknifeScheme::JK1);
+    }
+
+    #[test]
+    fn test_check_design() {
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+        assert_eq!(Ok(()), check_design(&wgt, &dvector![1.0, 1.0, 2.0, 2.0], &dvector![1.0, 2.0, 1.0, 2.0], true));
+        assert_eq!(Ok(()), check_design(&wgt, &dvector![1.0, 1.0, 2.0, 2.0], &dvector![1.0, 2.0, 1.0, 1.0], false));
+        assert_eq!(Err("every stratum has to have exactly two psus".to_string()), check_design(&wgt, &dvector![1.0, 1.0, 2.0, 2.0], &dvector![1.0, 2.0, 1.0, 1.0], true));
+        assert_eq!(Err("design has less than two psus".to_string()), check_design(&wgt, &dvector![1.0, 1.0, 1.0, 1.0], &dvector![1.0, 1.0, 1.0, 1.0], false));
+        assert_eq!(Err("strata and psus cannot be missing".to_string()), check_design(&wgt, &dvector![1.0, f64::NAN, 2.0, 2.0], &dvector![1.0, 2.0, 1.0, 2.0], false));
+        assert_eq!(Err("strata and psus have to be given for every case".to_string()), check_design(&wgt, &dvector![1.0, 1.0, 2.0], &dvector![1.0, 2.0, 1.0], false));
+    }
+
+    #[test]
+    fn test_brr_replicate_weights_end_to_end() {
+        let data = dmatrix![1.0; 3.0; 2.0; 6.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+        let strata = dvector![1.0, 1.0, 2.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0, 2.0];
+
+        let replicate_weights = brr_replicate_weights(&wgt, &strata, &psus);
+        let factor = brr_variance_adjustment_factor(&replicate_weights);
+        let result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&replicate_weights], factor).unwrap();
+
+        // half-samples give means of 1.5, 2.5, 3.5 and 4.5 around the full sample mean of 3.0
+        assert_eq!(dvector![3.0], *result.final_estimates());
+        assert_eq!(dvector![1.25], *result.sampling_variances());
+    }
+
+    #[test]
+    fn test_bootstrap_replicate_weights() {
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 3.0, 1.0, 2.0];
+        let strata = dvector![1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0, 5.0, 4.0, 4.0, 6.0];
+
+        let replicate_weights = bootstrap_replicate_weights(&wgt, &strata, &psus, 50, 4711);
+        assert_eq!((7, 50), replicate_weights.shape());
+        assert_eq!(0.02, bootstrap_variance_adjustment_factor(&replicate_weights));
+        assert_eq!(replicate_weights, bootstrap_replicate_weights(&wgt, &strata, &psus, 50, 4711));
+        assert_ne!(replicate_weights, bootstrap_replicate_weights(&wgt, &strata, &psus, 50, 4712));
+
+        for replicate in 0..50 {
+            // cases of the same psu share their rescaling factor
+            assert_eq!(replicate_weights[(0, replicate)], replicate_weights[(2, replicate)]);
+            assert_eq!(replicate_weights[(4, replicate)], 3.0 * replicate_weights[(5, replicate)]);
+
+            // rescaling factors add up to the number of psus within each stratum
+            let factors_stratum1 = replicate_weights[(0, replicate)] + replicate_weights[(1, replicate)] / 2.0;
+            let factors_stratum2 = replicate_weights[(3, replicate)] + replicate_weights[(5, replicate)] + replicate_weights[(6, replicate)] / 2.0;
+            assert!((factors_stratum1 - 2.0).abs() < 1e-10);
+            assert!((factors_stratum2 - 3.0).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "stratum with less than two psus in bootstrap_replicate_weights")]
+    fn test_bootstrap_replicate_weights_single_psu() {
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let strata = dvector![1.0, 1.0, 2.0];
+        let psus = dvector![1.0, 2.0, 1.0];
+
+        bootstrap_replicate_weights(&wgt, &strata, &psus, 10, 1);
+    }
+
+    #[test]
+    fn test_srs_replicate_weights() {
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 3.0];
+
+        let replicate_weights = srs_replicate_weights(&wgt, 2);
+        assert_eq!(dmatrix![0.0, 2.0; 4.0, 0.0; 0.0, 2.0; 2.0, 0.0; 0.0, 6.0], replicate_weights);
+        assert_eq!(0.5, srs_variance_adjustment_factor(&replicate_weights));
+
+        // a delete-one jackknife of the mean reproduces the textbook variance of s^2 / n
+        let data = dmatrix![1.0; 3.0; 5.0; 4.0; 7.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0];
+        let replicate_weights = srs_replicate_weights(&wgt, 100);
+        assert_eq!(5, replicate_weights.ncols());
+        let result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&replicate_weights], srs_variance_adjustment_factor(&replicate_weights)).unwrap();
+        assert!((result.sampling_variances()[0] - 5.0 / 5.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_normalize_weights() {
+        assert_eq!(dvector![0.5, 1.0, 1.5, 1.0], normalize_weights(&dvector![1.0, 2.0, 3.0, 2.0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "weights do not sum up to a positive value in normalize_weights")]
+    fn test_normalize_weights_panic() {
+        normalize_weights(&dvector![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_normalize_weights_to() {
+        let wgt = dvector![1.0, 2.0, 3.0, 2.0];
+        assert_eq!(normalize_weights(&wgt), normalize_weights_to(&wgt, To::SampleSize));
+        assert_eq!(dvector![12.5, 25.0, 37.5, 25.0], normalize_weights_to(&wgt, To::Sum(100.0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "target sum not positive in normalize_weights")]
+    fn test_normalize_weights_to_panic() {
+        normalize_weights_to(&dvector![1.0, 2.0], To::Sum(0.0));
+    }
+
+    #[test]
+    fn test_trim_weights() {
+        let wgt = dvector![1.0, 2.0, 3.0, 10.0];
+        assert_eq!(dvector![1.0, 2.0, 3.0, 3.0], trim_weights(&wgt, 0.75));
+        assert_eq!(wgt, trim_weights(&wgt, 1.0));
+
+        // zero weights do not count towards the percentile
+        assert_eq!(dvector![0.0, 2.0, 3.0, 3.0], trim_weights(&dvector![0.0, 2.0, 3.0, 10.0], 0.5));
+
+        let trimmed = trim_weights(&dvector![1.0, f64::NAN, 3.0, 10.0], 0.5);
+        assert!(trimmed[1].is_nan());
+        assert_eq!(vec![1.0, 3.0, 3.0], vec![trimmed[0], trimmed[2], trimmed[3]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "percentile not within (0, 1] in trim_weights")]
+    fn test_trim_weights_panic() {
+        trim_weights(&dvector![1.0, 2.0], 0.0);
+    }
+
+    #[test]
+    fn test_preprocess_weights() {
+        let wgt = dvector![1.0, 1.0, 2.0, 10.0];
+        let replicate_weights = dmatrix![
+            0.0, 2.0;
+            2.0, 2.0;
+            2.0, 0.0;
+            8.0, 20.0;
+        ];
+
+        let (new_wgt, new_replicate_weights) = preprocess_weights(&wgt, &replicate_weights, Some(To::SampleSize), Some(0.5));
+        assert_eq!(dvector![1.0, 1.0, 1.0, 1.0], new_wgt);
+        assert_eq!(dmatrix![
+            0.0, 4.0 / 3.0;
+            4.0 / 3.0, 4.0 / 3.0;
+            4.0 / 3.0, 0.0;
+            4.0 / 3.0, 4.0 / 3.0;
+        ], new_replicate_weights);
+
+        let (new_wgt, new_replicate_weights) = preprocess_weights(&wgt, &replicate_weights, Some(To::Sum(7.0)), None);
+        assert_eq!(dvector![0.5, 0.5, 1.0, 5.0], new_wgt);
+        for column in new_replicate_weights.column_iter() {
+            assert!((column.sum() - 7.0).abs() < 1e-10);
+        }
+
+        let (new_wgt, new_replicate_weights) = preprocess_weights(&wgt, &replicate_weights, None, None);
+        assert_eq!(wgt, new_wgt);
+        assert_eq!(replicate_weights, new_replicate_weights);
+    }
+
+    #[test]
+    fn test_senate_weights() {
+        let wgt = dvector![1.0, 3.0, 2.0, 2.0, 4.0];
+        let replicate_weights = dmatrix![
+            2.0, 1.0;
+            2.0, 3.0;
+            0.0, 2.0;
+            4.0, 2.0;
+            4.0, 0.0;
+        ];
+        let units = dvector![1.0, 1.0, 2.0, 2.0, 2.0];
+
+        let (new_wgt, new_replicate_weights) = senate_weights(&wgt, &replicate_weights, &units, 500.0);
+        assert_eq!(dvector![125.0, 375.0, 125.0, 125.0, 250.0], new_wgt);
+        assert_eq!(dmatrix![
+            250.0, 125.0;
+            250.0, 375.0;
+            0.0, 250.0;
+            250.0, 250.0;
+            250.0, 0.0;
+        ], new_replicate_weights);
+    }
+
+    #[test]
+    #[should_panic(expected = "dimension mismatch of wgt and units in senate_weights")]
+    fn test_senate_weights_panic() {
+        senate_weights(&dvector![1.0, 2.0], &DMatrix::from_row_slice(2, 0, &[]), &dvector![1.0], 100.0);
+    }
+}
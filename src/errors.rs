@@ -74,4 +74,73 @@ impl Error for InconsistencyError {
     fn description(&self) -> &str {
         &self.details
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug)]
+pub struct ReplicateWeightError {
+    details: String
+}
+
+impl ReplicateWeightError {
+    pub fn new(column: usize) -> ReplicateWeightError {
+        ReplicateWeightError {
+            details: format!("Replicate weight column {} contains NaN", column + 1)
+        }
+    }
+}
+
+impl Display for ReplicateWeightError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for ReplicateWeightError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+#[derive(Debug)]
+pub struct ColumnNotFoundError {
+    details: String
+}
+
+impl ColumnNotFoundError {
+    pub fn new(column: &str) -> ColumnNotFoundError {
+        ColumnNotFoundError {
+            details: format!("Column {} not found", column)
+        }
+    }
+}
+
+impl Display for ColumnNotFoundError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for ColumnNotFoundError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplicestError {
+    InvalidInput(String),
+    EstimationFailed(String),
+    Cancelled,
+}
+
+impl Display for ReplicestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplicestError::InvalidInput(details) => write!(f, "Invalid input: {}", details),
+            ReplicestError::EstimationFailed(details) => write!(f, "Estimation failed: {}", details),
+            ReplicestError::Cancelled => write!(f, "Calculation was cancelled"),
+        }
+    }
+}
+
+impl Error for ReplicestError {}
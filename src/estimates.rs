@@ -1,128 +1,2502 @@
 use nalgebra::{DMatrix, DVector};
-use crate::helper::ExtractValues;
+use serde::{Deserialize, Serialize};
+use crate::errors::ReplicestError;
+use crate::helper::{bivariate_normal_cdf, maximize_on_interval, normal_cdf, normal_quantile, ExtractValues};
 
+#[derive(Debug)]
 pub struct Estimates {
     parameter_names: Vec<String>,
     estimates: DVector<f64>,
 }
 
 impl Estimates {
+    pub fn new(parameter_names: Vec<String>, estimates: DVector<f64>) -> Estimates {
+        assert_eq!(parameter_names.len(), estimates.len(), "dimension mismatch of parameter_names and estimates in Estimates");
+
+        Estimates {
+            parameter_names,
+            estimates,
+        }
+    }
+
     pub fn parameter_names(&self) -> &Vec<String> {
         &self.parameter_names
     }
 
-    pub fn estimates(&self) -> &DVector<f64> {
-        &self.estimates
+    pub fn estimates(&self) -> &DVector<f64> {
+        &self.estimates
+    }
+}
+
+pub type EstimateFunction = dyn Fn(&DMatrix<f64>, &DVector<f64>) -> Result<Estimates, ReplicestError> + Send + Sync;
+
+// the name of column c of x within parameter names, x1, x2, ... if there are no variable names
+fn variable_name(variable_names: &[String], c: usize) -> String {
+    variable_names.get(c).cloned().unwrap_or_else(|| format!("x{}", c + 1))
+}
+
+fn check_weights(x: &DMatrix<f64>, wgt: &DVector<f64>, estimator: &str) -> Result<(), ReplicestError> {
+    if x.nrows() != wgt.len() {
+        return Err(ReplicestError::InvalidInput(format!("dimension mismatch of x and wgt in {}", estimator)));
+    }
+    if wgt.iter().any(|e| e.is_nan()) {
+        return Err(ReplicestError::InvalidInput(format!("wgt contains NaN in {}", estimator)));
+    }
+    Ok(())
+}
+
+pub trait Accumulator: Clone + Send {
+    fn accumulate(&mut self, x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<(), ReplicestError>;
+
+    fn estimates(&self) -> Estimates;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MeanAccumulator {
+    weighted_sums: DVector<f64>,
+    sums_of_weights: DVector<f64>,
+}
+
+impl Accumulator for MeanAccumulator {
+    fn accumulate(&mut self, x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<(), ReplicestError> {
+        check_weights(x, wgt, "MeanAccumulator")?;
+
+        if self.weighted_sums.is_empty() {
+            self.weighted_sums = DVector::<f64>::zeros(x.ncols());
+            self.sums_of_weights = DVector::<f64>::zeros(x.ncols());
+        }
+        if x.ncols() != self.weighted_sums.len() {
+            return Err(ReplicestError::InvalidInput("inconsistent number of columns in MeanAccumulator".to_string()));
+        }
+
+        let x_transpose = x.transpose();
+        self.weighted_sums += x_transpose.map(|e| if e.is_nan() { 0.0_f64 } else { e }) * wgt;
+        self.sums_of_weights += x_transpose.map(|e| if e.is_nan() { 0.0_f64 } else { 1.0_f64 }) * wgt;
+
+        Ok(())
+    }
+
+    fn estimates(&self) -> Estimates {
+        Estimates {
+            parameter_names: (0..self.weighted_sums.len()).map(|c| format!("mean_{}", variable_name(&[], c))).collect(),
+            estimates: self.weighted_sums.component_div(&self.sums_of_weights),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TotalAccumulator {
+    weighted_sums: DVector<f64>,
+}
+
+impl Accumulator for TotalAccumulator {
+    fn accumulate(&mut self, x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<(), ReplicestError> {
+        check_weights(x, wgt, "TotalAccumulator")?;
+
+        if self.weighted_sums.is_empty() {
+            self.weighted_sums = DVector::<f64>::zeros(x.ncols());
+        }
+        if x.ncols() != self.weighted_sums.len() {
+            return Err(ReplicestError::InvalidInput("inconsistent number of columns in TotalAccumulator".to_string()));
+        }
+
+        self.weighted_sums += x.transpose().map(|e| if e.is_nan() { 0.0_f64 } else { e }) * wgt;
+
+        Ok(())
+    }
+
+    fn estimates(&self) -> Estimates {
+        Estimates {
+            parameter_names: (0..self.weighted_sums.len()).map(|c| format!("total_{}", variable_name(&[], c))).collect(),
+            estimates: self.weighted_sums.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FrequenciesAccumulator {
+    options: FrequenciesOptions,
+    counts: Vec<Vec<(f64, f64)>>,
+}
+
+impl FrequenciesAccumulator {
+    pub fn new(options: &FrequenciesOptions) -> FrequenciesAccumulator {
+        FrequenciesAccumulator {
+            options: options.clone(),
+            counts: Vec::new(),
+        }
+    }
+}
+
+impl Accumulator for FrequenciesAccumulator {
+    fn accumulate(&mut self, x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<(), ReplicestError> {
+        check_weights(x, wgt, "FrequenciesAccumulator")?;
+
+        if self.counts.is_empty() {
+            let initial_counts = match self.options.expected_categories {
+                Some(ref expected_categories) => expected_categories.iter().map(|&category| (category, 0.0)).collect(),
+                None => Vec::new(),
+            };
+            self.counts = vec![initial_counts; x.ncols()];
+        }
+        if x.ncols() != self.counts.len() {
+            return Err(ReplicestError::InvalidInput("inconsistent number of columns in FrequenciesAccumulator".to_string()));
+        }
+
+        // expected categories keep their given order and unexpected values are ignored, otherwise categories are kept sorted
+        for (c, column) in x.column_iter().enumerate() {
+            for (&value, &w) in column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan()) {
+                if self.options.expected_categories.is_some() {
+                    if let Some(position) = self.counts[c].iter().position(|(category, _)| *category == value) {
+                        self.counts[c][position].1 += w;
+                    }
+                    continue;
+                }
+
+                match self.counts[c].binary_search_by(|(category, _)| category.partial_cmp(&value).unwrap()) {
+                    Ok(position) => self.counts[c][position].1 += w,
+                    Err(position) => self.counts[c].insert(position, (value, w)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn estimates(&self) -> Estimates {
+        let mut parameter_names = Vec::<String>::new();
+        let mut parameter_names_percentages = Vec::<String>::new();
+        let mut estimates = Vec::<f64>::new();
+        let mut estimates_percentages = Vec::<f64>::new();
+
+        for (c, counts) in self.counts.iter().enumerate() {
+            let sum_of_counts : f64 = counts.iter().map(|(_, count)| count).sum();
+
+            for (category, count) in counts.iter() {
+                parameter_names.push(format!("frequency_{}_cat{}", variable_name(&[], c), category));
+                estimates.push(*count);
+                parameter_names_percentages.push(format!("percentage_{}_cat{}", variable_name(&[], c), category));
+                estimates_percentages.push(if sum_of_counts > 0.0 { 100.0 * count / sum_of_counts } else { f64::NAN });
+            }
+        }
+
+        if self.options.percentages {
+            parameter_names.append(&mut parameter_names_percentages);
+            estimates.append(&mut estimates_percentages);
+        }
+
+        Estimates {
+            parameter_names,
+            estimates: DVector::from_vec(estimates),
+        }
+    }
+}
+
+pub fn mean(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_mean(x, wgt, &[])
+}
+
+pub(crate) fn named_mean(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "mean")?;
+
+    let x_transpose = x.transpose();
+    let x_transpose_clean : DMatrix<f64> = x_transpose.map(|e| if e.is_nan() { 0.0_f64 } else { e });
+    let x_transpose_ind : DMatrix<f64> = x_transpose.map(|e| if e.is_nan() { 0.0_f64 } else { 1.0_f64 });
+
+    let weighted_sums = x_transpose_clean * wgt;
+    let sum_of_weights = x_transpose_ind * wgt;
+
+    Ok(Estimates {
+        parameter_names: (0..x.ncols()).map(|c| format!("mean_{}", variable_name(variable_names, c))).collect(),
+        estimates: weighted_sums.component_div(&sum_of_weights),
+    })
+}
+
+pub fn standardized_mean(x: &DMatrix<f64>, wgt: &DVector<f64>, reference_means: &[f64], reference_standard_deviations: &[f64]) -> Result<Estimates, ReplicestError> {
+    named_standardized_mean(x, wgt, reference_means, reference_standard_deviations, &[])
+}
+
+pub(crate) fn named_standardized_mean(x: &DMatrix<f64>, wgt: &DVector<f64>, reference_means: &[f64], reference_standard_deviations: &[f64], variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "standardized_mean")?;
+    if x.ncols() != reference_means.len() {
+        return Err(ReplicestError::InvalidInput("dimension mismatch of x and reference means in standardized_mean".to_string()));
+    }
+    if x.ncols() != reference_standard_deviations.len() {
+        return Err(ReplicestError::InvalidInput("dimension mismatch of x and reference standard deviations in standardized_mean".to_string()));
+    }
+
+    let means = mean(x, wgt)?.estimates;
+
+    Ok(Estimates {
+        parameter_names: (0..x.ncols()).map(|c| format!("standardized_mean_{}", variable_name(variable_names, c))).collect(),
+        estimates: DVector::from_iterator(x.ncols(), means.iter().enumerate().map(|(c, m)| (m - reference_means[c]) / reference_standard_deviations[c])),
+    })
+}
+
+// the last column of x marks the cases of the group with 1 and all other cases with 0, all cases are the reference,
+// so that replicate weights change the reference mean and standard deviation as well
+pub fn standardized_group_mean(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_standardized_group_mean(x, wgt, &[])
+}
+
+pub(crate) fn named_standardized_group_mean(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "standardized_group_mean")?;
+    if x.ncols() < 2 {
+        return Err(ReplicestError::InvalidInput("x lacks the group column in standardized_group_mean".to_string()));
+    }
+
+    let data = x.columns(0, x.ncols() - 1).clone_owned();
+
+    let variances = correlation(&data, wgt)?.estimates;
+    let mut reference_standard_deviations = Vec::<f64>::new();
+    let mut index = 0;
+    for c in 0..data.ncols() {
+        reference_standard_deviations.push(variances[index].sqrt());
+        index += data.ncols() - c;
+    }
+    let reference_means : Vec<f64> = mean(&data, wgt)?.estimates.iter().cloned().collect();
+
+    let group_wgt = wgt.component_mul(&x.column(x.ncols() - 1));
+    named_standardized_mean(&data, &group_wgt, &reference_means, &reference_standard_deviations, variable_names)
+}
+
+pub fn total(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_total(x, wgt, &[])
+}
+
+pub(crate) fn named_total(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "total")?;
+
+    let x_transpose_clean : DMatrix<f64> = x.transpose().map(|e| if e.is_nan() { 0.0_f64 } else { e });
+
+    Ok(Estimates {
+        parameter_names: (0..x.ncols()).map(|c| format!("total_{}", variable_name(variable_names, c))).collect(),
+        estimates: x_transpose_clean * wgt,
+    })
+}
+
+pub fn range(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_range(x, wgt, &[])
+}
+
+pub(crate) fn named_range(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "range")?;
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut parameter_names_maximum = Vec::<String>::new();
+    let mut parameter_names_range = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::from_element(3 * x.ncols(), f64::NAN);
+
+    for (c, column) in x.column_iter().enumerate() {
+        parameter_names.push(format!("minimum_{}", variable_name(variable_names, c)));
+        parameter_names_maximum.push(format!("maximum_{}", variable_name(variable_names, c)));
+        parameter_names_range.push(format!("range_{}", variable_name(variable_names, c)));
+
+        // cases with a weight of zero do not belong to the (replicate) sample
+        let values : Vec<f64> = column.iter().zip(wgt.iter()).filter(|(v, w)| !v.is_nan() && **w > 0.0).map(|(v, _)| *v).collect();
+        if values.is_empty() {
+            continue;
+        }
+
+        let minimum = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let maximum = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        estimates[c] = minimum;
+        estimates[x.ncols() + c] = maximum;
+        estimates[2 * x.ncols() + c] = maximum - minimum;
+    }
+    parameter_names.append(&mut parameter_names_maximum);
+    parameter_names.append(&mut parameter_names_range);
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn missingness(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_missingness(x, wgt, &[])
+}
+
+pub(crate) fn named_missingness(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "missingness")?;
+
+    let sum_of_weights = wgt.sum();
+    let mut parameter_names = Vec::<String>::new();
+    let mut parameter_names_unweighted = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::zeros(2 * x.ncols());
+
+    for (c, column) in x.column_iter().enumerate() {
+        parameter_names.push(format!("missing_weighted_{}", variable_name(variable_names, c)));
+        parameter_names_unweighted.push(format!("missing_unweighted_{}", variable_name(variable_names, c)));
+
+        let weight_missing : f64 = column.iter().zip(wgt.iter()).filter(|(v, _)| v.is_nan()).map(|(_, w)| w).sum();
+        let count_missing = column.iter().filter(|v| v.is_nan()).count();
+
+        estimates[c] = 100.0 * weight_missing / sum_of_weights;
+        estimates[x.ncols() + c] = 100.0 * count_missing as f64 / x.nrows() as f64;
+    }
+    parameter_names.append(&mut parameter_names_unweighted);
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrequenciesOptions {
+    pub percentages: bool,
+    pub expected_categories: Option<Vec<f64>>,
+}
+
+pub fn frequencies(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    frequencies_with_options(x, wgt, &FrequenciesOptions::default())
+}
+
+pub fn frequencies_with_options(x: &DMatrix<f64>, wgt: &DVector<f64>, options: &FrequenciesOptions) -> Result<Estimates, ReplicestError> {
+    named_frequencies(x, wgt, options, &[])
+}
+
+pub(crate) fn named_frequencies(x: &DMatrix<f64>, wgt: &DVector<f64>, options: &FrequenciesOptions, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "frequencies")?;
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut parameter_names_percentages = Vec::<String>::new();
+    let mut estimates = Vec::<f64>::new();
+    let mut estimates_percentages = Vec::<f64>::new();
+
+    for (c, column) in x.column_iter().enumerate() {
+        let categories = match options.expected_categories {
+            Some(ref expected_categories) => expected_categories.clone(),
+            None => {
+                let mut categories : Vec<f64> = column.iter().filter(|v| !v.is_nan()).cloned().collect();
+                categories.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                categories.dedup();
+                categories
+            }
+        };
+
+        let counts : Vec<f64> = categories.iter().map(|category| {
+            column.iter().zip(wgt.iter()).filter(|(v, _)| *v == category).map(|(_, w)| w).sum()
+        }).collect();
+        let sum_of_counts : f64 = counts.iter().sum();
+
+        for (category, count) in categories.iter().zip(counts.iter()) {
+            parameter_names.push(format!("frequency_{}_cat{}", variable_name(variable_names, c), category));
+            estimates.push(*count);
+            parameter_names_percentages.push(format!("percentage_{}_cat{}", variable_name(variable_names, c), category));
+            estimates_percentages.push(if sum_of_counts > 0.0 { 100.0 * count / sum_of_counts } else { f64::NAN });
+        }
+    }
+
+    if options.percentages {
+        parameter_names.append(&mut parameter_names_percentages);
+        estimates.append(&mut estimates_percentages);
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates: DVector::from_vec(estimates),
+    })
+}
+
+pub fn crosstab(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_crosstab(x, wgt, &[])
+}
+
+pub(crate) fn named_crosstab(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "crosstab")?;
+    if x.ncols() != 2 {
+        return Err(ReplicestError::InvalidInput("crosstab requires exactly two columns".to_string()));
+    }
+
+    let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| !x[(r, 0)].is_nan() && !x[(r, 1)].is_nan()).collect();
+    let sum_of_weights : f64 = complete_rows.iter().map(|&r| wgt[r]).sum();
+
+    let mut categories = Vec::<Vec<f64>>::new();
+    for c in 0..2 {
+        let mut categories_column : Vec<f64> = complete_rows.iter().map(|&r| x[(r, c)]).collect();
+        categories_column.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        categories_column.dedup();
+        categories.push(categories_column);
+    }
+
+    let mut table = DMatrix::<f64>::zeros(categories[0].len(), categories[1].len());
+    for &r in complete_rows.iter() {
+        let a = categories[0].iter().position(|&v| v == x[(r, 0)]).unwrap();
+        let b = categories[1].iter().position(|&v| v == x[(r, 1)]).unwrap();
+        table[(a, b)] += wgt[r] / sum_of_weights;
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = Vec::<f64>::new();
+    for (a, category_a) in categories[0].iter().enumerate() {
+        for (b, category_b) in categories[1].iter().enumerate() {
+            parameter_names.push(format!("crosstab_{}_{}_{}_{}", variable_name(variable_names, 0), category_a, variable_name(variable_names, 1), category_b));
+            estimates.push(table[(a, b)]);
+        }
+    }
+    for (a, category_a) in categories[0].iter().enumerate() {
+        parameter_names.push(format!("crosstab_{}_{}", variable_name(variable_names, 0), category_a));
+        estimates.push(table.row(a).sum());
+    }
+    for (b, category_b) in categories[1].iter().enumerate() {
+        parameter_names.push(format!("crosstab_{}_{}", variable_name(variable_names, 1), category_b));
+        estimates.push(table.column(b).sum());
+    }
+    parameter_names.push("crosstab_n".to_string());
+    estimates.push(complete_rows.len() as f64);
+
+    Ok(Estimates {
+        parameter_names,
+        estimates: DVector::from_vec(estimates),
+    })
+}
+
+pub fn agreement(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "agreement")?;
+    if x.ncols() != 2 {
+        return Err(ReplicestError::InvalidInput("agreement requires exactly two columns".to_string()));
+    }
+
+    let parameter_names = vec![
+        "percent_agreement".to_string(),
+        "cohens_kappa".to_string(),
+        "weighted_kappa_linear".to_string(),
+        "weighted_kappa_quadratic".to_string(),
+    ];
+
+    let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| !x[(r, 0)].is_nan() && !x[(r, 1)].is_nan()).collect();
+    let sum_of_weights : f64 = complete_rows.iter().map(|&r| wgt[r]).sum();
+
+    // both raters share the union of observed categories
+    let mut categories : Vec<f64> = complete_rows.iter().flat_map(|&r| [x[(r, 0)], x[(r, 1)]]).collect();
+    categories.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    categories.dedup();
+
+    if categories.len() < 2 {
+        return Ok(Estimates {
+            parameter_names,
+            estimates: DVector::<f64>::from_element(4, f64::NAN),
+        })
+    }
+
+    let mut table = DMatrix::<f64>::zeros(categories.len(), categories.len());
+    for &r in complete_rows.iter() {
+        let a = categories.iter().position(|&v| v == x[(r, 0)]).unwrap();
+        let b = categories.iter().position(|&v| v == x[(r, 1)]).unwrap();
+        table[(a, b)] += wgt[r] / sum_of_weights;
+    }
+
+    let row_margins : Vec<f64> = table.row_iter().map(|row| row.sum()).collect();
+    let column_margins : Vec<f64> = table.column_iter().map(|column| column.sum()).collect();
+    let maximum_distance = (categories.len() - 1) as f64;
+
+    let weighted_kappa = |disagreement_weight: &dyn Fn(usize, usize) -> f64| {
+        let mut observed = 0.0;
+        let mut expected = 0.0;
+        for a in 0..categories.len() {
+            for b in 0..categories.len() {
+                observed += disagreement_weight(a, b) * table[(a, b)];
+                expected += disagreement_weight(a, b) * row_margins[a] * column_margins[b];
+            }
+        }
+        1.0 - observed / expected
+    };
+
+    let percent_agreement = 100.0 * table.trace();
+    let cohens_kappa = weighted_kappa(&|a, b| if a == b { 0.0 } else { 1.0 });
+    let weighted_kappa_linear = weighted_kappa(&|a, b| a.abs_diff(b) as f64 / maximum_distance);
+    let weighted_kappa_quadratic = weighted_kappa(&|a, b| (a.abs_diff(b) as f64 / maximum_distance).powi(2));
+
+    Ok(Estimates {
+        parameter_names,
+        estimates: DVector::from_vec(vec![percent_agreement, cohens_kappa, weighted_kappa_linear, weighted_kappa_quadratic]),
+    })
+}
+
+pub fn proportions_above(x: &DMatrix<f64>, wgt: &DVector<f64>, cutpoints: &[f64]) -> Result<Estimates, ReplicestError> {
+    named_proportions_above(x, wgt, cutpoints, &[])
+}
+
+pub(crate) fn named_proportions_above(x: &DMatrix<f64>, wgt: &DVector<f64>, cutpoints: &[f64], variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "proportions_above")?;
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::zeros(x.ncols() * cutpoints.len());
+
+    for (c, column) in x.column_iter().enumerate() {
+        let sum_of_weights : f64 = column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan()).map(|(_, w)| w).sum();
+
+        for (p, cutpoint) in cutpoints.iter().enumerate() {
+            let sum_of_weights_above : f64 = column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan() && *v >= cutpoint).map(|(_, w)| w).sum();
+
+            parameter_names.push(format!("proportion_above_{}_{}", cutpoint, variable_name(variable_names, c)));
+            estimates[c * cutpoints.len() + p] = sum_of_weights_above / sum_of_weights;
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn percentile_ranks(x: &DMatrix<f64>, wgt: &DVector<f64>, values: &[f64]) -> Result<Estimates, ReplicestError> {
+    named_percentile_ranks(x, wgt, values, &[])
+}
+
+pub(crate) fn named_percentile_ranks(x: &DMatrix<f64>, wgt: &DVector<f64>, values: &[f64], variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "percentile_ranks")?;
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::zeros(x.ncols() * values.len());
+
+    for (c, column) in x.column_iter().enumerate() {
+        let sum_of_weights : f64 = column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan()).map(|(_, w)| w).sum();
+
+        for (p, value) in values.iter().enumerate() {
+            let sum_of_weights_below : f64 = column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan() && *v < value).map(|(_, w)| w).sum();
+            let sum_of_weights_equal : f64 = column.iter().zip(wgt.iter()).filter(|(v, _)| *v == value).map(|(_, w)| w).sum();
+
+            parameter_names.push(format!("percentile_rank_{}_{}", value, variable_name(variable_names, c)));
+            estimates[c * values.len() + p] = 100.0 * (sum_of_weights_below + 0.5 * sum_of_weights_equal) / sum_of_weights;
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn binned_frequencies(x: &DMatrix<f64>, wgt: &DVector<f64>, breaks: &[f64]) -> Result<Estimates, ReplicestError> {
+    named_binned_frequencies(x, wgt, breaks, &[])
+}
+
+pub(crate) fn named_binned_frequencies(x: &DMatrix<f64>, wgt: &DVector<f64>, breaks: &[f64], variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "binned_frequencies")?;
+    if breaks.len() < 2 {
+        return Err(ReplicestError::InvalidInput("less than two breaks in binned_frequencies".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    let number_bins = breaks.len() - 1;
+    let mut estimates = DVector::<f64>::zeros(x.ncols() * number_bins);
+
+    for (c, column) in x.column_iter().enumerate() {
+        let proportions = calc_bin_proportions(&DVector::from(column), wgt, breaks);
+        for b in 0..number_bins {
+            parameter_names.push(format!("bin_{}_{}_{}", breaks[b], breaks[b + 1], variable_name(variable_names, c)));
+            estimates[c * number_bins + b] = proportions[b];
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn equal_width_binned_frequencies(x: &DMatrix<f64>, wgt: &DVector<f64>, number_bins: usize) -> Result<Estimates, ReplicestError> {
+    named_equal_width_binned_frequencies(x, wgt, number_bins, &[])
+}
+
+pub(crate) fn named_equal_width_binned_frequencies(x: &DMatrix<f64>, wgt: &DVector<f64>, number_bins: usize, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "equal_width_binned_frequencies")?;
+    if number_bins == 0 {
+        return Err(ReplicestError::InvalidInput("no bins in equal_width_binned_frequencies".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::zeros(x.ncols() * number_bins);
+
+    for (c, column) in x.column_iter().enumerate() {
+        // breaks depend on the (unweighted) range only, so they are identical across replicate weights
+        let minimum = column.iter().filter(|v| !v.is_nan()).cloned().fold(f64::INFINITY, f64::min);
+        let maximum = column.iter().filter(|v| !v.is_nan()).cloned().fold(f64::NEG_INFINITY, f64::max);
+        let breaks : Vec<f64> = (0..=number_bins).map(|b| minimum + (maximum - minimum) * b as f64 / number_bins as f64).collect();
+
+        let proportions = calc_bin_proportions(&DVector::from(column), wgt, &breaks);
+        for b in 0..number_bins {
+            parameter_names.push(format!("bin{}_{}", b + 1, variable_name(variable_names, c)));
+            estimates[c * number_bins + b] = proportions[b];
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub const ILSA_BENCHMARKS: [f64; 4] = [400.0, 475.0, 550.0, 625.0];
+
+pub fn benchmark_levels(x: &DMatrix<f64>, wgt: &DVector<f64>, cutpoints: &[f64]) -> Result<Estimates, ReplicestError> {
+    named_benchmark_levels(x, wgt, cutpoints, &[])
+}
+
+pub(crate) fn named_benchmark_levels(x: &DMatrix<f64>, wgt: &DVector<f64>, cutpoints: &[f64], variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "benchmark_levels")?;
+    if cutpoints.is_empty() {
+        return Err(ReplicestError::InvalidInput("no cutpoints in benchmark_levels".to_string()));
+    }
+    if !cutpoints.windows(2).all(|w| w[0] < w[1]) {
+        return Err(ReplicestError::InvalidInput("cutpoints not increasing in benchmark_levels".to_string()));
+    }
+
+    let mut breaks = vec![f64::NEG_INFINITY];
+    breaks.extend_from_slice(cutpoints);
+    breaks.push(f64::INFINITY);
+    let number_levels = breaks.len() - 1;
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::zeros(x.ncols() * number_levels);
+
+    for (c, column) in x.column_iter().enumerate() {
+        let proportions = calc_bin_proportions(&DVector::from(column), wgt, &breaks);
+        for b in 0..number_levels {
+            let level_name = if b == 0 {
+                format!("below_{}", breaks[1])
+            } else if b == number_levels - 1 {
+                format!("{}_above", breaks[b])
+            } else {
+                format!("{}_{}", breaks[b], breaks[b + 1])
+            };
+            parameter_names.push(format!("benchmark_{}_{}", level_name, variable_name(variable_names, c)));
+            estimates[c * number_levels + b] = 100.0 * proportions[b];
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+fn calc_bin_proportions(x: &DVector<f64>, wgt: &DVector<f64>, breaks: &[f64]) -> DVector<f64> {
+    let sum_of_weights : f64 = x.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan()).map(|(_, w)| w).sum();
+
+    DVector::<f64>::from_fn(breaks.len() - 1, |b, _| {
+        let is_last_bin = b == breaks.len() - 2;
+        let sum_of_weights_bin : f64 = x.iter().zip(wgt.iter())
+            .filter(|(&v, _)| v >= breaks[b] && (v < breaks[b + 1] || (is_last_bin && v == breaks[b + 1])))
+            .map(|(_, w)| w)
+            .sum();
+
+        sum_of_weights_bin / sum_of_weights
+    })
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Contrast {
+    #[default]
+    Treatment,
+    Sum,
+    Helmert,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CategoricalPredictor {
+    pub column: usize,
+    pub reference_category: f64,
+    pub contrast: Contrast,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinregOptions {
+    pub residual_diagnostics: bool,
+    pub number_residual_bins: usize,
+    pub categorical_predictors: Vec<CategoricalPredictor>,
+    pub dependent: Option<usize>,
+    pub predictors: Option<Vec<usize>>,
+}
+
+impl Default for LinregOptions {
+    fn default() -> Self {
+        LinregOptions {
+            residual_diagnostics: false,
+            number_residual_bins: 5,
+            categorical_predictors: Vec::new(),
+            dependent: None,
+            predictors: None,
+        }
+    }
+}
+
+pub fn linreg(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    linreg_with_options(x, wgt, &LinregOptions::default())
+}
+
+pub fn linreg_with_options(x: &DMatrix<f64>, wgt: &DVector<f64>, options: &LinregOptions) -> Result<Estimates, ReplicestError> {
+    named_linreg(x, wgt, options, &[])
+}
+
+pub(crate) fn named_linreg(x: &DMatrix<f64>, wgt: &DVector<f64>, options: &LinregOptions, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "linreg")?;
+    if x.ncols() == 0 {
+        return Err(ReplicestError::InvalidInput("no dependent variable in linreg".to_string()));
+    }
+    if options.residual_diagnostics && options.number_residual_bins == 0 {
+        return Err(ReplicestError::InvalidInput("no residual bins in linreg".to_string()));
+    }
+
+    // by convention the first column is the dependent variable and all other columns are predictors
+    let dependent = options.dependent.unwrap_or(0);
+    let predictor_columns = match options.predictors {
+        Some(ref predictors) => predictors.clone(),
+        None => (0..x.ncols()).filter(|&c| c != dependent).collect(),
+    };
+    if dependent >= x.ncols() {
+        return Err(ReplicestError::InvalidInput("invalid dependent variable column in linreg".to_string()));
+    }
+    if !predictor_columns.iter().all(|&c| c != dependent && c < x.ncols()) {
+        return Err(ReplicestError::InvalidInput("invalid predictor column in linreg".to_string()));
+    }
+    if !options.categorical_predictors.iter().all(|p| predictor_columns.contains(&p.column)) {
+        return Err(ReplicestError::InvalidInput("invalid categorical predictor column in linreg".to_string()));
+    }
+
+    let (predictors, predictor_names) = expand_predictors(x, &predictor_columns, &options.categorical_predictors, variable_names)?;
+
+    // an explicitly selected dependent variable is part of every parameter name
+    let prefix = match options.dependent {
+        Some(dependent) => format!("linreg_{}", variable_name(variable_names, dependent)),
+        None => "linreg".to_string(),
+    };
+    let mut parameter_names = vec![format!("{}_intercept", prefix)];
+    for predictor_name in predictor_names.iter() {
+        parameter_names.push(format!("{}_b_{}", prefix, predictor_name));
+    }
+    parameter_names.push(format!("{}_r2", prefix));
+    parameter_names.push(format!("{}_sigma", prefix));
+    if options.residual_diagnostics {
+        parameter_names.push(format!("{}_rmse", prefix));
+        for b in 1..=options.number_residual_bins {
+            parameter_names.push(format!("{}_mean_residual_bin{}", prefix, b));
+        }
+        parameter_names.push(format!("{}_durbin_watson", prefix));
+    }
+
+    // listwise deletion of cases with missing values in any column
+    let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| !x[(r, dependent)].is_nan() && predictors.row(r).iter().all(|v| !v.is_nan())).collect();
+    let y = DVector::<f64>::from_iterator(complete_rows.len(), complete_rows.iter().map(|&r| x[(r, dependent)]));
+    let w = DVector::<f64>::from_iterator(complete_rows.len(), complete_rows.iter().map(|&r| wgt[r]));
+    let design = DMatrix::<f64>::from_fn(complete_rows.len(), predictors.ncols() + 1, |r, c| if c == 0 { 1.0 } else { predictors[(complete_rows[r], c - 1)] });
+
+    let design_weighted = DMatrix::<f64>::from_fn(design.nrows(), design.ncols(), |r, c| design[(r, c)] * w[r]);
+    let cross_product = design.transpose() * &design_weighted;
+    let coefficients = match cross_product.try_inverse() {
+        Some(inverse) => inverse * design_weighted.transpose() * &y,
+        None => {
+            return Ok(Estimates {
+                estimates: DVector::<f64>::from_element(parameter_names.len(), f64::NAN),
+                parameter_names,
+            })
+        }
+    };
+
+    let fitted = &design * &coefficients;
+    let residuals = &y - &fitted;
+    let sum_of_weights = w.sum();
+    let mean_y = y.dot(&w) / sum_of_weights;
+    let residual_sum_of_squares = residuals.component_mul(&residuals).dot(&w);
+    let total_sum_of_squares : f64 = y.iter().zip(w.iter()).map(|(v, w)| w * (v - mean_y).powi(2)).sum();
+
+    let mut estimates = coefficients.as_slice().to_vec();
+    estimates.push(1.0 - residual_sum_of_squares / total_sum_of_squares);
+    estimates.push((residual_sum_of_squares / (sum_of_weights - design.ncols() as f64)).sqrt());
+
+    if options.residual_diagnostics {
+        estimates.push((residual_sum_of_squares / sum_of_weights).sqrt());
+        estimates.append(&mut calc_residual_bin_means(&fitted, &residuals, &w, options.number_residual_bins));
+
+        let squared_differences : f64 = (1..residuals.len()).map(|r| w[r] * (residuals[r] - residuals[r - 1]).powi(2)).sum();
+        estimates.push(squared_differences / residual_sum_of_squares);
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates: DVector::from_vec(estimates),
+    })
+}
+
+fn expand_predictors(x: &DMatrix<f64>, predictor_columns: &[usize], categorical_predictors: &[CategoricalPredictor], variable_names: &[String]) -> Result<(DMatrix<f64>, Vec<String>), ReplicestError> {
+    let mut columns = Vec::<DVector<f64>>::new();
+    let mut names = Vec::<String>::new();
+
+    for &c in predictor_columns.iter() {
+        let column = x.column(c);
+        match categorical_predictors.iter().find(|p| p.column == c) {
+            None => {
+                columns.push(column.clone_owned());
+                names.push(variable_name(variable_names, c));
+            }
+            Some(predictor) => {
+                // categories are taken from the data regardless of weights, so that names are stable across replicates
+                let mut categories : Vec<f64> = column.iter().filter(|v| !v.is_nan()).cloned().collect();
+                categories.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                categories.dedup();
+
+                // without the reference category the dummies of all other categories add up to the intercept
+                if predictor.contrast != Contrast::Helmert && !categories.contains(&predictor.reference_category) {
+                    return Err(ReplicestError::InvalidInput(format!("reference category {} of {} does not occur in linreg", predictor.reference_category, variable_name(variable_names, c))));
+                }
+
+                match predictor.contrast {
+                    Contrast::Treatment => {
+                        for category in categories.iter().filter(|&&v| v != predictor.reference_category) {
+                            columns.push(column.map(|v| if v.is_nan() { f64::NAN } else if v == *category { 1.0 } else { 0.0 }));
+                            names.push(format!("{}_cat{}", variable_name(variable_names, c), category));
+                        }
+                    }
+                    Contrast::Sum => {
+                        for category in categories.iter().filter(|&&v| v != predictor.reference_category) {
+                            columns.push(column.map(|v| {
+                                if v.is_nan() { f64::NAN } else if v == *category { 1.0 } else if v == predictor.reference_category { -1.0 } else { 0.0 }
+                            }));
+                            names.push(format!("{}_sum{}", variable_name(variable_names, c), category));
+                        }
+                    }
+                    Contrast::Helmert => {
+                        // each category is contrasted with the mean of all lower categories
+                        for (k, category) in categories.iter().enumerate().skip(1) {
+                            columns.push(column.map(|v| {
+                                if v.is_nan() { f64::NAN } else if v == *category { k as f64 } else if v < *category { -1.0 } else { 0.0 }
+                            }));
+                            names.push(format!("{}_helmert{}", variable_name(variable_names, c), category));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if columns.is_empty() {
+        return Ok((DMatrix::<f64>::zeros(x.nrows(), 0), names));
+    }
+
+    Ok((DMatrix::<f64>::from_columns(&columns), names))
+}
+
+fn calc_residual_bin_means(fitted: &DVector<f64>, residuals: &DVector<f64>, w: &DVector<f64>, number_bins: usize) -> Vec<f64> {
+    let mut order : Vec<usize> = (0..fitted.len()).collect();
+    order.sort_by(|a, b| fitted[*a].partial_cmp(&fitted[*b]).unwrap());
+
+    // bins are weighted quantile groups of the fitted values
+    let sum_of_weights = w.sum();
+    let mut weighted_residual_sums = vec![0.0; number_bins];
+    let mut bin_weights = vec![0.0; number_bins];
+    let mut cumulated_weights = 0.0;
+    for r in order {
+        let bin = ((number_bins as f64 * cumulated_weights / sum_of_weights) as usize).min(number_bins - 1);
+        weighted_residual_sums[bin] += w[r] * residuals[r];
+        bin_weights[bin] += w[r];
+        cumulated_weights += w[r];
+    }
+
+    weighted_residual_sums.iter().zip(bin_weights.iter()).map(|(s, w)| if *w > 0.0 { s / w } else { f64::NAN }).collect()
+}
+
+pub fn poisson_regression(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_poisson_regression(x, wgt, &[])
+}
+
+pub(crate) fn named_poisson_regression(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "poisson_regression")?;
+    if x.ncols() == 0 {
+        return Err(ReplicestError::InvalidInput("no dependent variable in poisson_regression".to_string()));
+    }
+
+    let mut parameter_names = vec!["poisson_intercept".to_string()];
+    for c in 2..=x.ncols() {
+        parameter_names.push(format!("poisson_b_{}", variable_name(variable_names, c - 1)));
+    }
+    parameter_names.push("poisson_dispersion".to_string());
+
+    // listwise deletion of cases with missing values in any column
+    let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| x.row(r).iter().all(|v| !v.is_nan())).collect();
+    let y = DVector::<f64>::from_iterator(complete_rows.len(), complete_rows.iter().map(|&r| x[(r, 0)]));
+    let w = DVector::<f64>::from_iterator(complete_rows.len(), complete_rows.iter().map(|&r| wgt[r]));
+    let design = DMatrix::<f64>::from_fn(complete_rows.len(), x.ncols(), |r, c| if c == 0 { 1.0 } else { x[(complete_rows[r], c)] });
+
+    let mut coefficients = DVector::<f64>::zeros(x.ncols());
+    coefficients[0] = (y.dot(&w) / w.sum()).ln();
+
+    let mut converged = false;
+    for _ in 0..100 {
+        let linear_predictor = &design * &coefficients;
+        let fitted = linear_predictor.map(|v| v.exp());
+        let working_response = DVector::<f64>::from_fn(y.len(), |r, _| linear_predictor[r] + (y[r] - fitted[r]) / fitted[r]);
+        let working_weights = w.component_mul(&fitted);
+
+        let design_weighted = DMatrix::<f64>::from_fn(design.nrows(), design.ncols(), |r, c| design[(r, c)] * working_weights[r]);
+        let cross_product = design.transpose() * &design_weighted;
+        let new_coefficients = match cross_product.try_inverse() {
+            Some(inverse) => inverse * design_weighted.transpose() * working_response,
+            None => break,
+        };
+
+        let change = (&new_coefficients - &coefficients).amax();
+        coefficients = new_coefficients;
+        if change < 1e-10 {
+            converged = true;
+            break;
+        }
+    }
+
+    if !converged {
+        return Ok(Estimates {
+            parameter_names,
+            estimates: DVector::<f64>::from_element(x.ncols() + 1, f64::NAN),
+        })
+    }
+
+    let fitted = (&design * &coefficients).map(|v| v.exp());
+    let pearson_chi_square : f64 = (0..y.len()).map(|r| w[r] * (y[r] - fitted[r]).powi(2) / fitted[r]).sum();
+    let dispersion = pearson_chi_square / (w.sum() - x.ncols() as f64);
+
+    Ok(Estimates {
+        parameter_names,
+        estimates: coefficients.insert_row(x.ncols(), dispersion),
+    })
+}
+
+pub fn effect_size(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_effect_size(x, wgt, &[])
+}
+
+pub(crate) fn named_effect_size(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "effect_size")?;
+    if x.ncols() < 2 {
+        return Err(ReplicestError::InvalidInput("no outcome variable in effect_size".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    for c in 2..=x.ncols() {
+        parameter_names.push(format!("cohens_d_{}", variable_name(variable_names, c - 1)));
+        parameter_names.push(format!("hedges_g_{}", variable_name(variable_names, c - 1)));
+    }
+
+    // the first column defines the two groups; the effect is the second group (higher value) minus the first
+    let mut group_values : Vec<f64> = x.column(0).iter().filter(|v| !v.is_nan()).cloned().collect();
+    group_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    group_values.dedup();
+
+    if group_values.len() != 2 {
+        return Ok(Estimates {
+            parameter_names,
+            estimates: DVector::<f64>::from_element(2 * (x.ncols() - 1), f64::NAN),
+        })
+    }
+
+    let mut estimates = DVector::<f64>::zeros(2 * (x.ncols() - 1));
+
+    for c in 1..x.ncols() {
+        let mut sums_of_weights = [0.0; 2];
+        let mut weighted_sums = [0.0; 2];
+        let mut case_counts = [0.0; 2];
+
+        for r in 0..x.nrows() {
+            if x[(r, 0)].is_nan() || x[(r, c)].is_nan() {
+                continue;
+            }
+            let g = if x[(r, 0)] == group_values[0] { 0 } else { 1 };
+            sums_of_weights[g] += wgt[r];
+            weighted_sums[g] += wgt[r] * x[(r, c)];
+            case_counts[g] += 1.0;
+        }
+
+        let means = [weighted_sums[0] / sums_of_weights[0], weighted_sums[1] / sums_of_weights[1]];
+
+        let mut sum_of_squares = 0.0;
+        for r in 0..x.nrows() {
+            if x[(r, 0)].is_nan() || x[(r, c)].is_nan() {
+                continue;
+            }
+            let g = if x[(r, 0)] == group_values[0] { 0 } else { 1 };
+            sum_of_squares += wgt[r] * (x[(r, c)] - means[g]).powi(2);
+        }
+
+        let pooled_standard_deviation = (sum_of_squares / (sums_of_weights[0] + sums_of_weights[1] - 2.0)).sqrt();
+        let cohens_d = (means[1] - means[0]) / pooled_standard_deviation;
+        let small_sample_correction = 1.0 - 3.0 / (4.0 * (case_counts[0] + case_counts[1]) - 9.0);
+
+        estimates[2 * (c - 1)] = cohens_d;
+        estimates[2 * (c - 1) + 1] = cohens_d * small_sample_correction;
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn t_test(x: &DMatrix<f64>, wgt: &DVector<f64>, mu: f64) -> Result<Estimates, ReplicestError> {
+    named_t_test(x, wgt, mu, &[])
+}
+
+pub(crate) fn named_t_test(x: &DMatrix<f64>, wgt: &DVector<f64>, mu: f64, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "t_test")?;
+
+    let means = mean(x, wgt)?.estimates;
+
+    Ok(Estimates {
+        parameter_names: (0..x.ncols()).map(|c| format!("mean_diff_{}_{}", mu, variable_name(variable_names, c))).collect(),
+        estimates: means.map(|m| m - mu),
+    })
+}
+
+pub fn two_sample_t_test(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_two_sample_t_test(x, wgt, &[])
+}
+
+pub(crate) fn named_two_sample_t_test(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "two_sample_t_test")?;
+    if x.ncols() < 2 {
+        return Err(ReplicestError::InvalidInput("no outcome variable in two_sample_t_test".to_string()));
+    }
+
+    let parameter_names : Vec<String> = (1..x.ncols()).map(|c| format!("mean_diff_{}", variable_name(variable_names, c))).collect();
+
+    // the first column defines the two groups; the difference is the second group (higher value) minus the first
+    let mut group_values : Vec<f64> = x.column(0).iter().filter(|v| !v.is_nan()).cloned().collect();
+    group_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    group_values.dedup();
+
+    if group_values.len() != 2 {
+        return Ok(Estimates {
+            parameter_names,
+            estimates: DVector::<f64>::from_element(x.ncols() - 1, f64::NAN),
+        })
+    }
+
+    let mut estimates = DVector::<f64>::zeros(x.ncols() - 1);
+
+    for c in 1..x.ncols() {
+        let mut sums_of_weights = [0.0; 2];
+        let mut weighted_sums = [0.0; 2];
+
+        for r in 0..x.nrows() {
+            if x[(r, 0)].is_nan() || x[(r, c)].is_nan() {
+                continue;
+            }
+            let g = if x[(r, 0)] == group_values[0] { 0 } else { 1 };
+            sums_of_weights[g] += wgt[r];
+            weighted_sums[g] += wgt[r] * x[(r, c)];
+        }
+
+        estimates[c - 1] = weighted_sums[1] / sums_of_weights[1] - weighted_sums[0] / sums_of_weights[0];
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn eta_squared(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_eta_squared(x, wgt, &[])
+}
+
+pub(crate) fn named_eta_squared(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "eta_squared")?;
+    if x.ncols() < 2 {
+        return Err(ReplicestError::InvalidInput("no outcome variable in eta_squared".to_string()));
+    }
+
+    let parameter_names : Vec<String> = (1..x.ncols()).map(|c| format!("eta_squared_{}", variable_name(variable_names, c))).collect();
+    let mut estimates = DVector::<f64>::zeros(x.ncols() - 1);
+
+    // the first column defines the groups, all further columns are continuous outcomes
+    for c in 1..x.ncols() {
+        let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| !x[(r, 0)].is_nan() && !x[(r, c)].is_nan()).collect();
+        let sum_of_weights : f64 = complete_rows.iter().map(|&r| wgt[r]).sum();
+        let grand_mean = complete_rows.iter().map(|&r| wgt[r] * x[(r, c)]).sum::<f64>() / sum_of_weights;
+
+        let mut groups : Vec<f64> = complete_rows.iter().map(|&r| x[(r, 0)]).collect();
+        groups.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        groups.dedup();
+
+        let mut sums_of_weights = vec![0.0; groups.len()];
+        let mut weighted_sums = vec![0.0; groups.len()];
+        for &r in complete_rows.iter() {
+            let g = groups.iter().position(|&v| v == x[(r, 0)]).unwrap();
+            sums_of_weights[g] += wgt[r];
+            weighted_sums[g] += wgt[r] * x[(r, c)];
+        }
+
+        let sum_of_squares_between : f64 = (0..groups.len())
+            .filter(|&g| sums_of_weights[g] > 0.0)
+            .map(|g| sums_of_weights[g] * (weighted_sums[g] / sums_of_weights[g] - grand_mean).powi(2))
+            .sum();
+        let sum_of_squares_total : f64 = complete_rows.iter().map(|&r| wgt[r] * (x[(r, c)] - grand_mean).powi(2)).sum();
+
+        estimates[c - 1] = sum_of_squares_between / sum_of_squares_total;
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn mantel_haenszel_dif(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_mantel_haenszel_dif(x, wgt, &[])
+}
+
+pub(crate) fn named_mantel_haenszel_dif(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "mantel_haenszel_dif")?;
+    if x.ncols() < 2 {
+        return Err(ReplicestError::InvalidInput("no item in mantel_haenszel_dif".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    for c in 2..=x.ncols() {
+        parameter_names.push(format!("mh_odds_ratio_{}", variable_name(variable_names, c - 1)));
+        parameter_names.push(format!("mh_d_dif_{}", variable_name(variable_names, c - 1)));
+    }
+
+    let mut estimates = DVector::<f64>::from_element(2 * (x.ncols() - 1), f64::NAN);
+
+    // the first column defines the reference (lower value) and focal (higher value) group, all further columns are items scored 0/1
+    let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| x.row(r).iter().all(|v| !v.is_nan())).collect();
+    let mut group_values : Vec<f64> = complete_rows.iter().map(|&r| x[(r, 0)]).collect();
+    group_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    group_values.dedup();
+
+    if group_values.len() != 2 {
+        return Ok(Estimates {
+            parameter_names,
+            estimates,
+        })
+    }
+
+    // cases are matched on their total score across all items
+    let scores : Vec<f64> = complete_rows.iter().map(|&r| x.row(r).iter().skip(1).filter(|&&v| v > 0.0).count() as f64).collect();
+    let mut strata = scores.clone();
+    strata.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    strata.dedup();
+
+    for c in 1..x.ncols() {
+        let mut tables = vec![[[0.0; 2]; 2]; strata.len()];
+        for (i, &r) in complete_rows.iter().enumerate() {
+            let k = strata.iter().position(|&s| s == scores[i]).unwrap();
+            let g = if x[(r, 0)] == group_values[0] { 0 } else { 1 };
+            let correct = if x[(r, c)] > 0.0 { 1 } else { 0 };
+            tables[k][g][correct] += wgt[r];
+        }
+
+        let mut numerator = 0.0;
+        let mut denominator = 0.0;
+        for table in tables.iter() {
+            let total = table[0][0] + table[0][1] + table[1][0] + table[1][1];
+            if total > 0.0 {
+                numerator += table[0][1] * table[1][0] / total;
+                denominator += table[0][0] * table[1][1] / total;
+            }
+        }
+
+        let common_odds_ratio = numerator / denominator;
+        estimates[2 * (c - 1)] = common_odds_ratio;
+        estimates[2 * (c - 1) + 1] = -2.35 * common_odds_ratio.ln();
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn odds_ratio(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_odds_ratio(x, wgt, &[])
+}
+
+pub(crate) fn named_odds_ratio(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "odds_ratio")?;
+    if x.ncols() < 2 {
+        return Err(ReplicestError::InvalidInput("no outcome variable in odds_ratio".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    for c in 2..=x.ncols() {
+        parameter_names.push(format!("odds_ratio_{}", variable_name(variable_names, c - 1)));
+        parameter_names.push(format!("log_odds_ratio_{}", variable_name(variable_names, c - 1)));
+        parameter_names.push(format!("relative_risk_{}", variable_name(variable_names, c - 1)));
+        parameter_names.push(format!("log_relative_risk_{}", variable_name(variable_names, c - 1)));
+        parameter_names.push(format!("risk_difference_{}", variable_name(variable_names, c - 1)));
+    }
+
+    let mut estimates = DVector::<f64>::from_element(5 * (x.ncols() - 1), f64::NAN);
+
+    // the first column defines exposure (higher value) versus reference (lower value), outcome columns have the event as higher value
+    let mut exposure_values : Vec<f64> = x.column(0).iter().filter(|v| !v.is_nan()).cloned().collect();
+    exposure_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    exposure_values.dedup();
+
+    if exposure_values.len() != 2 {
+        return Ok(Estimates {
+            parameter_names,
+            estimates,
+        })
+    }
+
+    for c in 1..x.ncols() {
+        let mut outcome_values : Vec<f64> = x.column(c).iter().filter(|v| !v.is_nan()).cloned().collect();
+        outcome_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        outcome_values.dedup();
+
+        if outcome_values.len() != 2 {
+            continue;
+        }
+
+        let mut table = [[0.0; 2]; 2];
+        for r in 0..x.nrows() {
+            if x[(r, 0)].is_nan() || x[(r, c)].is_nan() {
+                continue;
+            }
+            let e = if x[(r, 0)] == exposure_values[0] { 0 } else { 1 };
+            let o = if x[(r, c)] == outcome_values[0] { 0 } else { 1 };
+            table[e][o] += wgt[r];
+        }
+
+        let odds_ratio = (table[1][1] * table[0][0]) / (table[1][0] * table[0][1]);
+        let risk_exposed = table[1][1] / (table[1][0] + table[1][1]);
+        let risk_reference = table[0][1] / (table[0][0] + table[0][1]);
+
+        estimates[5 * (c - 1)] = odds_ratio;
+        estimates[5 * (c - 1) + 1] = odds_ratio.ln();
+        estimates[5 * (c - 1) + 2] = risk_exposed / risk_reference;
+        estimates[5 * (c - 1) + 3] = (risk_exposed / risk_reference).ln();
+        estimates[5 * (c - 1) + 4] = risk_exposed - risk_reference;
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn gini(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_gini(x, wgt, &[])
+}
+
+pub(crate) fn named_gini(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "gini")?;
+
+    let mut estimates = DVector::<f64>::zeros(x.ncols());
+
+    for (c, column) in x.column_iter().enumerate() {
+        let mut values : Vec<(f64, f64)> = column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan()).map(|(&v, &w)| (v, w)).collect();
+        values.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let sum_of_weights : f64 = values.iter().map(|(_, w)| w).sum();
+        let mean = values.iter().map(|(v, w)| v * w).sum::<f64>() / sum_of_weights;
+
+        let mut cumulated_weights = 0.0;
+        let mut weighted_absolute_differences = 0.0;
+        for (v, w) in values {
+            weighted_absolute_differences += w * v * (2.0 * cumulated_weights + w - sum_of_weights);
+            cumulated_weights += w;
+        }
+
+        estimates[c] = weighted_absolute_differences / (sum_of_weights.powi(2) * mean);
+    }
+
+    Ok(Estimates {
+        parameter_names: (0..x.ncols()).map(|c| format!("gini_{}", variable_name(variable_names, c))).collect(),
+        estimates,
+    })
+}
+
+pub fn concentration(x: &DMatrix<f64>, wgt: &DVector<f64>, number_ordinates: usize) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "concentration")?;
+    if x.ncols() != 1 && x.ncols() != 2 {
+        return Err(ReplicestError::InvalidInput("concentration requires one or two columns".to_string()));
+    }
+    if number_ordinates == 0 {
+        return Err(ReplicestError::InvalidInput("no ordinates in concentration".to_string()));
+    }
+
+    let mut parameter_names = vec!["concentration_index".to_string()];
+    for k in 1..number_ordinates {
+        parameter_names.push(format!("lorenz_{}", k as f64 / number_ordinates as f64));
+    }
+
+    // the first column is the outcome, the optional second column is the ranking variable (defaults to the outcome)
+    let ranking_column = x.ncols() - 1;
+    let mut rows : Vec<usize> = (0..x.nrows()).filter(|&r| !x[(r, 0)].is_nan() && !x[(r, ranking_column)].is_nan() && wgt[r] > 0.0).collect();
+    rows.sort_by(|a, b| x[(*a, ranking_column)].partial_cmp(&x[(*b, ranking_column)]).unwrap());
+
+    let sum_of_weights : f64 = rows.iter().map(|&r| wgt[r]).sum();
+    let total : f64 = rows.iter().map(|&r| wgt[r] * x[(r, 0)]).sum();
+    let mean = total / sum_of_weights;
+
+    if rows.is_empty() {
+        return Ok(Estimates {
+            estimates: DVector::<f64>::from_element(parameter_names.len(), f64::NAN),
+            parameter_names,
+        })
+    }
+
+    let mut covariance = 0.0;
+    let mut cumulated_weights = 0.0;
+    for &r in rows.iter() {
+        let fractional_rank = (cumulated_weights + wgt[r] / 2.0) / sum_of_weights;
+        covariance += wgt[r] * (x[(r, 0)] - mean) * (fractional_rank - 0.5) / sum_of_weights;
+        cumulated_weights += wgt[r];
+    }
+
+    let values : Vec<f64> = rows.iter().map(|&r| x[(r, 0)]).collect();
+    let weights : Vec<f64> = rows.iter().map(|&r| wgt[r]).collect();
+    let population_shares : Vec<f64> = (1..number_ordinates).map(|k| k as f64 / number_ordinates as f64).collect();
+
+    let mut estimates = vec![2.0 * covariance / mean];
+    estimates.append(&mut calc_lorenz_ordinates(&values, &weights, &population_shares));
+
+    Ok(Estimates {
+        parameter_names,
+        estimates: DVector::from_vec(estimates),
+    })
+}
+
+pub fn percentile_shares(x: &DMatrix<f64>, wgt: &DVector<f64>, ranges: &[(f64, f64)]) -> Result<Estimates, ReplicestError> {
+    named_percentile_shares(x, wgt, ranges, &[])
+}
+
+pub(crate) fn named_percentile_shares(x: &DMatrix<f64>, wgt: &DVector<f64>, ranges: &[(f64, f64)], variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "percentile_shares")?;
+    if !ranges.iter().all(|(lower, upper)| 0.0 <= *lower && lower < upper && *upper <= 100.0) {
+        return Err(ReplicestError::InvalidInput("invalid percentile range in percentile_shares".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::from_element(x.ncols() * ranges.len(), f64::NAN);
+
+    for (c, column) in x.column_iter().enumerate() {
+        for (lower, upper) in ranges.iter() {
+            parameter_names.push(format!("share_{}_{}_{}", lower, upper, variable_name(variable_names, c)));
+        }
+
+        let mut rows : Vec<usize> = (0..x.nrows()).filter(|&r| !column[r].is_nan() && wgt[r] > 0.0).collect();
+        if rows.is_empty() {
+            continue;
+        }
+        rows.sort_by(|a, b| column[*a].partial_cmp(&column[*b]).unwrap());
+
+        let values : Vec<f64> = rows.iter().map(|&r| column[r]).collect();
+        let weights : Vec<f64> = rows.iter().map(|&r| wgt[r]).collect();
+        let population_shares : Vec<f64> = ranges.iter().flat_map(|(lower, upper)| [lower / 100.0, upper / 100.0]).collect();
+        let ordinates = calc_lorenz_ordinates(&values, &weights, &population_shares);
+
+        for i in 0..ranges.len() {
+            estimates[c * ranges.len() + i] = ordinates[2 * i + 1] - ordinates[2 * i];
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+fn calc_lorenz_ordinates(values: &[f64], weights: &[f64], population_shares: &[f64]) -> Vec<f64> {
+    let sum_of_weights : f64 = weights.iter().sum();
+    let total : f64 = values.iter().zip(weights.iter()).map(|(v, w)| v * w).sum();
+
+    // values have to be sorted; ordinates are interpolated linearly within the population share of each case
+    population_shares.iter().map(|share| {
+        let target_weight = share * sum_of_weights;
+        let mut cumulated_weights = 0.0;
+        let mut cumulated_total = 0.0;
+        for (value, weight) in values.iter().zip(weights.iter()) {
+            if cumulated_weights + weight >= target_weight {
+                return (cumulated_total + (target_weight - cumulated_weights) * value) / total;
+            }
+            cumulated_weights += weight;
+            cumulated_total += weight * value;
+        }
+        1.0
+    }).collect()
+}
+
+pub fn theil(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_theil(x, wgt, &[])
+}
+
+pub(crate) fn named_theil(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "theil")?;
+
+    let means = mean(x, wgt)?.estimates;
+    let mut estimates = DVector::<f64>::zeros(x.ncols());
+
+    for (c, column) in x.column_iter().enumerate() {
+        let mut sum_of_weights = 0.0;
+        let mut weighted_sum = 0.0;
+        for (&v, &w) in column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan()) {
+            sum_of_weights += w;
+            // zero values contribute nothing in the limit
+            if v != 0.0 {
+                weighted_sum += w * (v / means[c]) * (v / means[c]).ln();
+            }
+        }
+
+        estimates[c] = weighted_sum / sum_of_weights;
+    }
+
+    Ok(Estimates {
+        parameter_names: (0..x.ncols()).map(|c| format!("theil_{}", variable_name(variable_names, c))).collect(),
+        estimates,
+    })
+}
+
+pub fn atkinson(x: &DMatrix<f64>, wgt: &DVector<f64>, epsilon: f64) -> Result<Estimates, ReplicestError> {
+    named_atkinson(x, wgt, epsilon, &[])
+}
+
+pub(crate) fn named_atkinson(x: &DMatrix<f64>, wgt: &DVector<f64>, epsilon: f64, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "atkinson")?;
+
+    let means = mean(x, wgt)?.estimates;
+    let mut estimates = DVector::<f64>::zeros(x.ncols());
+
+    for (c, column) in x.column_iter().enumerate() {
+        let mut sum_of_weights = 0.0;
+        let mut weighted_sum = 0.0;
+        for (&v, &w) in column.iter().zip(wgt.iter()).filter(|(v, _)| !v.is_nan()) {
+            sum_of_weights += w;
+            weighted_sum += if epsilon == 1.0 { w * v.ln() } else { w * (v / means[c]).powf(1.0 - epsilon) };
+        }
+
+        estimates[c] = if epsilon == 1.0 {
+            1.0 - (weighted_sum / sum_of_weights).exp() / means[c]
+        } else {
+            1.0 - (weighted_sum / sum_of_weights).powf(1.0 / (1.0 - epsilon))
+        };
+    }
+
+    Ok(Estimates {
+        parameter_names: (0..x.ncols()).map(|c| format!("atkinson_{}_{}", epsilon, variable_name(variable_names, c))).collect(),
+        estimates,
+    })
+}
+
+pub fn correlation(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_correlation(x, wgt, &[])
+}
+
+pub(crate) fn named_correlation(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "correlation")?;
+
+    let means = mean(x, wgt)?.estimates;
+    let mut x_centered = DMatrix::<f64>::from_columns(
+        &Vec::from_iter(x.column_iter().enumerate().map(|(i, c)| c.clone_owned() - DVector::<f64>::from_element(c.nrows(), means[i])))
+    );
+
+    // take care of NaN by setting such values as well as such weights to zero
+    let mut weights_by_column : Vec<DVector<f64>> = Vec::new();
+    for i in 0..x_centered.ncols() {
+        weights_by_column.push(wgt.clone());
+        for j in 0..x_centered.nrows() {
+            if x_centered[(j, i)].is_nan() {
+                x_centered[(j, i)] = 0.0;
+                weights_by_column[i][j] = 0.0;
+            }
+        }
+    }
+    let weights_by_column_sum : Vec<f64> = weights_by_column.iter().map(|w| w.sum()).collect();
+
+    let x_centered_weighted = DMatrix::<f64>::from_columns(
+        &Vec::from_iter(x_centered.column_iter().map(|c| c.component_mul(wgt)))
+    );
+    let x_centered_transposed = x_centered.transpose();
+
+    let mut covariance_matrix = x_centered_transposed * x_centered_weighted;
+    for i in 0..covariance_matrix.nrows() {
+        for j in 0..covariance_matrix.ncols() {
+            covariance_matrix[(i, j)] /= weights_by_column_sum[i].min(weights_by_column_sum[j]) - 1.0;
+        }
+    }
+
+    let standard_deviations : Vec<f64> = covariance_matrix.diagonal().iter().map(|v| v.sqrt()).collect();
+    let mut standard_deviations_matrix_inverse = DMatrix::<f64>::zeros(standard_deviations.len(), standard_deviations.len());
+    for (i, standard_deviation) in standard_deviations.into_iter().enumerate() {
+        standard_deviations_matrix_inverse[(i,i)] = standard_deviation;
+    }
+    standard_deviations_matrix_inverse = standard_deviations_matrix_inverse.try_inverse()
+        .ok_or(ReplicestError::EstimationFailed("standard deviation matrix not invertible in correlation".to_string()))?;
+
+    let correlation_matrix = &standard_deviations_matrix_inverse * &covariance_matrix * &standard_deviations_matrix_inverse;
+
+    let mut estimates = covariance_matrix.extract_lower_triangle();
+    for correlation in correlation_matrix.extract_lower_triangle().iter() {
+         estimates = estimates.clone().insert_row(estimates.nrows(), *correlation);
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut parameter_names_correlation = Vec::<String>::new();
+    for i in 1..=x.ncols() {
+        for j in i..=x.ncols() {
+            parameter_names.push(format!("covariance_{}_{}", variable_name(variable_names, i - 1), variable_name(variable_names, j - 1)));
+            parameter_names_correlation.push(format!("correlation_{}_{}", variable_name(variable_names, i - 1), variable_name(variable_names, j - 1)));
+        }
+    }
+    parameter_names.append(&mut parameter_names_correlation);
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn polychoric_correlation(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_polychoric_correlation(x, wgt, &[])
+}
+
+pub(crate) fn named_polychoric_correlation(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "polychoric_correlation")?;
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = Vec::<f64>::new();
+
+    for i in 0..x.ncols() {
+        for j in i + 1..x.ncols() {
+            parameter_names.push(format!("polychoric_{}_{}", variable_name(variable_names, i), variable_name(variable_names, j)));
+
+            let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| !x[(r, i)].is_nan() && !x[(r, j)].is_nan()).collect();
+            let values_i : Vec<f64> = complete_rows.iter().map(|&r| x[(r, i)]).collect();
+            let values_j : Vec<f64> = complete_rows.iter().map(|&r| x[(r, j)]).collect();
+            let weights : Vec<f64> = complete_rows.iter().map(|&r| wgt[r]).collect();
+
+            let (categories_i, thresholds_i) = calc_ordinal_thresholds(&values_i, &weights);
+            let (categories_j, thresholds_j) = calc_ordinal_thresholds(&values_j, &weights);
+
+            if categories_i.len() < 2 || categories_j.len() < 2 {
+                estimates.push(f64::NAN);
+                continue;
+            }
+
+            let mut table = DMatrix::<f64>::zeros(categories_i.len(), categories_j.len());
+            for (r, weight) in weights.iter().enumerate() {
+                let a = categories_i.iter().position(|&c| c == values_i[r]).unwrap();
+                let b = categories_j.iter().position(|&c| c == values_j[r]).unwrap();
+                table[(a, b)] += weight;
+            }
+
+            let log_likelihood = |rho: f64| {
+                let mut log_likelihood = 0.0;
+                for a in 0..table.nrows() {
+                    for b in 0..table.ncols() {
+                        if table[(a, b)] == 0.0 {
+                            continue;
+                        }
+                        let probability = bivariate_normal_cdf(thresholds_i[a + 1], thresholds_j[b + 1], rho)
+                            - bivariate_normal_cdf(thresholds_i[a], thresholds_j[b + 1], rho)
+                            - bivariate_normal_cdf(thresholds_i[a + 1], thresholds_j[b], rho)
+                            + bivariate_normal_cdf(thresholds_i[a], thresholds_j[b], rho);
+                        log_likelihood += table[(a, b)] * probability.ln();
+                    }
+                }
+                log_likelihood
+            };
+
+            estimates.push(maximize_on_interval(log_likelihood, -0.999999, 0.999999));
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates: DVector::from_vec(estimates),
+    })
+}
+
+pub fn polyserial_correlation(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_polyserial_correlation(x, wgt, &[])
+}
+
+pub(crate) fn named_polyserial_correlation(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "polyserial_correlation")?;
+    if x.ncols() < 2 {
+        return Err(ReplicestError::InvalidInput("no ordinal variable in polyserial_correlation".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut estimates = DVector::<f64>::zeros(x.ncols() - 1);
+
+    // the first column is the continuous variable, all further columns are ordinal
+    for j in 1..x.ncols() {
+        parameter_names.push(format!("polyserial_{}_{}", variable_name(variable_names, 0), variable_name(variable_names, j)));
+
+        let complete_rows : Vec<usize> = (0..x.nrows()).filter(|&r| !x[(r, 0)].is_nan() && !x[(r, j)].is_nan()).collect();
+        let values_continuous : Vec<f64> = complete_rows.iter().map(|&r| x[(r, 0)]).collect();
+        let values_ordinal : Vec<f64> = complete_rows.iter().map(|&r| x[(r, j)]).collect();
+        let weights : Vec<f64> = complete_rows.iter().map(|&r| wgt[r]).collect();
+
+        let (categories, thresholds) = calc_ordinal_thresholds(&values_ordinal, &weights);
+        if categories.len() < 2 {
+            estimates[j - 1] = f64::NAN;
+            continue;
+        }
+
+        let sum_of_weights : f64 = weights.iter().sum();
+        let mean = values_continuous.iter().zip(weights.iter()).map(|(v, w)| v * w).sum::<f64>() / sum_of_weights;
+        let standard_deviation = (values_continuous.iter().zip(weights.iter()).map(|(v, w)| w * (v - mean).powi(2)).sum::<f64>() / sum_of_weights).sqrt();
+        let standardized : Vec<f64> = values_continuous.iter().map(|v| (v - mean) / standard_deviation).collect();
+        let category_indices : Vec<usize> = values_ordinal.iter().map(|v| categories.iter().position(|c| c == v).unwrap()).collect();
+
+        let log_likelihood = |rho: f64| {
+            let scale = (1.0 - rho * rho).sqrt();
+            let mut log_likelihood = 0.0;
+            for (r, weight) in weights.iter().enumerate() {
+                if *weight == 0.0 {
+                    continue;
+                }
+                let k = category_indices[r];
+                let probability = normal_cdf((thresholds[k + 1] - rho * standardized[r]) / scale) - normal_cdf((thresholds[k] - rho * standardized[r]) / scale);
+                log_likelihood += weight * probability.ln();
+            }
+            log_likelihood
+        };
+
+        estimates[j - 1] = maximize_on_interval(log_likelihood, -0.999999, 0.999999);
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+pub fn principal_components(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Result<Estimates, ReplicestError> {
+    named_principal_components(x, wgt, &[])
+}
+
+pub(crate) fn named_principal_components(x: &DMatrix<f64>, wgt: &DVector<f64>, variable_names: &[String]) -> Result<Estimates, ReplicestError> {
+    check_weights(x, wgt, "principal_components")?;
+
+    let number_variables = x.ncols();
+    let correlations = correlation(x, wgt)?.estimates;
+    let offset = number_variables * (number_variables + 1) / 2;
+
+    let mut correlation_matrix = DMatrix::<f64>::zeros(number_variables, number_variables);
+    let mut index = offset;
+    for i in 0..number_variables {
+        for j in i..number_variables {
+            correlation_matrix[(i, j)] = correlations[index];
+            correlation_matrix[(j, i)] = correlations[index];
+            index += 1;
+        }
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    for k in 1..=number_variables {
+        parameter_names.push(format!("eigenvalue_pc{}", k));
+    }
+    for k in 1..=number_variables {
+        for i in 1..=number_variables {
+            parameter_names.push(format!("loading_pc{}_{}", k, variable_name(variable_names, i - 1)));
+        }
+    }
+
+    if correlation_matrix.iter().any(|v| v.is_nan()) {
+        return Ok(Estimates {
+            parameter_names,
+            estimates: DVector::from_element(number_variables * (number_variables + 1), f64::NAN),
+        });
+    }
+
+    let eigen = correlation_matrix.symmetric_eigen();
+    let mut order : Vec<usize> = (0..number_variables).collect();
+    order.sort_by(|a, b| eigen.eigenvalues[*b].partial_cmp(&eigen.eigenvalues[*a]).unwrap());
+
+    let mut estimates = DVector::<f64>::zeros(number_variables * (number_variables + 1));
+    for (k, &component) in order.iter().enumerate() {
+        let eigenvalue = eigen.eigenvalues[component];
+        estimates[k] = eigenvalue;
+
+        // fix the arbitrary sign of the eigenvector so that loadings are comparable across replicates
+        let eigenvector = eigen.eigenvectors.column(component);
+        let largest = eigenvector.iter().fold(0.0, |acc : f64, v| if v.abs() > acc.abs() { *v } else { acc });
+        let sign = if largest < 0.0 { -1.0 } else { 1.0 };
+
+        for i in 0..number_variables {
+            estimates[number_variables + k * number_variables + i] = sign * eigenvector[i] * eigenvalue.max(0.0).sqrt();
+        }
+    }
+
+    Ok(Estimates {
+        parameter_names,
+        estimates,
+    })
+}
+
+fn calc_ordinal_thresholds(values: &[f64], weights: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let mut categories = values.to_vec();
+    categories.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    categories.dedup();
+
+    let sum_of_weights : f64 = weights.iter().sum();
+    let mut thresholds = vec![f64::NEG_INFINITY];
+    let mut cumulated_weights = 0.0;
+    for category in categories.iter().take(categories.len().saturating_sub(1)) {
+        cumulated_weights += values.iter().zip(weights.iter()).filter(|(v, _)| *v == category).map(|(_, w)| w).sum::<f64>();
+        thresholds.push(normal_quantile(cumulated_weights / sum_of_weights));
+    }
+    thresholds.push(f64::INFINITY);
+
+    (categories, thresholds)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{dmatrix, dvector};
+    use rand::prelude::*;
+    use crate::assert_approx_eq_iter_f64;
+    use super::*;
+
+    #[test]
+    fn test_mean() {
+        let data = DMatrix::from_row_slice(3, 4, &[
+            1.0, 4.0, 2.5, -1.0,
+            2.5, 1.75, 4.0, -2.5,
+            3.0, 3.0, 1.0, -3.5,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        let result = mean(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(result.parameter_names[1], "mean_x2");
+        assert_eq!(result.estimates, dvector![2.25, 3.125, 2.0, -2.5]);
+    }
+
+    #[test]
+    fn test_mean_error_dimension_mismatch() {
+        let data = DMatrix::from_row_slice(2, 3, &[
+            1.0, 4.0, 2.5,
+            2.5, 1.75, 4.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        assert_eq!("Invalid input: dimension mismatch of x and wgt in mean", mean(&data, &wgt).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_mean_error_wgt_containing_nan() {
+        let data = DMatrix::from_row_slice(3, 3, &[
+            1.0, 4.0, 2.5,
+            2.5, 1.75, 4.0,
+            3.0, 3.0, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, f64::NAN];
+
+        assert_eq!("Invalid input: wgt contains NaN in mean", mean(&data, &wgt).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_mean_with_nan() {
+        let data = DMatrix::from_row_slice(3, 3, &[
+            1.0, 4.0, 2.5,
+            2.5, 1.75, f64::NAN,
+            3.0, 3.0, 1.5,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        let result = mean(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 3);
+        assert_eq!(result.parameter_names[2], "mean_x3");
+        assert_eq!(result.estimates, dvector![2.25, 3.125, 1.9]);
+    }
+
+    #[test]
+    fn test_mean_all_nan() {
+        let data = DMatrix::from_row_slice(3, 1, &[
+            f64::NAN,
+            f64::NAN,
+            f64::NAN,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        let result = mean(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 1);
+        assert_eq!(result.parameter_names[0], "mean_x1");
+        assert_eq!(true, result.estimates[0].is_nan());
+    }
+
+    #[test]
+    fn test_total() {
+        let data = DMatrix::from_row_slice(3, 3, &[
+            1.0, 4.0, 2.5,
+            2.5, 1.75, f64::NAN,
+            3.0, 3.0, 1.5,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        let result = total(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 3);
+        assert_eq!(result.parameter_names[2], "total_x3");
+        assert_eq!(result.estimates, dvector![6.75, 9.375, 4.75]);
+    }
+
+    #[test]
+    fn test_standardized_mean() {
+        let data = DMatrix::from_row_slice(3, 2, &[
+            1.0, 4.0,
+            2.5, f64::NAN,
+            3.0, 3.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        let result = standardized_mean(&data, &wgt, &[2.0, 3.0], &[0.5, 2.0]).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_eq!(result.parameter_names[1], "standardized_mean_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.5, 0.2]);
+    }
+
+    #[test]
+    fn test_standardized_group_mean() {
+        let data = DMatrix::from_row_slice(4, 2, &[
+            1.0, 1.0,
+            3.0, 1.0,
+            5.0, 0.0,
+            7.0, 0.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+
+        let result = standardized_group_mean(&data, &wgt).unwrap();
+        assert_eq!(vec!["standardized_mean_x1".to_string()], result.parameter_names);
+        assert_approx_eq_iter_f64!(result.estimates, dvector![-2.0 / (20.0f64 / 3.0).sqrt()]);
+
+        // the reference follows the weights
+        let wgt = dvector![1.0, 1.0, 1.0, 0.0];
+        let result = standardized_group_mean(&data, &wgt).unwrap();
+        assert_approx_eq_iter_f64!(result.estimates, dvector![-1.0 / 4.0f64.sqrt()]);
+
+        assert_eq!("Invalid input: x lacks the group column in standardized_group_mean", standardized_group_mean(&data.columns(0, 1).clone_owned(), &wgt).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_standardized_mean_error_reference_mismatch() {
+        let data = DMatrix::from_row_slice(2, 2, &[
+            1.0, 4.0,
+            2.5, 1.75,
+        ]);
+
+        let wgt = dvector![1.0, 0.5];
+
+        assert_eq!("Invalid input: dimension mismatch of x and reference means in standardized_mean", standardized_mean(&data, &wgt, &[2.0], &[0.5, 2.0]).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_range() {
+        let data = DMatrix::from_row_slice(4, 3, &[
+            1.0, f64::NAN, 2.5,
+            2.5, 1.75, f64::NAN,
+            -4.0, f64::NAN, 1.5,
+            3.0, f64::NAN, 1.5,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 0.0, 2.0];
+
+        let result = range(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 9);
+        assert_eq!(result.parameter_names[0], "minimum_x1");
+        assert_eq!(result.parameter_names[4], "maximum_x2");
+        assert_eq!(result.parameter_names[8], "range_x3");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![1.0, 1.75, 1.5, 3.0, 1.75, 2.5, 2.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_range_all_missing() {
+        let data = DMatrix::from_row_slice(2, 1, &[
+            f64::NAN,
+            f64::NAN,
+        ]);
+
+        let wgt = dvector![1.0, 0.5];
+
+        let result = range(&data, &wgt).unwrap();
+        assert!(result.estimates.iter().all(|e| e.is_nan()));
+    }
+
+    #[test]
+    fn test_linreg() {
+        let data = DMatrix::from_row_slice(7, 2, &[
+            3.1, 1.0,
+            4.9, 2.0,
+            7.2, 3.0,
+            8.8, 4.0,
+            f64::NAN, 4.5,
+            11.1, 5.0,
+            13.0, 6.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.0, 0.5, 1.5];
+
+        let result = linreg(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(result.parameter_names[1], "linreg_b_x2");
+        assert_eq!(result.parameter_names[3], "linreg_sigma");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![1.0056198347107443, 1.9961983471074378, 0.9986738844502107, 0.1512154611885943]);
+    }
+
+    #[test]
+    fn test_linreg_with_residual_diagnostics() {
+        let data = DMatrix::from_row_slice(6, 2, &[
+            3.1, 1.0,
+            4.9, 2.0,
+            7.2, 3.0,
+            8.8, 4.0,
+            11.1, 5.0,
+            13.0, 6.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 0.5, 1.5];
+
+        let options = LinregOptions { residual_diagnostics: true, number_residual_bins: 2, ..Default::default() };
+        let result = linreg_with_options(&data, &wgt, &options).unwrap();
+        assert_eq!(result.parameter_names.len(), 8);
+        assert_eq!(result.parameter_names[4], "linreg_rmse");
+        assert_eq!(result.parameter_names[6], "linreg_mean_residual_bin2");
+        assert_eq!(result.parameter_names[7], "linreg_durbin_watson");
+        assert_approx_eq_iter_f64!(result.estimates.rows(4, 4), dvector![0.12780039040427535, 0.026983471074380483, -0.035977961432506866, 3.3786679602416827]);
+    }
+
+    #[test]
+    fn test_linreg_with_categorical_predictor() {
+        let data = DMatrix::from_row_slice(6, 3, &[
+            2.0, 1.0, 0.5,
+            4.0, 1.0, 1.5,
+            5.0, 2.0, 0.5,
+            7.0, 2.0, 1.5,
+            10.0, 3.0, 1.0,
+            8.0, f64::NAN, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+
+        let options = LinregOptions { categorical_predictors: vec![CategoricalPredictor { column: 1, reference_category: 2.0, contrast: Contrast::Treatment }], ..Default::default() };
+        let result = linreg_with_options(&data, &wgt, &options).unwrap();
+        assert_eq!(result.parameter_names.len(), 6);
+        assert_eq!(result.parameter_names[1], "linreg_b_x2_cat1");
+        assert_eq!(result.parameter_names[2], "linreg_b_x2_cat3");
+        assert_eq!(result.parameter_names[3], "linreg_b_x3");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 4), dvector![4.0, -3.0, 4.0, 2.0]);
+    }
+
+    #[test]
+    fn test_linreg_with_sum_and_helmert_contrasts() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            2.0, 1.0,
+            4.0, 1.0,
+            5.0, 2.0,
+            7.0, 2.0,
+            10.0, 3.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0];
+
+        let options = LinregOptions { categorical_predictors: vec![CategoricalPredictor { column: 1, reference_category: 3.0, contrast: Contrast::Sum }], ..Default::default() };
+        let result = linreg_with_options(&data, &wgt, &options).unwrap();
+        assert_eq!(result.parameter_names[1], "linreg_b_x2_sum1");
+        assert_eq!(result.parameter_names[2], "linreg_b_x2_sum2");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 3), dvector![19.0 / 3.0, -10.0 / 3.0, -1.0 / 3.0]);
+
+        let options = LinregOptions { categorical_predictors: vec![CategoricalPredictor { column: 1, reference_category: 1.0, contrast: Contrast::Helmert }], ..Default::default() };
+        let result = linreg_with_options(&data, &wgt, &options).unwrap();
+        assert_eq!(result.parameter_names[1], "linreg_b_x2_helmert2");
+        assert_eq!(result.parameter_names[2], "linreg_b_x2_helmert3");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 3), dvector![19.0 / 3.0, 1.5, 11.0 / 6.0]);
+    }
+
+    #[test]
+    fn test_linreg_error_categorical_dependent_variable() {
+        let data = DMatrix::from_row_slice(2, 2, &[
+            2.0, 1.0,
+            4.0, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0];
+
+        let options = LinregOptions { categorical_predictors: vec![CategoricalPredictor { column: 0, reference_category: 2.0, contrast: Contrast::Treatment }], ..Default::default() };
+        assert_eq!("Invalid input: invalid categorical predictor column in linreg", linreg_with_options(&data, &wgt, &options).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_linreg_error_absent_reference_category() {
+        let data = DMatrix::from_row_slice(4, 2, &[
+            2.0, 1.0,
+            4.0, 1.0,
+            5.0, 2.0,
+            7.0, 2.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+
+        for contrast in [Contrast::Treatment, Contrast::Sum] {
+            let options = LinregOptions { categorical_predictors: vec![CategoricalPredictor { column: 1, reference_category: 3.0, contrast }], ..Default::default() };
+            assert_eq!("Invalid input: reference category 3 of x2 does not occur in linreg", linreg_with_options(&data, &wgt, &options).unwrap_err().to_string());
+        }
+    }
+
+    #[test]
+    fn test_linreg_with_selected_columns() {
+        let data = DMatrix::from_row_slice(5, 3, &[
+            1.0, 2.0, 7.0,
+            2.0, 4.0, 1.0,
+            3.0, 5.0, 2.0,
+            4.0, 7.0, 9.0,
+            5.0, 10.0, 3.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0];
+
+        let options = LinregOptions { dependent: Some(1), predictors: Some(vec![0]), ..Default::default() };
+        let result = linreg_with_options(&data, &wgt, &options).unwrap();
+        assert_eq!(result.parameter_names, vec!["linreg_x2_intercept", "linreg_x2_b_x1", "linreg_x2_r2", "linreg_x2_sigma"]);
+
+        let reordered_data = DMatrix::from_columns(&[data.column(1), data.column(0)]);
+        let reference = linreg(&reordered_data, &wgt).unwrap();
+        assert_approx_eq_iter_f64!(result.estimates, reference.estimates);
+
+        let options = LinregOptions { dependent: Some(1), ..Default::default() };
+        let result = linreg_with_options(&data, &wgt, &options).unwrap();
+        assert_eq!(result.parameter_names[1], "linreg_x2_b_x1");
+        assert_eq!(result.parameter_names[2], "linreg_x2_b_x3");
+    }
+
+    #[test]
+    fn test_linreg_error_dependent_variable_as_predictor() {
+        let data = DMatrix::from_row_slice(2, 2, &[
+            2.0, 1.0,
+            4.0, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0];
+
+        let options = LinregOptions { dependent: Some(1), predictors: Some(vec![0, 1]), ..Default::default() };
+        assert_eq!("Invalid input: invalid predictor column in linreg", linreg_with_options(&data, &wgt, &options).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_linreg_singular() {
+        let data = DMatrix::from_row_slice(3, 2, &[
+            3.1, 1.0,
+            4.9, 1.0,
+            7.2, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0];
+
+        let result = linreg(&data, &wgt).unwrap();
+        assert_eq!(result.estimates.len(), 4);
+        assert!(result.estimates.iter().all(|e| e.is_nan()));
+    }
+
+    #[test]
+    fn test_frequencies() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            1.0, 2.0,
+            2.0, f64::NAN,
+            1.0, 3.0,
+            3.0, 2.0,
+            f64::NAN, 2.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5, 2.0, 1.0];
+
+        let result = frequencies(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 5);
+        assert_eq!(result.parameter_names[0], "frequency_x1_cat1");
+        assert_eq!(result.parameter_names[4], "frequency_x2_cat3");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![2.5, 0.5, 2.0, 4.0, 1.5]);
+    }
+
+    #[test]
+    fn test_frequencies_with_percentages_and_expected_categories() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            1.0, 2.0,
+            2.0, f64::NAN,
+            1.0, 3.0,
+            3.0, 2.0,
+            f64::NAN, 2.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5, 2.0, 1.0];
+
+        let options = FrequenciesOptions { percentages: true, expected_categories: Some(vec![1.0, 2.0, 4.0]) };
+        let result = frequencies_with_options(&data, &wgt, &options).unwrap();
+        assert_eq!(result.parameter_names.len(), 12);
+        assert_eq!(result.parameter_names[2], "frequency_x1_cat4");
+        assert_eq!(result.parameter_names[6], "percentage_x1_cat1");
+        assert_eq!(result.parameter_names[11], "percentage_x2_cat4");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![2.5, 0.5, 0.0, 0.0, 4.0, 0.0, 250.0 / 3.0, 50.0 / 3.0, 0.0, 0.0, 100.0, 0.0]);
+    }
+
+    #[test]
+    fn test_accumulators_match_estimators() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            1.0, 2.0,
+            2.0, f64::NAN,
+            1.0, 3.0,
+            3.0, 2.0,
+            f64::NAN, 2.0,
+        ]);
+        let wgt = dvector![1.0, 0.5, 1.5, 2.0, 1.0];
+        let options = FrequenciesOptions { percentages: true, expected_categories: None };
+
+        let mut mean_accumulator = MeanAccumulator::default();
+        let mut total_accumulator = TotalAccumulator::default();
+        let mut frequencies_accumulator = FrequenciesAccumulator::new(&options);
+        for (start, length) in [(0, 2), (2, 1), (3, 2)] {
+            let x_chunk = data.rows(start, length).clone_owned();
+            let wgt_chunk = wgt.rows(start, length).clone_owned();
+            mean_accumulator.accumulate(&x_chunk, &wgt_chunk).unwrap();
+            total_accumulator.accumulate(&x_chunk, &wgt_chunk).unwrap();
+            frequencies_accumulator.accumulate(&x_chunk, &wgt_chunk).unwrap();
+        }
+
+        let result = mean_accumulator.estimates();
+        assert_eq!(result.parameter_names, mean(&data, &wgt).unwrap().parameter_names);
+        assert_approx_eq_iter_f64!(result.estimates, mean(&data, &wgt).unwrap().estimates);
+
+        let result = total_accumulator.estimates();
+        assert_eq!(result.parameter_names, total(&data, &wgt).unwrap().parameter_names);
+        assert_approx_eq_iter_f64!(result.estimates, total(&data, &wgt).unwrap().estimates);
+
+        let result = frequencies_accumulator.estimates();
+        assert_eq!(result.parameter_names, frequencies_with_options(&data, &wgt, &options).unwrap().parameter_names);
+        assert_approx_eq_iter_f64!(result.estimates, frequencies_with_options(&data, &wgt, &options).unwrap().estimates);
+    }
+
+    #[test]
+    fn test_frequencies_accumulator_with_expected_categories() {
+        let data = DMatrix::from_row_slice(4, 1, &[3.0, 1.0, 5.0, 1.0]);
+        let wgt = dvector![1.0, 0.5, 1.5, 2.0];
+        let options = FrequenciesOptions { percentages: false, expected_categories: Some(vec![3.0, 1.0]) };
+
+        let mut accumulator = FrequenciesAccumulator::new(&options);
+        accumulator.accumulate(&data.rows(0, 2).clone_owned(), &wgt.rows(0, 2).clone_owned()).unwrap();
+        accumulator.accumulate(&data.rows(2, 2).clone_owned(), &wgt.rows(2, 2).clone_owned()).unwrap();
+
+        let result = accumulator.estimates();
+        assert_eq!(result.parameter_names, vec!["frequency_x1_cat3", "frequency_x1_cat1"]);
+        assert_eq!(result.estimates, dvector![1.0, 2.5]);
+    }
+
+    #[test]
+    fn test_mean_accumulator_inconsistent_columns() {
+        let mut accumulator = MeanAccumulator::default();
+        accumulator.accumulate(&DMatrix::from_element(2, 2, 1.0), &dvector![1.0, 1.0]).unwrap();
+        assert_eq!("Invalid input: inconsistent number of columns in MeanAccumulator", accumulator.accumulate(&DMatrix::from_element(2, 3, 1.0), &dvector![1.0, 1.0]).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_crosstab() {
+        let data = DMatrix::from_row_slice(6, 2, &[
+            1.0, 1.0,
+            1.0, 2.0,
+            2.0, 1.0,
+            2.0, 2.0,
+            2.0, 2.0,
+            f64::NAN, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 0.5, 0.5, 3.0];
+
+        let result = crosstab(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 9);
+        assert_eq!(result.parameter_names[1], "crosstab_x1_1_x2_2");
+        assert_eq!(result.parameter_names[4], "crosstab_x1_1");
+        assert_eq!(result.parameter_names[7], "crosstab_x2_2");
+        assert_eq!(result.parameter_names[8], "crosstab_n");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.2, 0.4, 0.2, 0.2, 0.6, 0.4, 0.4, 0.6, 5.0]);
+    }
+
+    #[test]
+    fn test_crosstab_error_number_of_columns() {
+        let data = DMatrix::from_row_slice(2, 1, &[
+            1.0,
+            2.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0];
+
+        assert_eq!("Invalid input: crosstab requires exactly two columns", crosstab(&data, &wgt).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_t_test() {
+        let data = DMatrix::from_row_slice(3, 2, &[
+            1.0, 4.0,
+            2.5, f64::NAN,
+            3.0, 3.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5];
+
+        let result = t_test(&data, &wgt, 2.5).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_eq!(result.parameter_names[1], "mean_diff_2.5_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![-0.25, 0.9]);
     }
-}
 
-pub fn mean(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Estimates {
-    assert_eq!(x.nrows(), wgt.len(), "dimension mismatch of x and wgt in mean");
-    assert_eq!(0, wgt.iter().filter(|e| e.is_nan()).count(), "wgt contains NaN in mean");
+    #[test]
+    fn test_two_sample_t_test() {
+        let data = DMatrix::from_row_slice(6, 3, &[
+            1.0, 4.0, 1.0,
+            1.0, 2.0, f64::NAN,
+            2.0, 3.0, 2.0,
+            2.0, 5.0, 4.0,
+            f64::NAN, 9.0, 9.0,
+            1.0, 3.0, 3.0,
+        ]);
 
-    let x_transpose = x.transpose();
-    let x_transpose_clean : DMatrix<f64> = x_transpose.map(|e| if e.is_nan() { 0.0_f64 } else { e });
-    let x_transpose_ind : DMatrix<f64> = x_transpose.map(|e| if e.is_nan() { 0.0_f64 } else { 1.0_f64 });
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.0, 1.0];
 
-    let weighted_sums = x_transpose_clean * wgt;
-    let sum_of_weights = x_transpose_ind * wgt;
+        let result = two_sample_t_test(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_eq!(result.parameter_names[0], "mean_diff_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![1.25, 1.0]);
 
-    Estimates {
-        parameter_names: (1..=x.ncols()).into_iter().map(|e| format!("mean_x{}", e)).collect(),
-        estimates: weighted_sums.component_div(&sum_of_weights),
+        let result = two_sample_t_test(&data.rows(0, 2).clone_owned(), &wgt.rows(0, 2).clone_owned()).unwrap();
+        assert!(result.estimates.iter().all(|e| e.is_nan()));
     }
-}
 
-pub fn correlation(x: &DMatrix<f64>, wgt: &DVector<f64>) -> Estimates {
-    assert_eq!(x.nrows(), wgt.len(), "dimension mismatch of x and wgt in correlation");
-    assert_eq!(0, wgt.iter().filter(|e| e.is_nan()).count(), "wgt contains NaN in correlation");
+    #[test]
+    fn test_eta_squared() {
+        let data = DMatrix::from_row_slice(7, 3, &[
+            1.0, 1.0, 2.0,
+            1.0, 3.0, 2.0,
+            2.0, 4.0, 2.0,
+            2.0, 6.0, f64::NAN,
+            3.0, 7.0, 2.0,
+            3.0, 9.0, 2.0,
+            f64::NAN, 100.0, 1.0,
+        ]);
 
-    let means = mean(&x, &wgt).estimates;
-    let mut x_centered = DMatrix::<f64>::from_columns(
-        &Vec::from_iter(x.column_iter().enumerate().map(|(i, c)| c.clone_owned() - DVector::<f64>::from_element(c.nrows(), means[i])))
-    );
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
 
-    // take care of NaN by setting such values as well as such weights to zero
-    let mut weights_by_column : Vec<DVector<f64>> = Vec::new();
-    for i in 0..x_centered.ncols() {
-        weights_by_column.push(wgt.clone());
-        for j in 0..x_centered.nrows() {
-            if x_centered[(j, i)].is_nan() {
-                x_centered[(j, i)] = 0.0;
-                weights_by_column[i][j] = 0.0;
-            }
-        }
+        let result = eta_squared(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_eq!(result.parameter_names[0], "eta_squared_x2");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 1), dvector![36.0 / 42.0]);
+        assert!(result.estimates[1].is_nan());
     }
-    let weights_by_column_sum : Vec<f64> = weights_by_column.iter().map(|w| w.sum()).collect();
 
-    let x_centered_weighted = DMatrix::<f64>::from_columns(
-        &Vec::from_iter(x_centered.column_iter().map(|c| c.component_mul(wgt)))
-    );
-    let x_centered_transposed = x_centered.transpose();
+    #[test]
+    fn test_mantel_haenszel_dif() {
+        let data = DMatrix::from_row_slice(13, 4, &[
+            0.0, 1.0, 1.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 1.0, 1.0, 1.0,
+            0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 1.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            1.0, 0.0, 1.0, 0.0,
+            1.0, 1.0, 1.0, 1.0,
+            1.0, 0.0, 0.0, 0.0,
+            1.0, 1.0, 0.0, 0.0,
+            1.0, 0.0, 1.0, 1.0,
+            1.0, 1.0, 0.0, 1.0,
+            1.0, f64::NAN, 1.0, 1.0,
+        ]);
 
-    let mut covariance_matrix = x_centered_transposed * x_centered_weighted;
-    for i in 0..covariance_matrix.nrows() {
-        for j in 0..covariance_matrix.ncols() {
-            covariance_matrix[(i, j)] /= weights_by_column_sum[i].min(weights_by_column_sum[j]) - 1.0;
-        }
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.5, 1.0, 1.0, 1.0, 2.0, 1.0, 1.0, 0.5, 1.0];
+
+        let result = mantel_haenszel_dif(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 6);
+        assert_eq!(result.parameter_names[0], "mh_odds_ratio_x2");
+        assert_eq!(result.parameter_names[3], "mh_d_dif_x3");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 5), dvector![5.125, -3.8402067338075088, 1.28125, -0.5824149851757655, 0.0]);
+        assert!(result.estimates[5].is_infinite());
     }
 
-    let standard_deviations : Vec<f64> = covariance_matrix.diagonal().iter().map(|v| v.sqrt()).collect();
-    let mut standard_deviations_matrix_inverse = DMatrix::<f64>::zeros(standard_deviations.len(), standard_deviations.len());
-    for (i, standard_deviation) in standard_deviations.into_iter().enumerate() {
-        standard_deviations_matrix_inverse[(i,i)] = standard_deviation;
+    #[test]
+    fn test_odds_ratio() {
+        let data = DMatrix::from_row_slice(8, 3, &[
+            0.0, 1.0, 5.0,
+            0.0, 0.0, 5.0,
+            0.0, 0.0, 5.0,
+            1.0, 1.0, 5.0,
+            1.0, 1.0, 5.0,
+            1.0, 0.0, 5.0,
+            1.0, f64::NAN, 5.0,
+            f64::NAN, 1.0, 5.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 2.0, 1.0, 2.0, 1.0, 1.0, 1.0];
+
+        let result = odds_ratio(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 10);
+        assert_eq!(result.parameter_names[1], "log_odds_ratio_x2");
+        assert_eq!(result.parameter_names[4], "risk_difference_x2");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 5), dvector![9.0, 9.0_f64.ln(), 3.0, 3.0_f64.ln(), 0.5]);
+        assert!(result.estimates.rows(5, 5).iter().all(|e| e.is_nan()));
     }
-    standard_deviations_matrix_inverse = standard_deviations_matrix_inverse.try_inverse().unwrap_or_else(|| panic!("standard deviation matrix not invertible"));
 
-    let correlation_matrix = &standard_deviations_matrix_inverse * &covariance_matrix * &standard_deviations_matrix_inverse;
+    #[test]
+    fn test_concentration() {
+        let data = DMatrix::from_row_slice(5, 1, &[
+            3.0,
+            1.0,
+            f64::NAN,
+            4.0,
+            2.0,
+        ]);
 
-    let mut estimates = covariance_matrix.extract_lower_triangle();
-    for correlation in correlation_matrix.extract_lower_triangle().iter() {
-         estimates = estimates.clone().insert_row(estimates.nrows(), correlation.clone());
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0];
+
+        let result = concentration(&data, &wgt, 4).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(result.parameter_names[0], "concentration_index");
+        assert_eq!(result.parameter_names[1], "lorenz_0.25");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.25, 0.1, 0.3, 0.6]);
     }
 
-    let mut parameter_names = Vec::<String>::new();
-    let mut parameter_names_correlation = Vec::<String>::new();
-    for i in 1..=x.ncols() {
-        for j in i..=x.ncols() {
-            parameter_names.push(format!("covariance_x{}_x{}", i, j));
-            parameter_names_correlation.push(format!("correlation_x{}_x{}", i, j));
-        }
+    #[test]
+    fn test_concentration_with_ranking_variable() {
+        let data = DMatrix::from_row_slice(4, 2, &[
+            1.0, 40.0,
+            2.0, 30.0,
+            3.0, 20.0,
+            4.0, 10.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+
+        let result = concentration(&data, &wgt, 2).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_approx_eq_iter_f64!(result.estimates, dvector![-0.25, 0.7]);
     }
-    parameter_names.append(&mut parameter_names_correlation);
 
-    Estimates {
-        parameter_names,
-        estimates,
+    #[test]
+    fn test_percentile_shares() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            3.0, 1.0,
+            1.0, 1.0,
+            f64::NAN, 1.0,
+            4.0, 1.0,
+            2.0, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0];
+
+        let result = percentile_shares(&data, &wgt, &[(0.0, 50.0), (75.0, 100.0), (0.0, 12.5)]).unwrap();
+        assert_eq!(result.parameter_names.len(), 6);
+        assert_eq!(result.parameter_names[0], "share_0_50_x1");
+        assert_eq!(result.parameter_names[4], "share_75_100_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.3, 0.4, 0.05, 0.5, 0.25, 0.125]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use nalgebra::{dvector};
-    use rand::prelude::*;
-    use crate::assert_approx_eq_iter_f64;
-    use super::*;
+    #[test]
+    fn test_percentile_shares_error_invalid_range() {
+        let data = DMatrix::from_row_slice(2, 1, &[
+            1.0,
+            2.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0];
+
+        assert_eq!("Invalid input: invalid percentile range in percentile_shares", percentile_shares(&data, &wgt, &[(50.0, 20.0)]).unwrap_err().to_string());
+    }
 
     #[test]
-    fn test_mean() {
-        let data = DMatrix::from_row_slice(3, 4, &[
-            1.0, 4.0, 2.5, -1.0,
-            2.5, 1.75, 4.0, -2.5,
-            3.0, 3.0, 1.0, -3.5,
+    fn test_agreement() {
+        let data = DMatrix::from_row_slice(11, 2, &[
+            1.0, 1.0,
+            1.0, 1.0,
+            2.0, 2.0,
+            3.0, 3.0,
+            1.0, 2.0,
+            2.0, 3.0,
+            3.0, 3.0,
+            2.0, 2.0,
+            3.0, 1.0,
+            2.0, 2.0,
+            f64::NAN, 2.0,
         ]);
 
-        let wgt = dvector![1.0, 0.5, 1.5];
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.0, 0.5, 1.0, 1.0, 1.0, 1.5, 1.0];
 
-        let result = mean(&data, &wgt);
+        let result = agreement(&data, &wgt).unwrap();
         assert_eq!(result.parameter_names.len(), 4);
-        assert_eq!(result.parameter_names[1], "mean_x2");
-        assert_eq!(result.estimates, dvector![2.25, 3.125, 2.0, -2.5]);
+        assert_eq!(result.parameter_names[1], "cohens_kappa");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![77.27272727272727, 0.6540880503144654, 0.6206896551724139, 0.584192439862543]);
+    }
+
+    #[test]
+    fn test_agreement_error_number_of_columns() {
+        let data = DMatrix::from_row_slice(2, 1, &[
+            1.0,
+            2.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0];
+
+        assert_eq!("Invalid input: agreement requires exactly two columns", agreement(&data, &wgt).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_benchmark_levels() {
+        let data = DMatrix::from_row_slice(6, 1, &[
+            380.0,
+            475.0,
+            530.0,
+            f64::NAN,
+            640.0,
+            560.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5, 1.0, 1.0, 1.0];
+
+        let result = benchmark_levels(&data, &wgt, &ILSA_BENCHMARKS).unwrap();
+        assert_eq!(result.parameter_names.len(), 5);
+        assert_eq!(result.parameter_names[0], "benchmark_below_400_x1");
+        assert_eq!(result.parameter_names[2], "benchmark_475_550_x1");
+        assert_eq!(result.parameter_names[4], "benchmark_625_above_x1");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![20.0, 0.0, 40.0, 20.0, 20.0]);
+    }
+
+    #[test]
+    fn test_benchmark_levels_error_unordered_cutpoints() {
+        let data = DMatrix::from_row_slice(2, 1, &[
+            380.0,
+            475.0,
+        ]);
+
+        let wgt = dvector![1.0, 0.5];
+
+        assert_eq!("Invalid input: cutpoints not increasing in benchmark_levels", benchmark_levels(&data, &wgt, &[475.0, 400.0]).unwrap_err().to_string());
     }
 
     #[test]
-    #[should_panic(expected = "dimension mismatch of x and wgt in mean")]
-    fn test_mean_panic_dimension_mismatch() {
+    fn test_missingness() {
+        let data = DMatrix::from_row_slice(4, 3, &[
+            1.0, f64::NAN, 2.5,
+            2.5, 1.75, f64::NAN,
+            f64::NAN, f64::NAN, 1.5,
+            3.0, 3.0, 1.5,
+        ]);
+
+        let wgt = dvector![1.0, 0.5, 1.5, 2.0];
+
+        let result = missingness(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 6);
+        assert_eq!(result.parameter_names[1], "missing_weighted_x2");
+        assert_eq!(result.parameter_names[5], "missing_unweighted_x3");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![30.0, 50.0, 10.0, 25.0, 50.0, 25.0]);
+    }
+
+    #[test]
+    fn test_total_error_dimension_mismatch() {
         let data = DMatrix::from_row_slice(2, 3, &[
             1.0, 4.0, 2.5,
             2.5, 1.75, 4.0,
@@ -130,53 +2504,256 @@ mod tests {
 
         let wgt = dvector![1.0, 0.5, 1.5];
 
-        mean(&data, &wgt);
+        assert_eq!("Invalid input: dimension mismatch of x and wgt in total", total(&data, &wgt).unwrap_err().to_string());
     }
 
     #[test]
-    #[should_panic(expected = "wgt contains NaN in mean")]
-    fn test_mean_panic_wgt_containing_nan() {
-        let data = DMatrix::from_row_slice(3, 3, &[
-            1.0, 4.0, 2.5,
-            2.5, 1.75, 4.0,
-            3.0, 3.0, 1.0,
+    fn test_proportions_above() {
+        let data = DMatrix::from_row_slice(4, 2, &[
+            380.0, 512.0,
+            475.0, f64::NAN,
+            530.0, 401.0,
+            640.0, 470.0,
         ]);
 
-        let wgt = dvector![1.0, 0.5, f64::NAN];
+        let wgt = dvector![1.0, 0.5, 1.5, 1.0];
 
-        mean(&data, &wgt);
+        let result = proportions_above(&data, &wgt, &[400.0, 475.0, 625.0]).unwrap();
+        assert_eq!(result.parameter_names.len(), 6);
+        assert_eq!(result.parameter_names[1], "proportion_above_475_x1");
+        assert_eq!(result.parameter_names[3], "proportion_above_400_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.75, 0.75, 0.25, 1.0, 2.0 / 7.0, 0.0]);
     }
 
     #[test]
-    fn test_mean_with_nan() {
-        let data = DMatrix::from_row_slice(3, 3, &[
-            1.0, 4.0, 2.5,
-            2.5, 1.75, f64::NAN,
-            3.0, 3.0, 1.5,
+    fn test_proportions_above_error_wgt_containing_nan() {
+        let data = DMatrix::from_row_slice(2, 1, &[ 380.0, 512.0 ]);
+
+        let wgt = dvector![1.0, f64::NAN];
+
+        assert_eq!("Invalid input: wgt contains NaN in proportions_above", proportions_above(&data, &wgt, &[400.0]).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_percentile_ranks() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            380.0, 512.0,
+            475.0, f64::NAN,
+            530.0, 401.0,
+            640.0, 470.0,
+            500.0, 500.0,
         ]);
 
+        let wgt = dvector![1.0, 0.5, 1.5, 1.0, 1.0];
+
+        let result = percentile_ranks(&data, &wgt, &[300.0, 500.0]).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(result.parameter_names[1], "percentile_rank_500_x1");
+        assert_eq!(result.parameter_names[2], "percentile_rank_300_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.0, 40.0, 0.0, 3.0 / 4.5 * 100.0]);
+    }
+
+    #[test]
+    fn test_percentile_ranks_error_dimension_mismatch() {
+        let data = DMatrix::from_row_slice(2, 1, &[ 380.0, 512.0 ]);
+
         let wgt = dvector![1.0, 0.5, 1.5];
 
-        let result = mean(&data, &wgt);
+        assert_eq!("Invalid input: dimension mismatch of x and wgt in percentile_ranks", percentile_ranks(&data, &wgt, &[400.0]).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_binned_frequencies() {
+        let data = DMatrix::from_row_slice(6, 2, &[
+            1.0, 10.0,
+            2.5, 20.0,
+            f64::NAN, 30.0,
+            4.0, 35.0,
+            5.0, 40.0,
+            9.0, 50.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 0.5, 0.5];
+
+        let result = binned_frequencies(&data, &wgt, &[0.0, 2.5, 5.0]).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(result.parameter_names[0], "bin_0_2.5_x1");
+        assert_eq!(result.parameter_names[3], "bin_2.5_5_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.2, 0.7, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_binned_frequencies_error_too_few_breaks() {
+        let data = DMatrix::from_row_slice(2, 1, &[ 1.0, 2.0 ]);
+
+        let wgt = dvector![1.0, 0.5];
+
+        assert_eq!("Invalid input: less than two breaks in binned_frequencies", binned_frequencies(&data, &wgt, &[1.0]).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_equal_width_binned_frequencies() {
+        let data = DMatrix::from_row_slice(6, 2, &[
+            1.0, 10.0,
+            2.5, 20.0,
+            f64::NAN, 30.0,
+            4.0, 35.0,
+            5.0, 40.0,
+            9.0, 50.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 0.5, 0.5];
+
+        let result = equal_width_binned_frequencies(&data, &wgt, 2).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(result.parameter_names[1], "bin2_x1");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.8, 0.2, 3.0 / 6.0, 3.0 / 6.0]);
+    }
+
+    #[test]
+    fn test_poisson_regression() {
+        let data = DMatrix::from_row_slice(8, 2, &[
+            0.0, 0.0,
+            2.0, 0.0,
+            1.0, 0.0,
+            3.0, 1.0,
+            4.0, 1.0,
+            2.0, 1.0,
+            5.0, 1.0,
+            7.0, f64::NAN,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.5, 1.0, 1.0, 0.5, 1.0];
+
+        let result = poisson_regression(&data, &wgt).unwrap();
         assert_eq!(result.parameter_names.len(), 3);
-        assert_eq!(result.parameter_names[2], "mean_x3");
-        assert_eq!(result.estimates, dvector![2.25, 3.125, 1.9]);
+        assert_eq!(result.parameter_names[0], "poisson_intercept");
+        assert_eq!(result.parameter_names[1], "poisson_b_x2");
+        assert_eq!(result.parameter_names[2], "poisson_dispersion");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.22314355131420976, 0.9555114450274365, 0.5589743589743589]);
     }
 
     #[test]
-    fn test_mean_all_nan() {
-        let data = DMatrix::from_row_slice(3, 1, &[
-            f64::NAN,
-            f64::NAN,
-            f64::NAN,
+    fn test_poisson_regression_singular() {
+        let data = DMatrix::from_row_slice(3, 3, &[
+            1.0, 1.0, 2.0,
+            2.0, 2.0, 4.0,
+            4.0, 3.0, 6.0,
+        ]);
+
+        let wgt = dvector![1.0, 1.0, 1.0];
+
+        let result = poisson_regression(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(4, result.estimates.iter().filter(|v| v.is_nan()).count());
+    }
+
+    #[test]
+    fn test_effect_size() {
+        let data = DMatrix::from_row_slice(8, 3, &[
+            1.0, 1.0, 2.0,
+            1.0, 3.0, 2.0,
+            1.0, 5.0, 2.0,
+            2.0, 4.0, 2.0,
+            2.0, 6.0, 2.0,
+            2.0, 8.0, 2.0,
+            2.0, f64::NAN, 2.0,
+            f64::NAN, 100.0, 2.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.0, 2.0, 1.0, 1.0];
+
+        let result = effect_size(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 4);
+        assert_eq!(result.parameter_names[0], "cohens_d_x2");
+        assert_eq!(result.parameter_names[1], "hedges_g_x2");
+        assert_eq!(result.parameter_names[3], "hedges_g_x3");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 2), dvector![1.9668302043215573, 1.573464163457246]);
+        assert!(result.estimates[2].is_nan());
+    }
+
+    #[test]
+    fn test_effect_size_not_two_groups() {
+        let data = DMatrix::from_row_slice(3, 2, &[
+            1.0, 1.0,
+            2.0, 3.0,
+            3.0, 5.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0];
+
+        let result = effect_size(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_eq!(2, result.estimates.iter().filter(|v| v.is_nan()).count());
+    }
+
+    #[test]
+    fn test_gini() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            1.0, 5.0,
+            2.0, 5.0,
+            3.0, 5.0,
+            4.0, 5.0,
+            f64::NAN, 5.0,
         ]);
 
+        let wgt = dvector![1.0, 2.0, 1.0, 0.5, 1.0];
+
+        let result = gini(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_eq!(result.parameter_names[1], "gini_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.2222222222222222, 0.0]);
+
+        let data = DMatrix::from_row_slice(4, 1, &[ 1.0, 2.0, 3.0, 4.0 ]);
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+
+        let result = gini(&data, &wgt).unwrap();
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.25]);
+    }
+
+    #[test]
+    fn test_gini_error_dimension_mismatch() {
+        let data = DMatrix::from_row_slice(2, 1, &[ 1.0, 4.0 ]);
+
         let wgt = dvector![1.0, 0.5, 1.5];
 
-        let result = mean(&data, &wgt);
-        assert_eq!(result.parameter_names.len(), 1);
-        assert_eq!(result.parameter_names[0], "mean_x1");
-        assert_eq!(true, result.estimates[0].is_nan());
+        assert_eq!("Invalid input: dimension mismatch of x and wgt in gini", gini(&data, &wgt).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_theil() {
+        let data = DMatrix::from_row_slice(5, 2, &[
+            1.0, 5.0,
+            2.0, 5.0,
+            3.0, 5.0,
+            4.0, 5.0,
+            f64::NAN, 5.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 0.5, 1.0];
+
+        let result = theil(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 2);
+        assert_eq!(result.parameter_names[0], "theil_x1");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.08559373483061744, 0.0]);
+    }
+
+    #[test]
+    fn test_atkinson() {
+        let data = DMatrix::from_row_slice(5, 1, &[ 1.0, 2.0, 3.0, 4.0, f64::NAN ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 0.5, 1.0];
+
+        let result = atkinson(&data, &wgt, 0.5).unwrap();
+        assert_eq!(result.parameter_names[0], "atkinson_0.5_x1");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.04355842888015582]);
+
+        let result = atkinson(&data, &wgt, 1.0).unwrap();
+        assert_eq!(result.parameter_names[0], "atkinson_1_x1");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.0881442877677312]);
+
+        let result = atkinson(&data, &wgt, 2.0).unwrap();
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.17627118644067796]);
     }
 
     #[test]
@@ -206,7 +2783,7 @@ mod tests {
         }
         writer_wgt.flush().unwrap();
 
-        let result = correlation(&data, &wgt);
+        let result = correlation(&data, &wgt).unwrap();
 
         assert_eq!(result.parameter_names.len(), 30);
         assert_eq!(result.parameter_names[3], "covariance_x1_x4");
@@ -222,8 +2799,116 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "dimension mismatch of x and wgt in correlation")]
-    fn test_correlation_panic_dimension_mismatch() {
+    fn test_polychoric_correlation() {
+        let data = DMatrix::from_row_slice(12, 3, &[
+            1.0, 1.0, 1.0,
+            1.0, 2.0, 1.0,
+            2.0, 1.0, 1.0,
+            2.0, 2.0, 1.0,
+            3.0, 2.0, 1.0,
+            3.0, 2.0, 1.0,
+            1.0, 1.0, 1.0,
+            2.0, 2.0, 1.0,
+            3.0, 2.0, f64::NAN,
+            3.0, 1.0, 1.0,
+            2.0, 1.0, 1.0,
+            1.0, 1.0, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.5, 1.0, 1.0, 0.5, 1.0, 1.0, 2.0, 1.0];
+
+        let result = polychoric_correlation(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 3);
+        assert_eq!(result.parameter_names[0], "polychoric_x1_x2");
+        assert_eq!(result.parameter_names[2], "polychoric_x2_x3");
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 1), dvector![0.41649457312271443], 1e-7);
+        assert!(result.estimates[1].is_nan());
+        assert!(result.estimates[2].is_nan());
+    }
+
+    #[test]
+    fn test_polyserial_correlation() {
+        let data = DMatrix::from_row_slice(12, 2, &[
+            0.5, 1.0,
+            1.2, 1.0,
+            1.9, 2.0,
+            2.4, 2.0,
+            3.1, 3.0,
+            2.8, 3.0,
+            0.2, 1.0,
+            1.5, 2.0,
+            2.2, 3.0,
+            3.5, 3.0,
+            1.1, 2.0,
+            0.9, 1.0,
+        ]);
+
+        let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.5, 1.0, 1.0, 0.5, 1.0, 1.0, 2.0, 1.0];
+
+        let result = polyserial_correlation(&data, &wgt).unwrap();
+        assert_eq!(result.parameter_names.len(), 1);
+        assert_eq!(result.parameter_names[0], "polyserial_x1_x2");
+        assert_approx_eq_iter_f64!(result.estimates, dvector![0.9246541918260047], 1e-7);
+    }
+
+    #[test]
+    fn test_principal_components_two_variables() {
+        let data = DMatrix::from_row_slice(6, 2, &[
+            1.0, 2.0,
+            2.0, 1.5,
+            3.0, 3.5,
+            4.0, 3.0,
+            5.0, 5.5,
+            6.0, 4.0,
+        ]);
+        let wgt = dvector![1.0, 2.0, 1.0, 0.5, 1.0, 1.5];
+
+        let r = correlation(&data, &wgt).unwrap().estimates[4];
+        let result = principal_components(&data, &wgt).unwrap();
+
+        assert_eq!(result.parameter_names.len(), 6);
+        assert_eq!(result.parameter_names[0], "eigenvalue_pc1");
+        assert_eq!(result.parameter_names[3], "loading_pc1_x2");
+        assert_eq!(result.parameter_names[4], "loading_pc2_x1");
+        let loading1 = ((1.0 + r) / 2.0).sqrt();
+        let loading2 = ((1.0 - r) / 2.0).sqrt();
+        assert_approx_eq_iter_f64!(result.estimates.rows(0, 4), dvector![1.0 + r, 1.0 - r, loading1, loading1], 1e-10);
+        assert_approx_eq_iter_f64!(result.estimates.rows(4, 2).abs(), dvector![loading2, loading2]);
+    }
+
+    #[test]
+    fn test_principal_components() {
+        let data = DMatrix::from_row_slice(8, 3, &[
+            1.0, 2.0, 5.0,
+            2.0, 1.5, 4.0,
+            3.0, 3.5, 4.5,
+            4.0, 3.0, 2.0,
+            5.0, 5.5, 3.0,
+            6.0, 4.0, 1.0,
+            2.5, f64::NAN, 3.5,
+            4.5, 5.0, 2.5,
+        ]);
+        let wgt = dvector![1.0, 2.0, 1.0, 0.5, 1.0, 1.5, 1.0, 2.0];
+
+        let result = principal_components(&data, &wgt).unwrap();
+
+        assert_eq!(result.parameter_names.len(), 12);
+        assert_eq!(result.parameter_names[11], "loading_pc3_x3");
+        assert!((result.estimates.rows(0, 3).sum() - 3.0).abs() < 1e-10);
+        assert!(result.estimates[0] >= result.estimates[1] && result.estimates[1] >= result.estimates[2]);
+        for i in 0..3 {
+            let communality : f64 = (0..3).map(|k| result.estimates[3 + k * 3 + i].powi(2)).sum();
+            assert!((communality - 1.0).abs() < 1e-10);
+        }
+        for k in 0..3 {
+            let loadings = result.estimates.rows(3 + k * 3, 3);
+            let largest = loadings.iter().fold(0.0, |acc : f64, v| if v.abs() > acc.abs() { *v } else { acc });
+            assert!(largest > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_correlation_error_dimension_mismatch() {
         let data = DMatrix::from_row_slice(2, 3, &[
             1.0, 4.0, 2.5,
             2.5, 1.75, 4.0,
@@ -231,12 +2916,11 @@ mod tests {
 
         let wgt = dvector![1.0, 0.5, 1.5];
 
-        correlation(&data, &wgt);
+        assert_eq!("Invalid input: dimension mismatch of x and wgt in correlation", correlation(&data, &wgt).unwrap_err().to_string());
     }
 
     #[test]
-    #[should_panic(expected = "wgt contains NaN in correlation")]
-    fn test_correlation_panic_wgt_containing_nan() {
+    fn test_correlation_error_wgt_containing_nan() {
         let data = DMatrix::from_row_slice(3, 3, &[
             1.0, 4.0, 2.5,
             2.5, 1.75, 4.0,
@@ -245,7 +2929,7 @@ mod tests {
 
         let wgt = dvector![1.0, 0.5, f64::NAN];
 
-        correlation(&data, &wgt);
+        assert_eq!("Invalid input: wgt contains NaN in correlation", correlation(&data, &wgt).unwrap_err().to_string());
     }
 
     #[test]
@@ -260,7 +2944,7 @@ mod tests {
 
         let wgt = dvector![1.0, 2.0, 1.0, 1.0, 1.5];
 
-        let result = correlation(&data, &wgt);
+        let result = correlation(&data, &wgt).unwrap();
         assert_eq!(result.parameter_names.len(), 12);
         assert_eq!(result.parameter_names[2], "covariance_x1_x3");
         assert_approx_eq_iter_f64!(result.estimates, dvector![
@@ -270,7 +2954,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "standard deviation matrix not invertible")]
     fn test_correlation_all_nan() {
         let data = DMatrix::from_row_slice(3, 2, &[
             f64::NAN, 1.0,
@@ -280,6 +2963,31 @@ mod tests {
 
         let wgt = dvector![1.0, 0.5, 1.5];
 
-        correlation(&data, &wgt);
+        assert_eq!("Estimation failed: standard deviation matrix not invertible in correlation", correlation(&data, &wgt).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_named_estimators() {
+        let data = dmatrix![1.0, 2.0; 3.0, 1.0; 2.0, 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+
+        let result = named_correlation(&data, &wgt, &["ASRREA".to_string(), "SES_INDEX".to_string()]).unwrap();
+        assert_eq!("covariance_ASRREA_ASRREA", result.parameter_names[0]);
+        assert_eq!("covariance_ASRREA_SES_INDEX", result.parameter_names[1]);
+        assert_eq!("correlation_ASRREA_SES_INDEX", result.parameter_names[4]);
+
+        let result = named_crosstab(&data, &wgt, &["ITSEX".to_string(), "ASBG03".to_string()]).unwrap();
+        assert_eq!("crosstab_ITSEX_1_ASBG03_2", result.parameter_names[1]);
+        assert_eq!("crosstab_ITSEX_1", result.parameter_names[9]);
+        assert_eq!("crosstab_ASBG03_5", result.parameter_names[14]);
+
+        // variable names looking like generic column labels are kept as they are
+        let result = named_mean(&data, &wgt, &["x2".to_string(), "x1".to_string()]).unwrap();
+        assert_eq!(&vec!["mean_x2".to_string(), "mean_x1".to_string()], result.parameter_names());
+
+        // columns without a variable name and estimators without names keep the generic column labels
+        let result = named_mean(&data, &wgt, &["ASRREA".to_string()]).unwrap();
+        assert_eq!(&vec!["mean_ASRREA".to_string(), "mean_x2".to_string()], result.parameter_names());
+        assert_eq!(&vec!["mean_x1".to_string(), "mean_x2".to_string()], mean(&data, &wgt).unwrap().parameter_names());
     }
 }
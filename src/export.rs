@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::analysis::GroupKey;
+use crate::external;
+use crate::replication::ReplicatedEstimates;
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn results_to_csv(results: &BTreeMap<GroupKey, ReplicatedEstimates>) -> String {
+    let keys : Vec<&GroupKey> = results.keys().collect();
+
+    // one column per grouping variable, plus a column for the compared group if there are group differences
+    let group_columns = keys.iter().map(|key| key.variable_names().clone()).find(|names| !names.is_empty()).unwrap_or_default();
+    let with_comparisons = keys.iter().any(|key| key.compared_to().is_some());
+
+    let mut header : Vec<String> = group_columns.clone();
+    if with_comparisons {
+        header.push("compared_to".to_string());
+    }
+    header.extend(["parameter", "estimate", "standard_error"].iter().map(|s| s.to_string()));
+
+    let mut lines : Vec<String> = vec![header.iter().map(|h| csv_field(h)).collect::<Vec<String>>().join(",")];
+    for key in keys {
+        let estimates = &results[key];
+        let mut group_fields : Vec<String> = (0..group_columns.len()).map(|c| key.labels().get(c).cloned().unwrap_or_default()).collect();
+        if with_comparisons {
+            group_fields.push(key.compared_to().map(|other| other.labels().join("_")).unwrap_or_default());
+        }
+
+        for (p, parameter_name) in estimates.parameter_names().iter().enumerate() {
+            let mut fields = group_fields.clone();
+            fields.push(parameter_name.clone());
+            fields.push(estimates.final_estimates()[p].to_string());
+            fields.push(estimates.standard_errors()[p].to_string());
+            lines.push(fields.iter().map(|f| csv_field(f)).collect::<Vec<String>>().join(","));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[derive(Serialize)]
+struct JsonResult {
+    key: Vec<String>,
+    groups: BTreeMap<String, String>,
+    compared_to: Option<BTreeMap<String, String>>,
+    estimates: external::ReplicatedEstimates,
+}
+
+fn json_groups(key: &GroupKey) -> BTreeMap<String, String> {
+    key.variable_names().iter().cloned().zip(key.labels().iter().cloned()).collect()
+}
+
+pub fn results_to_json(results: &BTreeMap<GroupKey, ReplicatedEstimates>) -> Result<String, serde_json::Error> {
+    // group keys become objects mapping grouping variables to labels, non-finite numbers are written as null
+    let json_results : Vec<JsonResult> = results.keys().map(|key| JsonResult {
+        key: key.key().clone(),
+        groups: json_groups(key),
+        compared_to: key.compared_to().map(json_groups),
+        estimates: external::ReplicatedEstimates::from_internal(&results[key]),
+    }).collect();
+
+    serde_json::to_string(&json_results)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::dmatrix;
+    use crate::analysis::{analysis, Imputation};
+    use crate::export::{results_to_csv, results_to_json};
+
+    #[test]
+    fn test_results_to_csv() {
+        let data = dmatrix![1.0; 3.0; 5.0; 7.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).mean().calculate().unwrap();
+        assert_eq!("parameter,estimate,standard_error\nmean_x1,4,0\n", results_to_csv(&result));
+
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0];
+        let result = analysis1.group_by(Imputation::No(&groups)).set_group_names(&["gender"]).set_group_labels(0, &[(1.0, "female, all")])
+            .with_pairwise_differences().calculate().unwrap();
+        assert_eq!(
+            "gender,compared_to,parameter,estimate,standard_error\n\
+            \"female, all\",,mean_x1,2,0\n\
+            \"female, all\",2,\"mean_x1_diff_female, all_vs_2\",-4,0\n\
+            2,,mean_x1,6,0\n",
+            results_to_csv(&result)
+        );
+    }
+
+    #[test]
+    fn test_results_to_json() {
+        let data = dmatrix![1.0; 3.0; 5.0; 7.0];
+        let groups = dmatrix![1.0; 1.0; 2.0; 2.0];
+
+        let mut analysis1 = analysis();
+        let result = analysis1.for_data(Imputation::No(&data)).mean().group_by(Imputation::No(&groups)).set_group_names(&["gender"])
+            .set_group_labels(0, &[(1.0, "female"), (2.0, "male")]).with_pairwise_differences().calculate().unwrap();
+
+        let json : serde_json::Value = serde_json::from_str(&results_to_json(&result).unwrap()).unwrap();
+        let entries = json.as_array().unwrap();
+        assert_eq!(3, entries.len());
+
+        assert_eq!(serde_json::json!(["female"]), entries[0]["key"]);
+        assert_eq!(serde_json::json!({"gender": "female"}), entries[0]["groups"]);
+        assert!(entries[0]["compared_to"].is_null());
+        assert_eq!(serde_json::json!(["mean_x1"]), entries[0]["estimates"]["parameter_names"]);
+        assert_eq!(serde_json::json!([2.0]), entries[0]["estimates"]["final_estimates"]);
+
+        assert_eq!(serde_json::json!(["female", "vs", "male"]), entries[1]["key"]);
+        assert_eq!(serde_json::json!({"gender": "male"}), entries[1]["compared_to"]);
+        assert_eq!(serde_json::json!([-4.0]), entries[1]["estimates"]["final_estimates"]);
+
+        // degrees of freedom without replicate weights are infinite and therefore null
+        assert!(entries[2]["estimates"]["degrees_of_freedom"][0].is_null());
+    }
+}
@@ -1,10 +1,40 @@
+use std::collections::HashMap;
 use nalgebra::{DMatrix, DVector, Dyn, Matrix, U1};
 use serde::{Deserialize, Serialize};
-use crate::{estimates, replication};
+use crate::replication;
+use crate::analysis::EstimateSpecification;
+use crate::errors::ReplicestError;
 
 pub enum Estimate {
     Mean,
+    StandardizedMean,
+    Total,
+    Missingness,
+    Frequencies { percentages: bool },
+    Crosstab,
+    Agreement,
+    Range,
     Correlation,
+    LinearRegression { residual_diagnostics: bool, number_residual_bins: u32 },
+    PoissonRegression,
+    EffectSize,
+    TTest { mu: f64 },
+    TwoSampleTTest,
+    OddsRatio,
+    EtaSquared,
+    MantelHaenszelDif,
+    Gini,
+    Concentration { number_ordinates: u32 },
+    Theil,
+    Atkinson { epsilon: f64 },
+    PercentileRanks { values: Vec<f64> },
+    BinnedFrequencies { breaks: Vec<f64> },
+    EqualWidthBinnedFrequencies { number_bins: u32 },
+    BenchmarkLevels { cutpoints: Vec<f64> },
+    ProportionsAbove { cutpoints: Vec<f64> },
+    PolychoricCorrelation,
+    PolyserialCorrelation,
+    PrincipalComponents,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -14,6 +44,24 @@ pub struct ReplicatedEstimates {
     pub sampling_variances: Vec<f64>,
     pub imputation_variances: Vec<f64>,
     pub standard_errors: Vec<f64>,
+    pub degrees_of_freedom: Vec<f64>,
+    pub t_statistics: Vec<f64>,
+    pub p_values: Vec<f64>,
+    pub confidence_level: f64,
+    pub confidence_intervals_lower: Vec<f64>,
+    pub confidence_intervals_upper: Vec<f64>,
+    pub suppressed: bool,
+    pub design_effects: Vec<f64>,
+    pub effective_sample_sizes: Vec<f64>,
+    pub unweighted_estimates: Vec<f64>,
+    pub number_cases: Option<u64>,
+    pub weight_sum: Option<f64>,
+    pub valid_cases: Vec<f64>,
+    pub differences_to_overall: Vec<f64>,
+    pub difference_to_overall_standard_errors: Vec<f64>,
+    pub significantly_different_from_overall: Vec<bool>,
+    pub metadata: HashMap<String, String>,
+    pub warnings: Vec<String>,
 }
 
 impl ReplicatedEstimates {
@@ -24,20 +72,50 @@ impl ReplicatedEstimates {
             sampling_variances: Vec::from(internal_struct.sampling_variances().as_slice()),
             imputation_variances: Vec::from(internal_struct.imputation_variances().as_slice()),
             standard_errors: Vec::from(internal_struct.standard_errors().as_slice()),
+            degrees_of_freedom: Vec::from(internal_struct.degrees_of_freedom().as_slice()),
+            t_statistics: Vec::from(internal_struct.t_statistics().as_slice()),
+            p_values: Vec::from(internal_struct.p_values().as_slice()),
+            confidence_level: internal_struct.confidence_level(),
+            confidence_intervals_lower: Vec::from(internal_struct.confidence_intervals_lower().as_slice()),
+            confidence_intervals_upper: Vec::from(internal_struct.confidence_intervals_upper().as_slice()),
+            suppressed: internal_struct.suppressed(),
+            design_effects: internal_struct.design_effects().map(|v| Vec::from(v.as_slice())).unwrap_or_default(),
+            effective_sample_sizes: internal_struct.effective_sample_sizes().map(|v| Vec::from(v.as_slice())).unwrap_or_default(),
+            unweighted_estimates: internal_struct.unweighted_estimates().map(|v| Vec::from(v.as_slice())).unwrap_or_default(),
+            number_cases: internal_struct.number_cases().map(|n| n as u64),
+            weight_sum: internal_struct.weight_sum(),
+            valid_cases: internal_struct.valid_cases().map(|v| Vec::from(v.as_slice())).unwrap_or_default(),
+            differences_to_overall: internal_struct.difference_to_overall().map(|d| Vec::from(d.final_estimates().as_slice())).unwrap_or_default(),
+            difference_to_overall_standard_errors: internal_struct.difference_to_overall().map(|d| Vec::from(d.standard_errors().as_slice())).unwrap_or_default(),
+            significantly_different_from_overall: internal_struct.significantly_different_from_overall().unwrap_or_default(),
+            metadata: internal_struct.metadata().clone().into_iter().collect(),
+            warnings: internal_struct.warnings().clone(),
         }
     }
 }
 
-pub fn replicate_estimates(estimate: Estimate, x: &Vec<Vec<Vec<f64>>>, wgt: &Vec<Vec<f64>>, replicate_wgts: &Vec<Vec<Vec<f64>>>, factor: f64) -> ReplicatedEstimates {
-    let estimate_function = match estimate {
-        Estimate::Mean => { estimates::mean }
-        Estimate::Correlation => { estimates::correlation }
-    };
+pub fn replicate_estimates(estimate: Estimate, x: &Vec<Vec<Vec<f64>>>, wgt: &Vec<Vec<f64>>, replicate_wgts: &Vec<Vec<Vec<f64>>>, factor: f64) -> Result<ReplicatedEstimates, ReplicestError> {
+    replicate_estimates_with_variable_names(estimate, x, wgt, replicate_wgts, factor, &[])
+}
+
+pub fn replicate_estimates_with_variable_names(estimate: Estimate, x: &[Vec<Vec<f64>>], wgt: &[Vec<f64>], replicate_wgts: &[Vec<Vec<f64>>], factor: f64, variable_names: &[String]) -> Result<ReplicatedEstimates, ReplicestError> {
+    // standardization needs the reference values of the whole sample, which only an analysis provides
+    if let Estimate::StandardizedMean = estimate {
+        return Err(ReplicestError::InvalidInput("standardized mean requires an analysis".to_string()));
+    }
+    let estimate_function = EstimateSpecification::from(estimate).estimate_function(variable_names);
+
+    if x.is_empty() || x.iter().any(|imputation| imputation.is_empty()) {
+        return Err(ReplicestError::InvalidInput("no data".to_string()));
+    }
+    if x.iter().chain(replicate_wgts.iter()).any(|matrix| matrix.iter().any(|row| row.len() != matrix[0].len())) {
+        return Err(ReplicestError::InvalidInput("rows of unequal length".to_string()));
+    }
 
     let mut data : Vec<DMatrix<f64>> = Vec::new();
     for imputation in x.iter() {
         let mut imp_matrix : DMatrix<f64> = DMatrix::<f64>::zeros(imputation.len(), imputation[0].len());
-        for (r, row )in imputation.into_iter().enumerate() {
+        for (r, row) in imputation.iter().enumerate() {
             imp_matrix.set_row(r, &Matrix::<f64, U1, Dyn, _>::from_row_slice(row));
         }
 
@@ -47,14 +125,14 @@ pub fn replicate_estimates(estimate: Estimate, x: &Vec<Vec<Vec<f64>>>, wgt: &Vec
 
     let mut weights : Vec<DVector<f64>> = Vec::new();
     for weight in wgt.iter() {
-        weights.push(DVector::<f64>::from_row_slice(&weight))
+        weights.push(DVector::<f64>::from_row_slice(weight))
     }
     let ref_weights : Vec<&DVector<f64>> = Vec::from_iter(weights.iter());
 
     let mut replicate_weights : Vec<DMatrix<f64>> = Vec::new();
     for replicate_weight in replicate_wgts.iter() {
-        let mut rep_wgt_matrix : DMatrix<f64> = DMatrix::<f64>::zeros(replicate_weight.len(), if replicate_weight.len() == 0 { 0 } else { replicate_weight[0].len() });
-        for (r, row) in replicate_weight.into_iter().enumerate() {
+        let mut rep_wgt_matrix : DMatrix<f64> = DMatrix::<f64>::zeros(replicate_weight.len(), if replicate_weight.is_empty() { 0 } else { replicate_weight[0].len() });
+        for (r, row) in replicate_weight.iter().enumerate() {
             rep_wgt_matrix.set_row(r, &Matrix::<f64, U1, Dyn, _>::from_row_slice(row));
         }
 
@@ -63,15 +141,19 @@ pub fn replicate_estimates(estimate: Estimate, x: &Vec<Vec<Vec<f64>>>, wgt: &Vec
     let ref_replicate_weights : Vec<&DMatrix<f64>> = Vec::from_iter(replicate_weights.iter());
 
 
+    if !variable_names.is_empty() && variable_names.len() != x[0][0].len() {
+        return Err(ReplicestError::InvalidInput("number of variable names does not match number of columns".to_string()));
+    }
+
     let result = replication::replicate_estimates(
-        estimate_function,
+        estimate_function.as_ref(),
         &ref_data,
         &ref_weights,
         &ref_replicate_weights,
         factor
-    );
+    )?;
 
-    ReplicatedEstimates::from_internal(&result)
+    Ok(ReplicatedEstimates::from_internal(&result))
 }
 
 #[cfg(test)]
@@ -106,7 +188,7 @@ mod tests {
             vec![1.5, 1.5, 0.0],
         ];
 
-        let result = replicate_estimates(Estimate::Mean, &imp_data, &vec![wgt], &vec![rep_wgts], 1.0);
+        let result = replicate_estimates(Estimate::Mean, &imp_data, &vec![wgt], &vec![rep_wgts], 1.0).unwrap();
         assert_eq!(4, result.parameter_names.len());
         assert_eq!("mean_x2", result.parameter_names[1]);
 
@@ -120,4 +202,61 @@ mod tests {
         assert_approx_eq_iter_f64!(result.imputation_variances, expected_imputation_variances);
         assert_approx_eq_iter_f64!(result.standard_errors, expected_standard_errors);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_replicate_estimates_with_variable_names() {
+        let imp_data = vec![
+            vec![
+                vec![1.0, 4.0],
+                vec![2.5, 3.0],
+            ],
+        ];
+        let wgt = vec![1.0, 1.0];
+
+        let result = replicate_estimates_with_variable_names(Estimate::Mean, &imp_data, &vec![wgt.clone()], &vec![vec![]], 1.0, &["ASRREA".to_string(), "ASBGSES".to_string()]).unwrap();
+        assert_eq!(vec!["mean_ASRREA".to_string(), "mean_ASBGSES".to_string()], result.parameter_names);
+
+        let result = replicate_estimates_with_variable_names(Estimate::Mean, &imp_data, &vec![wgt], &vec![vec![]], 1.0, &["ASRREA".to_string()]);
+        assert!(matches!(result, Err(ReplicestError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_replicate_estimates_invalid_input() {
+        let imp_data = vec![
+            vec![
+                vec![1.0, 4.0],
+                vec![2.5],
+            ],
+        ];
+        let wgt = vec![1.0, 1.0];
+
+        let result = replicate_estimates(Estimate::Mean, &imp_data, &vec![wgt.clone()], &vec![vec![]], 1.0);
+        assert!(matches!(result, Err(ReplicestError::InvalidInput(details)) if details == "rows of unequal length"));
+
+        let imp_data = vec![
+            vec![
+                vec![1.0, 4.0],
+                vec![2.5, 3.0],
+            ],
+        ];
+        let result = replicate_estimates(Estimate::Crosstab, &imp_data, &vec![vec![1.0, f64::NAN]], &vec![vec![]], 1.0);
+        assert!(matches!(result, Err(ReplicestError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_replicate_estimates_with_estimate_options() {
+        let imp_data = vec![
+            vec![
+                vec![1.0],
+                vec![2.0],
+                vec![2.0],
+            ],
+        ];
+        let result = replicate_estimates(Estimate::Frequencies { percentages: true }, &imp_data, &vec![vec![1.0, 1.0, 2.0]], &vec![vec![]], 1.0).unwrap();
+        assert_eq!(vec!["frequency_x1_cat1", "frequency_x1_cat2", "percentage_x1_cat1", "percentage_x1_cat2"], result.parameter_names);
+        assert_eq!(vec![1.0, 3.0, 25.0, 75.0], result.final_estimates);
+
+        let result = replicate_estimates(Estimate::StandardizedMean, &imp_data, &vec![vec![1.0, 1.0, 2.0]], &vec![vec![]], 1.0);
+        assert!(matches!(result, Err(ReplicestError::InvalidInput(_))));
+    }
+}
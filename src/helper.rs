@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
 use nalgebra::{DMatrix, DVector, Dim, Matrix, RawStorage};
+use statrs::distribution::{ContinuousCDF, Normal};
 
 pub trait ExtractValues {
     fn extract_lower_triangle(&self) -> DVector<f64>;
@@ -116,6 +118,137 @@ impl Split<DVector<f64>> for DVector<f64> {
     }
 }
 
+pub fn normal_cdf(x: f64) -> f64 {
+    Normal::standard().cdf(x)
+}
+
+pub fn normal_quantile(p: f64) -> f64 {
+    Normal::standard().inverse_cdf(p)
+}
+
+const GAUSS_LEGENDRE_WEIGHTS : [[f64; 10]; 3] = [
+    [0.1713244923791705, 0.3607615730481384, 0.4679139345726904, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [0.04717533638651177, 0.1069393259953183, 0.1600783285433464, 0.2031674267230659, 0.2334925365383547, 0.2491470458134029, 0.0, 0.0, 0.0, 0.0],
+    [0.01761400713915212, 0.04060142980038694, 0.06267204833410905, 0.08327674157670475, 0.1019301198172404,
+        0.1181945319615184, 0.1316886384491766, 0.1420961093183821, 0.1491729864726037, 0.1527533871307259],
+];
+
+const GAUSS_LEGENDRE_POINTS : [[f64; 10]; 3] = [
+    [-0.9324695142031522, -0.6612093864662647, -0.238619186083197, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [-0.9815606342467191, -0.904117256370475, -0.769902674194305, -0.5873179542866171, -0.3678314989981802, -0.1252334085114692, 0.0, 0.0, 0.0, 0.0],
+    [-0.9931285991850949, -0.9639719272779138, -0.912234428251326, -0.8391169718222188, -0.7463319064601508,
+        -0.636053680726515, -0.5108670019508271, -0.3737060887154196, -0.2277858511416451, -0.07652652113349732],
+];
+
+// P(X < h, Y < k) for a standard bivariate normal with correlation r (Genz' algorithm for BVND)
+pub fn bivariate_normal_cdf(h: f64, k: f64, r: f64) -> f64 {
+    if h == f64::NEG_INFINITY || k == f64::NEG_INFINITY {
+        return 0.0;
+    }
+    if h == f64::INFINITY {
+        return normal_cdf(k);
+    }
+    if k == f64::INFINITY {
+        return normal_cdf(h);
+    }
+
+    let (ng, lg) = if r.abs() < 0.3 { (0, 3) } else if r.abs() < 0.75 { (1, 6) } else { (2, 10) };
+    let weights = &GAUSS_LEGENDRE_WEIGHTS[ng];
+    let points = &GAUSS_LEGENDRE_POINTS[ng];
+
+    let h = -h;
+    let mut k = -k;
+    let mut hk = h * k;
+    let mut bvn = 0.0;
+
+    if r.abs() < 0.925 {
+        let hs = (h * h + k * k) / 2.0;
+        let asr = r.asin();
+        for i in 0..lg {
+            let sn = (asr * (points[i] + 1.0) / 2.0).sin();
+            bvn += weights[i] * ((sn * hk - hs) / (1.0 - sn * sn)).exp();
+            let sn = (asr * (-points[i] + 1.0) / 2.0).sin();
+            bvn += weights[i] * ((sn * hk - hs) / (1.0 - sn * sn)).exp();
+        }
+        return bvn * asr / (4.0 * PI) + normal_cdf(-h) * normal_cdf(-k);
+    }
+
+    if r < 0.0 {
+        k = -k;
+        hk = -hk;
+    }
+
+    if r.abs() < 1.0 {
+        let a_squared = (1.0 - r) * (1.0 + r);
+        let mut a = a_squared.sqrt();
+        let b_squared = (h - k).powi(2);
+        let c = (4.0 - hk) / 8.0;
+        let d = (12.0 - hk) / 16.0;
+
+        bvn = a * (-(b_squared / a_squared + hk) / 2.0).exp() * (1.0 - c * (b_squared - a_squared) * (1.0 - d * b_squared / 5.0) / 3.0 + c * d * a_squared * a_squared / 5.0);
+        if hk > -160.0 {
+            let b = b_squared.sqrt();
+            bvn -= (-hk / 2.0).exp() * (2.0 * PI).sqrt() * normal_cdf(-b / a) * b * (1.0 - c * b_squared * (1.0 - d * b_squared / 5.0) / 3.0);
+        }
+
+        a /= 2.0;
+        for i in 0..lg {
+            let xs = (a * (points[i] + 1.0)).powi(2);
+            let rs = (1.0 - xs).sqrt();
+            bvn += a * weights[i] * ((-b_squared / (2.0 * xs) - hk / (1.0 + rs)).exp() / rs - (-(b_squared / xs + hk) / 2.0).exp() * (1.0 + c * xs * (1.0 + d * xs)));
+
+            let xs = a_squared * (-points[i] + 1.0).powi(2) / 4.0;
+            let rs = (1.0 - xs).sqrt();
+            bvn += a * weights[i] * (-(b_squared / xs + hk) / 2.0).exp() * ((-hk * (1.0 - rs) / (2.0 * (1.0 + rs))).exp() / rs - (1.0 + c * xs * (1.0 + d * xs)));
+        }
+        bvn = -bvn / (2.0 * PI);
+    }
+
+    if r > 0.0 {
+        bvn + normal_cdf(-h.max(k))
+    } else {
+        let mut bvn = -bvn;
+        if k > h {
+            if h < 0.0 {
+                bvn += normal_cdf(k) - normal_cdf(h);
+            } else {
+                bvn += normal_cdf(-h) - normal_cdf(-k);
+            }
+        }
+        bvn
+    }
+}
+
+// golden-section search for the maximum of a unimodal function on [lower, upper]
+pub fn maximize_on_interval<F: Fn(f64) -> f64>(function: F, lower: f64, upper: f64) -> f64 {
+    let inverse_golden_ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+
+    let mut lower = lower;
+    let mut upper = upper;
+    let mut inner_lower = upper - inverse_golden_ratio * (upper - lower);
+    let mut inner_upper = lower + inverse_golden_ratio * (upper - lower);
+    let mut value_inner_lower = function(inner_lower);
+    let mut value_inner_upper = function(inner_upper);
+
+    while upper - lower > 1e-10 {
+        if value_inner_lower > value_inner_upper {
+            upper = inner_upper;
+            inner_upper = inner_lower;
+            value_inner_upper = value_inner_lower;
+            inner_lower = upper - inverse_golden_ratio * (upper - lower);
+            value_inner_lower = function(inner_lower);
+        } else {
+            lower = inner_lower;
+            inner_lower = inner_upper;
+            value_inner_lower = value_inner_upper;
+            inner_upper = lower + inverse_golden_ratio * (upper - lower);
+            value_inner_upper = function(inner_upper);
+        }
+    }
+
+    (lower + upper) / 2.0
+}
+
 #[macro_export]
 macro_rules! assert_approx_eq_iter_f64 {
     ( $x: expr, $y: expr, $eps: literal ) => {
@@ -293,6 +426,41 @@ mod tests {
         assert_eq!(10.0, result[&vec!["2".to_string(), "2".to_string()]][0]);
     }
 
+    #[test]
+    fn test_normal_cdf_and_quantile() {
+        assert_approx_eq_iter_f64!(vec![normal_cdf(0.0), normal_cdf(1.959963984540054)], vec![0.5, 0.975]);
+        assert_approx_eq_iter_f64!(vec![normal_quantile(0.5), normal_quantile(0.975)], vec![0.0, 1.959963984540054]);
+    }
+
+    #[test]
+    fn test_bivariate_normal_cdf() {
+        let result = [
+            bivariate_normal_cdf(0.5, -0.3, 0.2),
+            bivariate_normal_cdf(1.2, 0.7, 0.6),
+            bivariate_normal_cdf(-0.4, 0.9, -0.5),
+            bivariate_normal_cdf(0.3, 0.2, 0.95),
+            bivariate_normal_cdf(-1.0, 0.5, -0.97),
+            bivariate_normal_cdf(0.0, 0.0, 0.0),
+        ];
+
+        assert_approx_eq_iter_f64!(result, vec![
+            0.29076428813655325, 0.7170219260282328, 0.22684534855876912, 0.5472227748552738, 0.0005618422216624243, 0.25
+        ], 1e-9);
+    }
+
+    #[test]
+    fn test_bivariate_normal_cdf_infinite_bounds() {
+        assert_eq!(0.0, bivariate_normal_cdf(f64::NEG_INFINITY, 0.5, 0.3));
+        assert_eq!(normal_cdf(0.5), bivariate_normal_cdf(f64::INFINITY, 0.5, 0.3));
+        assert_eq!(normal_cdf(-0.2), bivariate_normal_cdf(-0.2, f64::INFINITY, 0.3));
+    }
+
+    #[test]
+    fn test_maximize_on_interval() {
+        let result = maximize_on_interval(|x| -(x - 0.3).powi(2), -1.0, 1.0);
+        assert!((result - 0.3).abs() < 1e-8);
+    }
+
     #[test]
     fn test_assert_approx_eq_iter_f64() {
         assert_approx_eq_iter_f64!(vec![1.0, -5.0], vec![1.0000000000001, -5.0]);
@@ -0,0 +1,191 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::Cursor;
+use arrow_array::cast::AsArray;
+use arrow_array::types::Float64Type;
+use arrow_array::{RecordBatch, RecordBatchReader};
+use arrow_cast::cast;
+use arrow_ipc::reader::StreamReader;
+use arrow_schema::{ArrowError, DataType, SchemaRef};
+use nalgebra::DMatrix;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use crate::errors::ColumnNotFoundError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub header: bool,
+    pub columns: Vec<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            header: true,
+            columns: Vec::new(),
+        }
+    }
+}
+
+fn record_batches_to_matrix(schema: SchemaRef, batches: impl Iterator<Item = Result<RecordBatch, ArrowError>>, columns: &[&str]) -> Result<(DMatrix<f64>, Vec<String>), Box<dyn Error>> {
+    // without a selection all columns are used in the order of the schema
+    let column_names : Vec<String> = if columns.is_empty() {
+        schema.fields().iter().map(|field| field.name().clone()).collect()
+    } else {
+        columns.iter().map(|column| column.to_string()).collect()
+    };
+    let mut column_indices : Vec<usize> = Vec::new();
+    for name in column_names.iter() {
+        column_indices.push(schema.index_of(name).map_err(|_| ColumnNotFoundError::new(name))?);
+    }
+
+    // any numeric column type is accepted, nulls become NaN
+    let mut values : Vec<Vec<f64>> = vec![Vec::new(); column_names.len()];
+    for batch in batches {
+        let batch = batch?;
+        for (c, &index) in column_indices.iter().enumerate() {
+            let column = cast(batch.column(index), &DataType::Float64)?;
+            values[c].extend(column.as_primitive::<Float64Type>().iter().map(|value| value.unwrap_or(f64::NAN)));
+        }
+    }
+
+    let rows = values.first().map(|column| column.len()).unwrap_or(0);
+    Ok((DMatrix::from_iterator(rows, column_names.len(), values.into_iter().flatten()), column_names))
+}
+
+pub fn arrow_ipc_to_matrix(buffer: Vec<u8>) -> Result<(DMatrix<f64>, Vec<String>), Box<dyn Error>> {
+    let reader = StreamReader::try_new(Cursor::new(buffer), None)?;
+    record_batches_to_matrix(reader.schema(), reader, &[])
+}
+
+pub fn read_parquet(path: &str, columns: &[&str]) -> Result<(DMatrix<f64>, Vec<String>), Box<dyn Error>> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+    record_batches_to_matrix(reader.schema(), reader, columns)
+}
+
+pub fn read_csv(path: &str, options: &CsvOptions) -> Result<(DMatrix<f64>, Vec<String>), Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(options.delimiter).has_headers(options.header).from_path(path)?;
+
+    let mut rows : Vec<Vec<f64>> = Vec::new();
+    for (line, record) in reader.records().enumerate() {
+        // empty fields are missing values
+        let row : Result<Vec<f64>, _> = record?.iter().map(|field| if field.trim().is_empty() { Ok(f64::NAN) } else { field.trim().parse::<f64>() }).collect();
+        rows.push(row.map_err(|err| format!("cannot parse line {} of {}: {}", line + 1, path, err))?);
+    }
+
+    // without a header the columns are named x1, x2, ... as in the parameter names
+    let number_columns = rows.first().map(|row| row.len()).unwrap_or(0);
+    let all_names : Vec<String> = if options.header {
+        reader.headers()?.iter().map(|name| name.trim().to_string()).collect()
+    } else {
+        (1..=number_columns).map(|c| format!("x{}", c)).collect()
+    };
+
+    let column_names : Vec<String> = if options.columns.is_empty() { all_names.clone() } else { options.columns.clone() };
+    let mut column_indices : Vec<usize> = Vec::new();
+    for name in column_names.iter() {
+        column_indices.push(all_names.iter().position(|other| other == name).ok_or(ColumnNotFoundError::new(name))?);
+    }
+
+    Ok((DMatrix::from_fn(rows.len(), column_indices.len(), |r, c| rows[r].get(column_indices[c]).cloned().unwrap_or(f64::NAN)), column_names))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{remove_file, File};
+    use std::sync::Arc;
+    use arrow_array::{ArrayRef, Float64Array, Int32Array, RecordBatch};
+    use nalgebra::{dmatrix, dvector};
+    use parquet::arrow::ArrowWriter;
+    use crate::import::{arrow_ipc_to_matrix, read_csv, read_parquet, CsvOptions};
+
+    fn test_batch() -> RecordBatch {
+        RecordBatch::try_from_iter(vec![
+            ("ASRREA", Arc::new(Float64Array::from(vec![Some(512.5), None, Some(480.0)])) as ArrayRef),
+            ("ITSEX", Arc::new(Int32Array::from(vec![1, 2, 2])) as ArrayRef),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn test_arrow_ipc_to_matrix() {
+        let batch = test_batch();
+        let mut buffer : Vec<u8> = Vec::new();
+        let mut writer = arrow_ipc::writer::StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let (matrix, column_names) = arrow_ipc_to_matrix(buffer).unwrap();
+        assert_eq!(vec!["ASRREA".to_string(), "ITSEX".to_string()], column_names);
+        assert_eq!((3, 2), matrix.shape());
+        assert_eq!(512.5, matrix[(0, 0)]);
+        assert!(matrix[(1, 0)].is_nan());
+        assert_eq!(dvector![1.0, 2.0, 2.0], matrix.column(1).clone_owned());
+
+        assert!(arrow_ipc_to_matrix(b"no arrow".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_read_parquet() {
+        let path = "/tmp/replicest_test_read_parquet.parquet";
+        let batch = test_batch();
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let (matrix, column_names) = read_parquet(path, &[]).unwrap();
+        assert_eq!(vec!["ASRREA".to_string(), "ITSEX".to_string()], column_names);
+        assert_eq!((6, 2), matrix.shape());
+
+        let (matrix, column_names) = read_parquet(path, &["ITSEX"]).unwrap();
+        assert_eq!(vec!["ITSEX".to_string()], column_names);
+        assert_eq!(dvector![1.0, 2.0, 2.0, 1.0, 2.0, 2.0], matrix.column(0).clone_owned());
+
+        let result = read_parquet(path, &["ASRREA", "ASBGSES"]);
+        assert_eq!("Column ASBGSES not found", result.err().unwrap().to_string());
+
+        assert!(read_parquet("/tmp/replicest_test_read_parquet_missing.parquet", &[]).is_err());
+
+        let _ = remove_file(path);
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let path = "/tmp/replicest_test_read_csv.csv";
+        std::fs::write(path, "ASRREA;ITSEX;TOTWGT\n512.5;1;2.5\n;2;1.0\n480;2;1.5\n").unwrap();
+
+        let options = CsvOptions { delimiter: b';', ..CsvOptions::default() };
+        let (matrix, column_names) = read_csv(path, &options).unwrap();
+        assert_eq!(vec!["ASRREA".to_string(), "ITSEX".to_string(), "TOTWGT".to_string()], column_names);
+        assert_eq!((3, 3), matrix.shape());
+        assert!(matrix[(1, 0)].is_nan());
+
+        let options = CsvOptions { delimiter: b';', header: true, columns: vec!["TOTWGT".to_string(), "ITSEX".to_string()] };
+        let (matrix, column_names) = read_csv(path, &options).unwrap();
+        assert_eq!(vec!["TOTWGT".to_string(), "ITSEX".to_string()], column_names);
+        assert_eq!(dmatrix![2.5, 1.0; 1.0, 2.0; 1.5, 2.0], matrix);
+
+        let options = CsvOptions { delimiter: b';', header: true, columns: vec!["ASBGSES".to_string()] };
+        assert_eq!("Column ASBGSES not found", read_csv(path, &options).err().unwrap().to_string());
+
+        let options = CsvOptions { delimiter: b';', header: false, columns: vec!["x2".to_string()] };
+        let result = read_csv(path, &options);
+        assert!(result.err().unwrap().to_string().starts_with("cannot parse line 1"));
+
+        std::fs::write(path, "1,2\n3,4\n").unwrap();
+        let options = CsvOptions { header: false, columns: vec!["x2".to_string()], ..CsvOptions::default() };
+        let (matrix, column_names) = read_csv(path, &options).unwrap();
+        assert_eq!(vec!["x2".to_string()], column_names);
+        assert_eq!(dmatrix![2.0; 4.0], matrix);
+
+        let _ = remove_file(path);
+
+        let options = CsvOptions { header: false, ..CsvOptions::default() };
+        let (matrix, column_names) = read_csv("./tests/_data/wgt.csv", &options).unwrap();
+        assert_eq!(vec!["x1".to_string()], column_names);
+        assert_eq!(1, matrix.ncols());
+        assert_eq!(4.2388096190989, matrix[(0, 0)]);
+    }
+}
@@ -1,10 +1,16 @@
 pub mod estimates;
 pub mod replication;
+pub mod design;
+pub mod data_preparation;
+pub mod export;
+#[cfg(feature = "server")]
+pub mod import;
 pub mod helper;
 pub mod external;
 pub mod analysis;
 pub mod errors;
 
 pub use external::*;
+pub use errors::ReplicestError;
 
 uniffi::include_scaffolding!("replicest");
\ No newline at end of file
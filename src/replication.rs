@@ -1,6 +1,11 @@
-use std::sync::mpsc;
 use nalgebra::{DMatrix, DVector};
-use std::thread;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{ChiSquared, ContinuousCDF, Normal, StudentsT};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::errors::{ReplicateWeightError, ReplicestError};
 use crate::estimates;
 
 #[derive(Debug)]
@@ -11,6 +16,21 @@ pub struct ReplicatedEstimates {
     sampling_variances: DVector<f64>,
     imputation_variances: DVector<f64>,
     standard_errors: DVector<f64>,
+    degrees_of_freedom: DVector<f64>,
+    imputation_estimates: DMatrix<f64>,
+    replicated_estimates: Vec<DMatrix<f64>>,
+    variance_adjustment_factor: f64,
+    confidence_level: f64,
+    suppressed: bool,
+    design_effects: Option<DVector<f64>>,
+    effective_sample_sizes: Option<DVector<f64>>,
+    unweighted_estimates: Option<DVector<f64>>,
+    number_cases: Option<usize>,
+    weight_sum: Option<f64>,
+    valid_cases: Option<DVector<f64>>,
+    difference_to_overall: Option<Box<ReplicatedEstimates>>,
+    metadata: BTreeMap<String, String>,
+    warnings: Vec<String>,
 }
 
 impl ReplicatedEstimates {
@@ -33,100 +53,853 @@ impl ReplicatedEstimates {
     pub fn standard_errors(&self) -> &DVector<f64> {
         &self.standard_errors
     }
+
+    pub fn degrees_of_freedom(&self) -> &DVector<f64> {
+        &self.degrees_of_freedom
+    }
+
+    pub fn imputation_estimates(&self) -> &DMatrix<f64> {
+        &self.imputation_estimates
+    }
+
+    pub fn replicated_estimates(&self) -> &Vec<DMatrix<f64>> {
+        &self.replicated_estimates
+    }
+
+    pub fn covariance_matrix(&self) -> DMatrix<f64> {
+        let n_imp = self.imputation_estimates.ncols();
+        let n_par = self.parameter_names.len();
+
+        let mut sampling_covariance = DMatrix::<f64>::zeros(n_par, n_par);
+        for (imputation, replicated_estimates_imputation) in self.replicated_estimates.iter().enumerate() {
+            let deviations = DMatrix::from_fn(n_par, replicated_estimates_imputation.ncols(), |r, c| replicated_estimates_imputation[(r, c)] - self.imputation_estimates[(r, imputation)]);
+            sampling_covariance += &deviations * deviations.transpose() * self.variance_adjustment_factor;
+        }
+        sampling_covariance /= n_imp as f64;
+
+        if n_imp < 2 {
+            return sampling_covariance;
+        }
+
+        let deviations = DMatrix::from_fn(n_par, n_imp, |r, c| self.imputation_estimates[(r, c)] - self.final_estimates[r]);
+        let imputation_covariance = &deviations * deviations.transpose() / (n_imp - 1) as f64;
+
+        sampling_covariance + imputation_covariance * (1.0 + 1.0 / n_imp as f64)
+    }
+
+    pub fn t_statistics(&self) -> DVector<f64> {
+        self.final_estimates.component_div(&self.standard_errors)
+    }
+
+    pub fn p_values(&self) -> DVector<f64> {
+        DVector::from_fn(self.final_estimates.len(), |r, _| {
+            let t = self.final_estimates[r] / self.standard_errors[r];
+            if t.is_nan() { f64::NAN } else { 2.0 * (1.0 - reference_distribution_cdf(t.abs(), self.degrees_of_freedom[r])) }
+        })
+    }
+
+    pub fn confidence_level(&self) -> f64 {
+        self.confidence_level
+    }
+
+    pub fn design_effects(&self) -> Option<&DVector<f64>> {
+        self.design_effects.as_ref()
+    }
+
+    pub fn effective_sample_sizes(&self) -> Option<&DVector<f64>> {
+        self.effective_sample_sizes.as_ref()
+    }
+
+    pub fn set_design_effects(&mut self, srs_sampling_variances: &DVector<f64>, number_cases: usize) {
+        assert_eq!(self.sampling_variances.len(), srs_sampling_variances.len(), "dimension mismatch of sampling_variances and srs_sampling_variances in set_design_effects");
+
+        let design_effects = self.sampling_variances.component_div(srs_sampling_variances);
+        self.effective_sample_sizes = Some(design_effects.map(|deff| number_cases as f64 / deff));
+        self.design_effects = Some(design_effects);
+    }
+
+    pub fn unweighted_estimates(&self) -> Option<&DVector<f64>> {
+        self.unweighted_estimates.as_ref()
+    }
+
+    pub fn set_unweighted_estimates(&mut self, unweighted_estimates: &DVector<f64>) {
+        assert_eq!(self.final_estimates.len(), unweighted_estimates.len(), "dimension mismatch of final_estimates and unweighted_estimates in set_unweighted_estimates");
+        self.unweighted_estimates = Some(unweighted_estimates.clone());
+    }
+
+    pub fn number_cases(&self) -> Option<usize> {
+        self.number_cases
+    }
+
+    pub fn weight_sum(&self) -> Option<f64> {
+        self.weight_sum
+    }
+
+    pub fn valid_cases(&self) -> Option<&DVector<f64>> {
+        self.valid_cases.as_ref()
+    }
+
+    pub fn set_case_counts(&mut self, number_cases: usize, weight_sum: f64, valid_cases: &DVector<f64>) {
+        self.number_cases = Some(number_cases);
+        self.weight_sum = Some(weight_sum);
+        self.valid_cases = Some(valid_cases.clone());
+    }
+
+    pub fn difference_to_overall(&self) -> Option<&ReplicatedEstimates> {
+        self.difference_to_overall.as_deref()
+    }
+
+    pub fn set_difference_to_overall(&mut self, overall: &ReplicatedEstimates) -> Result<(), ReplicestError> {
+        // replicated differences keep the covariance of a group with the overall estimate it is part of
+        self.difference_to_overall = Some(Box::new(difference(self, overall)?));
+        Ok(())
+    }
+
+    pub fn significantly_different_from_overall(&self) -> Option<Vec<bool>> {
+        self.difference_to_overall.as_ref().map(|difference| {
+            difference.p_values().iter().map(|&p| p < 1.0 - self.confidence_level).collect()
+        })
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, metadata: &BTreeMap<String, String>) {
+        self.metadata = metadata.clone();
+    }
+
+    pub fn warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
+
+    pub fn add_warning(&mut self, warning: &str) {
+        self.warnings.push(warning.to_string());
+    }
+
+    pub fn suppressed(&self) -> bool {
+        self.suppressed
+    }
+
+    pub fn suppress(&mut self) {
+        self.final_estimates.fill(f64::NAN);
+        self.sampling_variances.fill(f64::NAN);
+        self.imputation_variances.fill(f64::NAN);
+        self.standard_errors.fill(f64::NAN);
+        self.imputation_estimates.fill(f64::NAN);
+        for replicated_estimates_imputation in self.replicated_estimates.iter_mut() {
+            replicated_estimates_imputation.fill(f64::NAN);
+        }
+        if let Some(unweighted_estimates) = self.unweighted_estimates.as_mut() {
+            unweighted_estimates.fill(f64::NAN);
+        }
+        self.suppressed = true;
+    }
+
+    pub fn set_confidence_level(&mut self, confidence_level: f64) -> Result<(), ReplicestError> {
+        if confidence_level <= 0.0 || confidence_level >= 1.0 || confidence_level.is_nan() {
+            return Err(ReplicestError::InvalidInput("confidence level not between 0 and 1 in set_confidence_level".to_string()));
+        }
+        self.confidence_level = confidence_level;
+        Ok(())
+    }
+
+    pub fn confidence_intervals_lower(&self) -> DVector<f64> {
+        DVector::from_fn(self.final_estimates.len(), |r, _| self.final_estimates[r] - self.standard_errors[r] * self.critical_value(r))
+    }
+
+    pub fn confidence_intervals_upper(&self) -> DVector<f64> {
+        DVector::from_fn(self.final_estimates.len(), |r, _| self.final_estimates[r] + self.standard_errors[r] * self.critical_value(r))
+    }
+
+    fn critical_value(&self, parameter: usize) -> f64 {
+        let probability = 1.0 - (1.0 - self.confidence_level) / 2.0;
+        match StudentsT::new(0.0, 1.0, self.degrees_of_freedom[parameter]) {
+            Ok(distribution) if self.degrees_of_freedom[parameter].is_finite() => distribution.inverse_cdf(probability),
+            _ => Normal::standard().inverse_cdf(probability),
+        }
+    }
+
+    pub fn set_parameter_names(&mut self, parameter_names: Vec<String>) {
+        assert_eq!(self.parameter_names.len(), parameter_names.len(), "unequal number of parameter names in set_parameter_names");
+        self.parameter_names = parameter_names;
+    }
 }
 
-pub fn replicate_estimates(estimator: fn(&DMatrix<f64>, &DVector<f64>) -> estimates::Estimates, x: &Vec<&DMatrix<f64>>, weights: &Vec<&DVector<f64>>, replicate_wgts: &Vec<&DMatrix<f64>>, factor: f64) -> ReplicatedEstimates {
-    assert!(weights.len() == 1 || weights.len() == x.len(), "length mismatch of weights and data in replicate_estimates");
-    assert!(replicate_wgts.len() == 1 || replicate_wgts.len() == x.len(), "length mismatch of replicate weights and data in replicate_estimates");
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VarianceMethod {
+    JK1,
+    JK2,
+    BRR,
+    Fay(f64),
+    Bootstrap(usize),
+}
 
-    let mut parameter_names = Vec::<String>::new();
-    let mut estimates = DMatrix::<f64>::zeros(0, 0);
-    let mut sampling_variances = DVector::<f64>::zeros(0);
+impl VarianceMethod {
+    pub fn variance_adjustment_factor(&self, number_replicates: usize) -> f64 {
+        let number_replicates = number_replicates as f64;
+
+        match self {
+            VarianceMethod::JK1 => (number_replicates - 1.0) / number_replicates,
+            // assumes both half-sample replicates of each zone are present, as in current IEA datasets
+            VarianceMethod::JK2 => 0.5,
+            VarianceMethod::BRR => 1.0 / number_replicates,
+            VarianceMethod::Fay(k) => 1.0 / (number_replicates * (1.0 - k).powi(2)),
+            VarianceMethod::Bootstrap(_) => 1.0 / number_replicates,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum NanReplicateWeightPolicy {
+    #[default]
+    Error,
+    TreatAsZero,
+    DropReplicate,
+}
+
+pub fn apply_nan_replicate_weight_policy(replicate_wgts: &DMatrix<f64>, policy: NanReplicateWeightPolicy) -> Result<DMatrix<f64>, ReplicateWeightError> {
+    let nan_columns : Vec<usize> = (0..replicate_wgts.ncols()).filter(|&c| replicate_wgts.column(c).iter().any(|w| w.is_nan())).collect();
+
+    match policy {
+        NanReplicateWeightPolicy::Error => match nan_columns.first() {
+            Some(&c) => Err(ReplicateWeightError::new(c)),
+            None => Ok(replicate_wgts.clone()),
+        },
+        NanReplicateWeightPolicy::TreatAsZero => Ok(replicate_wgts.map(|w| if w.is_nan() { 0.0 } else { w })),
+        NanReplicateWeightPolicy::DropReplicate => Ok(replicate_wgts.clone().remove_columns_at(&nan_columns)),
+    }
+}
+
+pub fn replicate_estimates<F>(estimator: F, x: &Vec<&DMatrix<f64>>, weights: &Vec<&DVector<f64>>, replicate_wgts: &Vec<&DMatrix<f64>>, factor: f64) -> Result<ReplicatedEstimates, ReplicestError>
+    where F: Fn(&DMatrix<f64>, &DVector<f64>) -> Result<estimates::Estimates, ReplicestError> + Sync
+{
+    replicate_estimates_with_threads(estimator, x, weights, replicate_wgts, factor, 0)
+}
+
+pub fn replicate_estimates_with_threads<F>(estimator: F, x: &Vec<&DMatrix<f64>>, weights: &Vec<&DVector<f64>>, replicate_wgts: &Vec<&DMatrix<f64>>, factor: f64, number_threads: usize) -> Result<ReplicatedEstimates, ReplicestError>
+    where F: Fn(&DMatrix<f64>, &DVector<f64>) -> Result<estimates::Estimates, ReplicestError> + Sync
+{
+    replicate_estimates_with_monitor(estimator, x, weights, replicate_wgts, factor, number_threads, &ReplicationMonitor::default())
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+pub type ProgressCallback = dyn Fn(usize, usize) + Send + Sync;
+
+#[derive(Clone, Default)]
+pub struct ReplicationMonitor {
+    progress_callback: Option<Arc<ProgressCallback>>,
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl ReplicationMonitor {
+    pub fn set_progress_callback(&mut self, progress_callback: Arc<ProgressCallback>) -> &mut Self {
+        self.progress_callback = Some(progress_callback);
+        self
+    }
+
+    pub fn set_cancellation_token(&mut self, cancellation_token: &CancellationToken) -> &mut Self {
+        self.cancellation_token = Some(cancellation_token.clone());
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation_token.as_ref().is_some_and(|token| token.is_cancelled())
+    }
+}
+
+fn check_replication_input(x: &[&DMatrix<f64>], weights: &[&DVector<f64>], replicate_wgts: &[&DMatrix<f64>]) -> Result<(), ReplicestError> {
+    if weights.len() != 1 && weights.len() != x.len() {
+        return Err(ReplicestError::InvalidInput("length mismatch of weights and data in replicate_estimates".to_string()));
+    }
+    if replicate_wgts.len() != 1 && replicate_wgts.len() != x.len() {
+        return Err(ReplicestError::InvalidInput("length mismatch of replicate weights and data in replicate_estimates".to_string()));
+    }
+
+    for (imputation, data) in x.iter().enumerate() {
+        let weight = if weights.len() > 1 { weights[imputation] } else { weights[0] };
+        if weight.len() != data.nrows() {
+            return Err(ReplicestError::InvalidInput("dimension mismatch of weights and data in replicate_estimates".to_string()));
+        }
+
+        let repweights = match replicate_wgts.len() {
+            0 => None,
+            1 => Some(replicate_wgts[0]),
+            _ => Some(replicate_wgts[imputation]),
+        };
+        if repweights.is_some_and(|repweights| repweights.ncols() > 0 && repweights.nrows() != data.nrows()) {
+            return Err(ReplicestError::InvalidInput("dimension mismatch of replicate weights and data in replicate_estimates".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn replicate_estimates_with_monitor<F>(estimator: F, x: &Vec<&DMatrix<f64>>, weights: &Vec<&DVector<f64>>, replicate_wgts: &Vec<&DMatrix<f64>>, factor: f64, number_threads: usize, monitor: &ReplicationMonitor) -> Result<ReplicatedEstimates, ReplicestError>
+    where F: Fn(&DMatrix<f64>, &DVector<f64>) -> Result<estimates::Estimates, ReplicestError> + Sync
+{
+    // a thread budget of 0 runs on rayon's global pool
+    if number_threads == 0 {
+        return replicate_estimates_in_current_pool(estimator, x, weights, replicate_wgts, factor, monitor);
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(number_threads).build()
+        .map_err(|error| ReplicestError::InvalidInput(error.to_string()))?;
+    let estimator = &estimator;
+    pool.install(|| replicate_estimates_in_current_pool(estimator, x, weights, replicate_wgts, factor, monitor))
+}
+
+// runs on the rayon pool it is called from, so that many replications can share one pool
+pub(crate) fn replicate_estimates_in_current_pool<F>(estimator: F, x: &Vec<&DMatrix<f64>>, weights: &Vec<&DVector<f64>>, replicate_wgts: &Vec<&DMatrix<f64>>, factor: f64, monitor: &ReplicationMonitor) -> Result<ReplicatedEstimates, ReplicestError>
+    where F: Fn(&DMatrix<f64>, &DVector<f64>) -> Result<estimates::Estimates, ReplicestError> + Sync
+{
+    check_replication_input(x, weights, replicate_wgts)?;
 
     let empty_matrix: DMatrix<f64> = DMatrix::<f64>::zeros(0, 0);
+    let estimator = &estimator;
+
+    // progress counts every evaluation of the estimator, i.e. once per imputation plus once per replicate weight
+    let number_replicates = |imputation: usize| match replicate_wgts.len() {
+        0 => 0,
+        1 => replicate_wgts[0].ncols(),
+        _ => replicate_wgts[imputation].ncols(),
+    };
+    let total_steps : usize = (0..x.len()).map(|imputation| 1 + number_replicates(imputation)).sum();
+    let completed_steps = AtomicUsize::new(0);
+    let report_progress = || {
+        let completed = completed_steps.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(progress_callback) = monitor.progress_callback.as_ref() {
+            progress_callback(completed, total_steps);
+        }
+    };
+
+    let results : Vec<(estimates::Estimates, DMatrix<f64>)> = (0..x.len()).into_par_iter().map(|imputation| {
+        let data = x[imputation];
+        let weight = if weights.len() > 1 { weights[imputation] } else { weights[0] };
+        let repweights = match replicate_wgts.len() {
+            0 => { &empty_matrix },
+            1 => { replicate_wgts[0] },
+            _ => { replicate_wgts[imputation] },
+        };
+
+        if monitor.is_cancelled() {
+            return Err(ReplicestError::Cancelled);
+        }
+        let estimates_imputation = estimator(data, weight)?;
+        report_progress();
+
+        let replicated_columns : Vec<DVector<f64>> = (0..repweights.ncols()).into_par_iter()
+            .map(|c| {
+                if monitor.is_cancelled() {
+                    return Err(ReplicestError::Cancelled);
+                }
+                let estimates_replicate = estimator(data, &DVector::from(repweights.column(c)))?;
+                report_progress();
+                Ok(estimates_replicate.estimates().clone())
+            })
+            .collect::<Result<Vec<_>, ReplicestError>>()?;
+
+        let mut replicated_estimates: DMatrix<f64> = DMatrix::<f64>::zeros(estimates_imputation.estimates().len(), repweights.ncols());
+        for (c, column) in replicated_columns.iter().enumerate() {
+            if column.len() != replicated_estimates.nrows() {
+                return Err(ReplicestError::EstimationFailed("inconsistent number of estimates between replicates".to_string()));
+            }
+            replicated_estimates.set_column(c, column);
+        }
+
+        Ok((estimates_imputation, replicated_estimates))
+    }).collect::<Result<Vec<_>, ReplicestError>>()?;
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut imputation_estimates : Vec<DVector<f64>> = Vec::new();
+    let mut replicated_estimates : Vec<DMatrix<f64>> = Vec::new();
+    for (estimates_imputation, replicated_estimates_imputation) in results {
+        parameter_names = estimates_imputation.parameter_names().clone();
+        imputation_estimates.push(estimates_imputation.estimates().clone());
+        replicated_estimates.push(replicated_estimates_imputation);
+    }
+
+    let imputation_estimates = if imputation_estimates.is_empty() {
+        DMatrix::<f64>::zeros(0, 0)
+    } else {
+        DMatrix::<f64>::from_columns(&imputation_estimates)
+    };
+
+    combine_replicated_estimates(parameter_names, imputation_estimates, replicated_estimates, factor)
+}
+
+pub fn replicate_group_difference<F>(estimator: F, x: &Vec<&DMatrix<f64>>, weights: &Vec<&DVector<f64>>, replicate_wgts: &Vec<&DMatrix<f64>>, groups: &DVector<f64>, compared_groups: (f64, f64), factor: f64) -> Result<ReplicatedEstimates, ReplicestError>
+    where F: Fn(&DMatrix<f64>, &DVector<f64>) -> Result<estimates::Estimates, ReplicestError> + Sync
+{
+    if x.iter().any(|data| data.nrows() != groups.len()) {
+        return Err(ReplicestError::InvalidInput("dimension mismatch of x and groups in replicate_group_difference".to_string()));
+    }
+
+    let (minuend_group, subtrahend_group) = compared_groups;
+    let minuend_rows : Vec<usize> = (0..groups.len()).filter(|&r| groups[r] == minuend_group).collect();
+    let subtrahend_rows : Vec<usize> = (0..groups.len()).filter(|&r| groups[r] == subtrahend_group).collect();
 
-    let (transmitter, receiver) = mpsc::channel();
-    thread::scope(|scope| {
-        for imputation in 0..x.len() {
-            let data = x[imputation];
-            let weight = if weights.len() > 1 { weights[imputation] } else { weights[0] };
-            let repweights = match replicate_wgts.len() {
-                0 => { &empty_matrix },
-                1 => { replicate_wgts[0] },
-                _ => { replicate_wgts[imputation] },
-            };
-            let transmitter1 = transmitter.clone();
-
-            scope.spawn(move || {
-                let estimates_imputation = estimator(&data, weight);
-
-                let sampling_variances_imputation: DVector<f64> = if repweights.ncols() > 0 {
-                    let mut replicated_estimates: DMatrix<f64> = DMatrix::<f64>::zeros(estimates_imputation.estimates().len(), repweights.ncols());
-                    for c in 0..repweights.ncols() {
-                        let estimates0 = estimator(&data, &DVector::from(repweights.column(c)));
-                        replicated_estimates.set_column(c, &estimates0.estimates());
-                    }
-
-                    calc_replication_variance(&estimates_imputation.estimates(), &replicated_estimates, factor)
-                } else {
-                    DVector::<f64>::zeros(estimates_imputation.estimates().len())
-                };
-                transmitter1.send((estimates_imputation, sampling_variances_imputation)).unwrap();
-            });
+    // both subsets are estimated with the same full sample or replicate weight vector within each pass of the replication loop
+    let difference_estimator = |data: &DMatrix<f64>, wgt: &DVector<f64>| {
+        let minuend = estimator(&data.select_rows(minuend_rows.iter()), &wgt.select_rows(minuend_rows.iter()))?;
+        let subtrahend = estimator(&data.select_rows(subtrahend_rows.iter()), &wgt.select_rows(subtrahend_rows.iter()))?;
+        if minuend.parameter_names() != subtrahend.parameter_names() {
+            return Err(ReplicestError::InvalidInput("unequal parameters between groups in replicate_group_difference".to_string()));
         }
+
+        Ok(estimates::Estimates::new(
+            minuend.parameter_names().iter().map(|name| format!("{}_diff_{}_vs_{}", name, minuend_group, subtrahend_group)).collect(),
+            minuend.estimates() - subtrahend.estimates(),
+        ))
+    };
+
+    replicate_estimates(difference_estimator, x, weights, replicate_wgts, factor)
+}
+
+pub type DataChunk = (Vec<DMatrix<f64>>, DVector<f64>, DMatrix<f64>);
+
+pub fn replicate_estimates_streaming<A, I>(accumulator: A, chunks: I, factor: f64) -> Result<ReplicatedEstimates, ReplicestError>
+    where A: estimates::Accumulator, I: Iterator<Item = DataChunk>
+{
+    // one accumulator per imputation for the weights and one per imputation and replicate weight column
+    let mut accumulators : Vec<(A, Vec<A>)> = Vec::new();
+
+    for (x, wgt, replicate_wgts) in chunks {
+        if accumulators.is_empty() {
+            accumulators = vec![(accumulator.clone(), vec![accumulator.clone(); replicate_wgts.ncols()]); x.len()];
+        }
+        if accumulators.len() != x.len() {
+            return Err(ReplicestError::InvalidInput("inconsistent number of imputations in replicate_estimates_streaming".to_string()));
+        }
+        if accumulators[0].1.len() != replicate_wgts.ncols() {
+            return Err(ReplicestError::InvalidInput("inconsistent number of replicate weights in replicate_estimates_streaming".to_string()));
+        }
+
+        for (data, (imputation_accumulator, replicate_accumulators)) in x.iter().zip(accumulators.iter_mut()) {
+            imputation_accumulator.accumulate(data, &wgt)?;
+            for (c, replicate_accumulator) in replicate_accumulators.iter_mut().enumerate() {
+                replicate_accumulator.accumulate(data, &DVector::from(replicate_wgts.column(c)))?;
+            }
+        }
+    }
+    if accumulators.is_empty() {
+        return Err(ReplicestError::InvalidInput("no data chunks in replicate_estimates_streaming".to_string()));
+    }
+
+    let mut parameter_names = Vec::<String>::new();
+    let mut imputation_estimates : Vec<DVector<f64>> = Vec::new();
+    let mut replicated_estimates : Vec<DMatrix<f64>> = Vec::new();
+    for (imputation_accumulator, replicate_accumulators) in accumulators.iter() {
+        let estimates_imputation = imputation_accumulator.estimates();
+
+        let mut replicated_estimates_imputation = DMatrix::<f64>::zeros(estimates_imputation.estimates().len(), replicate_accumulators.len());
+        for (c, replicate_accumulator) in replicate_accumulators.iter().enumerate() {
+            replicated_estimates_imputation.set_column(c, replicate_accumulator.estimates().estimates());
+        }
+
+        parameter_names = estimates_imputation.parameter_names().clone();
+        imputation_estimates.push(estimates_imputation.estimates().clone());
+        replicated_estimates.push(replicated_estimates_imputation);
+    }
+
+    combine_replicated_estimates(parameter_names, DMatrix::<f64>::from_columns(&imputation_estimates), replicated_estimates, factor)
+}
+
+pub fn difference(minuend: &ReplicatedEstimates, subtrahend: &ReplicatedEstimates) -> Result<ReplicatedEstimates, ReplicestError> {
+    if minuend.parameter_names != subtrahend.parameter_names {
+        return Err(ReplicestError::InvalidInput("unequal parameters in difference".to_string()));
+    }
+    if minuend.replicated_estimates.len() != subtrahend.replicated_estimates.len() {
+        return Err(ReplicestError::InvalidInput("unequal number of imputations in difference".to_string()));
+    }
+
+    let mut replicated_estimates : Vec<DMatrix<f64>> = Vec::new();
+    for (replicated_minuend, replicated_subtrahend) in minuend.replicated_estimates.iter().zip(subtrahend.replicated_estimates.iter()) {
+        if replicated_minuend.ncols() != replicated_subtrahend.ncols() {
+            return Err(ReplicestError::InvalidInput("unequal number of replicate weights in difference".to_string()));
+        }
+        replicated_estimates.push(replicated_minuend - replicated_subtrahend);
+    }
+
+    combine_replicated_estimates(
+        minuend.parameter_names.clone(),
+        &minuend.imputation_estimates - &subtrahend.imputation_estimates,
+        replicated_estimates,
+        minuend.variance_adjustment_factor,
+    )
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct LinearCombination {
+    name: String,
+    terms: Vec<(String, f64)>,
+}
+
+impl LinearCombination {
+    pub fn new(name: &str, terms: &[(&str, f64)]) -> LinearCombination {
+        LinearCombination {
+            name: name.to_string(),
+            terms: terms.iter().map(|(parameter, coefficient)| (parameter.to_string(), *coefficient)).collect(),
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn terms(&self) -> &Vec<(String, f64)> {
+        &self.terms
+    }
+}
+
+pub fn linear_combinations(estimates: &ReplicatedEstimates, combinations: &[LinearCombination]) -> Result<ReplicatedEstimates, ReplicestError> {
+    let mut coefficients = DMatrix::<f64>::zeros(combinations.len(), estimates.parameter_names.len());
+    for (r, combination) in combinations.iter().enumerate() {
+        for (parameter, coefficient) in combination.terms.iter() {
+            let position = estimates.parameter_names.iter().position(|name| name == parameter)
+                .ok_or(ReplicestError::InvalidInput(format!("unknown parameter {} in linear_combinations", parameter)))?;
+            coefficients[(r, position)] += coefficient;
+        }
+    }
+
+    // combinations are formed per imputation and replicate so that covariances between parameters are accounted for
+    let mut result = combine_replicated_estimates(
+        combinations.iter().map(|combination| combination.name.clone()).collect(),
+        &coefficients * &estimates.imputation_estimates,
+        estimates.replicated_estimates.iter().map(|replicated_estimates| &coefficients * replicated_estimates).collect(),
+        estimates.variance_adjustment_factor,
+    )?;
+    result.confidence_level = estimates.confidence_level;
+
+    Ok(result)
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct ChiSquareTest {
+    chi_square: f64,
+    mean_design_effect: f64,
+    adjusted_chi_square: f64,
+    degrees_of_freedom: f64,
+    p_value: f64,
+}
+
+impl ChiSquareTest {
+    pub fn chi_square(&self) -> f64 {
+        self.chi_square
+    }
+
+    pub fn mean_design_effect(&self) -> f64 {
+        self.mean_design_effect
+    }
+
+    pub fn adjusted_chi_square(&self) -> f64 {
+        self.adjusted_chi_square
+    }
+
+    pub fn degrees_of_freedom(&self) -> f64 {
+        self.degrees_of_freedom
+    }
+
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct WaldTest {
+    chi_square: f64,
+    degrees_of_freedom: f64,
+    p_value: f64,
+}
+
+impl WaldTest {
+    pub fn chi_square(&self) -> f64 {
+        self.chi_square
+    }
+
+    pub fn degrees_of_freedom(&self) -> f64 {
+        self.degrees_of_freedom
+    }
+
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Comparison {
+    parameter_names: Vec<String>,
+    differences: DVector<f64>,
+    standard_errors: DVector<f64>,
+    p_values: DVector<f64>,
+    confidence_level: f64,
+}
+
+impl Comparison {
+    pub fn parameter_names(&self) -> &Vec<String> {
+        &self.parameter_names
+    }
+
+    pub fn differences(&self) -> &DVector<f64> {
+        &self.differences
+    }
+
+    pub fn standard_errors(&self) -> &DVector<f64> {
+        &self.standard_errors
+    }
+
+    pub fn p_values(&self) -> &DVector<f64> {
+        &self.p_values
+    }
+
+    pub fn significant(&self) -> Vec<bool> {
+        self.p_values.iter().map(|&p| p < 1.0 - self.confidence_level).collect()
+    }
+}
+
+pub fn compare_independent(estimates_a: &ReplicatedEstimates, estimates_b: &ReplicatedEstimates) -> Comparison {
+    // only parameters present in both estimates are compared, in the order of the first
+    let aligned : Vec<(usize, usize)> = estimates_a.parameter_names.iter().enumerate()
+        .filter_map(|(i, name)| estimates_b.parameter_names.iter().position(|other| other == name).map(|j| (i, j)))
+        .collect();
+
+    // samples of different cycles are independent, so their sampling variances simply add up
+    let differences = DVector::from_iterator(aligned.len(), aligned.iter().map(|&(i, j)| estimates_a.final_estimates[i] - estimates_b.final_estimates[j]));
+    let standard_errors = DVector::from_iterator(aligned.len(), aligned.iter().map(|&(i, j)| (estimates_a.standard_errors[i].powi(2) + estimates_b.standard_errors[j].powi(2)).sqrt()));
+    let p_values = DVector::from_fn(aligned.len(), |r, _| {
+        let z = differences[r] / standard_errors[r];
+        if z.is_nan() { f64::NAN } else { 2.0 * (1.0 - reference_distribution_cdf(z.abs(), f64::INFINITY)) }
     });
 
-    drop(transmitter);
-    let mut next_column_estimates = 0;
-    for received in receiver {
-        parameter_names = received.0.parameter_names().clone();
-        if next_column_estimates == 0 {
-            estimates = DMatrix::<f64>::zeros(received.0.estimates().len(), x.len());
-            sampling_variances = DVector::<f64>::zeros(received.0.estimates().len());
+    Comparison {
+        parameter_names: aligned.iter().map(|&(i, _)| estimates_a.parameter_names[i].clone()).collect(),
+        differences,
+        standard_errors,
+        p_values,
+        confidence_level: estimates_a.confidence_level,
+    }
+}
+
+pub fn wald_test(estimates: &ReplicatedEstimates, constraints: &[LinearCombination]) -> Result<WaldTest, ReplicestError> {
+    if constraints.is_empty() {
+        return Err(ReplicestError::InvalidInput("no constraints in wald_test".to_string()));
+    }
+
+    // tests the joint hypothesis that all linear combinations are zero
+    let combined = linear_combinations(estimates, constraints)?;
+    let covariance = combined.covariance_matrix();
+
+    let chi_square = match covariance.try_inverse() {
+        Some(inverse) => (combined.final_estimates.transpose() * inverse * &combined.final_estimates)[(0, 0)],
+        None => f64::NAN,
+    };
+    let degrees_of_freedom = constraints.len() as f64;
+    let p_value = if chi_square.is_nan() { f64::NAN } else { 1.0 - ChiSquared::new(degrees_of_freedom).unwrap().cdf(chi_square) };
+
+    Ok(WaldTest {
+        chi_square,
+        degrees_of_freedom,
+        p_value,
+    })
+}
+
+pub fn wald_test_parameters(estimates: &ReplicatedEstimates, parameters: &[&str]) -> Result<WaldTest, ReplicestError> {
+    let constraints : Vec<LinearCombination> = parameters.iter().map(|&parameter| LinearCombination::new(parameter, &[(parameter, 1.0)])).collect();
+    wald_test(estimates, &constraints)
+}
+
+pub fn rao_scott_chi_square(crosstab: &ReplicatedEstimates) -> Result<ChiSquareTest, ReplicestError> {
+    let (number_rows, number_columns) = crosstab_dimensions(&crosstab.parameter_names)
+        .ok_or(ReplicestError::InvalidInput("no crosstab estimates in rao_scott_chi_square".to_string()))?;
+    let number_cells = number_rows * number_columns;
+
+    let proportions = &crosstab.final_estimates;
+    let variances = crosstab.standard_errors.map(|v| v.powi(2));
+    let n = proportions[proportions.len() - 1];
+    let degrees_of_freedom = ((number_rows - 1) * (number_columns - 1)) as f64;
+
+    let mut chi_square = 0.0;
+    let mut sum_cells = 0.0;
+    for a in 0..number_rows {
+        for b in 0..number_columns {
+            let expected = proportions[number_cells + a] * proportions[number_cells + number_rows + b];
+            if expected > 0.0 {
+                chi_square += n * (proportions[a * number_columns + b] - expected).powi(2) / expected;
+            }
+            if expected > 0.0 {
+                sum_cells += variances[a * number_columns + b] / expected;
+            }
         }
-        estimates.set_column(next_column_estimates, &received.0.estimates());
-        sampling_variances += &received.1;
-        next_column_estimates += 1;
+    }
+    let sum_margins : f64 = (number_cells..number_cells + number_rows + number_columns)
+        .filter(|&i| proportions[i] > 0.0)
+        .map(|i| variances[i] / proportions[i])
+        .sum();
+
+    // first-order correction by the mean generalized design effect (Rao & Scott, 1984)
+    let mean_design_effect = n * (sum_cells - sum_margins) / degrees_of_freedom;
+    let adjusted_chi_square = chi_square / mean_design_effect;
+    let p_value = match ChiSquared::new(degrees_of_freedom) {
+        Ok(distribution) if adjusted_chi_square.is_finite() => 1.0 - distribution.cdf(adjusted_chi_square),
+        _ => f64::NAN,
+    };
+
+    Ok(ChiSquareTest {
+        chi_square,
+        mean_design_effect,
+        adjusted_chi_square,
+        degrees_of_freedom,
+        p_value,
+    })
+}
+
+// a crosstab lists its cells row by row, then the row margins, the column margins and the number of cases,
+// where the name of each cell joins the names of its row and column margin
+fn crosstab_dimensions(parameter_names: &[String]) -> Option<(usize, usize)> {
+    let number_parameters = parameter_names.len();
+    if parameter_names.last().is_none_or(|name| name != "crosstab_n") {
+        return None;
+    }
+
+    (1..number_parameters)
+        .filter(|&number_rows| number_parameters.is_multiple_of(number_rows + 1) && number_parameters / (number_rows + 1) > 1)
+        .map(|number_rows| (number_rows, number_parameters / (number_rows + 1) - 1))
+        .find(|&(number_rows, number_columns)| {
+            let number_cells = number_rows * number_columns;
+            (0..number_rows).all(|a| (0..number_columns).all(|b| {
+                let row_name = &parameter_names[number_cells + a];
+                let column_name = &parameter_names[number_cells + number_rows + b];
+                column_name.strip_prefix("crosstab_").is_some_and(|column_name| parameter_names[a * number_columns + b] == format!("{}_{}", row_name, column_name))
+            }))
+        })
+}
+
+fn reference_distribution_cdf(value: f64, degrees_of_freedom: f64) -> f64 {
+    match StudentsT::new(0.0, 1.0, degrees_of_freedom) {
+        Ok(distribution) if degrees_of_freedom.is_finite() => distribution.cdf(value),
+        _ => Normal::standard().cdf(value),
+    }
+}
+
+fn combine_replicated_estimates(parameter_names: Vec<String>, imputation_estimates: DMatrix<f64>, replicated_estimates: Vec<DMatrix<f64>>, factor: f64) -> Result<ReplicatedEstimates, ReplicestError> {
+    let n_imp = imputation_estimates.ncols();
+
+    let mut sampling_variances = DVector::<f64>::zeros(imputation_estimates.nrows());
+    for (imputation, replicated_estimates_imputation) in replicated_estimates.iter().enumerate() {
+        sampling_variances += calc_replication_variance(&DVector::from(imputation_estimates.column(imputation)), replicated_estimates_imputation, factor)?;
     }
 
-    let final_estimates = DVector::from_fn(estimates.nrows(), |r, _| { estimates.row(r).mean() });
-    sampling_variances /= x.len() as f64;
-    let imputation_variances = if x.len() > 1 {
-        calc_replication_variance(&final_estimates, &estimates, 1.0 / (x.len() - 1) as f64)
+    let final_estimates = DVector::from_fn(imputation_estimates.nrows(), |r, _| { imputation_estimates.row(r).mean() });
+    sampling_variances /= n_imp as f64;
+    let imputation_variances = if n_imp > 1 {
+        calc_replication_variance(&final_estimates, &imputation_estimates, 1.0 / (n_imp - 1) as f64)?
     } else {
         DVector::<f64>::zeros(sampling_variances.len())
     };
-    let standard_errors = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, x.len());
+    let standard_errors = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, n_imp)?;
 
-    ReplicatedEstimates {
+    // without replicate weights, the complete-data degrees of freedom are treated as infinite
+    let n_rep = replicated_estimates.first().map(|r| r.ncols()).unwrap_or(0);
+    let complete_data_degrees_of_freedom = if n_rep > 0 { n_rep as f64 } else { f64::INFINITY };
+    let degrees_of_freedom = calc_degrees_of_freedom(&sampling_variances, &imputation_variances, n_imp, complete_data_degrees_of_freedom)?;
+
+    Ok(ReplicatedEstimates {
         parameter_names,
         final_estimates,
         sampling_variances,
         imputation_variances,
         standard_errors,
-    }
+        degrees_of_freedom,
+        imputation_estimates,
+        replicated_estimates,
+        variance_adjustment_factor: factor,
+        confidence_level: 0.95,
+        suppressed: false,
+        design_effects: None,
+        effective_sample_sizes: None,
+        unweighted_estimates: None,
+        number_cases: None,
+        weight_sum: None,
+        valid_cases: None,
+        difference_to_overall: None,
+        metadata: BTreeMap::new(),
+        warnings: Vec::new(),
+    })
 }
 
-fn calc_replication_variance(estimates: &DVector<f64>, replicated_estimates: &DMatrix<f64>, factor: f64) -> DVector<f64> {
-    assert_eq!(estimates.len(), replicated_estimates.nrows(), "dimension mismatch of estimates and replicated_estimates in calc_replication_variance");
+fn calc_replication_variance(estimates: &DVector<f64>, replicated_estimates: &DMatrix<f64>, factor: f64) -> Result<DVector<f64>, ReplicestError> {
+    if estimates.len() != replicated_estimates.nrows() {
+        return Err(ReplicestError::InvalidInput("dimension mismatch of estimates and replicated_estimates in calc_replication_variance".to_string()));
+    }
 
     let final_estimates_repeated = DMatrix::from_fn(estimates.len(), replicated_estimates.ncols(), |r, _| estimates[r]);
     let deviations = replicated_estimates - final_estimates_repeated;
 
-    DVector::from_fn(deviations.nrows(), |r, _| { deviations.row(r).map(|v| v.powf(2.0_f64)).sum() * factor })
+    Ok(DVector::from_fn(deviations.nrows(), |r, _| { deviations.row(r).map(|v| v.powf(2.0_f64)).sum() * factor }))
+}
+
+fn calc_standard_errors_from_variances(sampling_variances: &DVector<f64>, imputation_variances: &DVector<f64>, n_imp: usize) -> Result<DVector<f64>, ReplicestError> {
+    if sampling_variances.len() != imputation_variances.len() {
+        return Err(ReplicestError::InvalidInput("dimension mismatch of sampling_variances and imputation_variances in calc_standard_error_from_variances".to_string()));
+    }
+
+    Ok((sampling_variances + (imputation_variances * (1.0 + (1.0 / n_imp as f64)))).map(|v| v.sqrt()))
 }
 
-fn calc_standard_errors_from_variances(sampling_variances: &DVector<f64>, imputation_variances: &DVector<f64>, n_imp: usize) -> DVector<f64> {
-    assert_eq!(sampling_variances.len(), imputation_variances.len(), "dimension mismatch of sampling_variances and imputation_variances in calc_standard_error_from_variances");
+fn calc_degrees_of_freedom(sampling_variances: &DVector<f64>, imputation_variances: &DVector<f64>, n_imp: usize, complete_data_degrees_of_freedom: f64) -> Result<DVector<f64>, ReplicestError> {
+    if sampling_variances.len() != imputation_variances.len() {
+        return Err(ReplicestError::InvalidInput("dimension mismatch of sampling_variances and imputation_variances in calc_degrees_of_freedom".to_string()));
+    }
 
-    (sampling_variances + (imputation_variances * (1.0 + (1.0 / n_imp as f64)))).map(|v| v.sqrt())
+    if n_imp < 2 {
+        return Ok(DVector::from_element(sampling_variances.len(), complete_data_degrees_of_freedom));
+    }
+
+    // Barnard & Rubin (1999) small-sample degrees of freedom
+    Ok(DVector::from_fn(sampling_variances.len(), |r, _| {
+        let between_variance = imputation_variances[r] * (1.0 + 1.0 / n_imp as f64);
+        let total_variance = sampling_variances[r] + between_variance;
+        let lambda = if total_variance > 0.0 { between_variance / total_variance } else { 0.0 };
+
+        let degrees_of_freedom_old = (n_imp - 1) as f64 / lambda.powi(2);
+        let degrees_of_freedom_observed = if complete_data_degrees_of_freedom.is_finite() {
+            (complete_data_degrees_of_freedom + 1.0) / (complete_data_degrees_of_freedom + 3.0) * complete_data_degrees_of_freedom * (1.0 - lambda)
+        } else {
+            f64::INFINITY
+        };
+
+        1.0 / (1.0 / degrees_of_freedom_old + 1.0 / degrees_of_freedom_observed)
+    }))
 }
 
 #[cfg(test)]
 mod tests {
     use nalgebra::{dmatrix, dvector};
     use crate::assert_approx_eq_iter_f64;
-    use crate::estimates::mean;
+    use std::sync::Arc;
+    use crate::estimates::{crosstab, mean, named_crosstab, t_test};
     use super::*;
 
     #[test]
@@ -146,7 +919,7 @@ mod tests {
             1.5, 1.5, 0.0,
         ]);
 
-        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0/3.0);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
         assert_eq!(result.final_estimates, dvector![2.25, 3.125, 2.0, -2.5]);
         assert_eq!(result.sampling_variances, dvector![0.6370833333333332, 0.18843749999999995, 0.815, 1.0416666666666665]);
         assert_eq!(result.imputation_variances, dvector![0.0, 0.0, 0.0, 0.0]);
@@ -178,7 +951,7 @@ mod tests {
         let wgt = dvector![1.0, 0.5, 1.5];
         let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
 
-        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 1.0);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
         assert_approx_eq_iter_f64!(result.final_estimates, dvector![2.25, 3.125, 2.0, -2.5]);
         assert_approx_eq_iter_f64!(result.sampling_variances, dvector![0.0, 0.0, 0.0, 0.0]);
         assert_approx_eq_iter_f64!(result.imputation_variances, dvector![0.0069444444444443955, 0.0, 0.0002777777777777758, 0.0]);
@@ -214,7 +987,7 @@ mod tests {
             1.5, 1.5, 0.0,
         ]);
 
-        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 1.0);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
         assert_eq!(4, result.parameter_names.len());
         assert_eq!("mean_x2", result.parameter_names[1]);
         assert_approx_eq_iter_f64!(result.final_estimates, dvector![2.25, 3.125, 2.0, -2.5]);
@@ -224,7 +997,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "a scoped thread panicked")]
     fn test_replicate_estimate_mean_nan_in_replicate_weight() {
         let mut imp_data: Vec<&DMatrix<f64>> = Vec::new();
         let data0 = DMatrix::from_row_slice(3, 4, &[
@@ -241,7 +1013,8 @@ mod tests {
             1.5, 1.5, 0.0,
         ]);
 
-        replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0_f64/3.0_f64);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0_f64/3.0_f64);
+        assert_eq!("Invalid input: wgt contains NaN in mean", result.unwrap_err().to_string());
     }
 
     #[test]
@@ -261,7 +1034,7 @@ mod tests {
             1.5, 1.5, 0.0,
         ]);
 
-        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0_f64/3.0_f64);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0_f64/3.0_f64).unwrap();
         assert_eq!(1, result.parameter_names.len());
         assert_eq!("mean_x1", result.parameter_names[0]);
         assert_eq!(1, result.final_estimates.len());
@@ -283,11 +1056,521 @@ mod tests {
         let wgt = dvector![1.0, 0.5, 1.5];
         let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
 
-        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0_f64/3.0_f64);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt], &vec![&rep_wgts], 2.0_f64/3.0_f64).unwrap();
         assert_eq!(result.final_estimates, dvector![2.25, 3.125, 2.0, -2.5]);
         assert_eq!(result.sampling_variances, dvector![0.0, 0.0, 0.0, 0.0]);
     }
 
+    #[test]
+    fn test_replicate_estimates_preserves_imputation_order() {
+        let imputations : Vec<DMatrix<f64>> = (0..8).map(|i| DMatrix::from_element(50, 1, i as f64)).collect();
+        let wgt = DVector::from_element(50, 1.0);
+        let rep_wgts = DMatrix::from_fn(50, 10, |r, c| if r % 10 == c { 0.0 } else { 1.0 });
+
+        for _ in 0..5 {
+            let result = replicate_estimates(mean, &imputations.iter().collect(), &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+
+            assert_eq!(&DMatrix::from_fn(1, 8, |_, c| c as f64), result.imputation_estimates());
+            for (imputation, replicated_estimates_imputation) in result.replicated_estimates().iter().enumerate() {
+                assert_eq!(&DMatrix::from_element(1, 10, imputation as f64), replicated_estimates_imputation);
+            }
+        }
+    }
+
+    #[test]
+    fn test_replicate_estimates_with_monitor() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let progress = Arc::new(std::sync::Mutex::new(Vec::<(usize, usize)>::new()));
+        let progress_clone = progress.clone();
+        let mut monitor = ReplicationMonitor::default();
+        monitor.set_progress_callback(Arc::new(move |completed, total| progress_clone.lock().unwrap().push((completed, total))));
+
+        let result = replicate_estimates_with_monitor(mean, &vec![&data, &data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0, 2, &monitor).unwrap();
+        assert_approx_eq_iter_f64!(result.final_estimates, dvector![3.0]);
+
+        let mut progress = progress.lock().unwrap().clone();
+        progress.sort();
+        assert_eq!((1..=8).map(|completed| (completed, 8)).collect::<Vec<(usize, usize)>>(), progress);
+    }
+
+    #[test]
+    fn test_replicate_estimates_with_monitor_cancelled() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
+
+        let token = CancellationToken::new();
+        let mut monitor = ReplicationMonitor::default();
+        monitor.set_cancellation_token(&token);
+        assert!(replicate_estimates_with_monitor(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 1.0, 0, &monitor).is_ok());
+
+        token.cancel();
+        let result = replicate_estimates_with_monitor(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 1.0, 0, &monitor);
+        assert_eq!("Calculation was cancelled", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_replicate_estimates_with_threads() {
+        let imp1 = dmatrix![1.0, 2.0; 3.0, 4.0; 5.0, 6.0; 7.0, 9.0];
+        let imp2 = dmatrix![1.0, 2.0; 3.0, 5.0; 5.0, 6.0; 7.0, 8.0];
+        let wgt = dvector![1.0, 2.0, 1.0, 1.5];
+        let rep_wgts = DMatrix::from_fn(4, 20, |r, c| if r == c % 4 { 0.0 } else { wgt[r] * (1.0 + c as f64 / 20.0) });
+
+        let sequential = replicate_estimates_with_threads(estimates::correlation, &vec![&imp1, &imp2], &vec![&wgt], &vec![&rep_wgts], 1.0, 1).unwrap();
+        let parallel = replicate_estimates_with_threads(estimates::correlation, &vec![&imp1, &imp2], &vec![&wgt], &vec![&rep_wgts], 1.0, 4).unwrap();
+
+        assert_eq!(sequential.parameter_names, parallel.parameter_names);
+        assert_eq!(sequential.imputation_estimates, parallel.imputation_estimates);
+        assert_eq!(sequential.replicated_estimates, parallel.replicated_estimates);
+        assert_eq!(sequential.standard_errors, parallel.standard_errors);
+    }
+
+    #[test]
+    fn test_replicate_estimates_streaming() {
+        let imp1 = dmatrix![1.0, 2.0; 3.0, 4.0; 5.0, 6.0; 7.0, 9.0; 2.0, f64::NAN];
+        let imp2 = dmatrix![1.0, 2.0; 3.0, 5.0; 5.0, 6.0; 7.0, 8.0; 4.0, 1.0];
+        let wgt = dvector![1.0, 2.0, 1.0, 1.5, 0.5];
+        let rep_wgts = DMatrix::from_fn(5, 5, |r, c| if r == c { 0.0 } else { wgt[r] * 1.25 });
+
+        let chunks = [(0, 2), (2, 2), (4, 1)].map(|(start, length)| (
+            vec![imp1.rows(start, length).clone_owned(), imp2.rows(start, length).clone_owned()],
+            wgt.rows(start, length).clone_owned(),
+            rep_wgts.rows(start, length).clone_owned(),
+        ));
+
+        let streamed = replicate_estimates_streaming(estimates::MeanAccumulator::default(), chunks.into_iter(), 0.8).unwrap();
+        let expected = replicate_estimates(mean, &vec![&imp1, &imp2], &vec![&wgt], &vec![&rep_wgts], 0.8).unwrap();
+
+        assert_eq!(expected.parameter_names, streamed.parameter_names);
+        assert_approx_eq_iter_f64!(streamed.final_estimates, expected.final_estimates);
+        assert_approx_eq_iter_f64!(streamed.sampling_variances, expected.sampling_variances);
+        assert_approx_eq_iter_f64!(streamed.imputation_variances, expected.imputation_variances);
+    }
+
+    #[test]
+    fn test_replicate_estimates_streaming_without_chunks() {
+        let result = replicate_estimates_streaming(estimates::TotalAccumulator::default(), Vec::<DataChunk>::new().into_iter(), 1.0);
+        assert_eq!("Invalid input: no data chunks in replicate_estimates_streaming", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_replicate_estimates_with_shared_closure() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let mu = 1.0;
+        let estimate : Arc<estimates::EstimateFunction> = Arc::new(move |x, wgt| t_test(x, wgt, mu));
+        let from_shared = replicate_estimates(estimate.as_ref(), &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        let from_closure = replicate_estimates(|x, wgt| t_test(x, wgt, mu), &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+
+        assert_eq!(from_closure.parameter_names, from_shared.parameter_names);
+        assert_eq!(from_closure.final_estimates, from_shared.final_estimates);
+        assert_eq!(from_closure.sampling_variances, from_shared.sampling_variances);
+    }
+
+    #[test]
+    fn test_variance_method_adjustment_factors() {
+        assert_eq!(0.75, VarianceMethod::JK1.variance_adjustment_factor(4));
+        assert_eq!(0.5, VarianceMethod::JK2.variance_adjustment_factor(150));
+        assert_eq!(0.125, VarianceMethod::BRR.variance_adjustment_factor(8));
+        assert_eq!(0.5, VarianceMethod::Fay(0.5).variance_adjustment_factor(8));
+        assert_eq!(0.01, VarianceMethod::Bootstrap(100).variance_adjustment_factor(100));
+    }
+
+    #[test]
+    fn test_apply_nan_replicate_weight_policy() {
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            0.5, f64::NAN, 0.5,
+            1.5, 1.5, 0.0,
+        ]);
+
+        let result = apply_nan_replicate_weight_policy(&rep_wgts, NanReplicateWeightPolicy::Error);
+        assert_eq!("Replicate weight column 2 contains NaN", result.unwrap_err().to_string());
+
+        let result = apply_nan_replicate_weight_policy(&rep_wgts, NanReplicateWeightPolicy::TreatAsZero).unwrap();
+        assert_eq!(dmatrix![0.0, 1.0, 1.0; 0.5, 0.0, 0.5; 1.5, 1.5, 0.0], result);
+
+        let result = apply_nan_replicate_weight_policy(&rep_wgts, NanReplicateWeightPolicy::DropReplicate).unwrap();
+        assert_eq!(dmatrix![0.0, 1.0; 0.5, 0.5; 1.5, 0.0], result);
+
+        let result = apply_nan_replicate_weight_policy(&result, NanReplicateWeightPolicy::Error).unwrap();
+        assert_eq!(dmatrix![0.0, 1.0; 0.5, 0.5; 1.5, 0.0], result);
+    }
+
+    #[test]
+    fn test_linear_combinations() {
+        let data = dmatrix![1.0, 2.0; 3.0, 3.0; 5.0, 7.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        let combinations = [
+            LinearCombination::new("mean_x2_minus_x1", &[("mean_x2", 1.0), ("mean_x1", -1.0)]),
+            LinearCombination::new("composite", &[("mean_x1", 0.5), ("mean_x2", 0.5)]),
+        ];
+        let combined = linear_combinations(&result, &combinations).unwrap();
+
+        assert_eq!(vec!["mean_x2_minus_x1", "composite"], combined.parameter_names);
+        assert_approx_eq_iter_f64!(combined.final_estimates, dvector![1.0, 3.5]);
+        // replicated differences are 1.0, 1.5 and 0.5, replicated composites are 4.5, 3.75 and 2.25
+        assert_approx_eq_iter_f64!(combined.sampling_variances, dvector![0.5 * 2.0 / 3.0, 2.625 * 2.0 / 3.0]);
+        assert_eq!(result.confidence_level, combined.confidence_level);
+    }
+
+    #[test]
+    fn test_linear_combinations_unknown_parameter() {
+        let data = dmatrix![1.0, 2.0; 3.0, 3.0; 5.0, 7.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
+
+        let result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+        assert_eq!("Invalid input: unknown parameter mean_x3 in linear_combinations", linear_combinations(&result, &[LinearCombination::new("difference", &[("mean_x3", 1.0), ("mean_x1", -1.0)])]).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_covariance_matrix() {
+        let imp1 = dmatrix![1.0, 2.0; 3.0, 3.0; 5.0, 7.0];
+        let imp2 = dmatrix![1.0, 2.0; 3.0, 4.0; 5.0, 6.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let result = replicate_estimates(mean, &vec![&imp1, &imp2], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        let covariance = result.covariance_matrix();
+
+        assert_approx_eq_iter_f64!(covariance.diagonal(), result.standard_errors.map(|se| se.powi(2)));
+        assert_eq!(covariance, covariance.transpose());
+    }
+
+    #[test]
+    fn test_compare_independent() {
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+        let wgt = dvector![1.0, 1.0, 1.0];
+
+        let data_a = dmatrix![1.0, 2.0; 3.0, 3.0; 5.0, 7.0];
+        let result_a = replicate_estimates(mean, &vec![&data_a], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        let data_b = dmatrix![2.0; 2.0; 2.0];
+        let mut result_b = replicate_estimates(mean, &vec![&data_b], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        result_b.set_parameter_names(vec!["mean_x2".to_string()]);
+
+        let comparison = compare_independent(&result_a, &result_b);
+        assert_eq!(&vec!["mean_x2".to_string()], comparison.parameter_names());
+        assert_approx_eq_iter_f64!(comparison.differences(), dvector![2.0]);
+        assert_approx_eq_iter_f64!(comparison.standard_errors(), result_a.standard_errors().rows(1, 1));
+        assert_approx_eq_iter_f64!(comparison.p_values(), dvector![2.0 * (1.0 - Normal::standard().cdf(2.0 / result_a.standard_errors()[1]))]);
+        assert_eq!(vec![false], comparison.significant());
+
+        let comparison = compare_independent(&result_a, &result_a);
+        assert_approx_eq_iter_f64!(comparison.differences(), dvector![0.0, 0.0]);
+        assert_approx_eq_iter_f64!(comparison.standard_errors(), result_a.standard_errors() * 2.0_f64.sqrt());
+    }
+
+    #[test]
+    fn test_wald_test() {
+        let data = dmatrix![1.0, 2.0; 3.0, 3.0; 5.0, 7.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+
+        // a single constraint reduces to the squared t statistic
+        let test = wald_test(&result, &[LinearCombination::new("difference", &[("mean_x2", 1.0), ("mean_x1", -1.0)])]).unwrap();
+        assert_eq!(1.0, test.degrees_of_freedom());
+        assert!((test.chi_square() - 1.0 / (0.5 * 2.0 / 3.0)).abs() < 1e-10);
+        assert!((test.p_value() - 0.08326451666355043).abs() < 1e-10);
+
+        let test = wald_test_parameters(&result, &["mean_x1", "mean_x2"]).unwrap();
+        assert_eq!(2.0, test.degrees_of_freedom());
+        let covariance = result.covariance_matrix();
+        let expected = (result.final_estimates.transpose() * covariance.try_inverse().unwrap() * &result.final_estimates)[(0, 0)];
+        assert!((test.chi_square() - expected).abs() < 1e-10);
+        assert!((test.p_value() - (-expected / 2.0).exp()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_replicate_group_difference() {
+        let data = dmatrix![1.0; 3.0; 5.0; 2.0; 4.0; 9.0];
+        let groups = dvector![1.0, 1.0, 1.0, 2.0, 2.0, 2.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_fn(6, 3, |r, c| if r % 3 == c { 0.0 } else { 1.0 });
+
+        let result = replicate_group_difference(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], &groups, (2.0, 1.0), 2.0/3.0).unwrap();
+        assert_eq!("mean_x1_diff_2_vs_1", result.parameter_names[0]);
+        assert_approx_eq_iter_f64!(result.final_estimates, dvector![2.0]);
+        // replicated differences are 2.5, 2.5 and 1.0
+        assert_approx_eq_iter_f64!(result.sampling_variances, dvector![1.5 * 2.0 / 3.0]);
+
+        let data_group1 = data.rows(0, 3).clone_owned();
+        let data_group2 = data.rows(3, 3).clone_owned();
+        let wgt_group = dvector![1.0, 1.0, 1.0];
+        let rep_wgts_group = rep_wgts.rows(0, 3).clone_owned();
+        let expected = difference(
+            &replicate_estimates(mean, &vec![&data_group2], &vec![&wgt_group], &vec![&rep_wgts_group], 2.0/3.0).unwrap(),
+            &replicate_estimates(mean, &vec![&data_group1], &vec![&wgt_group], &vec![&rep_wgts_group], 2.0/3.0).unwrap(),
+        ).unwrap();
+        assert_approx_eq_iter_f64!(result.standard_errors, expected.standard_errors);
+    }
+
+    #[test]
+    fn test_difference() {
+        let data_group1 = dmatrix![1.0; 3.0; 5.0];
+        let data_group2 = dmatrix![2.0; 2.0; 8.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let result_group1 = replicate_estimates(mean, &vec![&data_group1], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        let result_group2 = replicate_estimates(mean, &vec![&data_group2], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+
+        let result = difference(&result_group1, &result_group2).unwrap();
+        assert_eq!("mean_x1", result.parameter_names[0]);
+        assert_approx_eq_iter_f64!(result.final_estimates, dvector![-1.0]);
+        // replicated differences are -1.0, -2.0 and 0.0, i.e. deviations of 0.0, -1.0 and 1.0 from the difference
+        assert_approx_eq_iter_f64!(result.sampling_variances, dvector![2.0 * 2.0 / 3.0]);
+        assert_approx_eq_iter_f64!(result.imputation_variances, dvector![0.0]);
+    }
+
+    #[test]
+    fn test_t_statistics_and_p_values() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let result = replicate_estimates(|x, wgt| t_test(x, wgt, 1.0), &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        assert_eq!("mean_diff_1_x1", result.parameter_names[0]);
+        assert_approx_eq_iter_f64!(result.final_estimates, dvector![2.0]);
+        // replicated means are 4.0, 3.0 and 2.0, i.e. a sampling variance of 2.0 * 2.0 / 3.0
+        let standard_error = (4.0_f64 / 3.0).sqrt();
+        assert_approx_eq_iter_f64!(result.t_statistics(), dvector![2.0 / standard_error]);
+        // three replicate weights give three degrees of freedom
+        assert_approx_eq_iter_f64!(result.degrees_of_freedom(), dvector![3.0]);
+        assert_approx_eq_iter_f64!(result.p_values(), dvector![0.5 - 1.0 / std::f64::consts::PI]);
+    }
+
+    #[test]
+    fn test_confidence_intervals() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let mut result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        let standard_error = (4.0_f64 / 3.0).sqrt();
+        assert_eq!(0.95, result.confidence_level());
+        assert_approx_eq_iter_f64!(result.confidence_intervals_lower(), dvector![3.0 - 3.182446305284263 * standard_error], 1e-8);
+        assert_approx_eq_iter_f64!(result.confidence_intervals_upper(), dvector![3.0 + 3.182446305284263 * standard_error], 1e-8);
+
+        result.set_confidence_level(0.9).unwrap();
+        assert_approx_eq_iter_f64!(result.confidence_intervals_upper(), dvector![3.0 + 2.353363434801823 * standard_error], 1e-8);
+    }
+
+    #[test]
+    fn test_set_design_effects() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let mut result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        assert!(result.design_effects().is_none());
+
+        result.set_design_effects(&dvector![2.0 / 3.0], 3);
+        assert_approx_eq_iter_f64!(result.design_effects().unwrap(), dvector![2.0]);
+        assert_approx_eq_iter_f64!(result.effective_sample_sizes().unwrap(), dvector![1.5]);
+    }
+
+    #[test]
+    fn test_suppress() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 1.0,
+            1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0,
+        ]);
+
+        let mut result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0/3.0).unwrap();
+        assert!(!result.suppressed());
+
+        result.suppress();
+        assert!(result.suppressed());
+        assert_eq!(vec!["mean_x1"], result.parameter_names);
+        assert!(result.final_estimates[0].is_nan());
+        assert!(result.standard_errors[0].is_nan());
+        assert!(result.confidence_intervals_lower()[0].is_nan());
+        assert!(result.replicated_estimates[0].iter().all(|e| e.is_nan()));
+    }
+
+    #[test]
+    fn test_set_confidence_level_error() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
+
+        let mut result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+        assert_eq!("Invalid input: confidence level not between 0 and 1 in set_confidence_level", result.set_confidence_level(95.0).unwrap_err().to_string());
+        assert!(result.set_confidence_level(f64::NAN).is_err());
+        assert_eq!(0.95, result.confidence_level());
+    }
+
+    #[test]
+    fn test_rao_scott_chi_square() {
+        let data = DMatrix::from_row_slice(8, 2, &[
+            1.0, 1.0,
+            1.0, 1.0,
+            1.0, 2.0,
+            2.0, 1.0,
+            2.0, 2.0,
+            2.0, 2.0,
+            2.0, 2.0,
+            1.0, 1.0,
+        ]);
+        let wgt = DVector::from_element(8, 1.0);
+        let rep_wgts = DMatrix::from_fn(8, 4, |r, c| if r / 2 == c { 0.0 } else { 1.0 });
+
+        let result = replicate_estimates(crosstab, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 0.75).unwrap();
+        let test = rao_scott_chi_square(&result).unwrap();
+
+        // all margins are 0.5 with variances of 1/24, the cells 3/8, 1/8, 1/8 and 3/8 have variances of 11/192, 1/64, 1/64 and 11/192,
+        // so that the mean design effect is 8 * ((11/96 + 1/32) / 0.25 - 4 * (1/24) / 0.5) = 2 by equation 4.3 of Rao & Scott (1984)
+        assert_approx_eq_iter_f64!(
+            dvector![test.chi_square(), test.mean_design_effect(), test.adjusted_chi_square(), test.degrees_of_freedom(), test.p_value()],
+            dvector![2.0, 2.0, 1.0, 1.0, 0.31731050786291415]
+        );
+
+        // the dimensions of the table do not depend on the variable names
+        let named_crosstab = |x: &DMatrix<f64>, wgt: &DVector<f64>| named_crosstab(x, wgt, &["IT_SEX".to_string(), "x1".to_string()]);
+        let result = replicate_estimates(named_crosstab, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 0.75).unwrap();
+        assert_eq!("crosstab_IT_SEX_1_x1_2", result.parameter_names()[1]);
+        assert_approx_eq_iter_f64!(dvector![rao_scott_chi_square(&result).unwrap().mean_design_effect()], dvector![2.0]);
+
+        let data = DMatrix::from_row_slice(6, 2, &[
+            1.0, 1.0,
+            1.0, 2.0,
+            1.0, 3.0,
+            2.0, 1.0,
+            2.0, 3.0,
+            2.0, 3.0,
+        ]);
+        let wgt = DVector::from_element(6, 1.0);
+        let rep_wgts = DMatrix::from_fn(6, 3, |r, c| if r / 2 == c { 0.0 } else { 1.5 });
+        let result = replicate_estimates(crosstab, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 2.0 / 3.0).unwrap();
+        assert_eq!(2.0, rao_scott_chi_square(&result).unwrap().degrees_of_freedom());
+    }
+
+    #[test]
+    fn test_rao_scott_chi_square_error_no_crosstab() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
+
+        let result = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+        assert_eq!("Invalid input: no crosstab estimates in rao_scott_chi_square", rao_scott_chi_square(&result).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_difference_to_overall() {
+        let data = dmatrix![1.0; 3.0; 5.0; 8.0];
+        let wgt = dvector![1.0, 1.0, 1.0, 1.0];
+        let rep_wgts = dmatrix![
+            0.0, 2.0, 1.0, 1.0;
+            2.0, 0.0, 1.0, 1.0;
+            1.0, 1.0, 0.0, 2.0;
+            1.0, 1.0, 2.0, 0.0;
+        ];
+
+        let overall = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 0.5).unwrap();
+        let group_data = dmatrix![1.0; 3.0];
+        let group_wgt = dvector![1.0, 1.0];
+        let group_rep_wgts = rep_wgts.rows(0, 2).clone_owned();
+        let mut group = replicate_estimates(mean, &vec![&group_data], &vec![&group_wgt], &vec![&group_rep_wgts], 0.5).unwrap();
+        assert!(group.difference_to_overall().is_none());
+        assert!(group.significantly_different_from_overall().is_none());
+
+        group.set_difference_to_overall(&overall).unwrap();
+
+        // replicated group means are 3, 1, 2 and 2 against overall means of 4.75, 3.75, 5.0 and 3.5
+        let difference = group.difference_to_overall().unwrap();
+        assert_approx_eq_iter_f64!(difference.final_estimates(), dvector![-2.25]);
+        assert_approx_eq_iter_f64!(difference.sampling_variances(), dvector![0.5 * (0.25 + 0.25 + 0.5625 + 0.5625)]);
+        assert_eq!(Some(vec![false]), group.significantly_different_from_overall());
+
+        group.set_confidence_level(0.5).unwrap();
+        assert_eq!(Some(vec![true]), group.significantly_different_from_overall());
+    }
+
+    #[test]
+    fn test_difference_with_imputations() {
+        let data_group1_imp1 = dmatrix![1.0; 3.0; 5.0];
+        let data_group1_imp2 = dmatrix![2.0; 3.0; 5.0];
+        let data_group2_imp1 = dmatrix![2.0; 2.0; 8.0];
+        let data_group2_imp2 = dmatrix![2.0; 2.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
+
+        let result_group1 = replicate_estimates(mean, &vec![&data_group1_imp1, &data_group1_imp2], &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+        let result_group2 = replicate_estimates(mean, &vec![&data_group2_imp1, &data_group2_imp2], &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+
+        let result = difference(&result_group1, &result_group2).unwrap();
+        assert_approx_eq_iter_f64!(result.final_estimates, dvector![-1.0 / 3.0]);
+        assert_approx_eq_iter_f64!(result.sampling_variances, dvector![0.0]);
+        // differences per imputation are -1 and 1/3
+        assert_approx_eq_iter_f64!(result.imputation_variances, dvector![2.0 * (2.0_f64 / 3.0).powi(2)]);
+    }
+
+    #[test]
+    fn test_difference_unequal_number_of_imputations() {
+        let data = dmatrix![1.0; 3.0; 5.0];
+        let wgt = dvector![1.0, 1.0, 1.0];
+        let rep_wgts = DMatrix::from_row_slice(3, 0, &[]);
+
+        let result1 = replicate_estimates(mean, &vec![&data], &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+        let result2 = replicate_estimates(mean, &vec![&data, &data], &vec![&wgt], &vec![&rep_wgts], 1.0).unwrap();
+
+        assert_eq!("Invalid input: unequal number of imputations in difference", difference(&result1, &result2).unwrap_err().to_string());
+    }
+
     #[test]
     fn test_calc_replication_variance() {
         let final_estimates = dvector![2.5, 4.0];
@@ -296,22 +1579,21 @@ mod tests {
             4.20, 4.05, 3.80, 3.95;
         ];
 
-        let result = calc_replication_variance(&final_estimates, &replicated_estimates, 1.0);
+        let result = calc_replication_variance(&final_estimates, &replicated_estimates, 1.0).unwrap();
         assert_eq!(result, dvector![0.011799999999999986, 0.08500000000000012]);
 
-        let result = calc_replication_variance(&final_estimates, &replicated_estimates, 0.5);
+        let result = calc_replication_variance(&final_estimates, &replicated_estimates, 0.5).unwrap();
         assert_eq!(result, dvector![0.005899999999999993, 0.04250000000000006]);
     }
 
     #[test]
-    #[should_panic(expected = "dimension mismatch of estimates and replicated_estimates in calc_replication_variance")]
     fn test_calc_replication_variance_dimension_mismatch() {
         let final_estimates = dvector![2.5, 4.0];
         let replicated_estimates = dmatrix![
             2.42, 2.57, 2.49, 2.52;
         ];
 
-        calc_replication_variance(&final_estimates, &replicated_estimates, 1.0);
+        assert_eq!("Invalid input: dimension mismatch of estimates and replicated_estimates in calc_replication_variance", calc_replication_variance(&final_estimates, &replicated_estimates, 1.0).unwrap_err().to_string());
     }
 
     #[test]
@@ -323,7 +1605,7 @@ mod tests {
             4.20, 4.05, 3.80, 3.95;
         ];
 
-        let result = calc_replication_variance(&final_estimates, &replicated_estimates, 1.0);
+        let result = calc_replication_variance(&final_estimates, &replicated_estimates, 1.0).unwrap();
         assert_eq!(3, result.len());
         assert_eq!(true, result[0].is_nan());
         assert_eq!(true, result[1].is_nan());
@@ -335,7 +1617,7 @@ mod tests {
         let sampling_variances = dvector![1.0, 4.0, 0.25];
         let imputation_variances = dvector![0.0, 0.0, 0.0];
 
-        let result = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 1);
+        let result = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 1).unwrap();
 
         assert_eq!(dvector![1.0, 2.0, 0.5], result);
     }
@@ -345,7 +1627,7 @@ mod tests {
         let sampling_variances = dvector![0.0, 0.0, 0.0];
         let imputation_variances = dvector![1.0 / 1.1, 4.0 / 1.1, 0.25 / 1.1];
 
-        let result = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 10);
+        let result = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 10).unwrap();
 
         assert_eq!(dvector![1.0, 2.0, 0.5], result);
     }
@@ -355,22 +1637,32 @@ mod tests {
         let sampling_variances = dvector![0.5, 2.0, 0.125];
         let imputation_variances = dvector![1.0 / 2.2, 4.0 / 2.2, 0.25 / 2.2];
 
-        let result = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 10);
+        let result = calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 10).unwrap();
 
         assert_eq!(dvector![1.0, 2.0, 0.5], result);
     }
 
     #[test]
-    #[should_panic(expected = "dimension mismatch of sampling_variances and imputation_variances in calc_standard_error_from_variances")]
     fn test_calc_standard_errors_from_variances_dimension_mismatch() {
         let sampling_variances = dvector![0.5, 2.0, 0.125];
         let imputation_variances = dvector![1.0 / 2.2, 4.0 / 2.2];
 
-        calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 10);
+        assert_eq!("Invalid input: dimension mismatch of sampling_variances and imputation_variances in calc_standard_error_from_variances", calc_standard_errors_from_variances(&sampling_variances, &imputation_variances, 10).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_calc_degrees_of_freedom() {
+        let sampling_variances = dvector![0.5, 1.0];
+        let imputation_variances = dvector![0.5 / 1.1, 0.0];
+
+        assert_eq!(dvector![80.0, 80.0], calc_degrees_of_freedom(&sampling_variances, &imputation_variances, 1, 80.0).unwrap());
+        assert_eq!(dvector![36.0, f64::INFINITY], calc_degrees_of_freedom(&sampling_variances, &imputation_variances, 10, f64::INFINITY).unwrap());
+
+        let result = calc_degrees_of_freedom(&sampling_variances, &imputation_variances, 10, 80.0).unwrap();
+        assert_approx_eq_iter_f64!(result, dvector![18.728323699421967, 81.0 / 83.0 * 80.0], 1e-8);
     }
 
     #[test]
-    #[should_panic(expected = "length mismatch of weights and data in replicate_estimates")]
     fn test_replicate_estimates_length_mismatch_weights() {
         let mut imp_data: Vec<&DMatrix<f64>> = Vec::new();
         let data0 = DMatrix::from_row_slice(3, 1, &[ 1.0, 2.5, 3.0 ]);
@@ -382,11 +1674,11 @@ mod tests {
 
         let wgt = dvector![1.0, 0.5, 1.5];
 
-        replicate_estimates(mean, &imp_data, &vec![&wgt, &wgt], &vec![], 1.0);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt, &wgt], &vec![], 1.0);
+        assert_eq!("Invalid input: length mismatch of weights and data in replicate_estimates", result.unwrap_err().to_string());
     }
 
     #[test]
-    #[should_panic(expected = "length mismatch of replicate weights and data in replicate_estimates")]
     fn test_replicate_estimates_length_mismatch_replicate_weights() {
         let mut imp_data: Vec<&DMatrix<f64>> = Vec::new();
         let data0 = DMatrix::from_row_slice(3, 1, &[ 1.0, 2.5, 3.0 ]);
@@ -403,7 +1695,8 @@ mod tests {
             1.5, 1.5, 0.0,
         ]);
 
-        replicate_estimates(mean, &imp_data, &vec![&wgt, &wgt, &wgt], &vec![&rep_wgts, &rep_wgts, &rep_wgts, &rep_wgts], 1.0);
+        let result = replicate_estimates(mean, &imp_data, &vec![&wgt, &wgt, &wgt], &vec![&rep_wgts, &rep_wgts, &rep_wgts, &rep_wgts], 1.0);
+        assert_eq!("Invalid input: length mismatch of replicate weights and data in replicate_estimates", result.unwrap_err().to_string());
     }
 
     #[test]
@@ -460,7 +1753,7 @@ mod tests {
         imp_repwgt.push(&repwgt3);
         imp_repwgt.push(&repwgt4);
 
-        let result = replicate_estimates(mean, &imp_data, &imp_wgt, &imp_repwgt, 1.0);
+        let result = replicate_estimates(mean, &imp_data, &imp_wgt, &imp_repwgt, 1.0).unwrap();
         assert_eq!(1, result.final_estimates.len());
         assert_approx_eq_iter_f64!(result.final_estimates, vec![5.9289630325814535]);
         assert_approx_eq_iter_f64!(result.sampling_variances, vec![1.1564444389077233]);